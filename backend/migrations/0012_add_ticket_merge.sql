@@ -0,0 +1,5 @@
+-- Track tickets that were merged into a surviving ticket
+ALTER TABLE tickets ADD COLUMN IF NOT EXISTS merged_into UUID REFERENCES tickets(id) ON DELETE SET NULL;
+
+-- Add index for redirect lookups
+CREATE INDEX IF NOT EXISTS tickets_merged_into_idx ON tickets(merged_into);
@@ -0,0 +1,2 @@
+-- Record why an email was linked to an existing ticket by correlation
+ALTER TABLE email_tickets ADD COLUMN IF NOT EXISTS correlation_reason TEXT;
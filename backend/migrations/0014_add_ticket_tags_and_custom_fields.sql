@@ -0,0 +1,15 @@
+-- Free-form tags and typed custom field values on tickets
+ALTER TABLE tickets ADD COLUMN IF NOT EXISTS tags TEXT[] NOT NULL DEFAULT '{}';
+ALTER TABLE tickets ADD COLUMN IF NOT EXISTS custom_fields JSONB NOT NULL DEFAULT '{}';
+
+-- Add index for tag filtering
+CREATE INDEX IF NOT EXISTS tickets_tags_idx ON tickets USING GIN (tags);
+
+-- Admin-defined custom fields
+CREATE TABLE IF NOT EXISTS ticket_field_definitions (
+    name TEXT PRIMARY KEY,
+    label TEXT NOT NULL,
+    field_type TEXT NOT NULL,
+    options TEXT[] NOT NULL DEFAULT '{}',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
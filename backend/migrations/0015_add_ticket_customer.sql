@@ -0,0 +1,6 @@
+-- Link tickets to the responsible customer
+ALTER TABLE tickets ADD COLUMN IF NOT EXISTS customer_uuid UUID REFERENCES customers(uuid) ON DELETE SET NULL;
+ALTER TABLE tickets ADD COLUMN IF NOT EXISTS customer_link_source TEXT;
+
+-- Add index for customer ticket history
+CREATE INDEX IF NOT EXISTS tickets_customer_uuid_idx ON tickets(customer_uuid);
@@ -0,0 +1,5 @@
+-- Track the user a ticket is assigned to
+ALTER TABLE tickets ADD COLUMN IF NOT EXISTS assigned_to UUID REFERENCES users(uuid) ON DELETE SET NULL;
+
+-- Add index for assignee lookups
+CREATE INDEX IF NOT EXISTS tickets_assigned_to_idx ON tickets(assigned_to);
@@ -0,0 +1,15 @@
+-- Human-readable sequential ticket numbers
+CREATE SEQUENCE IF NOT EXISTS ticket_number_seq;
+ALTER TABLE tickets ADD COLUMN IF NOT EXISTS number BIGINT;
+
+-- Number existing tickets in creation order
+WITH ordered AS (
+    SELECT id, row_number() OVER (ORDER BY created_at, id) AS n
+    FROM tickets WHERE number IS NULL
+)
+UPDATE tickets t SET number = ordered.n FROM ordered WHERE t.id = ordered.id;
+SELECT setval('ticket_number_seq', GREATEST((SELECT COALESCE(MAX(number), 0) FROM tickets), 1), (SELECT COUNT(*) > 0 FROM tickets));
+
+ALTER TABLE tickets ALTER COLUMN number SET DEFAULT nextval('ticket_number_seq');
+ALTER TABLE tickets ALTER COLUMN number SET NOT NULL;
+CREATE UNIQUE INDEX IF NOT EXISTS tickets_number_idx ON tickets(number);
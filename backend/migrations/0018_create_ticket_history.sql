@@ -0,0 +1,17 @@
+-- Ticket lifecycle history
+CREATE TABLE IF NOT EXISTS ticket_history (
+    id UUID PRIMARY KEY,
+    ticket_id UUID NOT NULL REFERENCES tickets(id) ON DELETE CASCADE,
+    event TEXT NOT NULL,
+    from_status TEXT,
+    to_status TEXT,
+    actor UUID REFERENCES users(uuid) ON DELETE SET NULL,
+    details TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Add index for per-ticket history lookups
+CREATE INDEX IF NOT EXISTS ticket_history_ticket_id_idx ON ticket_history(ticket_id, created_at);
+
+-- Track tickets flagged for inactivity
+ALTER TABLE tickets ADD COLUMN IF NOT EXISTS stale_since TIMESTAMPTZ;
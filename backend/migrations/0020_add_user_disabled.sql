@@ -0,0 +1,8 @@
+-- Track when a user account was disabled
+ALTER TABLE users ADD COLUMN IF NOT EXISTS disabled_at TIMESTAMPTZ;
+
+-- Keep activity logs of deleted users
+ALTER TABLE user_logs DROP CONSTRAINT IF EXISTS user_logs_user_uuid_fkey;
+ALTER TABLE user_logs
+    ADD CONSTRAINT user_logs_user_uuid_fkey
+    FOREIGN KEY (user_uuid) REFERENCES users(uuid) ON DELETE SET NULL;
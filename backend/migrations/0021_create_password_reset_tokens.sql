@@ -0,0 +1,11 @@
+-- Single-use password reset tokens, only the SHA-256 hash is stored
+CREATE TABLE IF NOT EXISTS password_reset_tokens (
+    token_hash TEXT PRIMARY KEY,
+    user_uuid UUID NOT NULL REFERENCES users(uuid) ON DELETE CASCADE,
+    expires_at TIMESTAMPTZ NOT NULL,
+    used_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Add index for per-user token lookups
+CREATE INDEX IF NOT EXISTS password_reset_tokens_user_uuid_idx ON password_reset_tokens(user_uuid);
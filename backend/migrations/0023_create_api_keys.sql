@@ -0,0 +1,17 @@
+-- Long-lived API keys for automation, only the SHA-256 hash is stored
+CREATE TABLE IF NOT EXISTS api_keys (
+    uuid UUID PRIMARY KEY,
+    user_uuid UUID NOT NULL REFERENCES users(uuid) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    -- Leading characters of the key, to tell keys apart
+    prefix TEXT NOT NULL,
+    key_hash TEXT NOT NULL UNIQUE,
+    scopes TEXT[] NOT NULL DEFAULT '{}',
+    expires_at TIMESTAMPTZ,
+    last_used_at TIMESTAMPTZ,
+    revoked_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Add index for per-user key listings
+CREATE INDEX IF NOT EXISTS api_keys_user_uuid_idx ON api_keys(user_uuid);
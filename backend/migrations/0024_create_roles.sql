@@ -0,0 +1,33 @@
+-- Roles as named sets of permissions
+CREATE TABLE IF NOT EXISTS roles (
+    name VARCHAR(50) PRIMARY KEY,
+    description TEXT NOT NULL DEFAULT '',
+    permissions TEXT[] NOT NULL DEFAULT '{}',
+    -- Built-in roles cannot be deleted
+    built_in BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+INSERT INTO roles (name, description, permissions, built_in)
+VALUES
+    (
+        'admin',
+        'Full access',
+        ARRAY[
+            'tickets:read', 'tickets:write', 'email:read', 'email:write', 'email:send',
+            'email:delete', 'customers:read', 'nctns:read', 'whois:lookup',
+            'ticket_fields:manage', 'users:manage', 'roles:manage'
+        ],
+        TRUE
+    ),
+    (
+        'user',
+        'Analyst',
+        ARRAY['tickets:read', 'tickets:write', 'nctns:read', 'whois:lookup'],
+        TRUE
+    )
+ON CONFLICT (name) DO NOTHING;
+
+-- Users can only be assigned existing roles
+ALTER TABLE users ADD CONSTRAINT users_role_fkey FOREIGN KEY (role) REFERENCES roles(name);
@@ -0,0 +1,16 @@
+-- Rotating refresh tokens, only the SHA-256 hash is stored
+CREATE TABLE IF NOT EXISTS refresh_tokens (
+    token_hash TEXT PRIMARY KEY,
+    -- Tokens descending from the same login
+    family_id UUID NOT NULL,
+    user_uuid UUID NOT NULL REFERENCES users(uuid) ON DELETE CASCADE,
+    expires_at TIMESTAMPTZ NOT NULL,
+    -- Set once the token was exchanged for its successor
+    used_at TIMESTAMPTZ,
+    revoked_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Add indexes for family revocation and per-user lookups
+CREATE INDEX IF NOT EXISTS refresh_tokens_family_id_idx ON refresh_tokens(family_id);
+CREATE INDEX IF NOT EXISTS refresh_tokens_user_uuid_idx ON refresh_tokens(user_uuid);
@@ -0,0 +1,14 @@
+-- Revoked access tokens keyed by their JWT id instead of the full token.
+-- The previous table could never be written to, so no entries are lost.
+DROP TABLE IF EXISTS blacklisted_tokens;
+
+CREATE TABLE blacklisted_tokens (
+    -- JWT `jti`, or the SHA-256 hash of tokens issued without one
+    token_id TEXT PRIMARY KEY,
+    -- Expiry of the revoked token, the entry is purged afterwards
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Add index for the expiry cleanup
+CREATE INDEX blacklisted_tokens_expires_at_idx ON blacklisted_tokens(expires_at);
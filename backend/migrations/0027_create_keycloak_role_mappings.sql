@@ -0,0 +1,19 @@
+-- Roles granted to Keycloak users by their realm roles, client roles and groups
+CREATE TABLE IF NOT EXISTS keycloak_role_mappings (
+    uuid UUID PRIMARY KEY,
+    -- Token claim matched: realm_role, client_role or group
+    claim VARCHAR(20) NOT NULL CHECK (claim IN ('realm_role', 'client_role', 'group')),
+    value TEXT NOT NULL,
+    role VARCHAR(50) NOT NULL REFERENCES roles(name),
+    -- Highest priority wins when several mappings match
+    priority INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (claim, value)
+);
+
+-- Keep the previous behaviour of the Keycloak realm roles
+INSERT INTO keycloak_role_mappings (uuid, claim, value, role, priority)
+VALUES
+    ('7c9e6679-7425-40de-944b-e07fc1f90ae7', 'realm_role', 'admin', 'admin', 100),
+    ('f47ac10b-58cc-4372-a567-0e02b2c3d479', 'realm_role', 'user', 'user', 0)
+ON CONFLICT (claim, value) DO NOTHING;
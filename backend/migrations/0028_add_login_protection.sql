@@ -0,0 +1,23 @@
+-- Consecutive failed logins, reset by a successful login
+ALTER TABLE users ADD COLUMN IF NOT EXISTS failed_login_attempts INTEGER NOT NULL DEFAULT 0;
+-- Logins are refused until then, after repeated failures
+ALTER TABLE users ADD COLUMN IF NOT EXISTS locked_until TIMESTAMPTZ;
+
+-- Audit trail of login attempts
+CREATE TABLE IF NOT EXISTS login_events (
+    uuid UUID PRIMARY KEY,
+    -- Unset for unknown emails
+    user_uuid UUID REFERENCES users(uuid) ON DELETE SET NULL,
+    email TEXT NOT NULL,
+    success BOOLEAN NOT NULL,
+    -- What happened, e.g. invalid_password or locked
+    event VARCHAR(30) NOT NULL,
+    ip_address TEXT,
+    user_agent TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Add indexes for per-IP throttling and per-user history
+CREATE INDEX IF NOT EXISTS login_events_ip_failures_idx
+    ON login_events(ip_address, created_at) WHERE NOT success;
+CREATE INDEX IF NOT EXISTS login_events_user_uuid_idx ON login_events(user_uuid, created_at);
@@ -0,0 +1,20 @@
+-- Login sessions, one per refresh token family
+CREATE TABLE IF NOT EXISTS sessions (
+    -- Refresh token family id, the `sid` of access tokens
+    uuid UUID PRIMARY KEY,
+    user_uuid UUID NOT NULL REFERENCES users(uuid) ON DELETE CASCADE,
+    ip_address TEXT,
+    user_agent TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_refreshed_at TIMESTAMPTZ
+);
+
+-- Sessions of logins before this migration, without client details
+INSERT INTO sessions (uuid, user_uuid, created_at, last_refreshed_at)
+SELECT family_id, user_uuid, MIN(created_at), NULLIF(MAX(created_at), MIN(created_at))
+FROM refresh_tokens
+GROUP BY family_id, user_uuid
+ON CONFLICT (uuid) DO NOTHING;
+
+-- Add index for per-user listing
+CREATE INDEX IF NOT EXISTS sessions_user_uuid_idx ON sessions(user_uuid);
@@ -0,0 +1,6 @@
+-- Admins can query the audit log
+UPDATE roles SET permissions = array_append(permissions, 'audit:read'), updated_at = NOW()
+WHERE name = 'admin' AND NOT ('audit:read' = ANY(permissions));
+
+-- Add index for route prefix filters
+CREATE INDEX IF NOT EXISTS idx_user_logs_route ON user_logs(route text_pattern_ops);
@@ -0,0 +1,20 @@
+-- Request details of audit log entries
+ALTER TABLE user_logs ALTER COLUMN route TYPE TEXT;
+ALTER TABLE user_logs ADD COLUMN IF NOT EXISTS method VARCHAR(10);
+-- Matched route, e.g. /email/{id}
+ALTER TABLE user_logs ADD COLUMN IF NOT EXISTS route_pattern TEXT;
+ALTER TABLE user_logs ADD COLUMN IF NOT EXISTS status_code SMALLINT;
+-- Entities named in the path, e.g. [{"type": "email", "id": "..."}]
+ALTER TABLE user_logs ADD COLUMN IF NOT EXISTS entities JSONB NOT NULL DEFAULT '[]';
+ALTER TABLE user_logs ADD COLUMN IF NOT EXISTS ip_address TEXT;
+ALTER TABLE user_logs ADD COLUMN IF NOT EXISTS user_agent TEXT;
+ALTER TABLE user_logs ADD COLUMN IF NOT EXISTS latency_ms INTEGER;
+ALTER TABLE user_logs ADD COLUMN IF NOT EXISTS request_id TEXT;
+
+-- Earlier entries stored the status code as the action
+UPDATE user_logs SET status_code = action::SMALLINT WHERE action ~ '^[0-9]{3}$';
+
+-- Add indexes for status, entity and request filters
+CREATE INDEX IF NOT EXISTS idx_user_logs_status_code ON user_logs(status_code);
+CREATE INDEX IF NOT EXISTS idx_user_logs_entities ON user_logs USING GIN (entities jsonb_path_ops);
+CREATE INDEX IF NOT EXISTS idx_user_logs_request_id ON user_logs(request_id);
@@ -0,0 +1,32 @@
+-- Hash chain position of audit records, set when they are sealed
+ALTER TABLE user_logs ADD COLUMN IF NOT EXISTS seq BIGINT UNIQUE;
+ALTER TABLE user_logs ADD COLUMN IF NOT EXISTS prev_hash TEXT;
+ALTER TABLE user_logs ADD COLUMN IF NOT EXISTS hash TEXT;
+ALTER TABLE ticket_history ADD COLUMN IF NOT EXISTS seq BIGINT UNIQUE;
+ALTER TABLE ticket_history ADD COLUMN IF NOT EXISTS prev_hash TEXT;
+ALTER TABLE ticket_history ADD COLUMN IF NOT EXISTS hash TEXT;
+
+-- Sealed records must not change, so deleting users and tickets keeps
+-- their audit records untouched instead of nulling or cascading
+ALTER TABLE user_logs DROP CONSTRAINT IF EXISTS user_logs_user_uuid_fkey;
+ALTER TABLE ticket_history DROP CONSTRAINT IF EXISTS ticket_history_ticket_id_fkey;
+ALTER TABLE ticket_history DROP CONSTRAINT IF EXISTS ticket_history_actor_fkey;
+
+-- Add indexes for sealing unsealed records in order
+CREATE INDEX IF NOT EXISTS idx_user_logs_unsealed ON user_logs(timestamp, uuid) WHERE seq IS NULL;
+CREATE INDEX IF NOT EXISTS ticket_history_unsealed_idx ON ticket_history(created_at, id) WHERE seq IS NULL;
+
+-- Signed chain heads
+CREATE TABLE IF NOT EXISTS audit_checkpoints (
+    uuid UUID PRIMARY KEY,
+    -- user_logs or ticket_history
+    trail VARCHAR(30) NOT NULL,
+    seq BIGINT NOT NULL,
+    hash TEXT NOT NULL,
+    -- Signing key and compact JWS of the checkpoint
+    kid TEXT NOT NULL,
+    signature TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS audit_checkpoints_trail_idx ON audit_checkpoints(trail, seq);
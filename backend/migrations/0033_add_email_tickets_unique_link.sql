@@ -0,0 +1,10 @@
+-- The primary key of email_tickets was dropped with the old email_id
+-- column, remove duplicate links so each email is linked once per ticket
+DELETE FROM email_tickets a
+USING email_tickets b
+WHERE a.email_id = b.email_id
+  AND a.ticket_id = b.ticket_id
+  AND a.ctid > b.ctid;
+
+-- Make ON CONFLICT DO NOTHING skip existing links
+CREATE UNIQUE INDEX IF NOT EXISTS email_tickets_email_ticket_key ON email_tickets(email_id, ticket_id);
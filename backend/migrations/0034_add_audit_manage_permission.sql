@@ -0,0 +1,3 @@
+-- Admins can sign audit checkpoints on demand
+UPDATE roles SET permissions = array_append(permissions, 'audit:manage'), updated_at = NOW()
+WHERE name = 'admin' AND NOT ('audit:manage' = ANY(permissions));
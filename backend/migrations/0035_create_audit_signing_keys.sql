@@ -0,0 +1,8 @@
+-- Public keys of every key that signed an audit checkpoint, kept after
+-- the key is rotated out so old checkpoints stay verifiable
+CREATE TABLE IF NOT EXISTS audit_signing_keys (
+    kid TEXT PRIMARY KEY,
+    -- Public JWK of the key
+    jwk JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
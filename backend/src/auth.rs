@@ -1,55 +1,52 @@
 use actix_web::{dev::ServiceRequest, Error};
 use chrono::{Duration, Utc};
 use deadpool_postgres::Pool;
-use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
-use reqwest::Client;
-use serde_json::Value;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
 use uuid::Uuid;
 
-use crate::models::auth::{Claims, TokenType};
+use crate::keycloak::{role_claims, verify_token, KeycloakConfig, KeycloakError};
+use crate::models::api_key::ApiKey;
+use crate::models::auth::{ApiKeyClaims, Claims, TokenType};
+use crate::models::auth_cache::AuthCache;
+use crate::models::keycloak_mapping::KeycloakMapping;
+use crate::models::token_blacklist::TokenBlacklist;
 use crate::models::user::User;
+use crate::signing::SigningKeys;
 
-/// Creates a new JWT token for a user
+/// Creates a new access token for a user
 ///
 /// # Arguments
 /// * `user_uuid` - User's unique identifier
 /// * `role` - User's role (e.g., "user", "admin")
-/// * `token_type` - Type of token (Access or Refresh)
+/// * `session` - Refresh token family the token belongs to
 ///
 /// # Returns
 /// * `Result<String, jsonwebtoken::errors::Error>` - JWT token or error
 ///
 /// # Token Expiration
 /// - Access tokens: 1 hour
-/// - Refresh tokens: 7 days
+/// - Refresh tokens are opaque, see `RefreshToken`
 pub fn create_jwt(
     user_uuid: &Uuid,
     role: &str,
-    token_type: TokenType,
+    session: Uuid,
 ) -> Result<String, jsonwebtoken::errors::Error> {
-    // Set the expiration date based on the token type
-    let expiration = match token_type {
-        TokenType::Access => Utc::now() + Duration::hours(1),
-        TokenType::Refresh => Utc::now() + Duration::days(7),
-    };
+    // Set the expiration date
+    let expiration = Utc::now() + Duration::hours(1);
 
     // Create the claims for the token
     let claims = Claims {
-        sub: user_uuid.clone(),
+        sub: *user_uuid,
         role: role.to_owned(),
         exp: expiration.timestamp() as usize,
-        token_type,
+        token_type: TokenType::Access,
+        sid: Some(session),
+        jti: Some(Uuid::new_v4()),
     };
 
-    // Get the secret from the environment
-    let secret = std::env::var("JWT_SECRET").expect("JWT_SECRET must be set");
-
-    // Encode the claims into a JWT token
-    encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(secret.as_bytes()),
-    )
+    // Sign the claims with the active key
+    SigningKeys::global().sign(&claims)
 }
 
 /// Verifies a JWT token's validity
@@ -65,17 +62,34 @@ pub fn create_jwt(
 /// - Expiration check
 /// - Token structure validation
 pub fn verify_jwt(token: &str) -> Result<Claims, jsonwebtoken::errors::Error> {
-    // Get the secret from the environment
-    let secret = std::env::var("JWT_SECRET").expect("JWT_SECRET must be set");
+    // Verify the token with the key named by its kid
+    SigningKeys::global().verify(token)
+}
 
-    // Decode the token
-    let token_data = decode::<Claims>(
-        token,
-        &DecodingKey::from_secret(secret.as_bytes()),
-        &Validation::default(),
-    )?;
+/// Generates a random opaque token
+///
+/// # Arguments
+/// * `bytes` - Number of random bytes
+///
+/// # Returns
+/// * `String` - Hex encoded token
+pub fn generate_token(bytes: usize) -> String {
+    let mut buffer = vec![0u8; bytes];
+    rand::thread_rng().fill_bytes(&mut buffer);
+    hex::encode(buffer)
+}
 
-    Ok(token_data.claims)
+/// Hashes an opaque token for storage and lookup
+///
+/// Tokens handed out to users are only stored as their SHA-256 hash.
+///
+/// # Arguments
+/// * `token` - Token string
+///
+/// # Returns
+/// * `String` - Hex encoded SHA-256 hash
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
 }
 
 /// Checks authentication for incoming requests
@@ -85,27 +99,50 @@ pub fn verify_jwt(token: &str) -> Result<Claims, jsonwebtoken::errors::Error> {
 /// * `pool` - Database connection pool
 ///
 /// # Returns
-/// * `Result<Claims, Error>` - Valid claims or error
+/// * `Result<(Claims, Option<ApiKeyClaims>), Error>` - Valid claims, with the
+///   key's claims for requests authenticated by an API key, or error
 ///
 /// # Validation Steps
 /// 1. Checks for Authorization header
-/// 2. Validates Bearer token format
-/// 3. Checks token blacklist
-/// 4. Verifies token validity
-/// 5. Validates token type
-pub async fn check_auth(req: &ServiceRequest, pool: &Pool) -> Result<Claims, Error> {
+/// 2. Validates Bearer token or API key format
+/// 3. Verifies token validity
+/// 4. Validates token type
+/// 5. Checks the in-memory token blacklist
+/// 6. Checks that the user account is active and loads its current role,
+///    which replaces the role in the token so role changes apply at once
+/// 7. Checks that the token's refresh token family was not revoked
+///
+/// Steps 5 to 7 are answered from memory, see `AuthCache`.
+///
+/// API keys (`Authorization: ApiKey <key>`) are checked against the stored
+/// keys instead of steps 3 to 7.
+pub async fn check_auth(
+    req: &ServiceRequest,
+    pool: &Pool,
+) -> Result<(Claims, Option<ApiKeyClaims>), Error> {
     // Log the path being checked
     log::info!("Checking auth for path: {}", req.path());
 
     // Check for the Authorization header
     if let Some(auth_header) = req.headers().get("Authorization") {
-        log::info!("Authorization header found: {:?}", auth_header);
-
         // Convert the header to a string
         let auth_str = auth_header
             .to_str()
             .map_err(|_| actix_web::error::ErrorUnauthorized("Invalid authorization header"))?;
 
+        // Log the scheme only, the credentials must never reach the logs
+        let scheme = auth_str
+            .split(' ')
+            .next()
+            .filter(|scheme| matches!(*scheme, "Bearer" | "ApiKey"))
+            .unwrap_or("unknown");
+        log::info!("Authorization header found with scheme {}", scheme);
+
+        // API keys act on behalf of their owner
+        if let Some(key) = auth_str.strip_prefix("ApiKey ") {
+            return check_api_key(pool, key).await;
+        }
+
         // Check if the header starts with "Bearer "
         if !auth_str.starts_with("Bearer ") {
             return Err(actix_web::error::ErrorUnauthorized(
@@ -116,21 +153,42 @@ pub async fn check_auth(req: &ServiceRequest, pool: &Pool) -> Result<Claims, Err
         // Extract the token from the header
         let token = &auth_str[7..];
 
-        // Check if the token is blacklisted
-        if is_token_blacklisted(pool, token).await? {
-            return Err(actix_web::error::ErrorUnauthorized(
-                "Token has been invalidated",
-            ));
-        }
-
         // Verify the token
         match verify_jwt(token) {
-            Ok(claims) => {
+            Ok(mut claims) => {
                 // Check if the token type is valid
                 if claims.token_type != TokenType::Access {
                     return Err(actix_web::error::ErrorUnauthorized("Invalid token type"));
                 }
-                Ok(claims)
+                // Check if the token is blacklisted
+                if TokenBlacklist::is_revoked(&TokenBlacklist::token_id(&claims, token)) {
+                    return Err(actix_web::error::ErrorUnauthorized(
+                        "Token has been invalidated",
+                    ));
+                }
+                // Check that the user still exists and is not disabled, and
+                // authorize with the user's current role
+                let role = AuthCache::user_role(pool, claims.sub)
+                    .await
+                    .map_err(|e| actix_web::error::ErrorInternalServerError(e.to_string()))?;
+                match role {
+                    Some(role) => claims.role = role,
+                    None => {
+                        return Err(actix_web::error::ErrorUnauthorized("Account is disabled"));
+                    }
+                }
+                // Check that the session was not logged out or revoked
+                if let Some(sid) = claims.sid {
+                    let active = AuthCache::session_active(pool, sid)
+                        .await
+                        .map_err(|e| actix_web::error::ErrorInternalServerError(e.to_string()))?;
+                    if !active {
+                        return Err(actix_web::error::ErrorUnauthorized(
+                            "Session has been revoked",
+                        ));
+                    }
+                }
+                Ok((claims, None))
             }
             // Handle the error
             Err(e) => match e.kind() {
@@ -150,158 +208,128 @@ pub async fn check_auth(req: &ServiceRequest, pool: &Pool) -> Result<Claims, Err
     }
 }
 
-/// Checks if a user has required role authorization
+/// Checks an API key and builds the claims of its owner
 ///
 /// # Arguments
+/// * `pool` - Database connection pool
+/// * `key` - Key from the Authorization header
+///
+/// # Returns
+/// * `Result<(Claims, Option<ApiKeyClaims>), Error>` - Owner and key claims or error
+async fn check_api_key(pool: &Pool, key: &str) -> Result<(Claims, Option<ApiKeyClaims>), Error> {
+    let (key_claims, role) = ApiKey::authenticate(pool, key)
+        .await
+        .map_err(|e| actix_web::error::ErrorInternalServerError(e.to_string()))?
+        .ok_or_else(|| actix_web::error::ErrorUnauthorized("Invalid API key"))?;
+
+    let claims = Claims {
+        sub: key_claims.owner,
+        role,
+        exp: key_claims
+            .expires_at
+            .map_or(usize::MAX, |expires_at| expires_at.timestamp() as usize),
+        token_type: TokenType::Access,
+        sid: None,
+        jti: None,
+    };
+
+    Ok((claims, Some(key_claims)))
+}
+
+/// Checks if a user has a required permission
+///
+/// # Arguments
+/// * `pool` - Database connection pool
 /// * `claims` - User's token claims
-/// * `required_role` - Role required for access
+/// * `key_claims` - Key claims, for requests authenticated by an API key
+/// * `permission` - Permission required for access
 ///
 /// # Returns
-/// * `bool` - True if authorized
+/// * `Result<bool, Error>` - True if authorized
 ///
 /// # Authorization Rules
-/// - Admin role has access to everything
-/// - Exact role match required otherwise
-pub fn is_authorized(claims: &Claims, required_role: &str) -> bool {
-    // Check if the user has the required role or is an admin
-    claims.role == required_role || claims.role == "admin"
+/// - The user's role must grant the permission, `check_auth` sets it to the
+///   user's current role
+/// - API keys must also have the permission among their scopes
+pub async fn has_permission(
+    pool: &Pool,
+    claims: &Claims,
+    key_claims: Option<&ApiKeyClaims>,
+    permission: &str,
+) -> Result<bool, Error> {
+    if key_claims.is_some_and(|key_claims| !key_claims.allows(permission)) {
+        return Ok(false);
+    }
+
+    AuthCache::role_grants(pool, &claims.role, permission)
+        .await
+        .map_err(|e| actix_web::error::ErrorInternalServerError(e.to_string()))
 }
 
 /// Invalidates a JWT token by adding it to blacklist
 ///
 /// # Arguments
 /// * `pool` - Database connection pool
+/// * `claims` - Verified claims of the token
 /// * `token` - Token to invalidate
 ///
 /// # Returns
 /// * `Result<(), Error>` - Success or error
 ///
 /// # Database Operation
-/// Inserts token into blacklisted_tokens table
-pub async fn invalidate_token(pool: &Pool, token: &str) -> Result<(), Error> {
-    // Get a client from the pool
-    let client = pool.get().await.map_err(|e| {
-        actix_web::error::ErrorInternalServerError(format!("Database error: {}", e))
-    })?;
-
-    // Insert the token into the blacklisted_tokens table
-    client
-        .execute(
-            "INSERT INTO blacklisted_tokens (token, created_at) VALUES ($1, NOW())",
-            &[&token],
-        )
+/// Stores the token id with the token's expiry in blacklisted_tokens
+pub async fn invalidate_token(pool: &Pool, claims: &Claims, token: &str) -> Result<(), Error> {
+    TokenBlacklist::revoke(pool, claims, token)
         .await
-        .map_err(|e| {
-            actix_web::error::ErrorInternalServerError(format!("Database error: {}", e))
-        })?;
-
-    Ok(())
-}
-
-async fn is_token_blacklisted(pool: &Pool, token: &str) -> Result<bool, Error> {
-    // Get a client from the pool
-    let client = pool.get().await.map_err(|e| {
-        actix_web::error::ErrorInternalServerError(format!("Database error: {}", e))
-    })?;
-
-    // Check if the token exists in the blacklisted_tokens table
-    let result = client
-        .query_one(
-            "SELECT EXISTS(SELECT 1 FROM blacklisted_tokens WHERE token = $1)",
-            &[&token],
-        )
-        .await
-        .map_err(|e| {
-            actix_web::error::ErrorInternalServerError(format!("Database error: {}", e))
-        })?;
-
-    Ok(result.get(0))
+        .map_err(|e| actix_web::error::ErrorInternalServerError(format!("Database error: {}", e)))
 }
 
 /// Exchanges a Keycloak token for user information
 ///
 /// # Arguments
+/// * `pool` - Database connection pool
 /// * `keycloak_token` - Valid Keycloak token
 ///
 /// # Returns
 /// * `Result<User, Error>` - User information or error
 ///
-/// # Environment Variables Required
-/// - KEYCLOAK_URL
-/// - KEYCLOAK_REALM
-/// - KEYCLOAK_CLIENT_ID
-/// - KEYCLOAK_CLIENT_SECRET
+/// # Environment Variables
+/// See `crate::keycloak` and `crate::models::keycloak_mapping`
 ///
 /// # Token Validation
-/// 1. Validates token with Keycloak
+/// 1. Verifies the token with the realm's JWKS, or introspection as fallback
 /// 2. Extracts user information
-/// 3. Maps Keycloak roles to system roles
-pub async fn exchange_keycloak_token(keycloak_token: &str) -> Result<User, Error> {
-    // Get the Keycloak URL from the environment
-    let keycloak_url = std::env::var("KEYCLOAK_URL").expect("KEYCLOAK_URL must be set");
-    // Get the Keycloak realm from the environment
-    let realm = std::env::var("KEYCLOAK_REALM").expect("KEYCLOAK_REALM must be set");
-    // Get the Keycloak client ID from the environment
-    let client_id = std::env::var("KEYCLOAK_CLIENT_ID").expect("KEYCLOAK_CLIENT_ID must be set");
-    // Get the Keycloak client secret from the environment
-    let client_secret =
-        std::env::var("KEYCLOAK_CLIENT_SECRET").expect("KEYCLOAK_CLIENT_SECRET must be set");
-
-    // Create a new HTTP client
-    let client = Client::new();
+/// 3. Maps Keycloak roles and groups to system roles
+pub async fn exchange_keycloak_token(pool: &Pool, keycloak_token: &str) -> Result<User, Error> {
+    let keycloak_error = |e: KeycloakError| match e {
+        KeycloakError::InvalidToken(_) => actix_web::error::ErrorUnauthorized(e.to_string()),
+        _ => {
+            log::error!("Keycloak token exchange failed: {}", e);
+            actix_web::error::ErrorServiceUnavailable(e.to_string())
+        }
+    };
 
-    // Send a POST request to the Keycloak introspection endpoint
-    let response = client
-        .post(format!(
-            "{}/realms/{}/protocol/openid-connect/token/introspect",
-            keycloak_url, realm
-        ))
-        .form(&[
-            ("client_id", &client_id),
-            ("client_secret", &client_secret),
-            ("token", &keycloak_token.to_string()),
-        ])
-        .send()
+    // Verify the token
+    let config = KeycloakConfig::from_env().map_err(keycloak_error)?;
+    let token_info = verify_token(&config, keycloak_token)
         .await
-        .map_err(|e| {
-            actix_web::error::ErrorInternalServerError(format!("Keycloak request failed: {}", e))
-        })?;
-
-    // Parse the response as JSON
-    let token_info: Value = response.json().await.map_err(|e| {
-        actix_web::error::ErrorInternalServerError(format!(
-            "Failed to parse Keycloak response: {}",
-            e
-        ))
-    })?;
-
-    // Check if the token is active
-    if !token_info["active"].as_bool().unwrap_or(false) {
-        return Err(actix_web::error::ErrorUnauthorized(
-            "Invalid Keycloak token",
-        ));
-    }
+        .map_err(keycloak_error)?;
 
     // Extract the email from the token
-    let email = token_info["email"].as_str().ok_or_else(|| {
-        actix_web::error::ErrorInternalServerError("Missing email in Keycloak token")
-    })?;
+    let email = token_info["email"]
+        .as_str()
+        .ok_or_else(|| actix_web::error::ErrorUnauthorized("Missing email in Keycloak token"))?;
 
     // Extract the name from the token
-    let name = token_info["name"].as_str().ok_or_else(|| {
-        actix_web::error::ErrorInternalServerError("Missing name in Keycloak token")
-    })?;
+    let name = token_info["name"]
+        .as_str()
+        .or_else(|| token_info["preferred_username"].as_str())
+        .ok_or_else(|| actix_web::error::ErrorUnauthorized("Missing name in Keycloak token"))?;
 
-    // Extract the role from the token
-    let role = token_info["realm_access"]["roles"]
-        .as_array()
-        .and_then(|roles| {
-            roles
-                .iter()
-                .find(|&r| r.as_str() == Some("user") || r.as_str() == Some("admin"))
-        })
-        .and_then(|role| role.as_str())
-        .unwrap_or("user");
+    // Map the Keycloak roles and groups to a role
+    let role = KeycloakMapping::resolve(pool, &role_claims(&config, &token_info))
+        .await
+        .map_err(|e| actix_web::error::ErrorInternalServerError(e.to_string()))?;
 
     // Create a new user
     let user = User {
@@ -309,9 +337,11 @@ pub async fn exchange_keycloak_token(keycloak_token: &str) -> Result<User, Error
         email: email.to_owned(),
         name: name.to_owned(),
         password_hash: "".to_string(),
-        role: role.to_owned(),
+        role,
         created_at: Utc::now(),
         updated_at: Utc::now(),
+        disabled_at: None,
+        totp_enabled_at: None,
     };
 
     Ok(user)
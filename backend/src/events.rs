@@ -0,0 +1,217 @@
+//! Live Event Bus
+//!
+//! In-process broadcast bus for live updates pushed to dashboards:
+//!
+//! # Events
+//! - `email_ingested`: A new email was stored
+//! - `ticket_created`: A ticket was created
+//! - `ticket_status_changed`: A ticket's status changed
+//!
+//! # Multi-Instance Deployments
+//! With `EVENT_BUS_POSTGRES=true` events are published through Postgres
+//! `NOTIFY` and every instance forwards the notifications it `LISTEN`s to
+//! onto its local bus, so subscribers see events from all instances.
+//!
+//! # Environment Variables
+//! * `EVENT_BUS_CAPACITY` - Events buffered per subscriber (default 1024)
+//! * `EVENT_BUS_POSTGRES` - Relay events through Postgres (default false)
+
+use crate::models::ticket::{TicketStatus, TicketType};
+use chrono::{DateTime, Utc};
+use deadpool_postgres::Pool;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio_postgres::AsyncMessage;
+use uuid::Uuid;
+
+/// Postgres notification channel used to relay events between instances.
+const PG_CHANNEL: &str = "abuse_helper_events";
+
+static BUS: OnceLock<EventBus> = OnceLock::new();
+
+/// Live update pushed to subscribers.
+///
+/// Serialized with a `type` tag, e.g.
+/// `{"type": "ticket_created", "ticket_id": "...", ...}`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    /// A new email was stored
+    EmailIngested {
+        email_id: Uuid,
+        sender: String,
+        subject: String,
+        received_at: DateTime<Utc>,
+    },
+    /// A ticket was created
+    TicketCreated {
+        ticket_id: Uuid,
+        reference: Option<String>,
+        ticket_type: TicketType,
+        subject: String,
+    },
+    /// A ticket's status changed
+    TicketStatusChanged {
+        ticket_id: Uuid,
+        from: Option<TicketStatus>,
+        to: TicketStatus,
+    },
+}
+
+impl Event {
+    /// Event name, matching the serialized `type` tag.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Event::EmailIngested { .. } => "email_ingested",
+            Event::TicketCreated { .. } => "ticket_created",
+            Event::TicketStatusChanged { .. } => "ticket_status_changed",
+        }
+    }
+
+    /// Renders the event as a Server-Sent Events message.
+    pub fn to_sse(&self) -> String {
+        format!(
+            "event: {}\ndata: {}\n\n",
+            self.name(),
+            serde_json::to_string(self).unwrap_or_default()
+        )
+    }
+}
+
+/// Broadcast bus distributing events to all subscribers.
+pub struct EventBus {
+    sender: broadcast::Sender<Event>,
+    relay: OnceLock<Pool>,
+}
+
+/// Returns the process-wide event bus.
+pub fn bus() -> &'static EventBus {
+    BUS.get_or_init(|| {
+        let capacity = std::env::var("EVENT_BUS_CAPACITY")
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+            .filter(|c| *c > 0)
+            .unwrap_or(1024);
+
+        EventBus {
+            sender: broadcast::channel(capacity).0,
+            relay: OnceLock::new(),
+        }
+    })
+}
+
+/// Publishes an event on the process-wide bus.
+pub fn publish(event: Event) {
+    bus().publish(event);
+}
+
+impl EventBus {
+    /// Subscribes to all future events.
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.sender.subscribe()
+    }
+
+    /// Publishes an event.
+    ///
+    /// Publishing never fails; events without subscribers are dropped and
+    /// relay errors are logged.
+    pub fn publish(&self, event: Event) {
+        match self.relay.get() {
+            Some(pool) => {
+                let pool = pool.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = relay(&pool, &event).await {
+                        log::error!("Failed to relay {} event: {}", event.name(), e);
+                    }
+                });
+            }
+            None => self.send_local(event),
+        }
+    }
+
+    /// Delivers an event to this instance's subscribers.
+    fn send_local(&self, event: Event) {
+        // Sending only fails when nobody is subscribed
+        let _ = self.sender.send(event);
+    }
+}
+
+/// Sends an event through Postgres `NOTIFY`.
+async fn relay(pool: &Pool, event: &Event) -> Result<(), Box<dyn std::error::Error>> {
+    let client = pool.get().await?;
+    client
+        .execute(
+            "SELECT pg_notify($1, $2)",
+            &[&PG_CHANNEL, &serde_json::to_string(event)?],
+        )
+        .await?;
+    Ok(())
+}
+
+/// Starts relaying events through Postgres if `EVENT_BUS_POSTGRES` is set.
+///
+/// Spawns a task holding a dedicated `LISTEN` connection that reconnects
+/// after failures.
+///
+/// # Arguments
+/// * `pool` - Database connection pool used to publish notifications
+pub fn start(pool: Pool) {
+    let enabled = std::env::var("EVENT_BUS_POSTGRES")
+        .map(|v| v == "true" || v == "1")
+        .unwrap_or(false);
+    if !enabled {
+        return;
+    }
+
+    log::info!(
+        "Relaying live events through Postgres channel {}",
+        PG_CHANNEL
+    );
+    let _ = bus().relay.set(pool);
+
+    tokio::spawn(async {
+        loop {
+            if let Err(e) = listen().await {
+                log::error!("Live event listener failed: {}", e);
+            }
+            tokio::time::sleep(Duration::from_secs(5)).await;
+        }
+    });
+}
+
+/// Forwards Postgres notifications to the local bus until the connection fails.
+async fn listen() -> Result<(), Box<dyn std::error::Error>> {
+    let (client, mut connection) = crate::postgres::connect().await?;
+    let mut messages = futures::stream::poll_fn(move |cx| connection.poll_message(cx));
+
+    // The connection only makes progress while it is polled
+    let sql = format!("LISTEN {}", PG_CHANNEL);
+    let listen = client.batch_execute(&sql);
+    tokio::pin!(listen);
+    loop {
+        tokio::select! {
+            result = &mut listen => {
+                result?;
+                break;
+            }
+            message = messages.next() => match message {
+                Some(message) => { message?; }
+                None => return Ok(()),
+            },
+        }
+    }
+
+    while let Some(message) = messages.next().await {
+        if let AsyncMessage::Notification(notification) = message? {
+            match serde_json::from_str::<Event>(notification.payload()) {
+                Ok(event) => bus().send_local(event),
+                Err(e) => log::warn!("Ignoring malformed live event: {}", e),
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,246 @@
+use crate::events::{self, Event};
+use crate::models::notification::{Notification, NotificationKind};
+use crate::models::ticket::{Ticket, TicketError, TicketStatus, TicketType};
+use crate::models::ticket_history::{TicketEvent, TicketHistory};
+use chrono::{Duration, Utc};
+use deadpool_postgres::Pool;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// What happens to a ticket that matches an inactivity rule.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum HousekeepingAction {
+    /// Set the status to Closed
+    Close,
+    /// Mark the ticket as stale without changing its status
+    Flag,
+}
+
+/// Inactivity rule applied by the housekeeping job.
+///
+/// A rule with a `ticket_type` overrides the general rule with the same
+/// status and action for tickets of that type.
+///
+/// # Fields
+/// * `status` - Status the ticket must have
+/// * `ticket_type` - Ticket type the rule is limited to, None for all types
+/// * `inactive_hours` - Hours since the last update before the rule applies
+/// * `action` - Action to take
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HousekeepingRule {
+    pub status: TicketStatus,
+    #[serde(default)]
+    pub ticket_type: Option<TicketType>,
+    pub inactive_hours: i64,
+    pub action: HousekeepingAction,
+}
+
+impl HousekeepingRule {
+    /// Default rules: close Resolved tickets after 7 days and flag Open
+    /// tickets after 3 days without activity.
+    pub fn defaults() -> Vec<Self> {
+        vec![
+            HousekeepingRule {
+                status: TicketStatus::Resolved,
+                ticket_type: None,
+                inactive_hours: 168,
+                action: HousekeepingAction::Close,
+            },
+            HousekeepingRule {
+                status: TicketStatus::Open,
+                ticket_type: None,
+                inactive_hours: 72,
+                action: HousekeepingAction::Flag,
+            },
+        ]
+    }
+
+    /// Reads the rules from `TICKET_HOUSEKEEPING_RULES`.
+    ///
+    /// The variable holds a JSON array of rules, for example
+    /// `[{"status": "Open", "ticket_type": "DDoS", "inactive_hours": 24, "action": "flag"}]`.
+    /// Falls back to the default rules if it is unset or invalid.
+    pub fn from_env() -> Vec<Self> {
+        match std::env::var("TICKET_HOUSEKEEPING_RULES") {
+            Ok(value) => match serde_json::from_str::<Vec<Self>>(&value) {
+                Ok(rules) => rules,
+                Err(e) => {
+                    log::error!("Invalid TICKET_HOUSEKEEPING_RULES, using defaults: {}", e);
+                    Self::defaults()
+                }
+            },
+            Err(_) => Self::defaults(),
+        }
+    }
+
+    /// Ticket types excluded from a general rule because a type-specific
+    /// rule with the same status and action exists.
+    fn overridden_types(&self, rules: &[HousekeepingRule]) -> Vec<String> {
+        if self.ticket_type.is_some() {
+            return Vec::new();
+        }
+
+        rules
+            .iter()
+            .filter(|r| r.action == self.action && r.status.to_string() == self.status.to_string())
+            .filter_map(|r| r.ticket_type.as_ref().map(|t| t.to_string()))
+            .collect()
+    }
+}
+
+/// Outcome of a housekeeping run.
+///
+/// # Fields
+/// * `closed` - Tickets that were closed
+/// * `flagged` - Tickets that were flagged as stale
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct HousekeepingReport {
+    pub closed: Vec<Uuid>,
+    pub flagged: Vec<Uuid>,
+}
+
+/// Applies the inactivity rules to all tickets.
+///
+/// Clears the stale flag of tickets that were updated since they were
+/// flagged, then applies each rule and records the changes in the ticket
+/// history.
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `rules` - Inactivity rules
+///
+/// # Returns
+/// * `Result<HousekeepingReport, TicketError>` - Affected tickets or error
+pub async fn run(
+    pool: &Pool,
+    rules: &[HousekeepingRule],
+) -> Result<HousekeepingReport, TicketError> {
+    let mut client = pool.get().await?;
+    let tx = client.transaction().await?;
+    let mut report = HousekeepingReport::default();
+    let mut live_events = Vec::new();
+
+    // Clear the flag of tickets that saw activity since they were flagged
+    let mut reindex: Vec<Uuid> = tx
+        .query(
+            "UPDATE tickets SET stale_since = NULL
+             WHERE stale_since IS NOT NULL AND updated_at > stale_since RETURNING id",
+            &[],
+        )
+        .await?
+        .iter()
+        .map(|row| row.get("id"))
+        .collect();
+
+    for rule in rules {
+        let cutoff = Utc::now() - Duration::hours(rule.inactive_hours.max(0));
+        let status = rule.status.to_string();
+        let ticket_type = rule.ticket_type.as_ref().map(|t| t.to_string());
+        let overridden = rule.overridden_types(rules);
+
+        match rule.action {
+            HousekeepingAction::Close => {
+                // Close the inactive tickets
+                let ids: Vec<Uuid> = tx
+                    .query(
+                        "UPDATE tickets SET status = $5, stale_since = NULL, updated_at = NOW()
+                         WHERE status = $1 AND merged_into IS NULL AND updated_at < $2
+                           AND ($3::text IS NULL OR ticket_type = $3)
+                           AND NOT (ticket_type = ANY($4))
+                         RETURNING id",
+                        &[
+                            &status,
+                            &cutoff,
+                            &ticket_type,
+                            &overridden,
+                            &TicketStatus::Closed.to_string(),
+                        ],
+                    )
+                    .await?
+                    .iter()
+                    .map(|row| row.get("id"))
+                    .collect();
+
+                for id in &ids {
+                    let details = format!("No activity for {} hours", rule.inactive_hours);
+                    TicketHistory::new(
+                        *id,
+                        TicketEvent::AutoClosed,
+                        Some(rule.status),
+                        Some(TicketStatus::Closed),
+                        Some(details.clone()),
+                    )
+                    .save(&*tx)
+                    .await?;
+                    Notification::notify_watchers(
+                        &*tx,
+                        id,
+                        NotificationKind::StatusChanged,
+                        &format!(
+                            "Status changed from {} to {}: {}",
+                            rule.status,
+                            TicketStatus::Closed,
+                            details
+                        ),
+                        None,
+                    )
+                    .await?;
+                    live_events.push(Event::TicketStatusChanged {
+                        ticket_id: *id,
+                        from: Some(rule.status),
+                        to: TicketStatus::Closed,
+                    });
+                }
+                report.closed.extend(ids);
+            }
+            HousekeepingAction::Flag => {
+                // Flag the inactive tickets, leaving updated_at untouched
+                let ids: Vec<Uuid> = tx
+                    .query(
+                        "UPDATE tickets SET stale_since = NOW()
+                         WHERE status = $1 AND merged_into IS NULL AND updated_at < $2
+                           AND stale_since IS NULL
+                           AND ($3::text IS NULL OR ticket_type = $3)
+                           AND NOT (ticket_type = ANY($4))
+                         RETURNING id",
+                        &[&status, &cutoff, &ticket_type, &overridden],
+                    )
+                    .await?
+                    .iter()
+                    .map(|row| row.get("id"))
+                    .collect();
+
+                for id in &ids {
+                    TicketHistory::new(
+                        *id,
+                        TicketEvent::FlaggedStale,
+                        Some(rule.status),
+                        Some(rule.status),
+                        Some(format!("No activity for {} hours", rule.inactive_hours)),
+                    )
+                    .save(&*tx)
+                    .await?;
+                }
+                report.flagged.extend(ids);
+            }
+        }
+    }
+
+    tx.commit().await?;
+    live_events.into_iter().for_each(events::publish);
+
+    // Reindex the affected tickets
+    reindex.extend(report.closed.iter().chain(report.flagged.iter()));
+    reindex.sort();
+    reindex.dedup();
+    for id in reindex {
+        if let Some(ticket) = Ticket::find_by_id(pool, id).await? {
+            if let Err(e) = ticket.index_to_es().await {
+                log::error!("Failed to index ticket to ElasticSearch: {}", e);
+            }
+        }
+    }
+
+    Ok(report)
+}
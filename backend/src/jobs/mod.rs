@@ -0,0 +1,155 @@
+//! Background Jobs
+//!
+//! Periodic maintenance tasks that run alongside the HTTP server:
+//!
+//! # Modules
+//! - `housekeeping`: Ticket inactivity rules
+//!   - Auto-closing stale resolved tickets
+//!   - Flagging inactive open tickets
+//! - Token blacklist cleanup (`spawn_token_cleanup`)
+//!   - Purging entries of expired tokens
+//!   - Syncing the in-memory blacklist
+//! - Audit chain sealing (`spawn_audit_chain`)
+//!   - Hash chaining new audit records
+//!   - Signing periodic checkpoints
+//!
+//! # Environment Variables
+//! * `TICKET_HOUSEKEEPING_INTERVAL_MINUTES` - Minutes between housekeeping
+//!   runs (default 60, 0 disables the job)
+//! * `TOKEN_CLEANUP_INTERVAL_MINUTES` - Minutes between token blacklist
+//!   cleanups (default 5)
+//! * `AUDIT_SEAL_INTERVAL_SECONDS` - Seconds between sealing new audit
+//!   records into their hash chains (default 10)
+//! * `AUDIT_CHECKPOINT_INTERVAL_MINUTES` - Minutes between signed audit
+//!   checkpoints (default 60), only signed with keys from `JWT_KEYS_DIR`
+
+use crate::models::audit_chain::{AuditChain, AuditTrail};
+use crate::models::token_blacklist::TokenBlacklist;
+use crate::signing::SigningKeys;
+use deadpool_postgres::Pool;
+use std::time::{Duration, Instant};
+
+pub mod housekeeping;
+
+/// Starts the ticket housekeeping job.
+///
+/// Runs the configured inactivity rules once per interval. Failed runs are
+/// logged and retried on the next tick.
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+pub fn spawn_housekeeping(pool: Pool) {
+    let minutes = std::env::var("TICKET_HOUSEKEEPING_INTERVAL_MINUTES")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or(60);
+
+    if minutes == 0 {
+        log::info!("Ticket housekeeping is disabled");
+        return;
+    }
+
+    let rules = housekeeping::HousekeepingRule::from_env();
+    log::info!(
+        "Starting ticket housekeeping every {} minutes with {} rules",
+        minutes,
+        rules.len()
+    );
+
+    actix_web::rt::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(minutes * 60));
+        loop {
+            interval.tick().await;
+            match housekeeping::run(&pool, &rules).await {
+                Ok(report) => log::info!(
+                    "Ticket housekeeping closed {} and flagged {} tickets",
+                    report.closed.len(),
+                    report.flagged.len()
+                ),
+                Err(e) => log::error!("Ticket housekeeping failed: {}", e),
+            }
+        }
+    });
+}
+
+/// Starts the token blacklist cleanup job.
+///
+/// Purges entries of expired tokens and syncs the in-memory blacklist with
+/// revocations made by other instances. The first run loads the blacklist
+/// on startup.
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+pub fn spawn_token_cleanup(pool: Pool) {
+    let minutes = std::env::var("TOKEN_CLEANUP_INTERVAL_MINUTES")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .filter(|m| *m > 0)
+        .unwrap_or(5);
+
+    actix_web::rt::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(minutes * 60));
+        loop {
+            interval.tick().await;
+            match TokenBlacklist::purge_expired(&pool).await {
+                Ok(0) => {}
+                Ok(deleted) => log::info!("Purged {} expired blacklisted tokens", deleted),
+                Err(e) => log::error!("Token blacklist cleanup failed: {}", e),
+            }
+            if let Err(e) = TokenBlacklist::sync(&pool).await {
+                log::error!("Token blacklist sync failed: {}", e);
+            }
+        }
+    });
+}
+
+/// Starts the audit chain job.
+///
+/// Seals new audit records into their hash chains once per seal interval,
+/// and signs a checkpoint of each trail once per checkpoint interval.
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+pub fn spawn_audit_chain(pool: Pool) {
+    let seconds = std::env::var("AUDIT_SEAL_INTERVAL_SECONDS")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .filter(|s| *s > 0)
+        .unwrap_or(10);
+    let checkpoint_every = Duration::from_secs(
+        std::env::var("AUDIT_CHECKPOINT_INTERVAL_MINUTES")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .filter(|m| *m > 0)
+            .unwrap_or(60)
+            * 60,
+    );
+
+    let signs_checkpoints = SigningKeys::global().is_persistent();
+    if !signs_checkpoints {
+        log::warn!("JWT_KEYS_DIR is not set, audit checkpoints are disabled");
+    }
+
+    actix_web::rt::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(seconds));
+        let mut last_checkpoint = Instant::now();
+        loop {
+            interval.tick().await;
+            let checkpoint = signs_checkpoints && last_checkpoint.elapsed() >= checkpoint_every;
+            for trail in AuditTrail::ALL {
+                if checkpoint {
+                    match AuditChain::checkpoint(&pool, trail).await {
+                        Ok(Some(c)) => log::info!("Signed audit checkpoint {} of {}", c.seq, trail),
+                        Ok(None) => {}
+                        Err(e) => log::error!("Audit checkpoint of {} failed: {}", trail, e),
+                    }
+                } else if let Err(e) = AuditChain::seal(&pool, trail).await {
+                    log::error!("Sealing audit chain {} failed: {}", trail, e);
+                }
+            }
+            if checkpoint {
+                last_checkpoint = Instant::now();
+            }
+        }
+    });
+}
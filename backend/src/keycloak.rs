@@ -0,0 +1,334 @@
+//! Keycloak Token Verification
+//!
+//! Keycloak access tokens are verified locally against the realm's public
+//! keys, so exchanging a token does not cost a round trip to Keycloak:
+//!
+//! # Verification
+//! 1. The signature is checked with the realm key named by the token's `kid`
+//! 2. `iss` must be the realm, `exp` must not have passed
+//! 3. `aud` or `azp` must name our client
+//!
+//! The realm's JWKS is cached and refetched when it gets stale or a token
+//! names an unknown key. Tokens that cannot be verified locally, because
+//! the JWKS cannot be fetched or does not have the token's key, are checked
+//! with the introspection endpoint if a client secret is configured. The
+//! introspected token must be active and pass the same `iss` and `aud` or
+//! `azp` checks.
+//!
+//! # Environment Variables
+//! * `KEYCLOAK_URL` - Keycloak base URL, e.g. `http://keycloak:8080`
+//! * `KEYCLOAK_REALM` - Realm name
+//! * `KEYCLOAK_CLIENT_ID` - Our client, expected in `aud` or `azp`
+//! * `KEYCLOAK_CLIENT_SECRET` - Client secret, only needed for introspection
+//! * `KEYCLOAK_ISSUER` - Expected issuer when Keycloak is reached under
+//!   another URL than its public one (default `<KEYCLOAK_URL>/realms/<realm>`)
+//! * `KEYCLOAK_JWKS_TTL_SECONDS` - How long the JWKS is cached (default 300)
+
+use jsonwebtoken::jwk::{Jwk, JwkSet, PublicKeyUse};
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use reqwest::Client;
+use serde_json::Value;
+use std::sync::{OnceLock, RwLock};
+use std::time::{Duration, Instant};
+
+/// Minimum time between two JWKS fetches triggered by unknown keys.
+const MIN_REFETCH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Cached realm JWKS and when it was fetched.
+static JWKS: OnceLock<RwLock<Option<(JwkSet, Instant)>>> = OnceLock::new();
+
+fn jwks_cache() -> &'static RwLock<Option<(JwkSet, Instant)>> {
+    JWKS.get_or_init(|| RwLock::new(None))
+}
+
+/// Errors of Keycloak token verification.
+#[derive(Debug, thiserror::Error)]
+pub enum KeycloakError {
+    #[error("Keycloak is not configured: {0}")]
+    NotConfigured(String),
+    #[error("Invalid Keycloak token: {0}")]
+    InvalidToken(String),
+    #[error("Keycloak is unavailable: {0}")]
+    Unavailable(String),
+}
+
+/// Keycloak realm and client settings.
+#[derive(Debug, Clone)]
+pub struct KeycloakConfig {
+    pub url: String,
+    pub realm: String,
+    pub client_id: String,
+    pub client_secret: Option<String>,
+    pub issuer: String,
+}
+
+impl KeycloakConfig {
+    /// Reads the settings from the environment.
+    ///
+    /// # Returns
+    /// * `Result<KeycloakConfig, KeycloakError>` - Settings, or `NotConfigured`
+    ///   if a required variable is missing
+    pub fn from_env() -> Result<Self, KeycloakError> {
+        let var = |name: &str| {
+            std::env::var(name)
+                .map_err(|_| KeycloakError::NotConfigured(format!("{} is not set", name)))
+        };
+        let url = var("KEYCLOAK_URL")?.trim_end_matches('/').to_string();
+        let realm = var("KEYCLOAK_REALM")?;
+        let issuer = std::env::var("KEYCLOAK_ISSUER")
+            .unwrap_or_else(|_| format!("{}/realms/{}", url, realm));
+
+        Ok(Self {
+            client_id: var("KEYCLOAK_CLIENT_ID")?,
+            client_secret: std::env::var("KEYCLOAK_CLIENT_SECRET").ok(),
+            url,
+            realm,
+            issuer,
+        })
+    }
+
+    /// Base URL of the realm's OpenID Connect endpoints.
+    fn oidc_url(&self) -> String {
+        format!("{}/realms/{}/protocol/openid-connect", self.url, self.realm)
+    }
+}
+
+/// Verifies a token against a JWKS.
+///
+/// # Arguments
+/// * `config` - Realm and client settings
+/// * `jwks` - Realm public keys
+/// * `token` - Keycloak access token
+///
+/// # Returns
+/// * `Result<Option<Value>, KeycloakError>` - Token claims, `None` if the
+///   JWKS does not have the token's key, or `InvalidToken`
+pub fn verify_with_jwks(
+    config: &KeycloakConfig,
+    jwks: &JwkSet,
+    token: &str,
+) -> Result<Option<Value>, KeycloakError> {
+    let header = decode_header(token)
+        .map_err(|e| KeycloakError::InvalidToken(format!("Malformed token: {}", e)))?;
+    if matches!(
+        header.alg,
+        Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512
+    ) {
+        return Err(KeycloakError::InvalidToken(
+            "Symmetric signatures are not accepted".into(),
+        ));
+    }
+    let Some(jwk) = header.kid.as_deref().and_then(|kid| jwks.find(kid)) else {
+        return Ok(None);
+    };
+    let key = DecodingKey::from_jwk(jwk)
+        .map_err(|e| KeycloakError::InvalidToken(format!("Unusable signing key: {}", e)))?;
+
+    let mut validation = Validation::new(header.alg);
+    validation.set_issuer(&[&config.issuer]);
+    // Keycloak puts the client in `azp` and other services in `aud`
+    validation.validate_aud = false;
+    let claims = decode::<Value>(token, &key, &validation)
+        .map_err(|e| KeycloakError::InvalidToken(e.to_string()))?
+        .claims;
+    check_audience(config, &claims)?;
+
+    Ok(Some(claims))
+}
+
+/// Checks that token claims name our client in `aud` or `azp`.
+fn check_audience(config: &KeycloakConfig, claims: &Value) -> Result<(), KeycloakError> {
+    let audience_matches = match &claims["aud"] {
+        Value::String(aud) => *aud == config.client_id,
+        Value::Array(aud) => aud
+            .iter()
+            .any(|aud| aud.as_str() == Some(&config.client_id)),
+        _ => false,
+    };
+    if !audience_matches && claims["azp"].as_str() != Some(&config.client_id) {
+        return Err(KeycloakError::InvalidToken(format!(
+            "Token was not issued for {}",
+            config.client_id
+        )));
+    }
+    Ok(())
+}
+
+/// Checks an introspection response like a locally verified token.
+///
+/// # Arguments
+/// * `config` - Realm and client settings
+/// * `token_info` - Response of the introspection endpoint
+///
+/// # Returns
+/// * `Result<Value, KeycloakError>` - Token claims, or `InvalidToken` for
+///   inactive tokens, other issuers and tokens of other clients
+pub fn verify_introspection(
+    config: &KeycloakConfig,
+    token_info: Value,
+) -> Result<Value, KeycloakError> {
+    if !token_info["active"].as_bool().unwrap_or(false) {
+        return Err(KeycloakError::InvalidToken("Token is not active".into()));
+    }
+    if token_info["iss"].as_str() != Some(&config.issuer) {
+        return Err(KeycloakError::InvalidToken(format!(
+            "Token was not issued by {}",
+            config.issuer
+        )));
+    }
+    check_audience(config, &token_info)?;
+
+    Ok(token_info)
+}
+
+/// Fetches the realm's signing keys.
+///
+/// Encryption keys and keys of unsupported types are skipped.
+async fn fetch_jwks(config: &KeycloakConfig) -> Result<JwkSet, KeycloakError> {
+    let body: Value = Client::new()
+        .get(format!("{}/certs", config.oidc_url()))
+        .timeout(Duration::from_secs(10))
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| KeycloakError::Unavailable(format!("JWKS request failed: {}", e)))?
+        .json()
+        .await
+        .map_err(|e| KeycloakError::Unavailable(format!("Invalid JWKS: {}", e)))?;
+
+    let keys = body["keys"]
+        .as_array()
+        .ok_or_else(|| KeycloakError::Unavailable("JWKS without keys".into()))?
+        .iter()
+        .filter_map(|key| serde_json::from_value::<Jwk>(key.clone()).ok())
+        .filter(|key| key.common.public_key_use != Some(PublicKeyUse::Encryption))
+        .collect();
+
+    Ok(JwkSet { keys })
+}
+
+/// Gets the cached JWKS, fetching it if it is missing or stale.
+///
+/// # Arguments
+/// * `config` - Realm settings
+/// * `refetch` - Fetch the JWKS again unless it was fetched very recently
+async fn cached_jwks(config: &KeycloakConfig, refetch: bool) -> Result<JwkSet, KeycloakError> {
+    let ttl = std::env::var("KEYCLOAK_JWKS_TTL_SECONDS")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .map(Duration::from_secs)
+        .unwrap_or(Duration::from_secs(300));
+
+    if let Some((jwks, fetched_at)) = jwks_cache()
+        .read()
+        .expect("Keycloak JWKS lock poisoned")
+        .as_ref()
+    {
+        let max_age = if refetch { MIN_REFETCH_INTERVAL } else { ttl };
+        if fetched_at.elapsed() < max_age {
+            return Ok(jwks.clone());
+        }
+    }
+
+    let jwks = fetch_jwks(config).await?;
+    *jwks_cache().write().expect("Keycloak JWKS lock poisoned") =
+        Some((jwks.clone(), Instant::now()));
+    Ok(jwks)
+}
+
+/// Checks a token with the introspection endpoint.
+async fn introspect(config: &KeycloakConfig, token: &str) -> Result<Value, KeycloakError> {
+    let client_secret = config.client_secret.as_ref().ok_or_else(|| {
+        KeycloakError::NotConfigured("KEYCLOAK_CLIENT_SECRET is required for introspection".into())
+    })?;
+
+    let token_info: Value = Client::new()
+        .post(format!("{}/token/introspect", config.oidc_url()))
+        .timeout(Duration::from_secs(10))
+        .form(&[
+            ("client_id", config.client_id.as_str()),
+            ("client_secret", client_secret.as_str()),
+            ("token", token),
+        ])
+        .send()
+        .await
+        .map_err(|e| KeycloakError::Unavailable(format!("Keycloak request failed: {}", e)))?
+        .json()
+        .await
+        .map_err(|e| {
+            KeycloakError::Unavailable(format!("Failed to parse Keycloak response: {}", e))
+        })?;
+
+    verify_introspection(config, token_info)
+}
+
+/// Verifies a Keycloak access token.
+///
+/// # Arguments
+/// * `config` - Realm and client settings
+/// * `token` - Keycloak access token
+///
+/// # Returns
+/// * `Result<Value, KeycloakError>` - Token claims or error
+pub async fn verify_token(config: &KeycloakConfig, token: &str) -> Result<Value, KeycloakError> {
+    let mut unavailable = None;
+    for refetch in [false, true] {
+        match cached_jwks(config, refetch).await {
+            Ok(jwks) => {
+                if let Some(claims) = verify_with_jwks(config, &jwks, token)? {
+                    return Ok(claims);
+                }
+            }
+            Err(e) => {
+                unavailable = Some(e);
+                break;
+            }
+        }
+    }
+
+    // Fall back to introspection when the token cannot be verified locally
+    if config.client_secret.is_none() {
+        return Err(unavailable.unwrap_or_else(|| {
+            KeycloakError::InvalidToken("Token is signed with an unknown key".into())
+        }));
+    }
+    match unavailable {
+        Some(e) => log::warn!("Introspecting Keycloak token, {}", e),
+        None => log::warn!("Introspecting Keycloak token signed with an unknown key"),
+    }
+    introspect(config, token).await
+}
+
+/// Lists the roles and groups of a token, for the role mappings.
+///
+/// # Arguments
+/// * `config` - Client settings
+/// * `claims` - Token claims
+///
+/// # Returns
+/// * `Vec<(&str, String)>` - Pairs of `realm_role`, `client_role` or
+///   `group` and the role or group name
+pub fn role_claims(config: &KeycloakConfig, claims: &Value) -> Vec<(&'static str, String)> {
+    let names = |value: &Value| -> Vec<String> {
+        value
+            .as_array()
+            .map(|values| {
+                values
+                    .iter()
+                    .filter_map(|v| v.as_str().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+
+    let realm_roles = names(&claims["realm_access"]["roles"]);
+    let client_roles = names(&claims["resource_access"][&config.client_id]["roles"]);
+    let groups = names(&claims["groups"]);
+
+    realm_roles
+        .into_iter()
+        .map(|role| ("realm_role", role))
+        .chain(client_roles.into_iter().map(|role| ("client_role", role)))
+        .chain(groups.into_iter().map(|group| ("group", group)))
+        .collect()
+}
@@ -9,6 +9,12 @@
 //!   - JWT token management
 //!   - User authentication
 //!   - Role-based access control
+//! - `signing`: JWT signing keys
+//!   - Key rotation
+//!   - Public JWKS
+//! - `keycloak`: Keycloak token verification
+//!   - Cached realm JWKS
+//!   - Introspection fallback
 //!
 //! ## AI/ML Integration
 //! - `llm`: Language Learning Model integration
@@ -16,13 +22,28 @@
 //!   - Text analysis
 //!   - Natural language processing
 //!
+//! ## Background Jobs
+//! - `jobs`: Periodic maintenance tasks
+//!   - Ticket housekeeping
+//!
+//! ## Live Updates
+//! - `events`: In-process event bus
+//!   - Email and ticket events
+//!   - Optional Postgres relay
+//!
 //! ## Request Processing
 //! - `middleware`: Request processing layers
 //!   - Authentication middleware
 //!   - Logging middleware
+//!   - Request ID middleware
 //!   - Error handling
 //!   - Request validation
 //!
+//! ## Logging
+//! - `logging`: Log output
+//!   - Request IDs on every record
+//!   - Text and JSON formats
+//!
 //! ## Data Models
 //! - `models`: Core data structures
 //!   - User models
@@ -54,10 +75,18 @@
 
 // Authentication and Authorization
 pub mod auth;
+pub mod keycloak;
+pub mod signing;
 // AI/ML Integration
 pub mod llm;
+// Live Updates
+pub mod events;
+// Background Jobs
+pub mod jobs;
 // Request Processing
 pub mod middleware;
+// Logging
+pub mod logging;
 // Data Models
 pub mod models;
 // Database Integration
@@ -1,5 +1,4 @@
 use super::{client::LLMClient, parser, threat_analysis::ThreatAnalysis};
-use log;
 use ollama_rs::generation::completion::request::GenerationRequest;
 
 /// Performs security threat analysis on provided text using LLM.
@@ -1,4 +1,3 @@
-use log;
 use ollama_rs::Ollama;
 use std::env;
 
@@ -1,5 +1,4 @@
 use super::threat_analysis::ThreatAnalysis;
-use log;
 
 /// Parses and validates the LLM's response into a structured ThreatAnalysis object.
 ///
@@ -0,0 +1,192 @@
+//! Log Output
+//!
+//! Every log record, from `log` macros and `tracing` alike, goes through a
+//! `tracing` subscriber so it carries the fields of its request span, most
+//! importantly the `request_id` assigned by the `RequestId` middleware.
+//!
+//! # Formats
+//! - `text`: The env_logger lines used so far, e.g.
+//!   `[2024-01-01T00:00:00.000Z INFO  abuse_helper::routes::auth] message`,
+//!   with `[request_id=...]` before the message of records within a request
+//! - `json`: One JSON object per line, with the request fields under
+//!   `span`, for log shippers
+//!
+//! # Environment Variables
+//! * `LOG_FORMAT` - `text` (default) or `json`
+//! * `RUST_LOG` - Level filter, with the same syntax as before, e.g.
+//!   `info,abuse_helper=debug` (default `info`)
+
+use chrono::{SecondsFormat, Utc};
+use std::fmt;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id};
+use tracing::{Event, Subscriber};
+use tracing_log::NormalizeEvent;
+use tracing_subscriber::fmt::format::Writer;
+use tracing_subscriber::fmt::{FmtContext, FormatEvent, FormatFields, MakeWriter};
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::EnvFilter;
+
+/// Log output formats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// env_logger lines
+    Text,
+    /// One JSON object per line
+    Json,
+}
+
+impl LogFormat {
+    /// Reads the format from `LOG_FORMAT`.
+    ///
+    /// # Returns
+    /// * `LogFormat` - `Json` for `json`, `Text` otherwise
+    pub fn from_env() -> Self {
+        match std::env::var("LOG_FORMAT") {
+            Ok(format) if format.eq_ignore_ascii_case("json") => LogFormat::Json,
+            _ => LogFormat::Text,
+        }
+    }
+}
+
+/// Request ID of a span, stored in its extensions.
+struct SpanRequestId(String);
+
+/// Reads the `request_id` field of a span.
+struct RequestIdVisitor(Option<String>);
+
+impl Visit for RequestIdVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "request_id" {
+            self.0 = Some(value.to_string());
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "request_id" {
+            self.0 = Some(format!("{:?}", value));
+        }
+    }
+}
+
+/// Remembers the request ID of request spans for the text format.
+struct RequestIdLayer;
+
+impl<S> Layer<S> for RequestIdLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let mut visitor = RequestIdVisitor(None);
+        attrs.record(&mut visitor);
+        if let (Some(request_id), Some(span)) = (visitor.0, ctx.span(id)) {
+            span.extensions_mut().insert(SpanRequestId(request_id));
+        }
+    }
+}
+
+/// Writes records as env_logger did, with the request ID if there is one.
+struct EnvLoggerFormat;
+
+impl<S, N> FormatEvent<S, N> for EnvLoggerFormat
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> fmt::Result {
+        // Records of `log` macros carry their own target
+        let normalized = event.normalized_metadata();
+        let metadata = normalized.as_ref().unwrap_or_else(|| event.metadata());
+        write!(
+            writer,
+            "[{} {:<5} {}] ",
+            Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+            metadata.level(),
+            metadata.target()
+        )?;
+
+        let request_id = ctx.event_scope().and_then(|scope| {
+            scope.from_root().find_map(|span| {
+                span.extensions()
+                    .get::<SpanRequestId>()
+                    .map(|id| id.0.clone())
+            })
+        });
+        if let Some(request_id) = request_id {
+            write!(writer, "[request_id={}] ", request_id)?;
+        }
+
+        ctx.field_format().format_fields(writer.by_ref(), event)?;
+        writeln!(writer)
+    }
+}
+
+/// Builds the subscriber writing log records in a format.
+///
+/// # Arguments
+/// * `format` - Output format
+/// * `filter` - Level filter
+/// * `writer` - Destination of the records
+///
+/// # Returns
+/// * `Box<dyn Subscriber + Send + Sync>` - Subscriber
+pub fn subscriber<W>(
+    format: LogFormat,
+    filter: EnvFilter,
+    writer: W,
+) -> Box<dyn Subscriber + Send + Sync>
+where
+    W: for<'a> MakeWriter<'a> + Send + Sync + 'static,
+{
+    let registry = tracing_subscriber::registry().with(filter);
+
+    match format {
+        LogFormat::Text => Box::new(
+            registry.with(RequestIdLayer).with(
+                tracing_subscriber::fmt::layer()
+                    .event_format(EnvLoggerFormat)
+                    .with_writer(writer),
+            ),
+        ),
+        LogFormat::Json => Box::new(
+            registry.with(
+                tracing_subscriber::fmt::layer()
+                    .json()
+                    .with_current_span(true)
+                    .with_span_list(false)
+                    .with_writer(writer),
+            ),
+        ),
+    }
+}
+
+/// Initializes logging to stderr.
+///
+/// Forwards `log` records to `tracing` and installs the subscriber for the
+/// format set in `LOG_FORMAT`, filtered by `RUST_LOG`, or at `info` if it
+/// is not set.
+///
+/// # Returns
+/// * `Result<(), String>` - Success, or error if logging is initialized or
+///   `RUST_LOG` is invalid
+pub fn init() -> Result<(), String> {
+    let filter = match std::env::var(EnvFilter::DEFAULT_ENV) {
+        Ok(directives) => EnvFilter::try_new(&directives)
+            .map_err(|e| format!("Invalid {} {:?}: {}", EnvFilter::DEFAULT_ENV, directives, e))?,
+        Err(_) => EnvFilter::new("info"),
+    };
+
+    tracing_log::LogTracer::init().map_err(|e| e.to_string())?;
+    tracing::subscriber::set_global_default(subscriber(
+        LogFormat::from_env(),
+        filter,
+        std::io::stderr,
+    ))
+    .map_err(|e| e.to_string())
+}
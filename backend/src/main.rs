@@ -1,18 +1,15 @@
-mod auth;
-mod llm;
-mod middleware;
-mod models;
-mod postgres;
-mod routes;
-
-use crate::models::es::ESClient;
-use crate::postgres::run_migrations;
+use abuse_helper::events;
+use abuse_helper::jobs;
+use abuse_helper::logging;
+use abuse_helper::middleware::{Logger, RequestId};
+use abuse_helper::models::es::ESClient;
+use abuse_helper::models::ticket_field::CustomFieldDefinition;
+use abuse_helper::postgres::{self, run_migrations};
+use abuse_helper::routes;
+use abuse_helper::signing::SigningKeys;
 use actix_web::{web, App, HttpServer};
 use deadpool_postgres::Pool;
-use env_logger::Env;
 use lettre::{message::header::ContentType, message::Mailbox, AsyncTransport, Message};
-use log;
-use middleware::Logger;
 
 /// Populates the system with test email data
 ///
@@ -101,7 +98,10 @@ async fn populate_test_emails() -> Result<(), String> {
 ///
 /// # Indices Created
 /// - emails: Email document storage
-/// - tickets: Ticket document storage
+/// - tickets: Ticket document storage, with the custom field mappings
+///
+/// # Arguments
+/// * `pool` - Database connection pool
 ///
 /// # Returns
 /// * `Result<(), Box<dyn std::error::Error>>` - Success or error
@@ -110,7 +110,7 @@ async fn populate_test_emails() -> Result<(), String> {
 /// - Creates indices if not exist
 /// - Updates mappings if needed
 /// - Ensures proper analyzers
-async fn init_elasticsearch() -> Result<(), Box<dyn std::error::Error>> {
+async fn init_elasticsearch(pool: &Pool) -> Result<(), Box<dyn std::error::Error>> {
     log::info!("Initializing ElasticSearch indices...");
     // Create the ElasticSearch client
     let client = ESClient::new().await?;
@@ -123,6 +123,9 @@ async fn init_elasticsearch() -> Result<(), Box<dyn std::error::Error>> {
     // Create the ticket index
     client.ensure_index("tickets").await?;
 
+    // Map the custom fields, the recreated index does not know them
+    CustomFieldDefinition::map_all(pool, &client).await?;
+
     log::info!("ElasticSearch indices initialized successfully");
     Ok(())
 }
@@ -177,12 +180,14 @@ async fn cleanup_database(pool: &Pool) -> Result<(), Box<dyn std::error::Error>>
 ///
 /// # Initialization Steps
 /// 1. Configures logging
-/// 2. Sets up database pool
-/// 3. Runs migrations
-/// 4. Performs cleanup
-/// 5. Populates test data
-/// 6. Initializes ElasticSearch
-/// 7. Starts HTTP server
+/// 2. Loads the JWT signing keys
+/// 3. Sets up database pool
+/// 4. Runs migrations
+/// 5. Performs cleanup
+/// 6. Populates test data
+/// 7. Initializes ElasticSearch
+/// 8. Starts background jobs and the live event relay
+/// 9. Starts HTTP server
 ///
 /// # Server Configuration
 /// - Uses environment variable `ADDRESS` for binding
@@ -190,13 +195,18 @@ async fn cleanup_database(pool: &Pool) -> Result<(), Box<dyn std::error::Error>>
 /// - Sets up connection pools
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    // Set up logging with filter for tokenizers warnings
-    std::env::set_var("RUST_LOG", "info");
+    // Initialize logging, in the format set in LOG_FORMAT, at the level set
+    // in RUST_LOG or info
+    if let Err(e) = logging::init() {
+        eprintln!("Failed to initialize logging: {}", e);
+        return Err(std::io::Error::other("Logging initialization failed"));
+    }
 
-    // Initialize logging
-    env_logger::Builder::from_env(Env::default())
-        .format_timestamp_millis()
-        .init();
+    // Load the JWT signing keys
+    if let Err(e) = SigningKeys::init() {
+        log::error!("Failed to load JWT signing keys: {}", e);
+        return Err(std::io::Error::other("Invalid JWT signing keys"));
+    }
 
     // Create the database pool and run migrations
     let pg_pool = postgres::create_pool();
@@ -204,19 +214,13 @@ async fn main() -> std::io::Result<()> {
     // Run migrations
     if let Err(e) = run_migrations(&pg_pool).await {
         log::error!("Failed to run migrations: {}", e);
-        return Err(std::io::Error::new(
-            std::io::ErrorKind::Other,
-            "Migration failed",
-        ));
+        return Err(std::io::Error::other("Migration failed"));
     }
 
     // Clean up database
     if let Err(e) = cleanup_database(&pg_pool).await {
         log::error!("Failed to clean up database: {}", e);
-        return Err(std::io::Error::new(
-            std::io::ErrorKind::Other,
-            "Database cleanup failed",
-        ));
+        return Err(std::io::Error::other("Database cleanup failed"));
     }
 
     // Populate test emails
@@ -225,14 +229,17 @@ async fn main() -> std::io::Result<()> {
     }
 
     // Initialize ElasticSearch
-    if let Err(e) = init_elasticsearch().await {
+    if let Err(e) = init_elasticsearch(&pg_pool).await {
         log::error!("Failed to initialize ElasticSearch: {}", e);
-        return Err(std::io::Error::new(
-            std::io::ErrorKind::Other,
-            "ElasticSearch initialization failed",
-        ));
+        return Err(std::io::Error::other("ElasticSearch initialization failed"));
     }
 
+    // Start the background jobs and the live event relay
+    jobs::spawn_housekeeping(pg_pool.clone());
+    jobs::spawn_token_cleanup(pg_pool.clone());
+    jobs::spawn_audit_chain(pg_pool.clone());
+    events::start(pg_pool.clone());
+
     // Start the Actix server
     let address = std::env::var("ADDRESS").unwrap_or_else(|_| "127.0.0.1:8000".into());
 
@@ -243,6 +250,8 @@ async fn main() -> std::io::Result<()> {
             .app_data(web::Data::new(pg_pool.clone()))
             // Add the logger middleware
             .wrap(Logger::new())
+            // Assign request IDs, outermost so every log record carries them
+            .wrap(RequestId::new())
             // Configure the routes
             .service(routes::config::configure_routes())
     })
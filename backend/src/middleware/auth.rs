@@ -9,28 +9,29 @@ use std::{
     rc::Rc,
 };
 
-use crate::auth::{check_auth, is_authorized};
+use crate::auth::{check_auth, has_permission};
 
 /// Authentication middleware service implementation.
 ///
-/// Handles request authentication and role-based access control.
+/// Handles request authentication and permission-based access control.
 /// Wraps the inner service and performs authentication checks before request processing.
 ///
 /// # Usage
 /// ```rust
-/// app.wrap(Auth::new().role("admin"))
+/// app.wrap(Auth::new().permission("users:manage"))
 /// ```
 pub struct AuthMiddleware<S> {
     /// Inner service being wrapped - handles the actual request after authentication
     service: Rc<S>,
-    /// Optional role requirement for authorization - if None, only authentication is performed
-    role: Option<String>,
+    /// Optional permission requirement for authorization - if None, only authentication
+    /// is performed and API keys are rejected
+    permission: Option<String>,
 }
 
 /// Service implementation for AuthMiddleware.
 ///
 /// Implements the core authentication and authorization logic for incoming requests.
-/// Validates JWT tokens and checks role permissions if specified.
+/// Validates JWT tokens or API keys and checks permissions if specified.
 ///
 /// # Type Parameters
 /// * `S` - The wrapped service type
@@ -51,19 +52,21 @@ where
     /// Processes incoming requests, performing authentication and authorization checks.
     ///
     /// # Flow
-    /// 1. Extracts and validates JWT token from request headers
-    /// 2. Checks role permissions if a role requirement is configured
+    /// 1. Extracts and validates JWT token or API key from request headers
+    /// 2. Checks the role's permissions, and the key's scopes for requests
+    ///    authenticated by an API key, if a permission requirement is configured
     /// 3. Injects validated claims into request extensions for downstream use
     /// 4. Forwards to inner service if all checks pass
     ///
     /// # Error Handling
     /// - Returns 401 Unauthorized for invalid/missing tokens
-    /// - Returns 403 Forbidden for insufficient role permissions
+    /// - Returns 403 Forbidden for insufficient permissions, or for API keys on
+    ///   routes without a permission requirement
     /// - Propagates database errors during authentication
     fn call(&self, req: ServiceRequest) -> Self::Future {
-        // Clone service and role for async block ownership
+        // Clone service and permission for async block ownership
         let srv = self.service.clone();
-        let role = self.role.clone();
+        let permission = self.permission.clone();
 
         Box::pin(async move {
             // Get database pool from application state
@@ -71,16 +74,26 @@ where
 
             // Perform authentication check
             match check_auth(&req, pool).await {
-                Ok(claims) => {
-                    // If role is specified, verify authorization
-                    if let Some(required_role) = role {
-                        if !is_authorized(&claims, &required_role) {
+                Ok((claims, key_claims)) => {
+                    // If permission is specified, verify authorization
+                    if let Some(required_permission) = permission {
+                        if !has_permission(pool, &claims, key_claims.as_ref(), &required_permission)
+                            .await?
+                        {
                             return Err(actix_web::error::ErrorForbidden(
                                 "Insufficient permissions",
                             ));
                         }
+                    } else if key_claims.is_some() {
+                        // API keys only reach routes with a permission requirement
+                        return Err(actix_web::error::ErrorForbidden(
+                            "API keys are not accepted for this endpoint",
+                        ));
                     }
                     // Store validated claims for downstream handlers
+                    if let Some(key_claims) = key_claims {
+                        req.extensions_mut().insert(key_claims);
+                    }
                     req.extensions_mut().insert(claims);
                     // Forward to inner service
                     let res = srv.call(req).await?;
@@ -95,32 +108,41 @@ where
 /// Authentication middleware factory.
 ///
 /// Provides a fluent interface for configuring authentication middleware
-/// with optional role-based access control.
+/// with optional permission-based access control.
 ///
 /// # Example
 /// ```rust
-/// let auth = Auth::new().role("admin");
+/// let auth = Auth::new().permission("users:manage");
 /// app.wrap(auth);
 /// ```
 pub struct Auth {
-    /// Required role for accessing protected resources
+    /// Required permission for accessing protected resources
     /// None means only authentication is required
-    role: Option<String>,
+    permission: Option<String>,
+}
+
+impl Default for Auth {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Auth {
-    /// Creates a new Auth middleware instance without role requirements.
-    /// By default, only validates authentication without role checks.
+    /// Creates a new Auth middleware instance without permission requirements.
+    /// By default, only validates authentication without permission checks
+    /// and rejects API keys.
     pub fn new() -> Self {
-        Auth { role: None }
+        Auth { permission: None }
     }
 
-    /// Adds role requirement to the authentication middleware.
+    /// Adds permission requirement to the authentication middleware.
+    ///
+    /// API keys are accepted if the permission is among their scopes.
     ///
     /// # Arguments
-    /// * `role` - Role string that will be required for access
-    pub fn role(mut self, role: &str) -> Self {
-        self.role = Some(role.to_string());
+    /// * `permission` - Permission that will be required for access, e.g. `tickets:read`
+    pub fn permission(mut self, permission: &str) -> Self {
+        self.permission = Some(permission.to_string());
         self
     }
 }
@@ -150,7 +172,7 @@ where
     fn new_transform(&self, service: S) -> Self::Future {
         ready(Ok(AuthMiddleware {
             service: Rc::new(service),
-            role: self.role.clone(),
+            permission: self.permission.clone(),
         }))
     }
 }
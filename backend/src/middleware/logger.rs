@@ -2,15 +2,19 @@ use actix_web::{
     dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
     Error, HttpMessage,
 };
+use chrono::Utc;
 use deadpool_postgres::Pool;
 use futures_util::future::LocalBoxFuture;
 use std::{
     future::{ready, Ready},
     rc::Rc,
+    time::Instant,
 };
+use tracing::Instrument;
 
-use crate::models::auth::Claims;
-use crate::models::user_log::UserLog;
+use super::request_id::AssignedRequestId;
+use crate::models::auth::{Claims, ClientInfo};
+use crate::models::user_log::{AuditEntity, UserLog};
 use uuid::Uuid;
 
 /// User activity logging middleware service implementation.
@@ -51,10 +55,12 @@ where
     /// Processes requests and logs user activity asynchronously.
     ///
     /// # Flow
-    /// 1. Forwards request to inner service for processing
-    /// 2. Extracts user claims from request extensions
-    /// 3. Captures request path and response status
-    /// 4. Spawns async task for database logging
+    /// 1. Takes the request ID and starts timing the request
+    /// 2. Forwards request to inner service for processing
+    /// 3. Extracts user claims from request extensions
+    /// 4. Captures the method, matched route, path entities, client,
+    ///    response status and latency
+    /// 5. Spawns async task for database logging
     ///
     /// # Error Handling
     /// - Logging errors are captured and logged to stderr
@@ -67,20 +73,57 @@ where
             .unwrap()
             .clone();
         let path = req.path().to_owned();
+        // Assigned by the RequestId middleware, if it wraps this one
+        let request_id = req
+            .extensions()
+            .get::<AssignedRequestId>()
+            .map(|id| id.0.clone())
+            .unwrap_or_else(|| Uuid::new_v4().to_string());
+        let started_at = Utc::now();
+        let timer = Instant::now();
 
         Box::pin(async move {
             let res = srv.call(req).await?;
 
-            if let Some(claims) = res.request().extensions().get::<Claims>() {
-                let user_uuid: Uuid = claims.sub;
-                let action = res.status().as_str().to_owned();
+            let user_uuid = res
+                .request()
+                .extensions()
+                .get::<Claims>()
+                .map(|claims| claims.sub);
+            if let Some(user_uuid) = user_uuid {
+                let request = res.request();
+                let method = request.method().to_string();
+                let route_pattern = request.match_pattern();
+                let entities = route_pattern
+                    .as_deref()
+                    .map(|pattern| AuditEntity::from_path(pattern, request.match_info().iter()))
+                    .unwrap_or_default();
+                let client = ClientInfo::from_request(request);
+                let entry = UserLog {
+                    uuid: Uuid::new_v4(),
+                    user_uuid: Some(user_uuid),
+                    action: format!("{} {}", method, route_pattern.as_deref().unwrap_or(&path)),
+                    route: path,
+                    method: Some(method),
+                    route_pattern,
+                    status_code: Some(res.status().as_u16() as i16),
+                    entities,
+                    ip_address: client.ip_address,
+                    user_agent: client.user_agent,
+                    latency_ms: Some(timer.elapsed().as_millis().min(i32::MAX as u128) as i32),
+                    request_id: Some(request_id),
+                    timestamp: started_at,
+                };
 
                 // Log the action asynchronously
-                actix_web::rt::spawn(async move {
-                    if let Err(e) = UserLog::create(&pool, user_uuid, &action, &path).await {
-                        eprintln!("Failed to log user action: {:?}", e);
+                actix_web::rt::spawn(
+                    async move {
+                        if let Err(e) = UserLog::create(&pool, &entry).await {
+                            log::error!("Failed to log user action: {:?}", e);
+                        }
                     }
-                });
+                    .in_current_span(),
+                );
             }
 
             Ok(res)
@@ -100,6 +143,12 @@ where
 /// ```
 pub struct Logger;
 
+impl Default for Logger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Logger {
     /// Creates a new Logger middleware instance.
     /// Initializes without any special configuration.
@@ -8,27 +8,31 @@
 //! The middleware stack consists of:
 //! - Authentication & Authorization (auth)
 //! - Activity Logging (logger)
+//! - Request IDs and request log spans (request_id)
 //!
 //! # Features
 //! - JWT-based authentication
-//! - Role-based access control
+//! - Permission-based access control
 //! - Async request logging
 //! - User activity tracking
+//! - Request IDs on every log record
 //!
 //! # Usage
 //! ```rust
 //! use actix_web::App;
-//! use crate::middleware::{Auth, Logger};
+//! use crate::middleware::{Auth, Logger, RequestId};
 //!
 //! fn configure_app(app: App) -> App {
-//!     app.wrap(Auth::new().role("admin"))
+//!     app.wrap(Auth::new().permission("users:manage"))
 //!        .wrap(Logger::new())
+//!        .wrap(RequestId::new())
 //! }
 //! ```
 //!
 //! # Order of Execution
-//! 1. Authentication middleware validates requests
-//! 2. Logger middleware tracks successful requests
+//! 1. Request ID middleware assigns the request ID and opens its log span
+//! 2. Authentication middleware validates requests
+//! 3. Logger middleware tracks successful requests
 //!
 //! # Module Structure
 
@@ -36,7 +40,10 @@
 mod auth;
 /// Request logging and activity tracking middleware
 mod logger;
+/// Request ID assignment and propagation middleware
+mod request_id;
 
 // Public exports for application use
 pub use auth::Auth;
 pub use logger::Logger;
+pub use request_id::{AssignedRequestId, RequestId};
@@ -0,0 +1,169 @@
+use actix_web::{
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    error::InternalError,
+    http::header::{HeaderName, HeaderValue},
+    Error, HttpMessage,
+};
+use futures_util::future::LocalBoxFuture;
+use std::{
+    future::{ready, Ready},
+    rc::Rc,
+};
+use tracing::Instrument;
+use uuid::Uuid;
+
+/// Header carrying the request ID.
+const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// ID of the current request, stored in the request extensions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssignedRequestId(pub String);
+
+/// Takes the request ID set by a reverse proxy, or generates one.
+///
+/// IDs from clients are only accepted if they are short and plain, so
+/// they cannot inject anything into logs.
+fn request_id(req: &ServiceRequest) -> String {
+    req.headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .filter(|id| {
+            !id.is_empty()
+                && id.len() <= 128
+                && id
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        })
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().to_string())
+}
+
+/// Request ID middleware service implementation.
+///
+/// Assigns every request an ID and runs the request in a `tracing` span
+/// carrying it, so every log record of the request can be tied to it.
+///
+/// # Usage
+/// ```rust
+/// app.wrap(RequestId::new())
+/// ```
+pub struct RequestIdMiddleware<S> {
+    /// Inner service being wrapped
+    service: Rc<S>,
+}
+
+/// Service implementation for RequestIdMiddleware.
+///
+/// # Type Parameters
+/// * `S` - The wrapped service type
+/// * `B` - The response body type
+impl<S, B> Service<ServiceRequest> for RequestIdMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    // Delegate readiness check to the inner service
+    forward_ready!(service);
+
+    /// Processes requests within their request span.
+    ///
+    /// # Flow
+    /// 1. Takes the `X-Request-ID` of the request, or generates one
+    /// 2. Stores it in the request extensions for other middleware
+    /// 3. Forwards the request within a `request` span carrying the ID,
+    ///    method and path
+    /// 4. Returns the request ID in `X-Request-ID`, also in the responses of
+    ///    errors of inner middleware
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let srv = self.service.clone();
+        let request_id = request_id(&req);
+        let span = tracing::info_span!(
+            "request",
+            request_id = %request_id,
+            method = %req.method(),
+            path = %req.path(),
+        );
+        req.extensions_mut()
+            .insert(AssignedRequestId(request_id.clone()));
+
+        Box::pin(
+            async move {
+                let header = HeaderValue::from_str(&request_id).ok();
+                match srv.call(req).await {
+                    Ok(mut res) => {
+                        if let Some(value) = header {
+                            res.headers_mut()
+                                .insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+                        }
+                        Ok(res)
+                    }
+                    // The error becomes its response once it leaves the
+                    // middleware, so build the response now to add the ID
+                    Err(e) => {
+                        let mut res = e.error_response();
+                        if let Some(value) = header {
+                            res.headers_mut()
+                                .insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+                        }
+                        Err(InternalError::from_response(e, res).into())
+                    }
+                }
+            }
+            .instrument(span),
+        )
+    }
+}
+
+/// Request ID middleware factory.
+///
+/// Wrap it outside of every other middleware, so their log records carry
+/// the request ID too.
+///
+/// # Example
+/// ```rust
+/// app.wrap(Logger::new()).wrap(RequestId::new());
+/// ```
+pub struct RequestId;
+
+impl Default for RequestId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RequestId {
+    /// Creates a new RequestId middleware instance.
+    pub fn new() -> Self {
+        RequestId
+    }
+}
+
+/// Transform implementation for RequestId middleware.
+///
+/// # Type Parameters
+/// * `S` - The service type being transformed
+/// * `B` - The response body type
+impl<S, B> Transform<S, ServiceRequest> for RequestId
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RequestIdMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    /// Creates a new RequestIdMiddleware instance wrapping the provided service.
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequestIdMiddleware {
+            service: Rc::new(service),
+        }))
+    }
+}
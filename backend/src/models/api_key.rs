@@ -0,0 +1,276 @@
+//! API Keys
+//!
+//! Long-lived credentials for scripts and service accounts that cannot go
+//! through the interactive login:
+//!
+//! # Usage
+//! Keys are sent as `Authorization: ApiKey <key>` instead of a Bearer JWT.
+//! They act on behalf of the user that created them and are only accepted
+//! by route groups that require a permission.
+//!
+//! # Scopes
+//! Scopes are permissions, see `API_KEY_SCOPES`. A request is only allowed
+//! if both the key's scopes and the owner's role grant the permission.
+
+use crate::auth::{generate_token, hash_token};
+use crate::models::auth::ApiKeyClaims;
+use crate::models::user::UserError;
+use chrono::{DateTime, Utc};
+use deadpool_postgres::Pool;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::Row;
+use uuid::Uuid;
+
+/// Permissions that can be granted to API keys.
+///
+/// User and role management are left to interactive logins.
+pub const API_KEY_SCOPES: [&str; 9] = [
+    "tickets:read",
+    "tickets:write",
+    "email:read",
+    "email:write",
+    "email:send",
+    "email:delete",
+    "customers:read",
+    "nctns:read",
+    "whois:lookup",
+];
+
+/// Prefix of every key, so leaked keys are easy to spot.
+const KEY_PREFIX: &str = "ah_";
+
+/// Number of characters of a key kept in clear to tell keys apart.
+const DISPLAY_PREFIX_LEN: usize = 11;
+
+/// Checks that a list of scopes can be granted.
+///
+/// # Arguments
+/// * `scopes` - Requested scopes
+///
+/// # Returns
+/// * `Result<(), UserError>` - Validation error for empty lists and unknown scopes
+pub fn validate_scopes(scopes: &[String]) -> Result<(), UserError> {
+    if scopes.is_empty() {
+        return Err(UserError::Validation(
+            "At least one scope is required".into(),
+        ));
+    }
+
+    match scopes
+        .iter()
+        .find(|scope| !API_KEY_SCOPES.contains(&scope.as_str()))
+    {
+        Some(scope) => Err(UserError::Validation(format!(
+            "Unknown scope {}, expected one of {}",
+            scope,
+            API_KEY_SCOPES.join(", ")
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// API key metadata.
+///
+/// The key itself is only returned once on creation, only its SHA-256
+/// hash is stored.
+///
+/// # Fields
+/// * `uuid` - Key identifier
+/// * `user_uuid` - Owner the key acts on behalf of
+/// * `name` - Description, e.g. the script using the key
+/// * `prefix` - Leading characters of the key
+/// * `scopes` - Granted scopes
+/// * `expires_at` - Expiry timestamp, if the key expires
+/// * `last_used_at` - When the key last authenticated a request
+/// * `revoked_at` - When the key was revoked, if it is
+/// * `created_at` - Creation timestamp
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ApiKey {
+    pub uuid: Uuid,
+    pub user_uuid: Uuid,
+    pub name: String,
+    pub prefix: String,
+    pub scopes: Vec<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<Row> for ApiKey {
+    fn from(row: Row) -> Self {
+        Self {
+            uuid: row.get("uuid"),
+            user_uuid: row.get("user_uuid"),
+            name: row.get("name"),
+            prefix: row.get("prefix"),
+            scopes: row.get("scopes"),
+            expires_at: row.get("expires_at"),
+            last_used_at: row.get("last_used_at"),
+            revoked_at: row.get("revoked_at"),
+            created_at: row.get("created_at"),
+        }
+    }
+}
+
+/// Newly created API key.
+///
+/// # Fields
+/// * `key` - Key to send in the `Authorization` header, shown only once
+/// * `api_key` - Key metadata
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreatedApiKey {
+    pub key: String,
+    #[serde(flatten)]
+    pub api_key: ApiKey,
+}
+
+impl ApiKey {
+    /// Creates a key for a user.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    /// * `user_uuid` - Owner of the key
+    /// * `name` - Description of the key
+    /// * `scopes` - Scopes to grant
+    /// * `expires_at` - Optional expiry, must be in the future
+    ///
+    /// # Returns
+    /// * `Result<CreatedApiKey, UserError>` - Key and metadata, or validation error
+    pub async fn create(
+        pool: &Pool,
+        user_uuid: Uuid,
+        name: &str,
+        scopes: &[String],
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<CreatedApiKey, UserError> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(UserError::Validation("Name must not be empty".into()));
+        }
+        validate_scopes(scopes)?;
+        if expires_at.is_some_and(|expires_at| expires_at <= Utc::now()) {
+            return Err(UserError::Validation("Expiry must be in the future".into()));
+        }
+
+        let mut scopes = scopes.to_vec();
+        scopes.sort();
+        scopes.dedup();
+
+        let key = format!("{}{}", KEY_PREFIX, generate_token(32));
+        let client = pool.get().await?;
+        let row = client
+            .query_one(
+                "INSERT INTO api_keys (uuid, user_uuid, name, prefix, key_hash, scopes, expires_at)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7)
+                 RETURNING *",
+                &[
+                    &Uuid::new_v4(),
+                    &user_uuid,
+                    &name,
+                    &&key[..DISPLAY_PREFIX_LEN],
+                    &hash_token(&key),
+                    &scopes,
+                    &expires_at,
+                ],
+            )
+            .await?;
+
+        Ok(CreatedApiKey {
+            key,
+            api_key: row.into(),
+        })
+    }
+
+    /// Lists a user's keys, newest first, including revoked and expired ones.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    /// * `user_uuid` - Owner of the keys
+    ///
+    /// # Returns
+    /// * `Result<Vec<ApiKey>, UserError>` - Keys or error
+    pub async fn list(pool: &Pool, user_uuid: Uuid) -> Result<Vec<Self>, UserError> {
+        let client = pool.get().await?;
+        let rows = client
+            .query(
+                "SELECT * FROM api_keys WHERE user_uuid = $1 ORDER BY created_at DESC",
+                &[&user_uuid],
+            )
+            .await?;
+
+        Ok(rows.into_iter().map(Self::from).collect())
+    }
+
+    /// Revokes one of a user's keys.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    /// * `user_uuid` - Owner of the key
+    /// * `key_uuid` - Key identifier
+    ///
+    /// # Returns
+    /// * `Result<(), UserError>` - Success, or `NotFound` for unknown or already revoked keys
+    pub async fn revoke(pool: &Pool, user_uuid: Uuid, key_uuid: Uuid) -> Result<(), UserError> {
+        let client = pool.get().await?;
+        let updated = client
+            .execute(
+                "UPDATE api_keys SET revoked_at = NOW()
+                 WHERE uuid = $1 AND user_uuid = $2 AND revoked_at IS NULL",
+                &[&key_uuid, &user_uuid],
+            )
+            .await?;
+
+        if updated == 0 {
+            return Err(UserError::NotFound(format!(
+                "API key {} not found",
+                key_uuid
+            )));
+        }
+        Ok(())
+    }
+
+    /// Looks up a key presented with a request and records its use.
+    ///
+    /// Revoked and expired keys, and keys of disabled or deleted users,
+    /// are not accepted.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    /// * `key` - Key from the `Authorization` header
+    ///
+    /// # Returns
+    /// * `Result<Option<(ApiKeyClaims, String)>, UserError>` - Key claims and
+    ///   the owner's role, or `None` if the key is not accepted
+    pub async fn authenticate(
+        pool: &Pool,
+        key: &str,
+    ) -> Result<Option<(ApiKeyClaims, String)>, UserError> {
+        let client = pool.get().await?;
+        let row = client
+            .query_opt(
+                "UPDATE api_keys k SET last_used_at = NOW()
+                 FROM users u
+                 WHERE k.key_hash = $1
+                   AND k.revoked_at IS NULL
+                   AND (k.expires_at IS NULL OR k.expires_at > NOW())
+                   AND u.uuid = k.user_uuid
+                   AND u.disabled_at IS NULL
+                 RETURNING k.uuid, k.user_uuid, k.scopes, k.expires_at, u.role",
+                &[&hash_token(key)],
+            )
+            .await?;
+
+        Ok(row.map(|row| {
+            (
+                ApiKeyClaims {
+                    key_uuid: row.get("uuid"),
+                    owner: row.get("user_uuid"),
+                    scopes: row.get("scopes"),
+                    expires_at: row.get("expires_at"),
+                },
+                row.get("role"),
+            )
+        }))
+    }
+}
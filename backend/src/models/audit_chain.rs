@@ -0,0 +1,617 @@
+//! Audit Hash Chain
+//!
+//! Makes the audit trails tamper-evident for legal holds:
+//!
+//! # Chaining
+//! Records of `user_logs` and `ticket_history` are sealed shortly after
+//! they are written: each record gets the next sequence number of its
+//! trail, the hash of the previous record and its own hash, computed as
+//! `SHA-256(previous hash || "\n" || canonical record)`. Changing, deleting
+//! or reordering a sealed record breaks the chain from that record on.
+//!
+//! # Checkpoints
+//! Checkpoints record the sequence number and hash of a trail's head,
+//! signed with the active JWT signing key as a compact JWS. They prove that
+//! records up to the checkpoint existed unchanged, even if the end of the
+//! chain is cut off. They are only created with keys from `JWT_KEYS_DIR`,
+//! an ephemeral key would make them unverifiable after a restart.
+//!
+//! The public key of every key that signed a checkpoint is kept in
+//! `audit_signing_keys`. Checkpoints are verified and exported with these
+//! keys, so they stay verifiable after the key is rotated out.
+
+use crate::models::user::UserError;
+use crate::signing::{self, SigningKeys};
+use chrono::{DateTime, SecondsFormat, Utc};
+use deadpool_postgres::Pool;
+use jsonwebtoken::jwk::{Jwk, JwkSet};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use tokio_postgres::Row;
+use uuid::Uuid;
+
+/// Previous hash of the first record of a trail.
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Number of records sealed or verified per query.
+const BATCH_SIZE: i64 = 1000;
+
+/// Audit trails protected by a hash chain.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditTrail {
+    /// User activity log
+    UserLogs,
+    /// Ticket lifecycle events
+    TicketHistory,
+}
+
+impl std::fmt::Display for AuditTrail {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.table())
+    }
+}
+
+impl AuditTrail {
+    /// Every trail.
+    pub const ALL: [AuditTrail; 2] = [AuditTrail::UserLogs, AuditTrail::TicketHistory];
+
+    /// Table of the trail.
+    fn table(&self) -> &'static str {
+        match self {
+            AuditTrail::UserLogs => "user_logs",
+            AuditTrail::TicketHistory => "ticket_history",
+        }
+    }
+
+    /// Primary key column.
+    fn id_column(&self) -> &'static str {
+        match self {
+            AuditTrail::UserLogs => "uuid",
+            AuditTrail::TicketHistory => "id",
+        }
+    }
+
+    /// Column records are sealed in order of.
+    fn time_column(&self) -> &'static str {
+        match self {
+            AuditTrail::UserLogs => "timestamp",
+            AuditTrail::TicketHistory => "created_at",
+        }
+    }
+
+    /// Advisory lock serializing the sealing of the trail.
+    fn lock_key(&self) -> i64 {
+        match self {
+            AuditTrail::UserLogs => 0x6175_6469_7401,
+            AuditTrail::TicketHistory => 0x6175_6469_7402,
+        }
+    }
+
+    /// Canonical form of a record, the hashed content.
+    ///
+    /// Covers every column except the chain columns, in a fixed order.
+    fn canonical(&self, row: &Row) -> String {
+        let time = |column: &str| {
+            row.get::<_, Option<DateTime<Utc>>>(column)
+                .map(|t| t.to_rfc3339_opts(SecondsFormat::Micros, true))
+        };
+        let record = match self {
+            AuditTrail::UserLogs => json!([
+                row.get::<_, Uuid>("uuid"),
+                row.get::<_, Option<Uuid>>("user_uuid"),
+                row.get::<_, String>("action"),
+                row.get::<_, String>("route"),
+                row.get::<_, Option<String>>("method"),
+                row.get::<_, Option<String>>("route_pattern"),
+                row.get::<_, Option<i16>>("status_code"),
+                row.get::<_, Value>("entities"),
+                row.get::<_, Option<String>>("ip_address"),
+                row.get::<_, Option<String>>("user_agent"),
+                row.get::<_, Option<i32>>("latency_ms"),
+                row.get::<_, Option<String>>("request_id"),
+                time("timestamp"),
+            ]),
+            AuditTrail::TicketHistory => json!([
+                row.get::<_, Uuid>("id"),
+                row.get::<_, Uuid>("ticket_id"),
+                row.get::<_, String>("event"),
+                row.get::<_, Option<String>>("from_status"),
+                row.get::<_, Option<String>>("to_status"),
+                row.get::<_, Option<Uuid>>("actor"),
+                row.get::<_, Option<String>>("details"),
+                time("created_at"),
+            ]),
+        };
+        record.to_string()
+    }
+}
+
+/// Hash of a record chained to its predecessor.
+///
+/// # Arguments
+/// * `prev_hash` - Hash of the previous record, `GENESIS_HASH` for the first
+/// * `canonical` - Canonical form of the record
+///
+/// # Returns
+/// * `String` - Hex encoded SHA-256 hash
+pub fn chain_hash(prev_hash: &str, canonical: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(prev_hash.as_bytes());
+    hasher.update(b"\n");
+    hasher.update(canonical.as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+/// First record where a chain does not verify.
+///
+/// # Fields
+/// * `seq` - Sequence number at which the chain breaks
+/// * `record_id` - Record at that position, if it exists
+/// * `reason` - What does not match
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BrokenLink {
+    pub seq: i64,
+    pub record_id: Option<Uuid>,
+    pub reason: String,
+}
+
+/// Result of verifying a trail.
+///
+/// # Fields
+/// * `trail` - Verified trail
+/// * `valid` - Whether the chain and every checkpoint verify, false if a
+///   checkpoint could not be checked
+/// * `checked` - Number of sealed records checked
+/// * `unsealed` - Number of records not sealed yet
+/// * `head_seq` - Sequence number of the last sealed record
+/// * `head_hash` - Hash of the last sealed record
+/// * `checkpoints` - Number of checkpoints checked
+/// * `unverifiable_checkpoints` - Checkpoints signed with unknown keys,
+///   whose signatures could not be checked
+/// * `first_broken` - First broken link, if any
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChainVerification {
+    pub trail: AuditTrail,
+    pub valid: bool,
+    pub checked: i64,
+    pub unsealed: i64,
+    pub head_seq: Option<i64>,
+    pub head_hash: Option<String>,
+    pub checkpoints: i64,
+    pub unverifiable_checkpoints: i64,
+    pub first_broken: Option<BrokenLink>,
+}
+
+/// Signed content of a checkpoint.
+///
+/// # Fields
+/// * `trail` - Checkpointed trail
+/// * `seq` - Sequence number of the trail's head
+/// * `hash` - Hash of the trail's head
+/// * `iat` - Creation time, in seconds since the epoch
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct CheckpointClaims {
+    pub trail: AuditTrail,
+    pub seq: i64,
+    pub hash: String,
+    pub iat: i64,
+}
+
+/// Signed head of a trail.
+///
+/// # Fields
+/// * `uuid` - Checkpoint identifier
+/// * `trail` - Checkpointed trail
+/// * `seq` - Sequence number of the trail's head
+/// * `hash` - Hash of the trail's head
+/// * `kid` - Signing key
+/// * `signature` - Compact JWS of the `CheckpointClaims`
+/// * `created_at` - Creation timestamp
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AuditCheckpoint {
+    pub uuid: Uuid,
+    pub trail: AuditTrail,
+    pub seq: i64,
+    pub hash: String,
+    pub kid: String,
+    pub signature: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<Row> for AuditCheckpoint {
+    fn from(row: Row) -> Self {
+        let trail: String = row.get("trail");
+        Self {
+            uuid: row.get("uuid"),
+            trail: serde_json::from_value(Value::String(trail)).unwrap_or(AuditTrail::UserLogs),
+            seq: row.get("seq"),
+            hash: row.get("hash"),
+            kid: row.get("kid"),
+            signature: row.get("signature"),
+            created_at: row.get("created_at"),
+        }
+    }
+}
+
+impl AuditCheckpoint {
+    /// Checks the signature of the checkpoint against its fields.
+    ///
+    /// # Arguments
+    /// * `keys` - Public keys of the checkpoint signing keys by `kid`
+    ///
+    /// # Returns
+    /// * `Result<bool, String>` - Whether the signing key is known, or why
+    ///   the signature does not match
+    fn check_signature(&self, keys: &HashMap<String, Jwk>) -> Result<bool, String> {
+        let Some(jwk) = keys.get(&self.kid) else {
+            return Ok(false);
+        };
+
+        let claims: CheckpointClaims = signing::verify_document_with(jwk, &self.signature)
+            .map_err(|e| format!("Invalid signature of checkpoint {}: {}", self.uuid, e))?;
+        if claims.trail != self.trail || claims.seq != self.seq || claims.hash != self.hash {
+            return Err(format!(
+                "Checkpoint {} does not match its signature",
+                self.uuid
+            ));
+        }
+        Ok(true)
+    }
+}
+
+/// Sealing, verification and checkpoints of the audit trails.
+pub struct AuditChain;
+
+impl AuditChain {
+    /// Seals the unsealed records of a trail, oldest first.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    /// * `trail` - Trail to seal
+    ///
+    /// # Returns
+    /// * `Result<u64, UserError>` - Number of sealed records or error
+    pub async fn seal(pool: &Pool, trail: AuditTrail) -> Result<u64, UserError> {
+        let mut sealed = 0;
+        loop {
+            let mut client = pool.get().await?;
+            let tx = client.transaction().await?;
+            tx.execute("SELECT pg_advisory_xact_lock($1)", &[&trail.lock_key()])
+                .await?;
+
+            let head = tx
+                .query_opt(
+                    &format!(
+                        "SELECT seq, hash FROM {} WHERE seq IS NOT NULL ORDER BY seq DESC LIMIT 1",
+                        trail.table()
+                    ),
+                    &[],
+                )
+                .await?;
+            let (mut seq, mut prev_hash) = match head {
+                Some(row) => (row.get::<_, i64>("seq"), row.get::<_, String>("hash")),
+                None => (0, GENESIS_HASH.to_string()),
+            };
+
+            let rows = tx
+                .query(
+                    &format!(
+                        "SELECT * FROM {table} WHERE seq IS NULL ORDER BY {time}, {id} LIMIT $1",
+                        table = trail.table(),
+                        time = trail.time_column(),
+                        id = trail.id_column()
+                    ),
+                    &[&BATCH_SIZE],
+                )
+                .await?;
+            let done = (rows.len() as i64) < BATCH_SIZE;
+
+            let update = format!(
+                "UPDATE {} SET seq = $2, prev_hash = $3, hash = $4 WHERE {} = $1",
+                trail.table(),
+                trail.id_column()
+            );
+            for row in &rows {
+                seq += 1;
+                let hash = chain_hash(&prev_hash, &trail.canonical(row));
+                let id: Uuid = row.get(trail.id_column());
+                tx.execute(&update, &[&id, &seq, &prev_hash, &hash]).await?;
+                prev_hash = hash;
+            }
+            tx.commit().await?;
+
+            sealed += rows.len() as u64;
+            if done {
+                return Ok(sealed);
+            }
+        }
+    }
+
+    /// Walks the chain of a trail and checks its checkpoints.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    /// * `trail` - Trail to verify
+    ///
+    /// # Returns
+    /// * `Result<ChainVerification, UserError>` - Verification result, with
+    ///   the first broken link if the chain does not verify
+    pub async fn verify(pool: &Pool, trail: AuditTrail) -> Result<ChainVerification, UserError> {
+        let checkpoints = Self::list_checkpoints(pool, Some(trail)).await?;
+        let keys: HashMap<String, Jwk> = Self::signing_keys(pool)
+            .await?
+            .keys
+            .into_iter()
+            .filter_map(|jwk| Some((jwk.common.key_id.clone()?, jwk)))
+            .collect();
+        let checkpoint_seqs: Vec<i64> = checkpoints.iter().map(|c| c.seq).collect();
+
+        let client = pool.get().await?;
+        let unsealed: i64 = client
+            .query_one(
+                &format!("SELECT COUNT(*) FROM {} WHERE seq IS NULL", trail.table()),
+                &[],
+            )
+            .await?
+            .get(0);
+
+        // Walk the chain, remembering the hashes at checkpointed positions
+        let mut result = ChainVerification {
+            trail,
+            valid: true,
+            checked: 0,
+            unsealed,
+            head_seq: None,
+            head_hash: None,
+            checkpoints: checkpoints.len() as i64,
+            unverifiable_checkpoints: 0,
+            first_broken: None,
+        };
+        let mut hashes_at: HashMap<i64, String> = HashMap::new();
+        let mut prev_hash = GENESIS_HASH.to_string();
+        let mut expected_seq = 1;
+        let query = format!(
+            "SELECT * FROM {} WHERE seq >= $1 ORDER BY seq LIMIT $2",
+            trail.table()
+        );
+
+        'walk: loop {
+            let rows = client.query(&query, &[&expected_seq, &BATCH_SIZE]).await?;
+            let done = (rows.len() as i64) < BATCH_SIZE;
+
+            for row in &rows {
+                let seq: i64 = row.get("seq");
+                let record_id: Uuid = row.get(trail.id_column());
+                let stored_prev: Option<String> = row.get("prev_hash");
+                let stored_hash: Option<String> = row.get("hash");
+
+                let reason = if seq != expected_seq {
+                    Some(format!(
+                        "Records {} to {} are missing",
+                        expected_seq,
+                        seq - 1
+                    ))
+                } else if stored_prev.as_deref() != Some(prev_hash.as_str()) {
+                    Some("Previous hash does not match the previous record".to_string())
+                } else if stored_hash.as_deref()
+                    != Some(chain_hash(&prev_hash, &trail.canonical(row)).as_str())
+                {
+                    Some("Record was altered after it was sealed".to_string())
+                } else {
+                    None
+                };
+                if let Some(reason) = reason {
+                    result.first_broken = Some(BrokenLink {
+                        seq: expected_seq,
+                        record_id: (seq == expected_seq).then_some(record_id),
+                        reason,
+                    });
+                    break 'walk;
+                }
+
+                prev_hash = stored_hash.unwrap_or_default();
+                if checkpoint_seqs.contains(&seq) {
+                    hashes_at.insert(seq, prev_hash.clone());
+                }
+                result.checked += 1;
+                result.head_seq = Some(seq);
+                expected_seq += 1;
+            }
+
+            if done {
+                break;
+            }
+        }
+        result.head_hash = result.head_seq.map(|_| prev_hash.clone());
+
+        // Checkpoints must be signed and match the chain
+        for checkpoint in &checkpoints {
+            if result.first_broken.is_some() {
+                break;
+            }
+            let broken = match checkpoint.check_signature(&keys) {
+                Ok(false) => {
+                    result.unverifiable_checkpoints += 1;
+                    None
+                }
+                Err(reason) => Some(reason),
+                Ok(true) => None,
+            }
+            .or_else(|| match hashes_at.get(&checkpoint.seq) {
+                Some(hash) if *hash == checkpoint.hash => None,
+                Some(_) => Some(format!(
+                    "Record does not match checkpoint {}",
+                    checkpoint.uuid
+                )),
+                None => Some(format!(
+                    "Chain ends before checkpoint {}, records were removed",
+                    checkpoint.uuid
+                )),
+            });
+            if let Some(reason) = broken {
+                result.first_broken = Some(BrokenLink {
+                    seq: checkpoint.seq,
+                    record_id: None,
+                    reason,
+                });
+            }
+        }
+
+        result.valid = result.first_broken.is_none() && result.unverifiable_checkpoints == 0;
+        Ok(result)
+    }
+
+    /// Seals a trail and signs a checkpoint of its head.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    /// * `trail` - Trail to checkpoint
+    ///
+    /// # Returns
+    /// * `Result<Option<AuditCheckpoint>, UserError>` - Checkpoint, or None
+    ///   if the trail is empty or its head is already checkpointed; a
+    ///   validation error without persistent signing keys
+    pub async fn checkpoint(
+        pool: &Pool,
+        trail: AuditTrail,
+    ) -> Result<Option<AuditCheckpoint>, UserError> {
+        let keys = SigningKeys::global();
+        if !keys.is_persistent() {
+            return Err(UserError::Validation(
+                "Checkpoints require persistent signing keys, set JWT_KEYS_DIR".into(),
+            ));
+        }
+
+        Self::seal(pool, trail).await?;
+
+        let client = pool.get().await?;
+        let Some(head) = client
+            .query_opt(
+                &format!(
+                    "SELECT seq, hash FROM {} WHERE seq IS NOT NULL ORDER BY seq DESC LIMIT 1",
+                    trail.table()
+                ),
+                &[],
+            )
+            .await?
+        else {
+            return Ok(None);
+        };
+        let seq: i64 = head.get("seq");
+        let hash: String = head.get("hash");
+
+        let checkpointed = client
+            .query_opt(
+                "SELECT 1 FROM audit_checkpoints WHERE trail = $1 AND seq = $2",
+                &[&trail.table(), &seq],
+            )
+            .await?;
+        if checkpointed.is_some() {
+            return Ok(None);
+        }
+
+        // Keep the public key so the checkpoint verifies after rotation
+        let jwk = serde_json::to_value(keys.active_jwk())
+            .map_err(|e| UserError::Validation(format!("Invalid signing key: {}", e)))?;
+        client
+            .execute(
+                "INSERT INTO audit_signing_keys (kid, jwk) VALUES ($1, $2) ON CONFLICT (kid) DO NOTHING",
+                &[&keys.active_kid(), &jwk],
+            )
+            .await?;
+        let recorded: Value = client
+            .query_one(
+                "SELECT jwk FROM audit_signing_keys WHERE kid = $1",
+                &[&keys.active_kid()],
+            )
+            .await?
+            .get("jwk");
+        if recorded != jwk {
+            return Err(UserError::Validation(format!(
+                "Signing key {} differs from the key of earlier checkpoints with this id",
+                keys.active_kid()
+            )));
+        }
+
+        let created_at = Utc::now();
+        let signature = keys
+            .sign(&CheckpointClaims {
+                trail,
+                seq,
+                hash: hash.clone(),
+                iat: created_at.timestamp(),
+            })
+            .map_err(|e| UserError::Validation(format!("Failed to sign checkpoint: {}", e)))?;
+
+        let row = client
+            .query_one(
+                "INSERT INTO audit_checkpoints (uuid, trail, seq, hash, kid, signature, created_at)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7)
+                 RETURNING *",
+                &[
+                    &Uuid::new_v4(),
+                    &trail.table(),
+                    &seq,
+                    &hash,
+                    &keys.active_kid(),
+                    &signature,
+                    &created_at,
+                ],
+            )
+            .await?;
+
+        Ok(Some(row.into()))
+    }
+
+    /// Lists checkpoints, oldest first.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    /// * `trail` - Only list checkpoints of this trail
+    ///
+    /// # Returns
+    /// * `Result<Vec<AuditCheckpoint>, UserError>` - Checkpoints or error
+    pub async fn list_checkpoints(
+        pool: &Pool,
+        trail: Option<AuditTrail>,
+    ) -> Result<Vec<AuditCheckpoint>, UserError> {
+        let client = pool.get().await?;
+        let rows = client
+            .query(
+                "SELECT * FROM audit_checkpoints
+                 WHERE ($1::TEXT IS NULL OR trail = $1)
+                 ORDER BY created_at, seq",
+                &[&trail.map(|t| t.table())],
+            )
+            .await?;
+
+        Ok(rows.into_iter().map(AuditCheckpoint::from).collect())
+    }
+
+    /// Public keys of every key that signed a checkpoint.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    ///
+    /// # Returns
+    /// * `Result<JwkSet, UserError>` - Keys, including rotated out ones, or error
+    pub async fn signing_keys(pool: &Pool) -> Result<JwkSet, UserError> {
+        let client = pool.get().await?;
+        let rows = client
+            .query(
+                "SELECT jwk FROM audit_signing_keys ORDER BY created_at, kid",
+                &[],
+            )
+            .await?;
+
+        let keys = rows
+            .into_iter()
+            .map(|row| serde_json::from_value(row.get("jwk")))
+            .collect::<Result<Vec<Jwk>, _>>()
+            .map_err(|e| UserError::Validation(format!("Invalid audit signing key: {}", e)))?;
+        Ok(JwkSet { keys })
+    }
+}
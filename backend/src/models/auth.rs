@@ -1,3 +1,7 @@
+use crate::models::session::Session;
+use crate::models::user::User;
+use actix_web::HttpRequest;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
@@ -11,23 +15,106 @@ use uuid::Uuid;
 /// * `role` - User's role for authorization
 /// * `exp` - Token expiration timestamp
 /// * `token_type` - Distinguishes between access and refresh tokens
+/// * `sid` - Refresh token family the token was issued for, if any
+/// * `jti` - Unique token id, used to revoke the token
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Claims {
     pub sub: Uuid,
     pub role: String,
     pub exp: usize,
     pub token_type: TokenType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<Uuid>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jti: Option<Uuid>,
+}
+
+/// API key claims, stored in request extensions next to `Claims`.
+///
+/// Only present for requests authenticated with an API key, in which case
+/// `Claims` describe the key's owner.
+///
+/// # Fields
+/// * `key_uuid` - Key identifier
+/// * `owner` - User the key acts on behalf of
+/// * `scopes` - Granted permissions
+/// * `expires_at` - Key expiry, if it expires
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ApiKeyClaims {
+    pub key_uuid: Uuid,
+    pub owner: Uuid,
+    pub scopes: Vec<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl ApiKeyClaims {
+    /// Checks whether the key was granted a permission.
+    ///
+    /// # Arguments
+    /// * `permission` - Permission required by the route group
+    ///
+    /// # Returns
+    /// * `bool` - True if the permission is among the key's scopes
+    pub fn allows(&self, permission: &str) -> bool {
+        self.scopes.iter().any(|scope| scope == permission)
+    }
+}
+
+/// Where a request came from, recorded with logins and sessions.
+///
+/// The IP address is the peer address, or the client address from the
+/// `Forwarded` / `X-Forwarded-For` headers if `TRUST_PROXY_HEADERS=true`
+/// because the API runs behind a reverse proxy.
+///
+/// # Fields
+/// * `ip_address` - Client IP address
+/// * `user_agent` - `User-Agent` header
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ClientInfo {
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+impl ClientInfo {
+    /// Extracts the client information of a request.
+    ///
+    /// # Arguments
+    /// * `req` - Incoming request
+    ///
+    /// # Returns
+    /// * `ClientInfo` - IP address and user agent, if known
+    pub fn from_request(req: &HttpRequest) -> Self {
+        let trust_proxy = std::env::var("TRUST_PROXY_HEADERS").is_ok_and(|v| v == "true");
+        let ip_address = if trust_proxy {
+            req.connection_info()
+                .realip_remote_addr()
+                .map(|addr| addr.to_string())
+        } else {
+            req.peer_addr().map(|addr| addr.ip().to_string())
+        };
+        let user_agent = req
+            .headers()
+            .get("User-Agent")
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.chars().take(512).collect());
+
+        Self {
+            ip_address,
+            user_agent,
+        }
+    }
 }
 
 /// Defines the types of JWT tokens supported by the system.
 ///
 /// Used to differentiate between access tokens (short-lived, for API access)
-/// and refresh tokens (long-lived, for obtaining new access tokens).
+/// and refresh tokens. Refresh tokens are now opaque and stored in the
+/// database, refresh JWTs issued before that are rejected.
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
 pub enum TokenType {
     /// Short-lived token for API access
     Access,
-    /// Long-lived token for refreshing access tokens
+    /// Legacy long-lived token for refreshing access tokens
     Refresh,
 }
 
@@ -69,7 +156,7 @@ pub struct ExchangeTokenRequest {
 pub struct TokenResponse {
     /// JWT access token for API authorization
     pub access_token: String,
-    /// Optional refresh token for obtaining new access tokens
+    /// Single-use refresh token for obtaining new tokens
     pub refresh_token: Option<String>,
     /// User profile information
     pub user: UserResponse,
@@ -89,6 +176,26 @@ pub struct UserResponse {
     pub name: String,
     /// User's role for authorization
     pub role: String,
+    /// Account creation timestamp
+    pub created_at: DateTime<Utc>,
+    /// When the account was disabled, if it is
+    pub disabled_at: Option<DateTime<Utc>>,
+    /// Whether two-factor authentication is enabled
+    pub totp_enabled: bool,
+}
+
+impl From<User> for UserResponse {
+    fn from(user: User) -> Self {
+        UserResponse {
+            uuid: user.uuid,
+            email: user.email,
+            name: user.name,
+            role: user.role,
+            created_at: user.created_at,
+            disabled_at: user.disabled_at,
+            totp_enabled: user.totp_enabled_at.is_some(),
+        }
+    }
 }
 
 /// New user creation request structure.
@@ -106,3 +213,179 @@ pub struct CreateUserRequest {
     /// Initial role assignment
     pub role: String,
 }
+
+/// User list query parameters.
+///
+/// Used by the admin user management endpoints.
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct UserQuery {
+    /// Only return users with this role
+    pub role: Option<String>,
+    /// Only return disabled (`true`) or active (`false`) users
+    pub disabled: Option<bool>,
+    /// Page size (default 50)
+    pub limit: Option<i64>,
+    /// Pagination offset
+    pub offset: Option<i64>,
+}
+
+/// User profile update request structure.
+///
+/// Omitted fields are left unchanged.
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct UpdateUserRequest {
+    /// New display name
+    pub name: Option<String>,
+    /// New email address
+    pub email: Option<String>,
+}
+
+/// Role assignment request structure.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateRoleRequest {
+    /// Role to assign
+    pub role: String,
+}
+
+/// Password change request structure.
+///
+/// Used by users to change their own password.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChangePasswordRequest {
+    /// Current password
+    pub current_password: String,
+    /// New password, checked against the password policy
+    pub new_password: String,
+}
+
+/// Password reset request structure.
+///
+/// Asks for a reset token to be emailed to the account.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ForgotPasswordRequest {
+    /// Email address of the account
+    pub email: String,
+}
+
+/// Password reset confirmation structure.
+///
+/// Sets a new password using an emailed reset token.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ResetPasswordRequest {
+    /// Token from the reset email
+    pub token: String,
+    /// New password, checked against the password policy
+    pub new_password: String,
+}
+
+/// Password reset response structure.
+///
+/// Returned when a reset token was emailed on behalf of a user.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PasswordResetResponse {
+    /// When the emailed token expires
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Second-factor login response structure.
+///
+/// Returned by the login endpoint instead of tokens when the account has
+/// two-factor authentication enabled.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MfaChallengeResponse {
+    /// Always true, distinguishes the response from a `TokenResponse`
+    pub mfa_required: bool,
+    /// Token to send back together with the code
+    pub mfa_token: String,
+    /// When the challenge expires
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Second-factor login request structure.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MfaLoginRequest {
+    /// Token from the login response
+    pub mfa_token: String,
+    /// Current TOTP code or a recovery code
+    pub code: String,
+}
+
+/// TOTP code request structure.
+///
+/// Used to confirm enrollment and for sensitive second-factor changes.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TotpCodeRequest {
+    /// Current TOTP code or a recovery code
+    pub code: String,
+}
+
+/// Recovery codes response structure.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecoveryCodesResponse {
+    /// Single-use recovery codes, shown only once
+    pub recovery_codes: Vec<String>,
+}
+
+/// Session response structure.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SessionResponse {
+    #[serde(flatten)]
+    pub session: Session,
+    /// Whether the request was made with a token of this session
+    pub current: bool,
+}
+
+/// Session revocation response structure.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RevokedSessionsResponse {
+    /// Number of sessions signed out
+    pub revoked: u64,
+}
+
+/// API key creation request structure.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateApiKeyRequest {
+    /// Description, e.g. the script using the key
+    pub name: String,
+    /// Permissions to grant, e.g. `tickets:read`
+    pub scopes: Vec<String>,
+    /// Optional expiry, keys without one stay valid until revoked
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// Role creation request structure.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateRoleRequest {
+    /// Unique role name
+    pub name: String,
+    /// What the role is for
+    #[serde(default)]
+    pub description: String,
+    /// Permissions to grant, e.g. `tickets:read`
+    pub permissions: Vec<String>,
+}
+
+/// Keycloak role mapping creation request structure.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateKeycloakMappingRequest {
+    /// Claim to match: `realm_role`, `client_role` or `group`
+    pub claim: String,
+    /// Keycloak role or group name
+    pub value: String,
+    /// Role to grant
+    pub role: String,
+    /// Precedence over other matching mappings
+    #[serde(default)]
+    pub priority: i32,
+}
+
+/// Role update request structure.
+///
+/// Omitted fields are left unchanged.
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct EditRoleRequest {
+    /// New description
+    pub description: Option<String>,
+    /// Permissions replacing the current ones
+    pub permissions: Option<Vec<String>>,
+}
@@ -0,0 +1,188 @@
+//! Authentication Cache
+//!
+//! Per-request authentication state kept in memory, so `check_auth` does
+//! not query Postgres on every request:
+//!
+//! # Entries
+//! - Current role of active users, None for disabled and deleted users
+//! - Whether sessions (refresh token families) were revoked
+//! - Permissions of roles
+//!
+//! # Invalidation
+//! Entries are dropped when this instance disables, deletes or changes the
+//! role of a user, revokes a session or changes a role. Entries also expire
+//! after `AUTH_CACHE_TTL_SECONDS` (default 30), so changes made by other
+//! instances are picked up within that time.
+
+use crate::models::refresh_token::RefreshToken;
+use crate::models::role::Role;
+use crate::models::user::{User, UserError};
+use deadpool_postgres::Pool;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::{OnceLock, RwLock};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// Cached values with their load time.
+struct Entries<K, V>(RwLock<HashMap<K, (V, Instant)>>);
+
+impl<K: Eq + Hash, V: Clone> Entries<K, V> {
+    fn new() -> Self {
+        Self(RwLock::new(HashMap::new()))
+    }
+
+    /// Returns a value loaded within the TTL.
+    fn get(&self, key: &K) -> Option<V> {
+        self.0
+            .read()
+            .expect("Auth cache lock poisoned")
+            .get(key)
+            .filter(|(_, loaded_at)| loaded_at.elapsed() < ttl())
+            .map(|(value, _)| value.clone())
+    }
+
+    fn insert(&self, key: K, value: V) {
+        let mut entries = self.0.write().expect("Auth cache lock poisoned");
+        // Drop expired entries, so the cache does not grow without bound
+        let ttl = ttl();
+        entries.retain(|_, (_, loaded_at)| loaded_at.elapsed() < ttl);
+        entries.insert(key, (value, Instant::now()));
+    }
+
+    fn remove(&self, key: &K) {
+        self.0
+            .write()
+            .expect("Auth cache lock poisoned")
+            .remove(key);
+    }
+
+    fn clear(&self) {
+        self.0.write().expect("Auth cache lock poisoned").clear();
+    }
+}
+
+/// How long entries are used before they are reloaded.
+fn ttl() -> Duration {
+    static TTL: OnceLock<Duration> = OnceLock::new();
+    *TTL.get_or_init(|| {
+        Duration::from_secs(
+            std::env::var("AUTH_CACHE_TTL_SECONDS")
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .unwrap_or(30),
+        )
+    })
+}
+
+fn users() -> &'static Entries<Uuid, Option<String>> {
+    static USERS: OnceLock<Entries<Uuid, Option<String>>> = OnceLock::new();
+    USERS.get_or_init(Entries::new)
+}
+
+fn sessions() -> &'static Entries<Uuid, bool> {
+    static SESSIONS: OnceLock<Entries<Uuid, bool>> = OnceLock::new();
+    SESSIONS.get_or_init(Entries::new)
+}
+
+fn roles() -> &'static Entries<String, Vec<String>> {
+    static ROLES: OnceLock<Entries<String, Vec<String>>> = OnceLock::new();
+    ROLES.get_or_init(Entries::new)
+}
+
+/// Cached authentication state.
+pub struct AuthCache;
+
+impl AuthCache {
+    /// Current role of an active user.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool, used on cache misses
+    /// * `user_uuid` - User identifier
+    ///
+    /// # Returns
+    /// * `Result<Option<String>, UserError>` - Role, or None for deleted and
+    ///   disabled users
+    pub async fn user_role(pool: &Pool, user_uuid: Uuid) -> Result<Option<String>, UserError> {
+        if let Some(role) = users().get(&user_uuid) {
+            return Ok(role);
+        }
+
+        let role = match User::get(pool, user_uuid).await {
+            Ok(user) if !user.is_disabled() => Some(user.role),
+            Ok(_) | Err(UserError::NotFound(_)) => None,
+            Err(e) => return Err(e),
+        };
+
+        users().insert(user_uuid, role.clone());
+        Ok(role)
+    }
+
+    /// Whether a session was not revoked.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool, used on cache misses
+    /// * `session` - Refresh token family identifier
+    ///
+    /// # Returns
+    /// * `Result<bool, UserError>` - True if the family has unrevoked tokens
+    pub async fn session_active(pool: &Pool, session: Uuid) -> Result<bool, UserError> {
+        if let Some(active) = sessions().get(&session) {
+            return Ok(active);
+        }
+
+        let active = RefreshToken::is_family_active(pool, session).await?;
+
+        sessions().insert(session, active);
+        Ok(active)
+    }
+
+    /// Whether a role grants a permission.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool, used on cache misses
+    /// * `role` - Role name
+    /// * `permission` - Required permission
+    ///
+    /// # Returns
+    /// * `Result<bool, UserError>` - True if the role exists and grants the permission
+    pub async fn role_grants(pool: &Pool, role: &str, permission: &str) -> Result<bool, UserError> {
+        let permissions = match roles().get(&role.to_string()) {
+            Some(permissions) => permissions,
+            None => {
+                // Unknown roles grant nothing
+                let permissions = match Role::get(pool, role).await {
+                    Ok(role) => role.permissions,
+                    Err(UserError::NotFound(_)) => Vec::new(),
+                    Err(e) => return Err(e),
+                };
+
+                roles().insert(role.to_string(), permissions.clone());
+                permissions
+            }
+        };
+
+        Ok(permissions.iter().any(|p| p == permission))
+    }
+
+    /// Drops the cached state of a user, after its role or account changed.
+    pub fn invalidate_user(user_uuid: Uuid) {
+        users().remove(&user_uuid);
+    }
+
+    /// Drops the cached state of a session, after it was revoked.
+    pub fn invalidate_session(session: Uuid) {
+        sessions().remove(&session);
+    }
+
+    /// Drops the cached state of every session, after sessions of a user
+    /// were revoked at once.
+    pub fn invalidate_sessions() {
+        sessions().clear();
+    }
+
+    /// Drops the cached permissions of a role, after it changed.
+    pub fn invalidate_role(role: &str) {
+        roles().remove(&role.to_string());
+    }
+}
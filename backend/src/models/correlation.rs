@@ -0,0 +1,288 @@
+use crate::models::notification::{Notification, NotificationKind};
+use crate::models::ticket::{TicketError, TicketStatus};
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+use tokio_postgres::GenericClient;
+use uuid::Uuid;
+
+/// File extensions that look like top-level domains but name files.
+const FILE_EXTENSIONS: [&str; 16] = [
+    "exe", "dll", "scr", "bat", "cmd", "ps1", "vbs", "js", "jar", "zip", "rar", "iso", "pdf",
+    "doc", "docx", "xlsx",
+];
+
+/// Kinds of threat indicators used to correlate tickets.
+///
+/// Only indicators that identify the same infrastructure or payload
+/// across reports are considered; free-form threat descriptions are not.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum IndicatorKind {
+    /// IPv4 or IPv6 address
+    Ip,
+    /// Fully qualified domain name
+    Domain,
+    /// Absolute URL
+    Url,
+    /// MD5, SHA-1, SHA-256 or SHA-512 file hash
+    FileHash,
+}
+
+impl std::fmt::Display for IndicatorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            IndicatorKind::Ip => "ip",
+            IndicatorKind::Domain => "domain",
+            IndicatorKind::Url => "url",
+            IndicatorKind::FileHash => "hash",
+        })
+    }
+}
+
+/// Normalizes an indicator for comparison.
+///
+/// Trims surrounding whitespace and trailing dots and lowercases the value.
+pub fn normalize_indicator(value: &str) -> String {
+    value.trim().trim_end_matches('.').to_lowercase()
+}
+
+/// Classifies an extracted indicator.
+///
+/// # Arguments
+/// * `value` - Indicator as extracted by the threat analysis
+///
+/// # Returns
+/// * `Option<IndicatorKind>` - Indicator kind, or None if it cannot be correlated
+pub fn classify_indicator(value: &str) -> Option<IndicatorKind> {
+    let value = normalize_indicator(value);
+
+    // Check for IP addresses
+    if value.parse::<IpAddr>().is_ok() {
+        return Some(IndicatorKind::Ip);
+    }
+
+    // Check for absolute URLs
+    if value.contains("://") {
+        return url::Url::parse(&value).ok().map(|_| IndicatorKind::Url);
+    }
+
+    // Check for hex-encoded file hashes
+    if matches!(value.len(), 32 | 40 | 64 | 128) && value.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Some(IndicatorKind::FileHash);
+    }
+
+    // Check for domain names, skipping file names such as payload.exe
+    let labels: Vec<&str> = value.split('.').collect();
+    let tld = labels.last().copied().unwrap_or_default();
+    let valid_labels = labels.iter().all(|label| {
+        !label.is_empty()
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+            && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+    });
+    if labels.len() >= 2
+        && valid_labels
+        && tld.len() >= 2
+        && tld.chars().all(|c| c.is_ascii_alphabetic())
+        && !FILE_EXTENSIONS.contains(&tld)
+    {
+        return Some(IndicatorKind::Domain);
+    }
+
+    None
+}
+
+/// Correlation settings.
+///
+/// # Environment Variables
+/// * `TICKET_CORRELATION_WINDOW_HOURS` - How long a ticket stays eligible for
+///   correlation after its last update (default 72, 0 disables correlation)
+#[derive(Debug, Clone, Copy)]
+pub struct CorrelationConfig {
+    pub window: Duration,
+}
+
+impl CorrelationConfig {
+    /// Reads the correlation settings from the environment.
+    pub fn from_env() -> Self {
+        let hours = std::env::var("TICKET_CORRELATION_WINDOW_HOURS")
+            .ok()
+            .and_then(|v| v.parse::<i64>().ok())
+            .unwrap_or(72);
+
+        Self {
+            window: Duration::hours(hours.max(0)),
+        }
+    }
+
+    /// Whether correlation is enabled.
+    pub fn enabled(&self) -> bool {
+        self.window > Duration::zero()
+    }
+}
+
+/// Existing ticket matched by shared indicators.
+///
+/// # Fields
+/// * `ticket_id` - Ticket sharing indicators with the new report
+/// * `matched_indicators` - Shared indicators with their kinds
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Correlation {
+    pub ticket_id: Uuid,
+    pub matched_indicators: Vec<(IndicatorKind, String)>,
+}
+
+impl Correlation {
+    /// Finds the ticket sharing the most indicators with a new report.
+    ///
+    /// Only tickets that are Open, InProgress or Closed, were not merged,
+    /// and were updated within the correlation window are considered, so
+    /// recently closed tickets can be reopened by new reports. Active
+    /// tickets win ties.
+    ///
+    /// # Arguments
+    /// * `client` - Database client for executing queries
+    /// * `indicators` - Indicators extracted from the new report
+    /// * `config` - Correlation settings
+    ///
+    /// # Returns
+    /// * `Result<Option<Correlation>, TicketError>` - Best match or None
+    pub async fn find<C: GenericClient>(
+        client: &C,
+        indicators: &[String],
+        config: &CorrelationConfig,
+    ) -> Result<Option<Self>, TicketError> {
+        if !config.enabled() {
+            return Ok(None);
+        }
+
+        // Keep only indicators that identify shared infrastructure
+        let mut candidates: Vec<String> = indicators
+            .iter()
+            .filter(|i| classify_indicator(i).is_some())
+            .map(|i| normalize_indicator(i))
+            .collect();
+        candidates.sort();
+        candidates.dedup();
+
+        if candidates.is_empty() {
+            return Ok(None);
+        }
+
+        let since = Utc::now() - config.window;
+        let statuses = vec![
+            TicketStatus::Open.to_string(),
+            TicketStatus::InProgress.to_string(),
+            TicketStatus::Closed.to_string(),
+        ];
+
+        // Find the ticket with the largest indicator overlap
+        let row = client
+            .query_opt(
+                "SELECT id, matched FROM (
+                    SELECT t.id, t.updated_at, t.status = $4 AS closed, ARRAY(
+                        SELECT DISTINCT lower(trim(trailing '.' from trim(i)))
+                        FROM unnest(t.extracted_indicators) i
+                        WHERE lower(trim(trailing '.' from trim(i))) = ANY($1)
+                    ) AS matched
+                    FROM tickets t
+                    WHERE t.merged_into IS NULL
+                      AND t.status = ANY($2)
+                      AND t.updated_at >= $3
+                 ) candidates
+                 WHERE cardinality(matched) > 0
+                 ORDER BY cardinality(matched) DESC, closed, updated_at DESC
+                 LIMIT 1",
+                &[
+                    &candidates,
+                    &statuses,
+                    &since,
+                    &TicketStatus::Closed.to_string(),
+                ],
+            )
+            .await?;
+
+        Ok(row.map(|row| {
+            let matched: Vec<String> = row.get("matched");
+            Correlation {
+                ticket_id: row.get("id"),
+                matched_indicators: matched
+                    .into_iter()
+                    .filter_map(|i| classify_indicator(&i).map(|kind| (kind, i)))
+                    .collect(),
+            }
+        }))
+    }
+
+    /// Human-readable reason recorded on the email link.
+    pub fn reason(&self) -> String {
+        let matched: Vec<String> = self
+            .matched_indicators
+            .iter()
+            .map(|(kind, value)| format!("{} {}", kind, value))
+            .collect();
+
+        format!("Shared indicators: {}", matched.join(", "))
+    }
+
+    /// Links an email to the correlated ticket.
+    ///
+    /// Records the correlation reason on the link, adds the email's
+    /// indicators to the ticket and notifies the ticket's watchers.
+    ///
+    /// # Arguments
+    /// * `client` - Database client (usually a transaction)
+    /// * `email_id` - Email to link
+    /// * `indicators` - Indicators extracted from the email
+    ///
+    /// # Returns
+    /// * `Result<bool, TicketError>` - Whether a new link was inserted
+    pub async fn link_email<C: GenericClient>(
+        &self,
+        client: &C,
+        email_id: &Uuid,
+        indicators: &[String],
+    ) -> Result<bool, TicketError> {
+        // Record the reason on an existing link, or insert a new one
+        let reason = self.reason();
+        let updated = client
+            .execute(
+                "UPDATE email_tickets SET correlation_reason = $3 WHERE email_id = $1 AND ticket_id = $2",
+                &[email_id, &self.ticket_id, &reason],
+            )
+            .await?;
+        if updated == 0 {
+            client
+                .execute(
+                    "INSERT INTO email_tickets (email_id, ticket_id, correlation_reason) VALUES ($1, $2, $3)",
+                    &[email_id, &self.ticket_id, &reason],
+                )
+                .await?;
+
+            // Notify the watchers about the new email
+            Notification::notify_watchers(
+                client,
+                &self.ticket_id,
+                NotificationKind::EmailLinked,
+                &format!("Email {} was linked. {}", email_id, reason),
+                None,
+            )
+            .await?;
+        }
+
+        // Add the new indicators to the ticket
+        client
+            .execute(
+                "UPDATE tickets SET
+                    extracted_indicators = ARRAY(
+                        SELECT DISTINCT v FROM unnest(COALESCE(extracted_indicators, ARRAY[]::text[]) || $2::text[]) v
+                    ),
+                    updated_at = NOW()
+                 WHERE id = $1",
+                &[&self.ticket_id, &indicators],
+            )
+            .await?;
+
+        Ok(updated == 0)
+    }
+}
@@ -1,6 +1,11 @@
+use crate::events::{self, Event};
 use crate::llm::analyze_threat;
+use crate::models::correlation::{
+    classify_indicator, Correlation, CorrelationConfig, IndicatorKind,
+};
 use crate::models::es::{ESClient, ESError};
-use crate::models::ticket::Ticket;
+use crate::models::notification::{Notification, NotificationKind};
+use crate::models::ticket::{Ticket, TicketError};
 use chrono::{DateTime, Utc};
 use deadpool_postgres::Pool;
 use futures::future;
@@ -81,6 +86,10 @@ pub enum EmailError {
     #[error("Failed to analyze threat: {0}")]
     ThreatAnalysis(String),
 
+    /// Errors correlating and linking emails to tickets
+    #[error("Failed to link ticket: {0}")]
+    Correlation(String),
+
     /// Environment variable errors
     #[error("Environment variable error: {0}")]
     Env(#[from] std::env::VarError),
@@ -292,7 +301,7 @@ impl Email {
                 &[],
             )
             .await
-            .map_err(|e| EmailError::Database(e))?;
+            .map_err(EmailError::Database)?;
 
         // Convert the rows to a vector of Email structs
         let emails: Vec<Email> = rows
@@ -319,7 +328,7 @@ impl Email {
         // Fetch from database
         let mut emails = Self::fetch_from_db(pool).await?;
         let mut existing_ids: std::collections::HashSet<Uuid> =
-            emails.iter().map(|e| e.id.clone()).collect();
+            emails.iter().map(|e| e.id).collect();
 
         // Fetch new emails from IMAP
         let imap_emails = Self::fetch_from_imap().await?;
@@ -335,7 +344,7 @@ impl Email {
                     log::error!("Failed to save email: {}", e);
                 } else {
                     // Add the new email to the existing IDs set
-                    existing_ids.insert(email.id.clone());
+                    existing_ids.insert(email.id);
                     // Add the new email to the emails vector
                     emails.push(email);
                     // Increment the new count
@@ -363,9 +372,14 @@ impl Email {
         let ip_address = analysis
             .extracted_indicators
             .iter()
-            .find(|indicator| indicator.contains('.'))
+            .find(|indicator| classify_indicator(indicator) == Some(IndicatorKind::Ip))
             .cloned();
 
+        // Link to a ticket sharing indicators with this report, if any
+        if let Some(ticket_id) = self.correlate(pool, &analysis.extracted_indicators).await? {
+            return Ok(ticket_id);
+        }
+
         // Create an enhanced description for the ticket
         let enhanced_description = format!(
             "Original Content:\n{}\n\nThreat Analysis:\n- Confidence: {}\n- Identified Threats: {}\n- Extracted Indicators: {}\n\nSummary: {}",
@@ -377,7 +391,7 @@ impl Email {
         );
 
         // Create a new ticket
-        let ticket = Ticket::new(
+        let mut ticket = Ticket::new(
             analysis.threat_type,
             self.subject.clone(),
             enhanced_description,
@@ -393,13 +407,20 @@ impl Email {
         // Start a transaction
         let tx = client.transaction().await?;
 
+        // Link the ticket to the responsible customer
+        if let Err(e) = ticket.resolve_customer(&*tx, &[self.id]).await {
+            let _ = tx.rollback().await;
+            log::error!("Failed to resolve customer: {}", e);
+            return Err(e.into());
+        }
+
         // Save the ticket within the transaction
         let ticket_id = match ticket.save_with_client(&tx).await {
             Ok(id) => id,
             Err(e) => {
                 let _ = tx.rollback().await;
                 log::error!("Failed to save ticket: {}", e);
-                return Err(EmailError::ThreatAnalysis(e.to_string()));
+                return Err(e.into());
             }
         };
 
@@ -407,7 +428,7 @@ impl Email {
         if let Err(e) = ticket.add_email_with_client(&tx, &self.id).await {
             let _ = tx.rollback().await;
             log::error!("Failed to link email: {}", e);
-            return Err(EmailError::ThreatAnalysis(e.to_string()));
+            return Err(e.into());
         }
 
         // Commit the transaction
@@ -415,6 +436,7 @@ impl Email {
             log::error!("Failed to commit transaction: {}", e);
             return Err(EmailError::Database(e));
         }
+        ticket.publish_created();
 
         // Log the successful creation of the ticket
         log::info!(
@@ -427,6 +449,82 @@ impl Email {
         Ok(ticket_id)
     }
 
+    /// Link this email to an existing ticket by shared indicators
+    ///
+    /// Looks for a ticket within the correlation window sharing IPs,
+    /// domains, URLs, or file hashes with this email and links the email to
+    /// it, recording the correlation reason. Closed tickets are reopened.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    /// * `indicators` - Indicators extracted from this email
+    ///
+    /// # Returns
+    /// * `Result<Option<Uuid>, EmailError>` - Correlated ticket ID, or None if no match
+    pub async fn correlate(
+        &self,
+        pool: &Pool,
+        indicators: &[String],
+    ) -> Result<Option<Uuid>, EmailError> {
+        let config = CorrelationConfig::from_env();
+        if !config.enabled() {
+            return Ok(None);
+        }
+
+        // Get a connection from the pool
+        let mut client = pool.get().await?;
+        // Start a transaction
+        let tx = client.transaction().await?;
+
+        // Find the best matching ticket
+        let correlation = match Correlation::find(&*tx, indicators, &config).await {
+            Ok(Some(correlation)) => correlation,
+            Ok(None) => return Ok(None),
+            Err(e) => {
+                log::error!("Failed to correlate email {}: {}", self.id, e);
+                return Err(e.into());
+            }
+        };
+
+        // Link this email to the correlated ticket
+        let inserted = match correlation.link_email(&*tx, &self.id, indicators).await {
+            Ok(inserted) => inserted,
+            Err(e) => {
+                let _ = tx.rollback().await;
+                log::error!("Failed to link correlated email: {}", e);
+                return Err(e.into());
+            }
+        };
+
+        // Commit the transaction
+        tx.commit().await?;
+
+        // Reopen the ticket if it was closed and the email is new to it
+        if inserted {
+            Ticket::reopen_for_email(&**client, &correlation.ticket_id, &self.id).await?;
+        }
+
+        log::info!(
+            "Linked email {} to ticket {} ({})",
+            self.id,
+            correlation.ticket_id,
+            correlation.reason()
+        );
+
+        // Reindex the ticket with the new email and indicators
+        match Ticket::find_by_id(pool, correlation.ticket_id).await {
+            Ok(Some(ticket)) => {
+                if let Err(e) = ticket.index_to_es().await {
+                    log::error!("Failed to index ticket to ElasticSearch: {}", e);
+                }
+            }
+            Ok(None) => {}
+            Err(e) => log::error!("Failed to reload ticket {}: {}", correlation.ticket_id, e),
+        }
+
+        Ok(Some(correlation.ticket_id))
+    }
+
     /// Get associated tickets for this email
     pub async fn get_tickets(&self, pool: &Pool) -> Result<Vec<Uuid>, EmailError> {
         // Get a connection from the pool
@@ -439,7 +537,7 @@ impl Email {
                 &[&self.id],
             )
             .await
-            .map_err(|e| EmailError::Database(e))?;
+            .map_err(EmailError::Database)?;
 
         // Return the ticket IDs
         Ok(rows.iter().map(|row| row.get("ticket_id")).collect())
@@ -456,7 +554,7 @@ impl Email {
                 &[&ticket_id],
             )
             .await
-            .map_err(|e| EmailError::Database(e))?
+            .map_err(EmailError::Database)?
             .get::<_, bool>(0);
 
         // If the ticket does not exist, return an error
@@ -468,13 +566,41 @@ impl Email {
         }
 
         // Insert the link into the database
-        client
+        let inserted = client
             .execute(
                 "INSERT INTO email_tickets (email_id, ticket_id) VALUES ($1, $2) ON CONFLICT DO NOTHING",
                 &[&self.id, &ticket_id],
             )
             .await
-            .map_err(|e| EmailError::Database(e))?;
+            .map_err(EmailError::Database)?;
+
+        // Notify the watchers about the new email
+        if inserted > 0 {
+            Notification::notify_watchers(
+                &**client,
+                &ticket_id,
+                NotificationKind::EmailLinked,
+                &format!("Email {} was linked: {}", self.id, self.subject),
+                None,
+            )
+            .await?;
+        }
+
+        // Reopen the ticket if it was closed and the email is new to it
+        if inserted == 0 {
+            return Ok(());
+        }
+        match Ticket::reopen_for_email(&**client, &ticket_id, &self.id).await {
+            Ok(true) => {
+                if let Ok(Some(ticket)) = Ticket::find_by_id(pool, ticket_id).await {
+                    if let Err(e) = ticket.index_to_es().await {
+                        log::error!("Failed to index ticket to ElasticSearch: {}", e);
+                    }
+                }
+            }
+            Ok(false) => {}
+            Err(e) => return Err(e.into()),
+        }
 
         Ok(())
     }
@@ -491,7 +617,7 @@ impl Email {
                 &[&self.id, &ticket_id],
             )
             .await
-            .map_err(|e| EmailError::Database(e))?;
+            .map_err(EmailError::Database)?;
 
         // If the link was not found, return an error
         if result == 0 {
@@ -561,13 +687,21 @@ impl Email {
                 ],
             )
             .await
-            .map_err(|e| EmailError::Database(e))?;
+            .map_err(EmailError::Database)?;
 
         // Index to ElasticSearch
         if let Err(e) = self.index_to_es().await {
             log::error!("Failed to index email to ElasticSearch: {}", e);
         }
 
+        // Announce the new email
+        events::publish(Event::EmailIngested {
+            email_id: self.id,
+            sender: self.sender.clone(),
+            subject: self.subject.clone(),
+            received_at: self.received_at,
+        });
+
         Ok(())
     }
 
@@ -660,7 +794,7 @@ impl Email {
         client
             .execute("DELETE FROM emails WHERE id = $1", &[&self.id])
             .await
-            .map_err(|e| EmailError::Database(e.into()))?;
+            .map_err(EmailError::Database)?;
 
         Ok(())
     }
@@ -848,7 +982,7 @@ impl OutgoingEmail {
                  RETURNING id",
             )
             .await
-            .map_err(|e| EmailError::Database(e.into()))?;
+            .map_err(EmailError::Database)?;
 
         // Execute the SQL statement
         client
@@ -927,7 +1061,7 @@ impl OutgoingEmail {
             .send(email_payload)
             .await
             .map(|_| format!("Successfully sent {} to {}", self.subject, recipient))
-            .map_err(|e| EmailError::Smtp(e))
+            .map_err(EmailError::Smtp)
     }
 }
 
@@ -959,6 +1093,21 @@ impl From<String> for EmailError {
     }
 }
 
+/// Ticket error conversion implementation.
+///
+/// Keeps database, pool and ElasticSearch failures in their own variants,
+/// other ticket errors become EmailError::Correlation.
+impl From<TicketError> for EmailError {
+    fn from(error: TicketError) -> Self {
+        match error {
+            TicketError::Database(e) => EmailError::Database(e),
+            TicketError::Pool(e) => EmailError::Pool(e),
+            TicketError::ES(e) => EmailError::ES(e),
+            TicketError::Validation(e) | TicketError::NotFound(e) => EmailError::Correlation(e),
+        }
+    }
+}
+
 /// Pool error conversion implementation.
 ///
 /// Converts connection pool errors to EmailError::Pool variant.
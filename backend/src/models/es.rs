@@ -1,4 +1,6 @@
-use elasticsearch::indices::{IndicesCreateParts, IndicesDeleteParts, IndicesExistsParts};
+use elasticsearch::indices::{
+    IndicesCreateParts, IndicesDeleteParts, IndicesExistsParts, IndicesPutMappingParts,
+};
 use elasticsearch::{
     http::transport::{BuildError, SingleNodeConnectionPool, TransportBuilder},
     Elasticsearch, Error as ElasticError,
@@ -313,7 +315,14 @@ impl ESClient {
                             "analysis_summary": { "type": "text", "analyzer": "ticket_analyzer" },
                             "created_at": { "type": "date" },
                             "updated_at": { "type": "date" },
-                            "email_ids": { "type": "keyword" }
+                            "email_ids": { "type": "keyword" },
+                            "tags": { "type": "keyword" },
+                            "custom_fields": { "type": "object", "dynamic": false },
+                            "customer_uuid": { "type": "keyword" },
+                            "assigned_to": { "type": "keyword" },
+                            "number": { "type": "long" },
+                            "reference": { "type": "keyword" },
+                            "stale_since": { "type": "date" }
                         }
                     }
                 }),
@@ -332,6 +341,32 @@ impl ESClient {
         Ok(())
     }
 
+    /// Adds field mappings to an existing index.
+    ///
+    /// # Arguments
+    /// * `index` - Target index
+    /// * `mapping` - Mapping body with the new `properties`
+    pub async fn put_mapping(&self, index: &str, mapping: Value) -> Result<(), ESError> {
+        // Send the mapping update to Elasticsearch
+        let response = self
+            .client
+            .indices()
+            .put_mapping(IndicesPutMappingParts::Index(&[index]))
+            .body(mapping)
+            .send()
+            .await?;
+
+        // Check if the mapping update was successful
+        if !response.status_code().is_success() {
+            return Err(ESError::InvalidInput(format!(
+                "Failed to update mapping: {}",
+                response.status_code()
+            )));
+        }
+
+        Ok(())
+    }
+
     /// Deletes an index if it exists.
     ///
     /// # Arguments
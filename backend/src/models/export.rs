@@ -0,0 +1,500 @@
+use crate::models::ticket::{SearchOptions, Ticket, TicketError};
+use crate::models::ticket_history::TicketHistory;
+use actix_web::web::Bytes;
+use chrono::{DateTime, Utc};
+use deadpool_postgres::Pool;
+use futures::stream::{self, Stream};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tokio_postgres::GenericClient;
+use uuid::Uuid;
+
+/// Maximum number of tickets in a single export.
+///
+/// Matches the default Elasticsearch result window.
+pub const EXPORT_LIMIT: usize = 10_000;
+
+/// Number of tickets fetched per search request while exporting.
+const EXPORT_PAGE_SIZE: usize = 500;
+
+/// Columns of the CSV export.
+const CSV_HEADER: [&str; 22] = [
+    "reference",
+    "id",
+    "ticket_type",
+    "status",
+    "subject",
+    "description",
+    "ip_address",
+    "confidence_score",
+    "identified_threats",
+    "extracted_indicators",
+    "analysis_summary",
+    "tags",
+    "customer_uuid",
+    "assigned_to",
+    "created_at",
+    "updated_at",
+    "email_count",
+    "email_ids",
+    "email_senders",
+    "email_subjects",
+    "first_email_at",
+    "last_email_at",
+];
+
+/// File formats supported by the ticket export.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    /// Comma-separated values with a header row
+    #[default]
+    Csv,
+    /// One JSON document per line
+    Ndjson,
+}
+
+impl ExportFormat {
+    /// MIME type of the exported file.
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Ndjson => "application/x-ndjson",
+        }
+    }
+
+    /// File extension of the exported file.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Ndjson => "ndjson",
+        }
+    }
+}
+
+/// Metadata of an email linked to an exported ticket.
+///
+/// Email bodies are left out; they can be fetched separately if needed.
+///
+/// # Fields
+/// * `id` - Email identifier
+/// * `sender` - Sender address
+/// * `subject` - Email subject
+/// * `received_at` - Reception timestamp
+/// * `correlation_reason` - Why the email was linked by correlation, if it was
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ExportedEmail {
+    pub id: Uuid,
+    pub sender: String,
+    pub subject: String,
+    pub received_at: DateTime<Utc>,
+    pub correlation_reason: Option<String>,
+}
+
+impl ExportedEmail {
+    /// Loads the linked email metadata for a set of tickets.
+    ///
+    /// # Arguments
+    /// * `client` - Database client for executing queries
+    /// * `ticket_ids` - Tickets to load emails for
+    ///
+    /// # Returns
+    /// * `Result<HashMap<Uuid, Vec<ExportedEmail>>, TicketError>` - Emails by ticket, oldest first
+    pub async fn for_tickets<C: GenericClient>(
+        client: &C,
+        ticket_ids: &[Uuid],
+    ) -> Result<HashMap<Uuid, Vec<Self>>, TicketError> {
+        let rows = client
+            .query(
+                "SELECT et.ticket_id, e.id, e.sender, e.subject, e.received_at, et.correlation_reason
+                 FROM email_tickets et
+                 JOIN emails e ON e.id = et.email_id
+                 WHERE et.ticket_id = ANY($1)
+                 ORDER BY e.received_at, e.id",
+                &[&ticket_ids],
+            )
+            .await?;
+
+        let mut emails: HashMap<Uuid, Vec<Self>> = HashMap::new();
+        for row in rows {
+            emails
+                .entry(row.get("ticket_id"))
+                .or_default()
+                .push(ExportedEmail {
+                    id: row.get("id"),
+                    sender: row.get("sender"),
+                    subject: row.get("subject"),
+                    received_at: row.get("received_at"),
+                    correlation_reason: row.get("correlation_reason"),
+                });
+        }
+
+        Ok(emails)
+    }
+}
+
+/// Exported ticket with its linked email metadata.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TicketExport {
+    #[serde(flatten)]
+    pub ticket: Ticket,
+    pub emails: Vec<ExportedEmail>,
+}
+
+impl TicketExport {
+    /// CSV header row, including the line break.
+    pub fn csv_header() -> String {
+        format!("{}\r\n", CSV_HEADER.join(","))
+    }
+
+    /// Renders the ticket as a CSV row, including the line break.
+    ///
+    /// List values are joined with `; `.
+    pub fn to_csv_row(&self) -> String {
+        let ticket = &self.ticket;
+        let join = |values: &Option<Vec<String>>| values.clone().unwrap_or_default().join("; ");
+        let emails = |f: fn(&ExportedEmail) -> String| {
+            self.emails.iter().map(f).collect::<Vec<_>>().join("; ")
+        };
+
+        let fields = [
+            ticket.reference.clone().unwrap_or_default(),
+            ticket.id.to_string(),
+            ticket.ticket_type.to_string(),
+            ticket.status.to_string(),
+            ticket.subject.clone(),
+            ticket.description.clone(),
+            ticket.ip_address.clone().unwrap_or_default(),
+            ticket
+                .confidence_score
+                .map(|s| s.to_string())
+                .unwrap_or_default(),
+            join(&ticket.identified_threats),
+            join(&ticket.extracted_indicators),
+            ticket.analysis_summary.clone().unwrap_or_default(),
+            ticket.tags.join("; "),
+            ticket
+                .customer_uuid
+                .map(|id| id.to_string())
+                .unwrap_or_default(),
+            ticket
+                .assigned_to
+                .map(|id| id.to_string())
+                .unwrap_or_default(),
+            ticket.created_at.to_rfc3339(),
+            ticket.updated_at.to_rfc3339(),
+            self.emails.len().to_string(),
+            emails(|e| e.id.to_string()),
+            emails(|e| e.sender.clone()),
+            emails(|e| e.subject.clone()),
+            self.emails
+                .first()
+                .map(|e| e.received_at.to_rfc3339())
+                .unwrap_or_default(),
+            self.emails
+                .last()
+                .map(|e| e.received_at.to_rfc3339())
+                .unwrap_or_default(),
+        ];
+
+        let row: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+        format!("{}\r\n", row.join(","))
+    }
+
+    /// Renders the ticket as a JSON line, including the line break.
+    pub fn to_ndjson_line(&self) -> Result<String, TicketError> {
+        serde_json::to_string(self)
+            .map(|line| format!("{}\n", line))
+            .map_err(|e| TicketError::Validation(e.to_string()))
+    }
+}
+
+/// Quotes a CSV field if needed.
+///
+/// Values that spreadsheet applications would evaluate as formulas are
+/// prefixed with a single quote.
+pub fn csv_field(value: &str) -> String {
+    let value = if value.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{}", value)
+    } else {
+        value.to_string()
+    };
+
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+/// Streaming export state.
+struct ExportState {
+    pool: Pool,
+    options: SearchOptions,
+    format: ExportFormat,
+    from: usize,
+    end: usize,
+}
+
+impl ExportState {
+    /// Fetches the next page of tickets and renders it.
+    ///
+    /// # Returns
+    /// * `Result<Option<String>, TicketError>` - Rendered page, or None when done
+    async fn next_page(&mut self) -> Result<Option<String>, TicketError> {
+        if self.from >= self.end {
+            return Ok(None);
+        }
+
+        // Search the next page with the export filters
+        let size = EXPORT_PAGE_SIZE.min(self.end - self.from);
+        let mut options = self.options.clone();
+        options.from = Some(self.from);
+        options.size = Some(size);
+        let tickets = Ticket::search(options).await?.hits;
+
+        // Stop after the last page
+        if tickets.len() < size {
+            self.end = self.from;
+        }
+        self.from += size;
+        if tickets.is_empty() {
+            return Ok(None);
+        }
+
+        // Load the linked email metadata
+        let client = self.pool.get().await?;
+        let ids: Vec<Uuid> = tickets.iter().map(|t| t.id).collect();
+        let mut emails = ExportedEmail::for_tickets(&**client, &ids).await?;
+
+        // Render the page
+        let mut chunk = String::new();
+        for ticket in tickets {
+            let export = TicketExport {
+                emails: emails.remove(&ticket.id).unwrap_or_default(),
+                ticket,
+            };
+            match self.format {
+                ExportFormat::Csv => chunk.push_str(&export.to_csv_row()),
+                ExportFormat::Ndjson => chunk.push_str(&export.to_ndjson_line()?),
+            }
+        }
+
+        Ok(Some(chunk))
+    }
+}
+
+/// Starts a streaming ticket export.
+///
+/// Uses the same query and filters as `Ticket::search`. `from` is the
+/// offset of the first exported ticket and `size` limits the number of
+/// exported tickets, up to `EXPORT_LIMIT`. The first page is fetched before
+/// the stream is returned so search errors can be reported to the caller.
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `options` - Search query and filters
+/// * `format` - Output format
+///
+/// # Returns
+/// * `Result<impl Stream, TicketError>` - Stream of file chunks or error
+pub async fn export_tickets(
+    pool: Pool,
+    options: SearchOptions,
+    format: ExportFormat,
+) -> Result<impl Stream<Item = Result<Bytes, TicketError>>, TicketError> {
+    let from = options.from.unwrap_or(0).min(EXPORT_LIMIT);
+    let end = options
+        .size
+        .map_or(EXPORT_LIMIT, |size| from.saturating_add(size))
+        .min(EXPORT_LIMIT);
+    let mut state = ExportState {
+        pool,
+        options,
+        format,
+        from,
+        end,
+    };
+
+    // Fetch the first page eagerly
+    let mut first = match format {
+        ExportFormat::Csv => TicketExport::csv_header(),
+        ExportFormat::Ndjson => String::new(),
+    };
+    first.push_str(&state.next_page().await?.unwrap_or_default());
+
+    let first = stream::once(async move { Ok(Bytes::from(first)) });
+    let rest = stream::try_unfold(state, |mut state| async move {
+        match state.next_page().await {
+            Ok(Some(chunk)) => Ok(Some((Bytes::from(chunk), state))),
+            Ok(None) => Ok(None),
+            Err(e) => {
+                log::error!("Ticket export failed: {}", e);
+                Err(e)
+            }
+        }
+    });
+
+    Ok(futures::StreamExt::chain(first, rest))
+}
+
+/// Printable summary of a single ticket.
+///
+/// # Fields
+/// * `ticket` - Reported ticket
+/// * `emails` - Linked email metadata
+/// * `history` - Lifecycle history
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TicketReport {
+    pub ticket: Ticket,
+    pub emails: Vec<ExportedEmail>,
+    pub history: Vec<TicketHistory>,
+}
+
+impl TicketReport {
+    /// Loads the report data for a ticket.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    /// * `ticket_id` - Ticket identifier
+    ///
+    /// # Returns
+    /// * `Result<Option<TicketReport>, TicketError>` - Report data or None if the ticket doesn't exist
+    pub async fn load(pool: &Pool, ticket_id: Uuid) -> Result<Option<Self>, TicketError> {
+        let ticket = match Ticket::find_by_id(pool, ticket_id).await? {
+            Some(ticket) => ticket,
+            None => return Ok(None),
+        };
+
+        let client = pool.get().await?;
+        let emails = ExportedEmail::for_tickets(&**client, &[ticket_id])
+            .await?
+            .remove(&ticket_id)
+            .unwrap_or_default();
+        let history = TicketHistory::list_for_ticket(pool, ticket_id).await?;
+
+        Ok(Some(Self {
+            ticket,
+            emails,
+            history,
+        }))
+    }
+
+    /// Timeline entries ordered by time.
+    pub fn timeline(&self) -> Vec<(DateTime<Utc>, String)> {
+        let mut timeline = vec![(self.ticket.created_at, "Ticket created".to_string())];
+
+        for email in &self.emails {
+            timeline.push((
+                email.received_at,
+                format!("Email received from {}: {}", email.sender, email.subject),
+            ));
+        }
+
+        for entry in &self.history {
+            let mut text = entry.event.to_string();
+            if let (Some(from), Some(to)) = (entry.from_status, entry.to_status) {
+                if from.to_string() != to.to_string() {
+                    text.push_str(&format!(" ({} → {})", from, to));
+                }
+            }
+            if let Some(details) = &entry.details {
+                text.push_str(&format!(": {}", details));
+            }
+            timeline.push((entry.created_at, text));
+        }
+
+        timeline.sort_by_key(|(at, _)| *at);
+        timeline
+    }
+
+    /// Renders the report as a self-contained, printable HTML page.
+    pub fn render_html(&self) -> String {
+        let ticket = &self.ticket;
+        let title = match &ticket.reference {
+            Some(reference) => format!("{} {}", reference, ticket.subject),
+            None => ticket.subject.clone(),
+        };
+        let list = |values: &[String]| {
+            if values.is_empty() {
+                "<p>None</p>".to_string()
+            } else {
+                let items: Vec<String> = values
+                    .iter()
+                    .map(|v| format!("<li>{}</li>", html_escape(v)))
+                    .collect();
+                format!("<ul>{}</ul>", items.join(""))
+            }
+        };
+
+        // Summary table
+        let mut summary = vec![
+            ("Ticket", ticket.id.to_string()),
+            ("Type", ticket.ticket_type.to_string()),
+            ("Status", ticket.status.to_string()),
+            ("Created", ticket.created_at.to_rfc3339()),
+            ("Last update", ticket.updated_at.to_rfc3339()),
+        ];
+        if let Some(ip) = &ticket.ip_address {
+            summary.push(("IP address", ip.clone()));
+        }
+        if let Some(score) = ticket.confidence_score {
+            summary.push(("Confidence", format!("{:.0}%", score * 100.0)));
+        }
+        if !ticket.tags.is_empty() {
+            summary.push(("Tags", ticket.tags.join(", ")));
+        }
+        let summary: Vec<String> = summary
+            .iter()
+            .map(|(k, v)| format!("<tr><th>{}</th><td>{}</td></tr>", k, html_escape(v)))
+            .collect();
+
+        // Timeline table
+        let timeline: Vec<String> = self
+            .timeline()
+            .iter()
+            .map(|(at, text)| {
+                format!(
+                    "<tr><td>{}</td><td>{}</td></tr>",
+                    at.format("%Y-%m-%d %H:%M UTC"),
+                    html_escape(text)
+                )
+            })
+            .collect();
+
+        format!(
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n<style>\n\
+             body {{ font-family: sans-serif; margin: 2em; color: #222; }}\n\
+             table {{ border-collapse: collapse; margin-bottom: 1em; }}\n\
+             th, td {{ text-align: left; padding: 4px 12px 4px 0; vertical-align: top; }}\n\
+             pre {{ white-space: pre-wrap; }}\n\
+             @media print {{ body {{ margin: 0; }} }}\n\
+             </style>\n</head>\n<body>\n\
+             <h1>{title}</h1>\n<table>{summary}</table>\n\
+             <h2>Description</h2>\n<pre>{description}</pre>\n\
+             <h2>Analysis summary</h2>\n<pre>{analysis}</pre>\n\
+             <h2>Identified threats</h2>\n{threats}\n\
+             <h2>Indicators</h2>\n{indicators}\n\
+             <h2>Timeline</h2>\n<table>{timeline}</table>\n\
+             <p><small>Generated {generated}</small></p>\n</body>\n</html>\n",
+            title = html_escape(&title),
+            summary = summary.join(""),
+            description = html_escape(&ticket.description),
+            analysis = html_escape(ticket.analysis_summary.as_deref().unwrap_or("None")),
+            threats = list(ticket.identified_threats.as_deref().unwrap_or_default()),
+            indicators = list(ticket.extracted_indicators.as_deref().unwrap_or_default()),
+            timeline = timeline.join(""),
+            generated = Utc::now().format("%Y-%m-%d %H:%M UTC"),
+        )
+    }
+}
+
+/// Escapes text for use in HTML content and attributes.
+fn html_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
@@ -0,0 +1,183 @@
+//! Keycloak Role Mappings
+//!
+//! Users signing in through Keycloak are granted the role mapped to their
+//! Keycloak realm roles, client roles or groups. When several mappings
+//! match, the one with the highest priority wins.
+//!
+//! # Environment Variables
+//! * `KEYCLOAK_DEFAULT_ROLE` - Role of users no mapping matches (default `user`)
+
+use crate::models::role::Role;
+use crate::models::user::UserError;
+use chrono::{DateTime, Utc};
+use deadpool_postgres::Pool;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::error::SqlState;
+use tokio_postgres::Row;
+use uuid::Uuid;
+
+/// Token claims a mapping can match.
+///
+/// * `realm_role` - Entry of `realm_access.roles`
+/// * `client_role` - Entry of `resource_access.<client>.roles` for our client
+/// * `group` - Entry of `groups`, e.g. `/analysts`
+pub const MAPPING_CLAIMS: [&str; 3] = ["realm_role", "client_role", "group"];
+
+/// Mapping from a Keycloak role or group to a role.
+///
+/// # Fields
+/// * `uuid` - Mapping identifier
+/// * `claim` - Claim matched, see `MAPPING_CLAIMS`
+/// * `value` - Role or group name matched
+/// * `role` - Role granted
+/// * `priority` - Precedence over other matching mappings
+/// * `created_at` - Creation timestamp
+#[derive(Debug, Serialize, Deserialize)]
+pub struct KeycloakMapping {
+    pub uuid: Uuid,
+    pub claim: String,
+    pub value: String,
+    pub role: String,
+    pub priority: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<Row> for KeycloakMapping {
+    fn from(row: Row) -> Self {
+        Self {
+            uuid: row.get("uuid"),
+            claim: row.get("claim"),
+            value: row.get("value"),
+            role: row.get("role"),
+            priority: row.get("priority"),
+            created_at: row.get("created_at"),
+        }
+    }
+}
+
+impl KeycloakMapping {
+    /// Lists all mappings, highest priority first.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    ///
+    /// # Returns
+    /// * `Result<Vec<KeycloakMapping>, UserError>` - Mappings or error
+    pub async fn list(pool: &Pool) -> Result<Vec<Self>, UserError> {
+        let client = pool.get().await?;
+        let rows = client
+            .query(
+                "SELECT * FROM keycloak_role_mappings ORDER BY priority DESC, claim, value",
+                &[],
+            )
+            .await?;
+
+        Ok(rows.into_iter().map(Self::from).collect())
+    }
+
+    /// Creates a mapping.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    /// * `claim` - Claim to match, see `MAPPING_CLAIMS`
+    /// * `value` - Role or group name to match
+    /// * `role` - Role to grant
+    /// * `priority` - Precedence over other matching mappings
+    ///
+    /// # Returns
+    /// * `Result<KeycloakMapping, UserError>` - Created mapping or validation error
+    pub async fn create(
+        pool: &Pool,
+        claim: &str,
+        value: &str,
+        role: &str,
+        priority: i32,
+    ) -> Result<Self, UserError> {
+        if !MAPPING_CLAIMS.contains(&claim) {
+            return Err(UserError::Validation(format!(
+                "Unknown claim {}, expected one of {}",
+                claim,
+                MAPPING_CLAIMS.join(", ")
+            )));
+        }
+        let value = value.trim();
+        if value.is_empty() {
+            return Err(UserError::Validation("Value must not be empty".into()));
+        }
+        Role::validate_assignable(pool, role).await?;
+
+        let client = pool.get().await?;
+        let row = client
+            .query_one(
+                "INSERT INTO keycloak_role_mappings (uuid, claim, value, role, priority)
+                 VALUES ($1, $2, $3, $4, $5)
+                 RETURNING *",
+                &[&Uuid::new_v4(), &claim, &value, &role, &priority],
+            )
+            .await
+            .map_err(|e| {
+                if e.code() == Some(&SqlState::UNIQUE_VIOLATION) {
+                    UserError::Validation(format!("{} {} is already mapped", claim, value))
+                } else {
+                    UserError::Database(e)
+                }
+            })?;
+
+        Ok(row.into())
+    }
+
+    /// Deletes a mapping.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    /// * `uuid` - Mapping identifier
+    ///
+    /// # Returns
+    /// * `Result<(), UserError>` - Success, or `NotFound` for unknown mappings
+    pub async fn delete(pool: &Pool, uuid: Uuid) -> Result<(), UserError> {
+        let client = pool.get().await?;
+        let deleted = client
+            .execute(
+                "DELETE FROM keycloak_role_mappings WHERE uuid = $1",
+                &[&uuid],
+            )
+            .await?;
+
+        if deleted == 0 {
+            return Err(UserError::NotFound(format!("Mapping {} not found", uuid)));
+        }
+        Ok(())
+    }
+
+    /// Resolves the role of a Keycloak user.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    /// * `claims` - Claim and value pairs found in the user's token
+    ///
+    /// # Returns
+    /// * `Result<String, UserError>` - Role of the highest priority matching
+    ///   mapping, or the default role
+    pub async fn resolve(pool: &Pool, claims: &[(&str, String)]) -> Result<String, UserError> {
+        let keys: Vec<String> = claims
+            .iter()
+            .map(|(claim, value)| format!("{}:{}", claim, value))
+            .collect();
+
+        let client = pool.get().await?;
+        let row = client
+            .query_opt(
+                "SELECT role FROM keycloak_role_mappings
+                 WHERE claim || ':' || value = ANY($1)
+                 ORDER BY priority DESC, created_at
+                 LIMIT 1",
+                &[&keys],
+            )
+            .await?;
+
+        Ok(match row {
+            Some(row) => row.get("role"),
+            None => std::env::var("KEYCLOAK_DEFAULT_ROLE").unwrap_or_else(|_| "user".into()),
+        })
+    }
+}
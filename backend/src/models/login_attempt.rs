@@ -0,0 +1,366 @@
+//! Login Attempts
+//!
+//! Brute-force protection for the password login and its audit trail:
+//!
+//! # Account Throttling
+//! Consecutive failed logins of an account, wrong passwords and wrong
+//! second-factor codes alike, delay its next login attempt,
+//! doubling from one second once `LOGIN_DELAY_AFTER` failures are reached.
+//! After `LOGIN_LOCKOUT_THRESHOLD` failures the account is locked for
+//! `LOGIN_LOCKOUT_MINUTES`, or until an admin unlocks it. Attempts during
+//! a delay or lockout are refused without checking the password.
+//!
+//! # IP Throttling
+//! An IP address with `LOGIN_IP_MAX_FAILURES` failed logins within
+//! `LOGIN_IP_WINDOW_MINUTES` is refused until older failures leave the
+//! window, whichever accounts it tried.
+//!
+//! # Environment Variables
+//! * `LOGIN_DELAY_AFTER` - Failures before delays start (default 3)
+//! * `LOGIN_LOCKOUT_THRESHOLD` - Failures before a lockout (default 10)
+//! * `LOGIN_LOCKOUT_MINUTES` - Lockout duration (default 15)
+//! * `LOGIN_IP_MAX_FAILURES` - Failures per IP within the window (default 50)
+//! * `LOGIN_IP_WINDOW_MINUTES` - IP throttling window (default 15)
+
+use crate::models::auth::ClientInfo;
+use crate::models::user::UserError;
+use chrono::{DateTime, Duration, Utc};
+use deadpool_postgres::Pool;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::Row;
+use uuid::Uuid;
+
+/// Upper bound of the progressive delay before a lockout.
+const MAX_DELAY_SECONDS: i64 = 300;
+
+/// Reads a positive number from the environment.
+fn env_or(name: &str, default: i64) -> i64 {
+    std::env::var(name)
+        .ok()
+        .and_then(|v| v.parse::<i64>().ok())
+        .filter(|v| *v > 0)
+        .unwrap_or(default)
+}
+
+/// Throttling thresholds, see the module documentation.
+#[derive(Debug, Clone)]
+pub struct LoginPolicy {
+    pub delay_after: i64,
+    pub lockout_threshold: i64,
+    pub lockout: Duration,
+    pub ip_max_failures: i64,
+    pub ip_window: Duration,
+}
+
+impl LoginPolicy {
+    /// Reads the thresholds from the environment.
+    pub fn from_env() -> Self {
+        Self {
+            delay_after: env_or("LOGIN_DELAY_AFTER", 3),
+            lockout_threshold: env_or("LOGIN_LOCKOUT_THRESHOLD", 10),
+            lockout: Duration::minutes(env_or("LOGIN_LOCKOUT_MINUTES", 15)),
+            ip_max_failures: env_or("LOGIN_IP_MAX_FAILURES", 50),
+            ip_window: Duration::minutes(env_or("LOGIN_IP_WINDOW_MINUTES", 15)),
+        }
+    }
+
+    /// How long an account is blocked after a number of consecutive failures.
+    ///
+    /// # Arguments
+    /// * `failures` - Consecutive failed logins
+    ///
+    /// # Returns
+    /// * `Option<Duration>` - Delay or lockout, `None` below `delay_after`
+    pub fn block_for(&self, failures: i64) -> Option<Duration> {
+        if failures >= self.lockout_threshold {
+            Some(self.lockout)
+        } else if failures >= self.delay_after {
+            let exponent = (failures - self.delay_after).min(16) as u32;
+            Some(Duration::seconds(
+                2_i64.pow(exponent).min(MAX_DELAY_SECONDS),
+            ))
+        } else {
+            None
+        }
+    }
+}
+
+/// Refused login attempt.
+///
+/// # Fields
+/// * `message` - Reason shown to the client
+/// * `until` - When logins are accepted again
+#[derive(Debug)]
+pub struct LoginBlocked {
+    pub message: String,
+    pub until: DateTime<Utc>,
+}
+
+impl LoginBlocked {
+    /// Seconds until logins are accepted again, for `Retry-After`.
+    pub fn retry_after(&self) -> i64 {
+        (self.until - Utc::now()).num_seconds().max(1)
+    }
+}
+
+/// Recorded login attempt.
+///
+/// # Fields
+/// * `uuid` - Event identifier
+/// * `user_uuid` - Account, unset for unknown emails
+/// * `email` - Email the login was attempted with
+/// * `success` - Whether the login was completed
+/// * `event` - What happened, see `LoginAttempts`
+/// * `ip_address` - Client IP address
+/// * `user_agent` - Client user agent
+/// * `created_at` - When the attempt happened
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LoginEvent {
+    pub uuid: Uuid,
+    pub user_uuid: Option<Uuid>,
+    pub email: String,
+    pub success: bool,
+    pub event: String,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<Row> for LoginEvent {
+    fn from(row: Row) -> Self {
+        Self {
+            uuid: row.get("uuid"),
+            user_uuid: row.get("user_uuid"),
+            email: row.get("email"),
+            success: row.get("success"),
+            event: row.get("event"),
+            ip_address: row.get("ip_address"),
+            user_agent: row.get("user_agent"),
+            created_at: row.get("created_at"),
+        }
+    }
+}
+
+/// Login throttling and login events.
+///
+/// # Events
+/// * `login_succeeded` - Tokens issued
+/// * `password_verified` - Password accepted, second factor pending; neither
+///   a success nor a failure
+/// * `mfa_succeeded` - Second factor accepted, tokens issued
+/// * `invalid_password` - Wrong password, counts towards the lockout
+/// * `mfa_failed` - Wrong second-factor code, counts towards the lockout
+/// * `unknown_email` - No account with the email
+/// * `account_disabled` - Correct password of a disabled account
+/// * `account_locked` - Refused during a delay or lockout
+pub struct LoginAttempts;
+
+impl LoginAttempts {
+    /// Checks whether an IP address may attempt to log in.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    /// * `policy` - Throttling thresholds
+    /// * `client` - Client of the attempt
+    ///
+    /// # Returns
+    /// * `Result<Option<LoginBlocked>, UserError>` - Block if the IP has too
+    ///   many recent failures
+    pub async fn check_ip(
+        pool: &Pool,
+        policy: &LoginPolicy,
+        client: &ClientInfo,
+    ) -> Result<Option<LoginBlocked>, UserError> {
+        let Some(ip_address) = &client.ip_address else {
+            return Ok(None);
+        };
+
+        let db = pool.get().await?;
+        let since = Utc::now() - policy.ip_window;
+        let row = db
+            .query_one(
+                "SELECT COUNT(*) AS failures, MIN(created_at) AS oldest FROM (
+                     SELECT created_at FROM login_events
+                     WHERE ip_address = $1 AND NOT success AND created_at > $2
+                       AND event <> 'password_verified'
+                     ORDER BY created_at DESC
+                     LIMIT $3
+                 ) recent",
+                &[ip_address, &since, &policy.ip_max_failures],
+            )
+            .await?;
+        let failures: i64 = row.get("failures");
+        let oldest: Option<DateTime<Utc>> = row.get("oldest");
+
+        Ok(match oldest {
+            Some(oldest) if failures >= policy.ip_max_failures => Some(LoginBlocked {
+                message: "Too many failed logins from this address, try again later".into(),
+                until: oldest + policy.ip_window,
+            }),
+            _ => None,
+        })
+    }
+
+    /// Checks whether an account may attempt to log in.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    /// * `user_uuid` - Account
+    ///
+    /// # Returns
+    /// * `Result<Option<LoginBlocked>, UserError>` - Block during a delay or lockout
+    pub async fn check_account(
+        pool: &Pool,
+        user_uuid: Uuid,
+    ) -> Result<Option<LoginBlocked>, UserError> {
+        let client = pool.get().await?;
+        let row = client
+            .query_opt(
+                "SELECT locked_until FROM users WHERE uuid = $1 AND locked_until > NOW()",
+                &[&user_uuid],
+            )
+            .await?;
+
+        Ok(row.map(|row| LoginBlocked {
+            message: "Too many failed logins, the account is temporarily locked".into(),
+            until: row.get("locked_until"),
+        }))
+    }
+
+    /// Records a login attempt.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    /// * `user_uuid` - Account, if the email is known
+    /// * `email` - Email the login was attempted with
+    /// * `event` - What happened, see `LoginAttempts`
+    /// * `client` - Client of the attempt
+    ///
+    /// # Returns
+    /// * `Result<(), UserError>` - Success or error
+    pub async fn record(
+        pool: &Pool,
+        user_uuid: Option<Uuid>,
+        email: &str,
+        event: &str,
+        client: &ClientInfo,
+    ) -> Result<(), UserError> {
+        let success = matches!(event, "login_succeeded" | "mfa_succeeded");
+
+        let db = pool.get().await?;
+        db.execute(
+            "INSERT INTO login_events
+                 (uuid, user_uuid, email, success, event, ip_address, user_agent)
+             VALUES ($1, $2, $3, $4, $5, $6, $7)",
+            &[
+                &Uuid::new_v4(),
+                &user_uuid,
+                &email,
+                &success,
+                &event,
+                &client.ip_address,
+                &client.user_agent,
+            ],
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Counts a wrong password or second-factor code of an account and
+    /// blocks it if needed.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    /// * `policy` - Throttling thresholds
+    /// * `user_uuid` - Account
+    ///
+    /// # Returns
+    /// * `Result<Option<DateTime<Utc>>, UserError>` - When the account is
+    ///   blocked until, if it is
+    pub async fn record_failure(
+        pool: &Pool,
+        policy: &LoginPolicy,
+        user_uuid: Uuid,
+    ) -> Result<Option<DateTime<Utc>>, UserError> {
+        let client = pool.get().await?;
+        let row = client
+            .query_one(
+                "UPDATE users SET failed_login_attempts = failed_login_attempts + 1
+                 WHERE uuid = $1
+                 RETURNING failed_login_attempts",
+                &[&user_uuid],
+            )
+            .await?;
+        let failures: i32 = row.get("failed_login_attempts");
+
+        let Some(block) = policy.block_for(failures as i64) else {
+            return Ok(None);
+        };
+        let locked_until = Utc::now() + block;
+        client
+            .execute(
+                "UPDATE users SET locked_until = $2 WHERE uuid = $1",
+                &[&user_uuid, &locked_until],
+            )
+            .await?;
+
+        if failures as i64 >= policy.lockout_threshold {
+            log::warn!("Locked user {} after {} failed logins", user_uuid, failures);
+        }
+        Ok(Some(locked_until))
+    }
+
+    /// Clears the failed logins and lockout of an account.
+    ///
+    /// Called once a login is complete, after the second factor if the
+    /// account has one, and by admins.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    /// * `user_uuid` - Account
+    ///
+    /// # Returns
+    /// * `Result<(), UserError>` - Success, or `NotFound` for unknown users
+    pub async fn reset(pool: &Pool, user_uuid: Uuid) -> Result<(), UserError> {
+        let client = pool.get().await?;
+        let updated = client
+            .execute(
+                "UPDATE users SET failed_login_attempts = 0, locked_until = NULL
+                 WHERE uuid = $1",
+                &[&user_uuid],
+            )
+            .await?;
+
+        if updated == 0 {
+            return Err(UserError::NotFound(format!("User {} not found", user_uuid)));
+        }
+        Ok(())
+    }
+
+    /// Lists the login events of an account, newest first.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    /// * `user_uuid` - Account
+    /// * `limit` - Maximum number of events
+    ///
+    /// # Returns
+    /// * `Result<Vec<LoginEvent>, UserError>` - Events or error
+    pub async fn list(
+        pool: &Pool,
+        user_uuid: Uuid,
+        limit: i64,
+    ) -> Result<Vec<LoginEvent>, UserError> {
+        let client = pool.get().await?;
+        let rows = client
+            .query(
+                "SELECT * FROM login_events WHERE user_uuid = $1
+                 ORDER BY created_at DESC LIMIT $2",
+                &[&user_uuid, &limit],
+            )
+            .await?;
+
+        Ok(rows.into_iter().map(LoginEvent::from).collect())
+    }
+}
@@ -8,12 +8,28 @@
 //!
 //! ## Authentication and Authorization
 //! * `auth` - JWT tokens, claims, and authentication structures
+//! * `role` - Roles and their permissions
+//! * `refresh_token` - Rotating refresh tokens
+//! * `session` - Login sessions and remote sign-out
+//! * `token_blacklist` - Revoked access tokens
+//! * `auth_cache` - Cached user, session and role state for auth checks
+//! * `api_key` - API keys for service accounts and automation
+//! * `keycloak_mapping` - Roles granted to Keycloak users
+//! * `login_attempt` - Login throttling, lockout and login events
 //!
 //! ## Core Business Entities
 //! * `customer` - Customer profile and management
 //! * `user` - User account management and profiles
+//! * `password` - Password policy and reset tokens
+//! * `totp` - TOTP two-factor authentication
 //! * `email` - Email processing and storage
 //! * `ticket` - Support ticket tracking and management
+//! * `correlation` - Ticket correlation by shared threat indicators
+//! * `ticket_field` - Admin-defined custom ticket fields
+//! * `ticket_history` - Ticket lifecycle history
+//! * `export` - Ticket exports and printable reports
+//! * `ticket_comment` - Comments on tickets
+//! * `notification` - Ticket watchers and in-app notifications
 //!
 //! ## Infrastructure
 //! * `es` - Elasticsearch integration and search functionality
@@ -22,6 +38,7 @@
 //! ## Supporting Structures
 //! * `requests` - API request/response structures
 //! * `user_log` - User activity logging and audit trails
+//! * `audit_chain` - Tamper-evident hash chain and signed checkpoints
 //!
 //! # Features
 //! - Type-safe database operations
@@ -32,20 +49,54 @@
 //! - Audit logging
 //! - Search functionality
 
+/// API keys for automation
+pub mod api_key;
+/// Audit hash chain and checkpoints
+pub mod audit_chain;
 /// Authentication and authorization models
 pub mod auth;
+/// Authentication state cache
+pub mod auth_cache;
+/// Ticket correlation by shared indicators
+pub mod correlation;
 /// Customer data and operations
 pub mod customer;
 /// Email processing and management
 pub mod email;
 /// Elasticsearch integration
 pub mod es;
+/// Ticket exports and printable reports
+pub mod export;
+/// Roles granted to Keycloak users
+pub mod keycloak_mapping;
+/// Login throttling, lockout and login events
+pub mod login_attempt;
 /// Notification system models
 pub mod nctns;
+/// Ticket watchers and in-app notifications
+pub mod notification;
+/// Password policy and reset tokens
+pub mod password;
+/// Rotating refresh tokens
+pub mod refresh_token;
 /// API request/response structures
 pub mod requests;
+/// Roles and permissions
+pub mod role;
+/// Login sessions
+pub mod session;
 /// Support ticket management
 pub mod ticket;
+/// Comments on tickets
+pub mod ticket_comment;
+/// Custom ticket field definitions
+pub mod ticket_field;
+/// Ticket lifecycle history
+pub mod ticket_history;
+/// Revoked access tokens
+pub mod token_blacklist;
+/// TOTP two-factor authentication
+pub mod totp;
 /// User account management
 pub mod user;
 /// User activity logging
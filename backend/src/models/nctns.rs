@@ -80,6 +80,7 @@ pub struct NCTNS {
 /// 0. uuid
 /// 1. source_time
 /// 2. time
+///
 /// ... (continues for all fields in order)
 impl From<Row> for NCTNS {
     fn from(row: Row) -> Self {
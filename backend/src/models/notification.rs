@@ -0,0 +1,332 @@
+use crate::models::ticket::{Ticket, TicketError};
+use chrono::{DateTime, Utc};
+use deadpool_postgres::Pool;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::{GenericClient, Row};
+use uuid::Uuid;
+
+/// Maximum number of notifications returned per page.
+pub const NOTIFICATION_PAGE_LIMIT: i64 = 200;
+
+/// Kinds of ticket events users are notified about.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationKind {
+    /// Ticket status changed
+    StatusChanged,
+    /// Comment added to the ticket
+    Comment,
+    /// Email linked to the ticket
+    EmailLinked,
+    /// Ticket assigned or unassigned
+    Assigned,
+}
+
+impl std::fmt::Display for NotificationKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            NotificationKind::StatusChanged => "StatusChanged",
+            NotificationKind::Comment => "Comment",
+            NotificationKind::EmailLinked => "EmailLinked",
+            NotificationKind::Assigned => "Assigned",
+        })
+    }
+}
+
+impl From<String> for NotificationKind {
+    fn from(s: String) -> Self {
+        match s.as_str() {
+            "StatusChanged" => NotificationKind::StatusChanged,
+            "Comment" => NotificationKind::Comment,
+            "EmailLinked" => NotificationKind::EmailLinked,
+            _ => NotificationKind::Assigned,
+        }
+    }
+}
+
+/// In-app notification about a watched ticket.
+///
+/// # Fields
+/// * `id` - Unique identifier
+/// * `user_id` - Notified user
+/// * `ticket_id` - Ticket the event happened on
+/// * `reference` - Human-readable ticket reference
+/// * `kind` - Kind of event
+/// * `message` - Human-readable description
+/// * `actor` - User who caused the event, None for automatic events
+/// * `read_at` - When the notification was marked as read
+/// * `created_at` - Event timestamp
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Notification {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub ticket_id: Uuid,
+    pub reference: Option<String>,
+    pub kind: NotificationKind,
+    pub message: String,
+    pub actor: Option<Uuid>,
+    pub read_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<Row> for Notification {
+    fn from(row: Row) -> Self {
+        Notification {
+            id: row.get("id"),
+            user_id: row.get("user_id"),
+            ticket_id: row.get("ticket_id"),
+            reference: row
+                .get::<_, Option<i64>>("number")
+                .map(Ticket::format_reference),
+            kind: NotificationKind::from(row.get::<_, String>("kind")),
+            message: row.get("message"),
+            actor: row.get("actor"),
+            read_at: row.get("read_at"),
+            created_at: row.get("created_at"),
+        }
+    }
+}
+
+impl Notification {
+    /// Notifies all watchers of a ticket about an event.
+    ///
+    /// The user who caused the event is not notified.
+    ///
+    /// # Arguments
+    /// * `client` - Database client (usually the transaction making the change)
+    /// * `ticket_id` - Ticket the event happened on
+    /// * `kind` - Kind of event
+    /// * `message` - Human-readable description
+    /// * `actor` - User who caused the event
+    ///
+    /// # Returns
+    /// * `Result<u64, TicketError>` - Number of notified users or error
+    pub async fn notify_watchers<C: GenericClient>(
+        client: &C,
+        ticket_id: &Uuid,
+        kind: NotificationKind,
+        message: &str,
+        actor: Option<Uuid>,
+    ) -> Result<u64, TicketError> {
+        let notified = client
+            .execute(
+                "INSERT INTO notifications (id, user_id, ticket_id, kind, message, actor)
+                 SELECT uuid_generate_v4(), w.user_id, w.ticket_id, $2, $3, $4
+                 FROM ticket_watchers w
+                 WHERE w.ticket_id = $1 AND w.user_id IS DISTINCT FROM $4",
+                &[ticket_id, &kind.to_string(), &message, &actor],
+            )
+            .await?;
+
+        Ok(notified)
+    }
+
+    /// Lists a user's notifications, newest first.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    /// * `user_id` - Notified user
+    /// * `unread_only` - Only return unread notifications
+    /// * `limit` - Page size, capped at `NOTIFICATION_PAGE_LIMIT`
+    /// * `offset` - Page offset
+    ///
+    /// # Returns
+    /// * `Result<Vec<Notification>, TicketError>` - Notifications or error
+    pub async fn list_for_user(
+        pool: &Pool,
+        user_id: Uuid,
+        unread_only: bool,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<Self>, TicketError> {
+        let client = pool.get().await?;
+
+        let rows = client
+            .query(
+                "SELECT n.*, t.number FROM notifications n
+                 JOIN tickets t ON t.id = n.ticket_id
+                 WHERE n.user_id = $1 AND (NOT $2 OR n.read_at IS NULL)
+                 ORDER BY n.created_at DESC, n.id
+                 LIMIT $3 OFFSET $4",
+                &[
+                    &user_id,
+                    &unread_only,
+                    &limit.clamp(1, NOTIFICATION_PAGE_LIMIT),
+                    &offset.max(0),
+                ],
+            )
+            .await?;
+
+        Ok(rows.into_iter().map(Notification::from).collect())
+    }
+
+    /// Counts a user's unread notifications.
+    pub async fn unread_count(pool: &Pool, user_id: Uuid) -> Result<i64, TicketError> {
+        let client = pool.get().await?;
+
+        let row = client
+            .query_one(
+                "SELECT COUNT(*) FROM notifications WHERE user_id = $1 AND read_at IS NULL",
+                &[&user_id],
+            )
+            .await?;
+
+        Ok(row.get(0))
+    }
+
+    /// Marks one of a user's notifications as read.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    /// * `user_id` - Notified user
+    /// * `id` - Notification identifier
+    ///
+    /// # Returns
+    /// * `Result<(), TicketError>` - Success, or NotFound if the user has no such notification
+    pub async fn mark_read(pool: &Pool, user_id: Uuid, id: Uuid) -> Result<(), TicketError> {
+        let client = pool.get().await?;
+
+        let updated = client
+            .execute(
+                "UPDATE notifications SET read_at = COALESCE(read_at, NOW())
+                 WHERE id = $1 AND user_id = $2",
+                &[&id, &user_id],
+            )
+            .await?;
+
+        if updated == 0 {
+            return Err(TicketError::NotFound(format!(
+                "Notification {} not found",
+                id
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Marks all of a user's notifications as read.
+    ///
+    /// # Returns
+    /// * `Result<u64, TicketError>` - Number of notifications marked or error
+    pub async fn mark_all_read(pool: &Pool, user_id: Uuid) -> Result<u64, TicketError> {
+        let client = pool.get().await?;
+
+        let updated = client
+            .execute(
+                "UPDATE notifications SET read_at = NOW() WHERE user_id = $1 AND read_at IS NULL",
+                &[&user_id],
+            )
+            .await?;
+
+        Ok(updated)
+    }
+}
+
+/// User following a ticket.
+///
+/// # Fields
+/// * `ticket_id` - Watched ticket
+/// * `user_id` - Watching user
+/// * `created_at` - When the user started watching
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TicketWatcher {
+    pub ticket_id: Uuid,
+    pub user_id: Uuid,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<Row> for TicketWatcher {
+    fn from(row: Row) -> Self {
+        TicketWatcher {
+            ticket_id: row.get("ticket_id"),
+            user_id: row.get("user_id"),
+            created_at: row.get("created_at"),
+        }
+    }
+}
+
+impl TicketWatcher {
+    /// Adds a watcher to a ticket. Watching twice has no effect, and watching
+    /// a merged ticket watches the surviving ticket.
+    ///
+    /// # Arguments
+    /// * `client` - Database client for executing queries
+    /// * `ticket_id` - Ticket to watch
+    /// * `user_id` - Watching user
+    ///
+    /// # Returns
+    /// * `Result<(), TicketError>` - Success, or NotFound if the ticket or user doesn't exist
+    pub async fn watch<C: GenericClient>(
+        client: &C,
+        ticket_id: &Uuid,
+        user_id: &Uuid,
+    ) -> Result<(), TicketError> {
+        // Follow the merge redirect to the surviving ticket
+        let ticket_id = Ticket::resolve_id(client, *ticket_id)
+            .await?
+            .ok_or_else(|| TicketError::NotFound(format!("Ticket {} not found", ticket_id)))?;
+
+        let inserted = client
+            .execute(
+                "INSERT INTO ticket_watchers (ticket_id, user_id)
+                 SELECT t.id, u.uuid FROM tickets t, users u WHERE t.id = $1 AND u.uuid = $2
+                 ON CONFLICT DO NOTHING",
+                &[&ticket_id, user_id],
+            )
+            .await?;
+
+        // Distinguish an existing watch from a missing ticket or user
+        if inserted == 0 {
+            let exists = client
+                .query_opt(
+                    "SELECT 1 FROM ticket_watchers WHERE ticket_id = $1 AND user_id = $2",
+                    &[&ticket_id, user_id],
+                )
+                .await?;
+            if exists.is_none() {
+                return Err(TicketError::NotFound(format!(
+                    "Ticket {} or user {} not found",
+                    ticket_id, user_id
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Removes a watcher from a ticket, or from the surviving ticket of a merged one.
+    ///
+    /// # Returns
+    /// * `Result<bool, TicketError>` - Whether the user was watching the ticket
+    pub async fn unwatch(pool: &Pool, ticket_id: Uuid, user_id: Uuid) -> Result<bool, TicketError> {
+        let client = pool.get().await?;
+        let ticket_id = Ticket::resolve_id(&**client, ticket_id)
+            .await?
+            .unwrap_or(ticket_id);
+
+        let deleted = client
+            .execute(
+                "DELETE FROM ticket_watchers WHERE ticket_id = $1 AND user_id = $2",
+                &[&ticket_id, &user_id],
+            )
+            .await?;
+
+        Ok(deleted > 0)
+    }
+
+    /// Lists the watchers of a ticket, or of the surviving ticket of a merged one.
+    pub async fn list_for_ticket(pool: &Pool, ticket_id: Uuid) -> Result<Vec<Self>, TicketError> {
+        let client = pool.get().await?;
+        let ticket_id = Ticket::resolve_id(&**client, ticket_id)
+            .await?
+            .unwrap_or(ticket_id);
+
+        let rows = client
+            .query(
+                "SELECT * FROM ticket_watchers WHERE ticket_id = $1 ORDER BY created_at",
+                &[&ticket_id],
+            )
+            .await?;
+
+        Ok(rows.into_iter().map(TicketWatcher::from).collect())
+    }
+}
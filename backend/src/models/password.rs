@@ -0,0 +1,277 @@
+//! Password Management
+//!
+//! Password strength rules, hashing and emailed reset tokens.
+//!
+//! # Environment Variables
+//! * `PASSWORD_MIN_LENGTH` - Minimum password length (default 8)
+//! * `PASSWORD_REQUIRE_UPPERCASE` - Require an uppercase letter (default false)
+//! * `PASSWORD_REQUIRE_LOWERCASE` - Require a lowercase letter (default false)
+//! * `PASSWORD_REQUIRE_DIGIT` - Require a digit (default false)
+//! * `PASSWORD_REQUIRE_SYMBOL` - Require a non-alphanumeric character (default false)
+//! * `BCRYPT_COST` - bcrypt work factor, 4 to 31 (default 12)
+//! * `PASSWORD_RESET_TTL_MINUTES` - Reset token lifetime (default 60)
+//! * `PASSWORD_RESET_URL` - Link the reset token is appended to in emails
+
+use crate::auth::{generate_token, hash_token};
+use crate::models::email::OutgoingEmail;
+use crate::models::session::Session;
+use crate::models::user::{User, UserError};
+use chrono::{DateTime, Duration, Utc};
+use deadpool_postgres::Pool;
+use lettre::message::Mailbox;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Password strength rules.
+///
+/// # Fields
+/// * `min_length` - Minimum number of characters
+/// * `require_uppercase` - At least one uppercase letter
+/// * `require_lowercase` - At least one lowercase letter
+/// * `require_digit` - At least one digit
+/// * `require_symbol` - At least one non-alphanumeric character
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    pub require_uppercase: bool,
+    pub require_lowercase: bool,
+    pub require_digit: bool,
+    pub require_symbol: bool,
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        Self {
+            min_length: 8,
+            require_uppercase: false,
+            require_lowercase: false,
+            require_digit: false,
+            require_symbol: false,
+        }
+    }
+}
+
+/// Reads a boolean flag from the environment.
+fn env_flag(name: &str) -> Option<bool> {
+    std::env::var(name).ok().map(|v| v == "true" || v == "1")
+}
+
+impl PasswordPolicy {
+    /// Loads the policy from the environment, falling back to the defaults.
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            min_length: std::env::var("PASSWORD_MIN_LENGTH")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.min_length),
+            require_uppercase: env_flag("PASSWORD_REQUIRE_UPPERCASE")
+                .unwrap_or(defaults.require_uppercase),
+            require_lowercase: env_flag("PASSWORD_REQUIRE_LOWERCASE")
+                .unwrap_or(defaults.require_lowercase),
+            require_digit: env_flag("PASSWORD_REQUIRE_DIGIT").unwrap_or(defaults.require_digit),
+            require_symbol: env_flag("PASSWORD_REQUIRE_SYMBOL").unwrap_or(defaults.require_symbol),
+        }
+    }
+
+    /// Checks a password against the policy.
+    ///
+    /// # Arguments
+    /// * `password` - Plain text password
+    ///
+    /// # Returns
+    /// * `Result<(), UserError>` - Validation error listing every unmet rule
+    pub fn validate(&self, password: &str) -> Result<(), UserError> {
+        let mut problems = Vec::new();
+
+        if password.chars().count() < self.min_length {
+            problems.push(format!("at least {} characters", self.min_length));
+        }
+        if self.require_uppercase && !password.chars().any(char::is_uppercase) {
+            problems.push("an uppercase letter".to_string());
+        }
+        if self.require_lowercase && !password.chars().any(char::is_lowercase) {
+            problems.push("a lowercase letter".to_string());
+        }
+        if self.require_digit && !password.chars().any(|c| c.is_ascii_digit()) {
+            problems.push("a digit".to_string());
+        }
+        if self.require_symbol && password.chars().all(char::is_alphanumeric) {
+            problems.push("a symbol".to_string());
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(UserError::Validation(format!(
+                "Password must contain {}",
+                problems.join(", ")
+            )))
+        }
+    }
+}
+
+/// bcrypt work factor from `BCRYPT_COST`, clamped to the range bcrypt accepts.
+pub fn bcrypt_cost() -> u32 {
+    std::env::var("BCRYPT_COST")
+        .ok()
+        .and_then(|v| v.parse::<u32>().ok())
+        .map(|cost| cost.clamp(4, 31))
+        .unwrap_or(bcrypt::DEFAULT_COST)
+}
+
+/// Validates a password against the configured policy and hashes it.
+///
+/// # Arguments
+/// * `password` - Plain text password
+///
+/// # Returns
+/// * `Result<String, UserError>` - bcrypt hash or validation error
+pub fn hash_password(password: &str) -> Result<String, UserError> {
+    PasswordPolicy::from_env().validate(password)?;
+    Ok(bcrypt::hash(password, bcrypt_cost())?)
+}
+
+/// Single-use, time-limited password reset token.
+///
+/// Only the SHA-256 hash of the token is stored, the token itself is
+/// emailed to the user.
+///
+/// # Fields
+/// * `token` - Token to send to the user
+/// * `user_uuid` - User the token resets the password of
+/// * `expires_at` - Expiry timestamp
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PasswordResetToken {
+    pub token: String,
+    pub user_uuid: Uuid,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl PasswordResetToken {
+    /// Issues a reset token, revoking the user's previous unused tokens.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    /// * `user_uuid` - User identifier
+    ///
+    /// # Returns
+    /// * `Result<PasswordResetToken, UserError>` - Token or error
+    pub async fn issue(pool: &Pool, user_uuid: Uuid) -> Result<Self, UserError> {
+        let ttl = std::env::var("PASSWORD_RESET_TTL_MINUTES")
+            .ok()
+            .and_then(|v| v.parse::<i64>().ok())
+            .filter(|m| *m > 0)
+            .unwrap_or(60);
+
+        let token = generate_token(32);
+        let expires_at = Utc::now() + Duration::minutes(ttl);
+
+        let mut client = pool.get().await?;
+        let tx = client.transaction().await?;
+
+        // Only the latest token can be used
+        tx.execute(
+            "DELETE FROM password_reset_tokens WHERE user_uuid = $1 AND used_at IS NULL",
+            &[&user_uuid],
+        )
+        .await?;
+
+        tx.execute(
+            "INSERT INTO password_reset_tokens (token_hash, user_uuid, expires_at)
+             VALUES ($1, $2, $3)",
+            &[&hash_token(&token), &user_uuid, &expires_at],
+        )
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(Self {
+            token,
+            user_uuid,
+            expires_at,
+        })
+    }
+
+    /// Emails the token to the user through the configured SMTP server.
+    ///
+    /// # Arguments
+    /// * `user` - Recipient of the token
+    ///
+    /// # Returns
+    /// * `Result<(), UserError>` - Success or sending error
+    pub async fn send(&self, user: &User) -> Result<(), UserError> {
+        let recipient = user
+            .email
+            .parse::<Mailbox>()
+            .map_err(|e| UserError::Validation(format!("Invalid email address: {}", e)))?;
+
+        // Link to the reset form if configured, the bare token otherwise
+        let reset = match std::env::var("PASSWORD_RESET_URL") {
+            Ok(url) => format!("{}{}", url, self.token),
+            Err(_) => format!("Reset token: {}", self.token),
+        };
+
+        let email = OutgoingEmail {
+            recipient,
+            subject: "Password reset".to_string(),
+            body: format!(
+                "Hello {},\n\nA password reset was requested for your account.\n\n{}\n\nThis token can be used once and expires at {}.\nIf you did not request a reset, you can ignore this email.\n",
+                user.name,
+                reset,
+                self.expires_at.to_rfc3339()
+            ),
+        };
+
+        email
+            .send()
+            .await
+            .map(|_| ())
+            .map_err(|e| UserError::Email(e.to_string()))
+    }
+
+    /// Sets a new password using a reset token.
+    ///
+    /// The password is checked against the policy before the token is used
+    /// up, so a rejected password does not burn the token.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    /// * `token` - Token from the reset email
+    /// * `new_password` - New plain text password
+    ///
+    /// # Returns
+    /// * `Result<Uuid, UserError>` - User whose password was reset, or a
+    ///   validation error for unknown, used or expired tokens
+    pub async fn consume(pool: &Pool, token: &str, new_password: &str) -> Result<Uuid, UserError> {
+        let password_hash = hash_password(new_password)?;
+
+        let mut client = pool.get().await?;
+        let tx = client.transaction().await?;
+
+        // Use up the token if it is still valid
+        let row = tx
+            .query_opt(
+                "UPDATE password_reset_tokens SET used_at = NOW()
+                 WHERE token_hash = $1 AND used_at IS NULL AND expires_at > NOW()
+                 RETURNING user_uuid",
+                &[&hash_token(token)],
+            )
+            .await?;
+        let user_uuid: Uuid = match row {
+            Some(row) => row.get("user_uuid"),
+            None => {
+                return Err(UserError::Validation(
+                    "Invalid or expired reset token".into(),
+                ))
+            }
+        };
+
+        // Sign out everywhere, whoever knew the old password included
+        User::set_password_hash(&*tx, user_uuid, &password_hash).await?;
+        Session::revoke_all_with_client(&*tx, user_uuid, None).await?;
+
+        tx.commit().await?;
+        Ok(user_uuid)
+    }
+}
@@ -0,0 +1,206 @@
+//! Refresh Tokens
+//!
+//! Opaque, single-use refresh tokens:
+//!
+//! # Rotation
+//! Every login starts a token family. Each refresh marks the presented
+//! token as used and issues its successor in the same family. Presenting
+//! a used token again means it was copied, so the whole family is revoked
+//! and both the legitimate client and the copy have to log in again.
+//!
+//! Access tokens carry their family id, so revoking a family (on reuse or
+//! logout) also rejects the access tokens issued for it.
+//!
+//! # Environment Variables
+//! * `REFRESH_TOKEN_TTL_DAYS` - Lifetime of each refresh token (default 7)
+
+use crate::auth::{generate_token, hash_token};
+use crate::models::auth::ClientInfo;
+use crate::models::auth_cache::AuthCache;
+use crate::models::session::Session;
+use crate::models::user::UserError;
+use chrono::{DateTime, Duration, Utc};
+use deadpool_postgres::Pool;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::GenericClient;
+use uuid::Uuid;
+
+/// Refresh token handed out to a client.
+///
+/// Only the SHA-256 hash of the token is stored.
+///
+/// # Fields
+/// * `token` - Token to send to the refresh endpoint
+/// * `family_id` - Family the token belongs to
+/// * `user_uuid` - User the token refreshes
+/// * `expires_at` - Expiry timestamp
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RefreshToken {
+    pub token: String,
+    pub family_id: Uuid,
+    pub user_uuid: Uuid,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl RefreshToken {
+    /// Inserts a token into a family.
+    async fn insert<C: GenericClient>(
+        client: &C,
+        family_id: Uuid,
+        user_uuid: Uuid,
+    ) -> Result<Self, UserError> {
+        let ttl = std::env::var("REFRESH_TOKEN_TTL_DAYS")
+            .ok()
+            .and_then(|v| v.parse::<i64>().ok())
+            .filter(|d| *d > 0)
+            .unwrap_or(7);
+
+        let token = generate_token(32);
+        let expires_at = Utc::now() + Duration::days(ttl);
+
+        client
+            .execute(
+                "INSERT INTO refresh_tokens (token_hash, family_id, user_uuid, expires_at)
+                 VALUES ($1, $2, $3, $4)",
+                &[&hash_token(&token), &family_id, &user_uuid, &expires_at],
+            )
+            .await?;
+
+        Ok(Self {
+            token,
+            family_id,
+            user_uuid,
+            expires_at,
+        })
+    }
+
+    /// Issues the first token of a new family after a login.
+    ///
+    /// Records the family as the login's session.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    /// * `user_uuid` - User that logged in
+    /// * `client_info` - Client of the login
+    ///
+    /// # Returns
+    /// * `Result<RefreshToken, UserError>` - Token or error
+    pub async fn issue(
+        pool: &Pool,
+        user_uuid: Uuid,
+        client_info: &ClientInfo,
+    ) -> Result<Self, UserError> {
+        let mut client = pool.get().await?;
+        let tx = client.transaction().await?;
+
+        let family_id = Uuid::new_v4();
+        Session::create(&*tx, family_id, user_uuid, client_info).await?;
+        let token = Self::insert(&*tx, family_id, user_uuid).await?;
+
+        tx.commit().await?;
+        Ok(token)
+    }
+
+    /// Exchanges a token for its successor.
+    ///
+    /// Reusing a token that was already exchanged revokes its family.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    /// * `token` - Token presented by the client
+    ///
+    /// # Returns
+    /// * `Result<RefreshToken, UserError>` - Successor token, or a validation
+    ///   error for unknown, expired, revoked and reused tokens
+    pub async fn rotate(pool: &Pool, token: &str) -> Result<Self, UserError> {
+        let mut client = pool.get().await?;
+        let tx = client.transaction().await?;
+
+        let row = tx
+            .query_opt(
+                "SELECT family_id, user_uuid, expires_at, used_at, revoked_at
+                 FROM refresh_tokens WHERE token_hash = $1 FOR UPDATE",
+                &[&hash_token(token)],
+            )
+            .await?
+            .ok_or_else(|| UserError::Validation("Invalid refresh token".into()))?;
+        let family_id: Uuid = row.get("family_id");
+        let user_uuid: Uuid = row.get("user_uuid");
+        let expires_at: DateTime<Utc> = row.get("expires_at");
+        let used_at: Option<DateTime<Utc>> = row.get("used_at");
+        let revoked_at: Option<DateTime<Utc>> = row.get("revoked_at");
+
+        if revoked_at.is_some() || expires_at <= Utc::now() {
+            return Err(UserError::Validation("Invalid refresh token".into()));
+        }
+
+        // A used token is presented again, the family is compromised
+        if used_at.is_some() {
+            Self::revoke_family(&*tx, family_id).await?;
+            tx.commit().await?;
+            log::warn!(
+                "Refresh token reuse for user {}, revoked token family {}",
+                user_uuid,
+                family_id
+            );
+            return Err(UserError::Validation("Refresh token was reused".into()));
+        }
+
+        tx.execute(
+            "UPDATE refresh_tokens SET used_at = NOW() WHERE token_hash = $1",
+            &[&hash_token(token)],
+        )
+        .await?;
+        let successor = Self::insert(&*tx, family_id, user_uuid).await?;
+        Session::touch(&*tx, family_id).await?;
+
+        tx.commit().await?;
+        Ok(successor)
+    }
+
+    /// Revokes every token of a family.
+    ///
+    /// # Arguments
+    /// * `client` - Database client or transaction
+    /// * `family_id` - Family identifier
+    ///
+    /// # Returns
+    /// * `Result<(), UserError>` - Success or error
+    pub async fn revoke_family<C: GenericClient>(
+        client: &C,
+        family_id: Uuid,
+    ) -> Result<(), UserError> {
+        client
+            .execute(
+                "UPDATE refresh_tokens SET revoked_at = NOW()
+                 WHERE family_id = $1 AND revoked_at IS NULL",
+                &[&family_id],
+            )
+            .await?;
+        AuthCache::invalidate_session(family_id);
+
+        Ok(())
+    }
+
+    /// Checks whether a family has not been revoked.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    /// * `family_id` - Family identifier
+    ///
+    /// # Returns
+    /// * `Result<bool, UserError>` - True if the family has unrevoked tokens
+    pub async fn is_family_active(pool: &Pool, family_id: Uuid) -> Result<bool, UserError> {
+        let client = pool.get().await?;
+        let row = client
+            .query_one(
+                "SELECT EXISTS(
+                     SELECT 1 FROM refresh_tokens WHERE family_id = $1 AND revoked_at IS NULL
+                 )",
+                &[&family_id],
+            )
+            .await?;
+
+        Ok(row.get(0))
+    }
+}
@@ -1,5 +1,14 @@
-use crate::models::ticket::TicketType;
+use crate::models::audit_chain::{AuditCheckpoint, AuditTrail};
+use crate::models::export::ExportFormat;
+use crate::models::notification::Notification;
+use crate::models::ticket::{BulkAction, BulkTicketResult, SearchOptions, TicketType};
+use crate::models::ticket_field::CustomFieldType;
+use crate::models::user_log::UserLog;
+use chrono::{DateTime, Utc};
+use jsonwebtoken::jwk::JwkSet;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
 use uuid::Uuid;
 
 /// Request structure for associating an email with a ticket.
@@ -23,7 +32,7 @@ pub struct AddEmailRequest {
 /// * `ticket_id` - Unique identifier of the created ticket
 /// * `linked_emails` - List of successfully linked email IDs
 /// * `failed_emails` - List of emails that failed to link, with error messages
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize)]
 pub struct CreateTicketResponse {
     pub ticket_id: Uuid,
     pub linked_emails: Vec<String>,
@@ -104,3 +113,212 @@ impl CreateTicketRequest {
         Ok(())
     }
 }
+
+/// Request payload for merging tickets.
+///
+/// Lists the tickets whose emails and indicators are moved into
+/// the surviving ticket.
+///
+/// # Fields
+/// * `ticket_ids` - Tickets to merge into the surviving ticket
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MergeTicketsRequest {
+    pub ticket_ids: Vec<Uuid>,
+}
+
+/// Request payload for splitting emails off a ticket.
+///
+/// # Fields
+/// * `email_ids` - Emails to move to the new ticket
+/// * `subject` - Optional subject for the new ticket
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SplitTicketRequest {
+    pub email_ids: Vec<Uuid>,
+    pub subject: Option<String>,
+}
+
+/// Request payload for replacing a ticket's tags.
+///
+/// # Fields
+/// * `tags` - New set of tags
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateTagsRequest {
+    pub tags: Vec<String>,
+}
+
+/// Request payload for setting custom field values on a ticket.
+///
+/// A null value clears the field.
+///
+/// # Fields
+/// * `fields` - Custom field values by field name
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateCustomFieldsRequest {
+    pub fields: HashMap<String, Value>,
+}
+
+/// Request payload for defining a custom ticket field.
+///
+/// # Fields
+/// * `name` - Unique field key
+/// * `label` - Display name
+/// * `field_type` - Value type
+/// * `options` - Allowed values for enum fields
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateCustomFieldRequest {
+    pub name: String,
+    pub label: String,
+    pub field_type: CustomFieldType,
+    #[serde(default)]
+    pub options: Vec<String>,
+}
+
+/// Request payload for linking a ticket to a customer.
+///
+/// # Fields
+/// * `customer_uuid` - Customer to link, or null to resolve automatically
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SetCustomerRequest {
+    pub customer_uuid: Option<Uuid>,
+}
+
+/// Request payload for bulk ticket operations.
+///
+/// Tickets are selected either by ID or by a search query.
+///
+/// # Fields
+/// * `ticket_ids` - Tickets to update
+/// * `query` - Search selecting the tickets to update
+/// * `action` - Operation to apply, flattened into the request
+/// * `atomic` - Roll back all tickets if any ticket fails
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BulkTicketRequest {
+    pub ticket_ids: Option<Vec<Uuid>>,
+    pub query: Option<SearchOptions>,
+    #[serde(flatten)]
+    pub action: BulkAction,
+    #[serde(default)]
+    pub atomic: bool,
+}
+
+/// Response structure for bulk ticket operations.
+///
+/// # Fields
+/// * `succeeded` - Number of updated tickets
+/// * `failed` - Number of tickets that could not be updated
+/// * `results` - Per-ticket outcome
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BulkTicketResponse {
+    pub succeeded: usize,
+    pub failed: usize,
+    pub results: Vec<BulkTicketResult>,
+}
+
+/// Request payload for ticket exports.
+///
+/// # Fields
+/// * `format` - Output format, `csv` or `ndjson`
+/// * `search` - Search query and filters, flattened into the request
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportTicketsRequest {
+    #[serde(default)]
+    pub format: ExportFormat,
+    #[serde(flatten)]
+    pub search: SearchOptions,
+}
+
+/// Request payload for ticket comments.
+///
+/// # Fields
+/// * `body` - Comment text
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateCommentRequest {
+    pub body: String,
+}
+
+/// Query parameters for listing notifications.
+///
+/// # Fields
+/// * `unread` - Only return unread notifications
+/// * `limit` - Page size (default 50)
+/// * `offset` - Page offset
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NotificationQuery {
+    #[serde(default)]
+    pub unread: bool,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+/// Response structure for notification listings.
+///
+/// # Fields
+/// * `unread` - Number of unread notifications
+/// * `notifications` - Requested page of notifications
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NotificationListResponse {
+    pub unread: i64,
+    pub notifications: Vec<Notification>,
+}
+
+/// Query parameters for the audit log.
+///
+/// # Fields
+/// * `user` - Only return entries of this user
+/// * `route` - Only return entries whose route starts with this prefix
+/// * `status` - Only return entries with this status code, e.g. `404`, or
+///   status class, e.g. `4xx`
+/// * `method` - Only return entries with this HTTP method
+/// * `entity` - Only return entries naming this ticket, email, customer or
+///   other entity in their path
+/// * `request_id` - Only return entries of this request
+/// * `from` - Only return entries at or after this time
+/// * `to` - Only return entries before this time
+/// * `limit` - Page size (default 50, max 500)
+/// * `offset` - Page offset
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct AuditQuery {
+    pub user: Option<Uuid>,
+    pub route: Option<String>,
+    pub status: Option<String>,
+    pub method: Option<String>,
+    pub entity: Option<Uuid>,
+    pub request_id: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+/// Response structure for audit log queries.
+///
+/// # Fields
+/// * `total` - Number of entries matching the filters
+/// * `entries` - Requested page of entries, newest first
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AuditLogResponse {
+    pub total: i64,
+    pub entries: Vec<UserLog>,
+}
+
+/// Query parameters selecting an audit trail.
+///
+/// # Fields
+/// * `trail` - `user_logs` or `ticket_history`, every trail if omitted
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct AuditTrailQuery {
+    pub trail: Option<AuditTrail>,
+}
+
+/// Exported audit checkpoints.
+///
+/// # Fields
+/// * `exported_at` - Export timestamp
+/// * `jwks` - Public keys to verify the checkpoint signatures with
+/// * `checkpoints` - Checkpoints, oldest first
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CheckpointExport {
+    pub exported_at: DateTime<Utc>,
+    pub jwks: JwkSet,
+    pub checkpoints: Vec<AuditCheckpoint>,
+}
@@ -0,0 +1,288 @@
+//! Roles and Permissions
+//!
+//! Roles are named sets of permissions stored in Postgres. Route groups
+//! require a permission (`Auth::new().permission("tickets:read")`) and
+//! users are granted the permissions of their role.
+//!
+//! # Built-in Roles
+//! * `admin` - Every permission, cannot be changed or deleted
+//! * `user` - Analyst access to tickets, can be changed but not deleted
+
+use crate::models::auth_cache::AuthCache;
+use crate::models::user::UserError;
+use chrono::{DateTime, Utc};
+use deadpool_postgres::Pool;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::error::SqlState;
+use tokio_postgres::Row;
+
+/// Permissions that can be granted to roles.
+pub const PERMISSIONS: [&str; 14] = [
+    "tickets:read",
+    "tickets:write",
+    "email:read",
+    "email:write",
+    "email:send",
+    "email:delete",
+    "customers:read",
+    "nctns:read",
+    "whois:lookup",
+    "ticket_fields:manage",
+    "users:manage",
+    "roles:manage",
+    "audit:read",
+    "audit:manage",
+];
+
+/// Role that cannot be changed, so every permission stays grantable.
+const ADMIN_ROLE: &str = "admin";
+
+/// Maximum length of role names.
+const MAX_NAME_LEN: usize = 50;
+
+/// Checks that a list of permissions can be granted.
+///
+/// # Arguments
+/// * `permissions` - Requested permissions
+///
+/// # Returns
+/// * `Result<(), UserError>` - Validation error for unknown permissions
+pub fn validate_permissions(permissions: &[String]) -> Result<(), UserError> {
+    match permissions
+        .iter()
+        .find(|permission| !PERMISSIONS.contains(&permission.as_str()))
+    {
+        Some(permission) => Err(UserError::Validation(format!(
+            "Unknown permission {}, expected one of {}",
+            permission,
+            PERMISSIONS.join(", ")
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Sorts and deduplicates permissions for storage.
+fn normalize_permissions(permissions: &[String]) -> Vec<String> {
+    let mut permissions = permissions.to_vec();
+    permissions.sort();
+    permissions.dedup();
+    permissions
+}
+
+/// Role definition.
+///
+/// # Fields
+/// * `name` - Unique role name, assigned to users
+/// * `description` - What the role is for
+/// * `permissions` - Granted permissions
+/// * `built_in` - Whether the role ships with the application
+/// * `created_at` - Creation timestamp
+/// * `updated_at` - Last modification timestamp
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Role {
+    pub name: String,
+    pub description: String,
+    pub permissions: Vec<String>,
+    pub built_in: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<Row> for Role {
+    fn from(row: Row) -> Self {
+        Self {
+            name: row.get("name"),
+            description: row.get("description"),
+            permissions: row.get("permissions"),
+            built_in: row.get("built_in"),
+            created_at: row.get("created_at"),
+            updated_at: row.get("updated_at"),
+        }
+    }
+}
+
+impl Role {
+    /// Lists all roles by name.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    ///
+    /// # Returns
+    /// * `Result<Vec<Role>, UserError>` - Roles or error
+    pub async fn list(pool: &Pool) -> Result<Vec<Self>, UserError> {
+        let client = pool.get().await?;
+        let rows = client
+            .query("SELECT * FROM roles ORDER BY name", &[])
+            .await?;
+
+        Ok(rows.into_iter().map(Self::from).collect())
+    }
+
+    /// Gets a role by name.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    /// * `name` - Role name
+    ///
+    /// # Returns
+    /// * `Result<Role, UserError>` - Role, or `NotFound` if it does not exist
+    pub async fn get(pool: &Pool, name: &str) -> Result<Self, UserError> {
+        let client = pool.get().await?;
+
+        client
+            .query_opt("SELECT * FROM roles WHERE name = $1", &[&name])
+            .await?
+            .map(Self::from)
+            .ok_or_else(|| UserError::NotFound(format!("Role {} not found", name)))
+    }
+
+    /// Creates a role.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    /// * `name` - Role name, lowercase letters, digits, `_` and `-`
+    /// * `description` - What the role is for
+    /// * `permissions` - Permissions to grant
+    ///
+    /// # Returns
+    /// * `Result<Role, UserError>` - Created role or validation error
+    pub async fn create(
+        pool: &Pool,
+        name: &str,
+        description: &str,
+        permissions: &[String],
+    ) -> Result<Self, UserError> {
+        if name.is_empty()
+            || name.len() > MAX_NAME_LEN
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-')
+        {
+            return Err(UserError::Validation(format!(
+                "Role names must be 1 to {} lowercase letters, digits, '_' or '-'",
+                MAX_NAME_LEN
+            )));
+        }
+        validate_permissions(permissions)?;
+
+        let client = pool.get().await?;
+        let row = client
+            .query_one(
+                "INSERT INTO roles (name, description, permissions) VALUES ($1, $2, $3)
+                 RETURNING *",
+                &[&name, &description, &normalize_permissions(permissions)],
+            )
+            .await
+            .map_err(|e| {
+                if e.code() == Some(&SqlState::UNIQUE_VIOLATION) {
+                    UserError::Validation(format!("Role {} already exists", name))
+                } else {
+                    UserError::Database(e)
+                }
+            })?;
+        AuthCache::invalidate_role(name);
+
+        Ok(row.into())
+    }
+
+    /// Updates the description and permissions of a role.
+    ///
+    /// Omitted values are left unchanged. The admin role cannot be changed.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    /// * `name` - Role name
+    /// * `description` - New description
+    /// * `permissions` - New permissions, replacing the current ones
+    ///
+    /// # Returns
+    /// * `Result<Role, UserError>` - Updated role or error
+    pub async fn update(
+        pool: &Pool,
+        name: &str,
+        description: Option<&str>,
+        permissions: Option<&[String]>,
+    ) -> Result<Self, UserError> {
+        if name == ADMIN_ROLE {
+            return Err(UserError::Validation(
+                "The admin role cannot be changed".into(),
+            ));
+        }
+        if let Some(permissions) = permissions {
+            validate_permissions(permissions)?;
+        }
+        let permissions = permissions.map(normalize_permissions);
+
+        let client = pool.get().await?;
+        client
+            .query_opt(
+                "UPDATE roles
+                 SET description = COALESCE($2, description),
+                     permissions = COALESCE($3, permissions),
+                     updated_at = NOW()
+                 WHERE name = $1
+                 RETURNING *",
+                &[&name, &description, &permissions],
+            )
+            .await?
+            .map(Self::from)
+            .ok_or_else(|| UserError::NotFound(format!("Role {} not found", name)))
+            .inspect(|_| AuthCache::invalidate_role(name))
+    }
+
+    /// Deletes a role that is not built in and not assigned to any user or
+    /// Keycloak mapping.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    /// * `name` - Role name
+    ///
+    /// # Returns
+    /// * `Result<(), UserError>` - Success, `NotFound`, or validation error
+    pub async fn delete(pool: &Pool, name: &str) -> Result<(), UserError> {
+        let role = Self::get(pool, name).await?;
+        if role.built_in {
+            return Err(UserError::Validation(format!(
+                "Built-in role {} cannot be deleted",
+                name
+            )));
+        }
+
+        let client = pool.get().await?;
+        client
+            .execute("DELETE FROM roles WHERE name = $1", &[&name])
+            .await
+            .map_err(|e| {
+                if e.code() == Some(&SqlState::FOREIGN_KEY_VIOLATION) {
+                    UserError::Validation(format!(
+                        "Role {} is assigned to users or Keycloak mappings",
+                        name
+                    ))
+                } else {
+                    UserError::Database(e)
+                }
+            })?;
+        AuthCache::invalidate_role(name);
+
+        Ok(())
+    }
+
+    /// Checks that a role exists and can be assigned to users.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    /// * `name` - Role name
+    ///
+    /// # Returns
+    /// * `Result<(), UserError>` - Validation error for unknown roles
+    pub async fn validate_assignable(pool: &Pool, name: &str) -> Result<(), UserError> {
+        match Self::get(pool, name).await {
+            Ok(_) => Ok(()),
+            Err(UserError::NotFound(_)) => Err(UserError::Validation(format!(
+                "Unknown role {}, see /roles for the available roles",
+                name
+            ))),
+            Err(e) => Err(e),
+        }
+    }
+}
@@ -0,0 +1,212 @@
+//! Login Sessions
+//!
+//! Every login starts a session, identified by its refresh token family
+//! and carried by access tokens as `sid`. A session is active while its
+//! latest refresh token is unused, unrevoked and unexpired.
+//!
+//! Revoking a session revokes its refresh token family, which rejects the
+//! session's refresh token and every access token issued for it.
+
+use crate::models::auth::ClientInfo;
+use crate::models::auth_cache::AuthCache;
+use crate::models::refresh_token::RefreshToken;
+use crate::models::user::UserError;
+use chrono::{DateTime, Utc};
+use deadpool_postgres::Pool;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::{GenericClient, Row};
+use uuid::Uuid;
+
+/// Condition selecting active sessions `s`.
+const ACTIVE: &str = "EXISTS (
+    SELECT 1 FROM refresh_tokens t
+    WHERE t.family_id = s.uuid
+      AND t.used_at IS NULL AND t.revoked_at IS NULL AND t.expires_at > NOW()
+)";
+
+/// Login session.
+///
+/// # Fields
+/// * `uuid` - Session identifier, the refresh token family id
+/// * `user_uuid` - Logged in user
+/// * `ip_address` - Client IP address at login
+/// * `user_agent` - Client user agent at login
+/// * `created_at` - Login timestamp
+/// * `last_refreshed_at` - Last token refresh, if any
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Session {
+    pub uuid: Uuid,
+    pub user_uuid: Uuid,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_refreshed_at: Option<DateTime<Utc>>,
+}
+
+impl From<Row> for Session {
+    fn from(row: Row) -> Self {
+        Self {
+            uuid: row.get("uuid"),
+            user_uuid: row.get("user_uuid"),
+            ip_address: row.get("ip_address"),
+            user_agent: row.get("user_agent"),
+            created_at: row.get("created_at"),
+            last_refreshed_at: row.get("last_refreshed_at"),
+        }
+    }
+}
+
+impl Session {
+    /// Records the session of a login.
+    ///
+    /// # Arguments
+    /// * `client` - Database client or transaction
+    /// * `uuid` - Refresh token family of the login
+    /// * `user_uuid` - Logged in user
+    /// * `client_info` - Client of the login
+    ///
+    /// # Returns
+    /// * `Result<(), UserError>` - Success or error
+    pub async fn create<C: GenericClient>(
+        client: &C,
+        uuid: Uuid,
+        user_uuid: Uuid,
+        client_info: &ClientInfo,
+    ) -> Result<(), UserError> {
+        client
+            .execute(
+                "INSERT INTO sessions (uuid, user_uuid, ip_address, user_agent)
+                 VALUES ($1, $2, $3, $4)",
+                &[
+                    &uuid,
+                    &user_uuid,
+                    &client_info.ip_address,
+                    &client_info.user_agent,
+                ],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Records a token refresh of a session.
+    ///
+    /// # Arguments
+    /// * `client` - Database client or transaction
+    /// * `uuid` - Session identifier
+    ///
+    /// # Returns
+    /// * `Result<(), UserError>` - Success or error
+    pub async fn touch<C: GenericClient>(client: &C, uuid: Uuid) -> Result<(), UserError> {
+        client
+            .execute(
+                "UPDATE sessions SET last_refreshed_at = NOW() WHERE uuid = $1",
+                &[&uuid],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Lists the active sessions of a user, most recent first.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    /// * `user_uuid` - User identifier
+    ///
+    /// # Returns
+    /// * `Result<Vec<Session>, UserError>` - Sessions or error
+    pub async fn list(pool: &Pool, user_uuid: Uuid) -> Result<Vec<Self>, UserError> {
+        let client = pool.get().await?;
+        let rows = client
+            .query(
+                &format!(
+                    "SELECT s.* FROM sessions s
+                     WHERE s.user_uuid = $1 AND {}
+                     ORDER BY COALESCE(s.last_refreshed_at, s.created_at) DESC",
+                    ACTIVE
+                ),
+                &[&user_uuid],
+            )
+            .await?;
+
+        Ok(rows.into_iter().map(Self::from).collect())
+    }
+
+    /// Revokes an active session of a user.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    /// * `user_uuid` - User the session must belong to
+    /// * `uuid` - Session identifier
+    ///
+    /// # Returns
+    /// * `Result<(), UserError>` - Success, or `NotFound` for unknown,
+    ///   inactive and other users' sessions
+    pub async fn revoke(pool: &Pool, user_uuid: Uuid, uuid: Uuid) -> Result<(), UserError> {
+        let client = pool.get().await?;
+        let found = client
+            .query_opt(
+                &format!(
+                    "SELECT 1 FROM sessions s WHERE s.uuid = $1 AND s.user_uuid = $2 AND {}",
+                    ACTIVE
+                ),
+                &[&uuid, &user_uuid],
+            )
+            .await?;
+        if found.is_none() {
+            return Err(UserError::NotFound(format!("Session {} not found", uuid)));
+        }
+
+        RefreshToken::revoke_family(&**client, uuid).await
+    }
+
+    /// Revokes the active sessions of a user.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    /// * `user_uuid` - User identifier
+    /// * `except` - Session to keep, e.g. the caller's own
+    ///
+    /// # Returns
+    /// * `Result<u64, UserError>` - Number of revoked sessions or error
+    pub async fn revoke_all(
+        pool: &Pool,
+        user_uuid: Uuid,
+        except: Option<Uuid>,
+    ) -> Result<u64, UserError> {
+        let client = pool.get().await?;
+        Self::revoke_all_with_client(&**client, user_uuid, except).await
+    }
+
+    /// Revokes the active sessions of a user using a specific client.
+    ///
+    /// # Arguments
+    /// * `client` - Database client or transaction
+    /// * `user_uuid` - User identifier
+    /// * `except` - Session to keep, e.g. the caller's own
+    ///
+    /// # Returns
+    /// * `Result<u64, UserError>` - Number of revoked sessions or error
+    pub async fn revoke_all_with_client<C: GenericClient>(
+        client: &C,
+        user_uuid: Uuid,
+        except: Option<Uuid>,
+    ) -> Result<u64, UserError> {
+        let revoked = client
+            .query_one(
+                "WITH revoked AS (
+                     UPDATE refresh_tokens SET revoked_at = NOW()
+                     WHERE user_uuid = $1 AND revoked_at IS NULL
+                       AND family_id IS DISTINCT FROM $2
+                     RETURNING family_id, used_at IS NULL AND expires_at > NOW() AS active
+                 )
+                 SELECT COUNT(DISTINCT family_id) FROM revoked WHERE active",
+                &[&user_uuid, &except],
+            )
+            .await?;
+        AuthCache::invalidate_sessions();
+
+        Ok(revoked.get::<_, i64>(0) as u64)
+    }
+}
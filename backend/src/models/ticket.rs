@@ -1,9 +1,14 @@
+use crate::events::{self, Event};
 use crate::models::es::{ESClient, ESError};
+use crate::models::notification::{Notification, NotificationKind, TicketWatcher};
+use crate::models::ticket_field::CustomFieldDefinition;
+use crate::models::ticket_history::{TicketEvent, TicketHistory};
 use chrono::{DateTime, Utc};
 use deadpool_postgres::Pool;
 use serde::{Deserialize, Serialize};
-use serde_json::json;
-use tokio_postgres::Row;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use tokio_postgres::{GenericClient, Row};
 use uuid::Uuid;
 
 /// Types of security incidents that can be reported.
@@ -48,9 +53,9 @@ pub enum TicketType {
     Other,
 }
 
-impl ToString for TicketType {
-    fn to_string(&self) -> String {
-        match self {
+impl std::fmt::Display for TicketType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
             TicketType::Malware => "Malware",
             TicketType::Phishing => "Phishing",
             TicketType::Scam => "Scam",
@@ -68,8 +73,7 @@ impl ToString for TicketType {
             TicketType::BruteForce => "BruteForce",
             TicketType::C2 => "C2",
             TicketType::Other => "Other",
-        }
-        .to_string()
+        })
     }
 }
 
@@ -100,9 +104,10 @@ impl From<String> for TicketType {
 /// Ticket processing status indicators.
 ///
 /// Tracks the lifecycle stage of security incident tickets.
-#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default)]
 pub enum TicketStatus {
     /// Initial state, awaiting processing
+    #[default]
     Open,
     /// Currently being investigated
     InProgress,
@@ -112,15 +117,14 @@ pub enum TicketStatus {
     Resolved,
 }
 
-impl ToString for TicketStatus {
-    fn to_string(&self) -> String {
-        match self {
+impl std::fmt::Display for TicketStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
             TicketStatus::Open => "Open",
             TicketStatus::InProgress => "InProgress",
             TicketStatus::Closed => "Closed",
             TicketStatus::Resolved => "Resolved",
-        }
-        .to_string()
+        })
     }
 }
 
@@ -135,9 +139,38 @@ impl From<String> for TicketStatus {
     }
 }
 
-impl Default for TicketStatus {
-    fn default() -> Self {
-        TicketStatus::Open
+/// How a ticket was linked to its customer.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum CustomerLinkSource {
+    /// Ticket IP address matches the customer's IP
+    Ip,
+    /// A linked email was sent by the customer
+    Sender,
+    /// A linked email was addressed to the customer
+    Recipient,
+    /// Set by a user, never replaced automatically
+    Manual,
+}
+
+impl std::fmt::Display for CustomerLinkSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            CustomerLinkSource::Ip => "Ip",
+            CustomerLinkSource::Sender => "Sender",
+            CustomerLinkSource::Recipient => "Recipient",
+            CustomerLinkSource::Manual => "Manual",
+        })
+    }
+}
+
+impl From<String> for CustomerLinkSource {
+    fn from(s: String) -> Self {
+        match s.as_str() {
+            "Ip" => CustomerLinkSource::Ip,
+            "Sender" => CustomerLinkSource::Sender,
+            "Recipient" => CustomerLinkSource::Recipient,
+            _ => CustomerLinkSource::Manual,
+        }
     }
 }
 
@@ -160,6 +193,15 @@ impl Default for TicketStatus {
 /// * `created_at` - Creation timestamp
 /// * `updated_at` - Last modification timestamp
 /// * `email_ids` - Associated email identifiers
+/// * `merged_into` - Surviving ticket if this ticket was merged
+/// * `tags` - Free-form labels such as campaign names
+/// * `custom_fields` - Values of admin-defined custom fields by field name
+/// * `customer_uuid` - Responsible customer
+/// * `customer_link_source` - How the customer was resolved
+/// * `assigned_to` - User the ticket is assigned to
+/// * `number` - Sequential ticket number, assigned on save
+/// * `reference` - Human-readable reference such as `AH-1234`
+/// * `stale_since` - When housekeeping flagged the ticket as inactive
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Ticket {
     pub id: Uuid,
@@ -176,6 +218,24 @@ pub struct Ticket {
     pub updated_at: DateTime<Utc>,
     #[serde(default)]
     pub email_ids: Vec<Uuid>,
+    #[serde(default)]
+    pub merged_into: Option<Uuid>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub custom_fields: HashMap<String, Value>,
+    #[serde(default)]
+    pub customer_uuid: Option<Uuid>,
+    #[serde(default)]
+    pub customer_link_source: Option<CustomerLinkSource>,
+    #[serde(default)]
+    pub assigned_to: Option<Uuid>,
+    #[serde(default)]
+    pub number: Option<i64>,
+    #[serde(default)]
+    pub reference: Option<String>,
+    #[serde(default)]
+    pub stale_since: Option<DateTime<Utc>>,
 }
 
 impl From<Row> for Ticket {
@@ -194,6 +254,18 @@ impl From<Row> for Ticket {
             created_at: row.get("created_at"),
             updated_at: row.get("updated_at"),
             email_ids: Vec::new(),
+            merged_into: row.get("merged_into"),
+            tags: row.get("tags"),
+            custom_fields: serde_json::from_value(row.get::<_, Value>("custom_fields"))
+                .unwrap_or_default(),
+            customer_uuid: row.get("customer_uuid"),
+            customer_link_source: row
+                .get::<_, Option<String>>("customer_link_source")
+                .map(CustomerLinkSource::from),
+            assigned_to: row.get("assigned_to"),
+            number: row.get("number"),
+            reference: Some(Ticket::format_reference(row.get("number"))),
+            stale_since: row.get("stale_since"),
         }
     }
 }
@@ -206,6 +278,7 @@ impl From<Row> for Ticket {
 /// - Database errors
 /// - Connection pool errors
 /// - Validation failures
+/// - Missing tickets
 /// - Elasticsearch errors
 #[derive(Debug, thiserror::Error)]
 pub enum TicketError {
@@ -218,6 +291,9 @@ pub enum TicketError {
     #[error("Validation error: {0}")]
     Validation(String),
 
+    #[error("Not found: {0}")]
+    NotFound(String),
+
     #[error("ElasticSearch error: {0}")]
     ES(#[from] ESError),
 }
@@ -236,15 +312,22 @@ impl From<deadpool_postgres::PoolError> for TicketError {
 /// * `status` - Filter by processing status
 /// * `ticket_type` - Filter by incident type
 /// * `has_emails` - Filter by email association
-#[derive(Debug, Serialize, Deserialize)]
+/// * `tags` - Filter by tags (all must match)
+/// * `custom_fields` - Filter by exact custom field values
+/// * `stale` - Filter by inactivity flag
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct SearchFilters {
     pub status: Option<TicketStatus>,
     pub ticket_type: Option<TicketType>,
     pub has_emails: Option<bool>,
+    pub tags: Option<Vec<String>>,
+    pub custom_fields: Option<HashMap<String, Value>>,
+    pub stale: Option<bool>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct SearchOptions {
+    #[serde(default)]
     pub query: String,
     pub filters: Option<SearchFilters>,
     pub from: Option<usize>,
@@ -257,6 +340,41 @@ pub struct SearchResponse {
     pub total: u64,
 }
 
+/// Maximum number of tickets affected by a single bulk operation.
+pub const BULK_LIMIT: usize = 1000;
+
+/// Operation applied to every ticket of a bulk request.
+///
+/// Serialized with an `action` tag, e.g. `{"action": "status", "status": "Closed"}`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum BulkAction {
+    /// Change the ticket status
+    Status { status: TicketStatus },
+    /// Assign the tickets to a user, or unassign them
+    Assign { user_id: Option<Uuid> },
+    /// Add tags to the tickets
+    AddTags { tags: Vec<String> },
+    /// Remove tags from the tickets
+    RemoveTags { tags: Vec<String> },
+    /// Delete the tickets
+    Delete,
+}
+
+/// Outcome of a bulk operation for a single ticket.
+///
+/// # Fields
+/// * `ticket_id` - Affected ticket
+/// * `success` - Whether the operation was applied
+/// * `error` - Failure reason
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BulkTicketResult {
+    pub ticket_id: Uuid,
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
 impl Ticket {
     /// Creates a new ticket instance.
     ///
@@ -269,6 +387,7 @@ impl Ticket {
     /// * `identified_threats` - Detected threats
     /// * `extracted_indicators` - Security indicators
     /// * `analysis_summary` - Analysis results
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         ticket_type: TicketType,
         subject: String,
@@ -293,17 +412,93 @@ impl Ticket {
             created_at: Utc::now(),
             updated_at: Utc::now(),
             email_ids: Vec::new(),
+            merged_into: None,
+            tags: Vec::new(),
+            custom_fields: HashMap::new(),
+            customer_uuid: None,
+            customer_link_source: None,
+            assigned_to: None,
+            number: None,
+            reference: None,
+            stale_since: None,
+        }
+    }
+
+    /// Prefix of human-readable ticket references.
+    ///
+    /// # Environment Variables
+    /// * `TICKET_NUMBER_PREFIX` - Reference prefix (default `AH`)
+    pub fn number_prefix() -> String {
+        std::env::var("TICKET_NUMBER_PREFIX")
+            .ok()
+            .map(|p| p.trim().to_uppercase())
+            .filter(|p| !p.is_empty())
+            .unwrap_or_else(|| "AH".to_string())
+    }
+
+    /// Formats a ticket number as a reference such as `AH-1234`.
+    pub fn format_reference(number: i64) -> String {
+        format!("{}-{}", Self::number_prefix(), number)
+    }
+
+    /// Parses a ticket reference into a ticket number.
+    ///
+    /// Accepts `AH-1234`, `#1234` and `1234`; the prefix is case-insensitive.
+    pub fn parse_reference(reference: &str) -> Option<i64> {
+        let reference = reference.trim().to_uppercase();
+        let prefix = format!("{}-", Self::number_prefix());
+        let number = reference
+            .strip_prefix(&prefix)
+            .or_else(|| reference.strip_prefix('#'))
+            .unwrap_or(&reference);
+
+        number.parse::<i64>().ok().filter(|n| *n > 0)
+    }
+
+    /// Sets the ticket number and reference.
+    fn set_number(&mut self, number: i64) {
+        self.number = Some(number);
+        self.reference = Some(Self::format_reference(number));
+    }
+
+    /// Finds a ticket by its number or reference.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    /// * `reference` - Ticket reference such as `AH-1234`
+    ///
+    /// # Returns
+    /// * `Result<Option<Ticket>, TicketError>` - Found ticket or error
+    pub async fn find_by_reference(
+        pool: &Pool,
+        reference: &str,
+    ) -> Result<Option<Ticket>, TicketError> {
+        let number = match Self::parse_reference(reference) {
+            Some(number) => number,
+            None => return Ok(None),
+        };
+
+        let client = pool.get().await?;
+        let row = client
+            .query_opt("SELECT id FROM tickets WHERE number = $1", &[&number])
+            .await?;
+
+        match row {
+            Some(row) => Self::find_by_id(pool, row.get("id")).await,
+            None => Ok(None),
         }
     }
 
     /// Persists ticket to database and search index.
     ///
+    /// Assigns the next sequential ticket number.
+    ///
     /// # Arguments
     /// * `pool` - Database connection pool
     ///
     /// # Returns
     /// * `Result<Uuid, TicketError>` - Ticket ID or error
-    pub async fn save(&self, pool: &Pool) -> Result<Uuid, TicketError> {
+    pub async fn save(&mut self, pool: &Pool) -> Result<Uuid, TicketError> {
         log::info!("Saving ticket {}", self.id);
         let client = pool.get().await?;
 
@@ -312,9 +507,9 @@ impl Ticket {
                 "INSERT INTO tickets (
                     id, ticket_type, status, ip_address, subject, description,
                     confidence_score, identified_threats, extracted_indicators, analysis_summary,
-                    created_at, updated_at
-                ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8::text[], $9::text[], $10, $11, $12) 
-                RETURNING id",
+                    created_at, updated_at, tags, custom_fields, customer_uuid, customer_link_source
+                ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8::text[], $9::text[], $10, $11, $12, $13::text[], $14, $15, $16) 
+                RETURNING id, number",
             )
             .await?;
 
@@ -335,20 +530,39 @@ impl Ticket {
                     &self.analysis_summary,
                     &self.created_at,
                     &self.updated_at,
+                    &self.tags,
+                    &json!(self.custom_fields),
+                    &self.customer_uuid,
+                    &self.customer_link_source.map(|s| s.to_string()),
                 ],
             )
             .await?;
 
         let ticket_id = row.get("id");
+        self.set_number(row.get("number"));
 
         // Index to ElasticSearch
         if let Err(e) = self.index_to_es().await {
             log::error!("Failed to index ticket to ElasticSearch: {}", e);
         }
 
+        self.publish_created();
         Ok(ticket_id)
     }
 
+    /// Announces the ticket on the live event bus.
+    ///
+    /// Called by `save`; callers of `save_with_client` call it after
+    /// committing their transaction.
+    pub fn publish_created(&self) {
+        events::publish(Event::TicketCreated {
+            ticket_id: self.id,
+            reference: self.reference.clone(),
+            ticket_type: self.ticket_type.clone(),
+            subject: self.subject.clone(),
+        });
+    }
+
     /// Associates an email with the ticket.
     ///
     /// # Arguments
@@ -378,13 +592,35 @@ impl Ticket {
         }
 
         // Insert the email association into the database
-        client
+        let inserted = client
             .execute(
                 "INSERT INTO email_tickets (email_id, ticket_id) VALUES ($1, $2) ON CONFLICT DO NOTHING",
                 &[&email_id, &self.id],
             )
             .await?;
 
+        // Notify the watchers about the new email
+        if inserted > 0 {
+            Notification::notify_watchers(
+                &**client,
+                &self.id,
+                NotificationKind::EmailLinked,
+                &format!("Email {} was linked", email_id),
+                None,
+            )
+            .await?;
+        }
+
+        // Reopen the ticket if it was closed and the email is new to it
+        if inserted > 0 && Self::reopen_for_email(&**client, &self.id, email_id).await? {
+            if let Some(ticket) = Self::find_by_id(pool, self.id).await? {
+                if let Err(e) = ticket.index_to_es().await {
+                    log::error!("Failed to index ticket to ElasticSearch: {}", e);
+                }
+            }
+            return Ok(());
+        }
+
         // Update ElasticSearch
         let es_client = ESClient::new()
             .await
@@ -407,6 +643,78 @@ impl Ticket {
         Ok(())
     }
 
+    /// Reopens a closed ticket after a new email was linked to it.
+    ///
+    /// Records the transition in the ticket history, notifies the ticket's
+    /// watchers and publishes a live event, so `client` should not be an
+    /// uncommitted transaction.
+    ///
+    /// # Arguments
+    /// * `client` - Database client for executing queries
+    /// * `ticket_id` - Ticket the email was linked to
+    /// * `email_id` - Newly linked email
+    ///
+    /// # Returns
+    /// * `Result<bool, TicketError>` - Whether the ticket was reopened
+    pub async fn reopen_for_email<C: GenericClient>(
+        client: &C,
+        ticket_id: &Uuid,
+        email_id: &Uuid,
+    ) -> Result<bool, TicketError> {
+        let reopened = client
+            .execute(
+                "UPDATE tickets SET status = $1, stale_since = NULL, updated_at = NOW()
+                 WHERE id = $2 AND status = $3 AND merged_into IS NULL",
+                &[
+                    &TicketStatus::Open.to_string(),
+                    ticket_id,
+                    &TicketStatus::Closed.to_string(),
+                ],
+            )
+            .await?;
+
+        if reopened == 0 {
+            return Ok(false);
+        }
+
+        TicketHistory::new(
+            *ticket_id,
+            TicketEvent::Reopened,
+            Some(TicketStatus::Closed),
+            Some(TicketStatus::Open),
+            Some(format!("New email {} was linked", email_id)),
+        )
+        .save(client)
+        .await?;
+
+        Notification::notify_watchers(
+            client,
+            ticket_id,
+            NotificationKind::StatusChanged,
+            &format!(
+                "Status changed from {} to {} after email {} was linked",
+                TicketStatus::Closed,
+                TicketStatus::Open,
+                email_id
+            ),
+            None,
+        )
+        .await?;
+
+        events::publish(Event::TicketStatusChanged {
+            ticket_id: *ticket_id,
+            from: Some(TicketStatus::Closed),
+            to: TicketStatus::Open,
+        });
+
+        log::info!(
+            "Reopened ticket {} after email {} was linked",
+            ticket_id,
+            email_id
+        );
+        Ok(true)
+    }
+
     /// Removes an email association.
     ///
     /// # Arguments
@@ -461,6 +769,7 @@ impl Ticket {
     /// # Arguments
     /// * `pool` - Database connection pool
     /// * `status` - New status
+    /// * `actor` - User making the change
     ///
     /// # Returns
     /// * `Result<(), TicketError>` - Success or error
@@ -468,36 +777,60 @@ impl Ticket {
         &mut self,
         pool: &Pool,
         status: TicketStatus,
+        actor: Option<Uuid>,
     ) -> Result<(), TicketError> {
         log::info!("Updating ticket {} status to {:?}", self.id, status);
-        let client = pool.get().await?;
+        let mut client = pool.get().await?;
+        let tx = client.transaction().await?;
 
         // Update the ticket status in the database
-        let row = client
+        let row = tx
             .query_one(
                 "UPDATE tickets SET status = $1, updated_at = NOW() WHERE id = $2 RETURNING updated_at",
                 &[&status.to_string(), &self.id],
             )
             .await?;
 
-        // Update ElasticSearch
-        let es_client = ESClient::new()
-            .await
-            .map_err(|e| TicketError::Pool(e.to_string()))?;
+        // Notify the watchers if the status changed
+        let changed = self.status.to_string() != status.to_string();
+        if changed {
+            Notification::notify_watchers(
+                &*tx,
+                &self.id,
+                NotificationKind::StatusChanged,
+                &format!("Status changed from {} to {}", self.status, status),
+                actor,
+            )
+            .await?;
+        }
+
+        tx.commit().await?;
+        if changed {
+            events::publish(Event::TicketStatusChanged {
+                ticket_id: self.id,
+                from: Some(self.status),
+                to: status,
+            });
+        }
 
         // Update the ElasticSearch document with the new status
-        if let Err(e) = es_client
-            .update_document(
-                "tickets",
-                &self.id.to_string(),
-                &json!({
-                    "status": status.to_string(),
-                    "updated_at": row.get::<_, DateTime<Utc>>("updated_at")
-                }),
-            )
-            .await
-        {
-            log::error!("Failed to update ticket in ElasticSearch: {}", e);
+        match ESClient::new().await {
+            Ok(es_client) => {
+                if let Err(e) = es_client
+                    .update_document(
+                        "tickets",
+                        &self.id.to_string(),
+                        &json!({
+                            "status": status.to_string(),
+                            "updated_at": row.get::<_, DateTime<Utc>>("updated_at")
+                        }),
+                    )
+                    .await
+                {
+                    log::error!("Failed to update ticket in ElasticSearch: {}", e);
+                }
+            }
+            Err(e) => log::error!("Failed to connect to ElasticSearch: {}", e),
         }
 
         self.status = status;
@@ -505,6 +838,578 @@ impl Ticket {
         Ok(())
     }
 
+    /// Replaces the ticket's tags.
+    ///
+    /// Tags are trimmed and deduplicated; empty tags are dropped.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    /// * `tags` - New set of tags
+    ///
+    /// # Returns
+    /// * `Result<(), TicketError>` - Success or error
+    pub async fn set_tags(&mut self, pool: &Pool, tags: Vec<String>) -> Result<(), TicketError> {
+        let tags = Self::normalize_tags(tags)?;
+        log::info!("Updating ticket {} tags to {:?}", self.id, tags);
+        let client = pool.get().await?;
+
+        // Update the tags in the database
+        let row = client
+            .query_one(
+                "UPDATE tickets SET tags = $1, updated_at = NOW() WHERE id = $2 RETURNING updated_at",
+                &[&tags, &self.id],
+            )
+            .await?;
+
+        // Update the ElasticSearch document with the new tags
+        match ESClient::new().await {
+            Ok(es_client) => {
+                if let Err(e) = es_client
+                    .update_document(
+                        "tickets",
+                        &self.id.to_string(),
+                        &json!({
+                            "tags": tags,
+                            "updated_at": row.get::<_, DateTime<Utc>>("updated_at")
+                        }),
+                    )
+                    .await
+                {
+                    log::error!("Failed to update ticket in ElasticSearch: {}", e);
+                }
+            }
+            Err(e) => log::error!("Failed to connect to ElasticSearch: {}", e),
+        }
+
+        self.tags = tags;
+        self.updated_at = row.get("updated_at");
+        Ok(())
+    }
+
+    /// Sets custom field values on the ticket.
+    ///
+    /// Each value is validated against its field definition. A null value
+    /// clears the field; fields not listed are left unchanged.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    /// * `fields` - Custom field values by field name
+    ///
+    /// # Returns
+    /// * `Result<(), TicketError>` - Success or error
+    pub async fn update_custom_fields(
+        &mut self,
+        pool: &Pool,
+        fields: HashMap<String, Value>,
+    ) -> Result<(), TicketError> {
+        log::info!("Updating ticket {} custom fields", self.id);
+        let client = pool.get().await?;
+
+        // Validate the values against the field definitions
+        let definitions = CustomFieldDefinition::list_with_client(&**client).await?;
+        let mut custom_fields = self.custom_fields.clone();
+        for (name, value) in fields {
+            let definition = definitions
+                .iter()
+                .find(|d| d.name == name)
+                .ok_or_else(|| TicketError::Validation(format!("Unknown custom field {}", name)))?;
+
+            if value.is_null() {
+                custom_fields.remove(&name);
+            } else {
+                custom_fields.insert(name, definition.validate_value(&value)?);
+            }
+        }
+
+        // Update the custom fields in the database
+        let row = client
+            .query_one(
+                "UPDATE tickets SET custom_fields = $1, updated_at = NOW() WHERE id = $2 RETURNING updated_at",
+                &[&json!(custom_fields), &self.id],
+            )
+            .await?;
+
+        self.custom_fields = custom_fields;
+        self.updated_at = row.get("updated_at");
+
+        // Reindex the whole document, partial updates would keep cleared fields
+        if let Err(e) = self.index_to_es().await {
+            log::error!("Failed to index ticket to ElasticSearch: {}", e);
+        }
+
+        Ok(())
+    }
+
+    /// Trims, deduplicates and validates tags.
+    fn normalize_tags(tags: Vec<String>) -> Result<Vec<String>, TicketError> {
+        let mut normalized: Vec<String> = Vec::new();
+        for tag in tags {
+            let tag = tag.trim().to_string();
+            if tag.is_empty() || normalized.contains(&tag) {
+                continue;
+            }
+            if tag.len() > 64 {
+                return Err(TicketError::Validation(format!(
+                    "Tag {} is longer than 64 characters",
+                    tag
+                )));
+            }
+            normalized.push(tag);
+        }
+        Ok(normalized)
+    }
+
+    /// Resolves the responsible customer for this ticket.
+    ///
+    /// Matches the ticket's IP address first, then the senders and
+    /// recipients of the given emails. Manually linked customers are kept.
+    ///
+    /// # Arguments
+    /// * `client` - Database client for executing queries
+    /// * `email_ids` - Emails reporting this ticket
+    ///
+    /// # Returns
+    /// * `Result<(), TicketError>` - Success or error
+    pub async fn resolve_customer<C: GenericClient>(
+        &mut self,
+        client: &C,
+        email_ids: &[Uuid],
+    ) -> Result<(), TicketError> {
+        if self.customer_link_source == Some(CustomerLinkSource::Manual) {
+            return Ok(());
+        }
+
+        // Match by IP, then by sender, then by recipient address
+        let row = client
+            .query_opt(
+                "SELECT uuid, source FROM (
+                    SELECT c.uuid, 'Ip' AS source, 1 AS priority
+                    FROM customers c WHERE c.ip = $1
+                    UNION ALL
+                    SELECT c.uuid, 'Sender', 2
+                    FROM emails e
+                    JOIN customers c ON lower(c.email) = lower(trim(COALESCE(substring(e.sender from '<([^>]+)>'), e.sender)))
+                    WHERE e.id = ANY($2)
+                    UNION ALL
+                    SELECT c.uuid, 'Recipient', 3
+                    FROM emails e CROSS JOIN unnest(e.recipients) r
+                    JOIN customers c ON lower(c.email) = lower(trim(COALESCE(substring(r from '<([^>]+)>'), r)))
+                    WHERE e.id = ANY($2)
+                 ) matches
+                 ORDER BY priority LIMIT 1",
+                &[&self.ip_address, &email_ids],
+            )
+            .await?;
+
+        if let Some(row) = row {
+            self.customer_uuid = Some(row.get("uuid"));
+            self.customer_link_source =
+                Some(CustomerLinkSource::from(row.get::<_, String>("source")));
+            log::info!(
+                "Resolved customer {:?} for ticket {} by {:?}",
+                self.customer_uuid,
+                self.id,
+                self.customer_link_source
+            );
+        } else {
+            self.customer_uuid = None;
+            self.customer_link_source = None;
+        }
+
+        Ok(())
+    }
+
+    /// Links the ticket to a customer, overriding automatic resolution.
+    ///
+    /// Passing None removes the override and resolves the customer again
+    /// from the ticket's IP address and linked emails.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    /// * `customer_uuid` - Customer to link, or None to resolve automatically
+    ///
+    /// # Returns
+    /// * `Result<(), TicketError>` - Success or error
+    pub async fn set_customer(
+        &mut self,
+        pool: &Pool,
+        customer_uuid: Option<Uuid>,
+    ) -> Result<(), TicketError> {
+        let client = pool.get().await?;
+
+        match customer_uuid {
+            Some(uuid) => {
+                // Verify the customer exists
+                let exists = client
+                    .query_one(
+                        "SELECT EXISTS(SELECT 1 FROM customers WHERE uuid = $1)",
+                        &[&uuid],
+                    )
+                    .await?
+                    .get::<_, bool>(0);
+                if !exists {
+                    return Err(TicketError::NotFound(format!(
+                        "Customer {} not found",
+                        uuid
+                    )));
+                }
+
+                self.customer_uuid = Some(uuid);
+                self.customer_link_source = Some(CustomerLinkSource::Manual);
+            }
+            None => {
+                self.customer_link_source = None;
+                let email_ids = self.get_emails(pool).await?;
+                self.resolve_customer(&**client, &email_ids).await?;
+            }
+        }
+
+        // Store the link
+        let row = client
+            .query_one(
+                "UPDATE tickets SET customer_uuid = $1, customer_link_source = $2, updated_at = NOW()
+                 WHERE id = $3 RETURNING updated_at",
+                &[
+                    &self.customer_uuid,
+                    &self.customer_link_source.map(|s| s.to_string()),
+                    &self.id,
+                ],
+            )
+            .await?;
+        self.updated_at = row.get("updated_at");
+
+        log::info!(
+            "Linked ticket {} to customer {:?}",
+            self.id,
+            self.customer_uuid
+        );
+
+        // Update the ElasticSearch document with the new customer
+        match ESClient::new().await {
+            Ok(es_client) => {
+                if let Err(e) = es_client
+                    .update_document(
+                        "tickets",
+                        &self.id.to_string(),
+                        &json!({
+                            "customer_uuid": self.customer_uuid,
+                            "updated_at": self.updated_at
+                        }),
+                    )
+                    .await
+                {
+                    log::error!("Failed to update ticket in ElasticSearch: {}", e);
+                }
+            }
+            Err(e) => log::error!("Failed to connect to ElasticSearch: {}", e),
+        }
+
+        Ok(())
+    }
+
+    /// Lists a customer's tickets, newest first.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    /// * `customer_uuid` - Customer identifier
+    ///
+    /// # Returns
+    /// * `Result<Vec<Ticket>, TicketError>` - Customer's tickets or error
+    pub async fn list_by_customer(
+        pool: &Pool,
+        customer_uuid: Uuid,
+    ) -> Result<Vec<Ticket>, TicketError> {
+        log::info!("Fetching tickets for customer {}", customer_uuid);
+        let client = pool.get().await?;
+
+        // Fetch the customer's tickets with associated emails
+        let rows = client
+            .query(
+                "SELECT t.*, COALESCE(array_agg(et.email_id) FILTER (WHERE et.email_id IS NOT NULL), ARRAY[]::uuid[]) as email_ids 
+                 FROM tickets t 
+                 LEFT JOIN email_tickets et ON t.id = et.ticket_id 
+                 WHERE t.customer_uuid = $1 AND t.merged_into IS NULL 
+                 GROUP BY t.id 
+                 ORDER BY t.created_at DESC",
+                &[&customer_uuid],
+            )
+            .await?;
+
+        // Convert rows to tickets
+        Ok(rows
+            .iter()
+            .map(|row| {
+                let mut ticket = Ticket::from(row.clone());
+                ticket.email_ids = row.get("email_ids");
+                ticket
+            })
+            .collect())
+    }
+
+    /// Applies an operation to many tickets in one transaction.
+    ///
+    /// Each ticket is updated within its own savepoint so that a failing
+    /// ticket does not affect the others. In atomic mode any failure rolls
+    /// back the whole operation.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    /// * `ticket_ids` - Tickets to update
+    /// * `action` - Operation to apply
+    /// * `atomic` - Roll back all tickets if any ticket fails
+    /// * `actor` - User making the change
+    ///
+    /// # Returns
+    /// * `Result<Vec<BulkTicketResult>, TicketError>` - Per-ticket results or error
+    pub async fn bulk_update(
+        pool: &Pool,
+        ticket_ids: &[Uuid],
+        action: &BulkAction,
+        atomic: bool,
+        actor: Option<Uuid>,
+    ) -> Result<Vec<BulkTicketResult>, TicketError> {
+        // Deduplicate the tickets while keeping their order
+        let mut ids: Vec<Uuid> = Vec::new();
+        for id in ticket_ids {
+            if !ids.contains(id) {
+                ids.push(*id);
+            }
+        }
+
+        if ids.is_empty() {
+            return Err(TicketError::Validation("No tickets selected".into()));
+        }
+        if ids.len() > BULK_LIMIT {
+            return Err(TicketError::Validation(format!(
+                "Bulk operations are limited to {} tickets",
+                BULK_LIMIT
+            )));
+        }
+
+        // Normalize tags up front so every ticket gets the same set
+        let action = match action {
+            BulkAction::AddTags { tags } => BulkAction::AddTags {
+                tags: Self::normalize_tags(tags.clone())?,
+            },
+            BulkAction::RemoveTags { tags } => BulkAction::RemoveTags {
+                tags: Self::normalize_tags(tags.clone())?,
+            },
+            action => action.clone(),
+        };
+
+        log::info!("Applying {:?} to {} tickets", action, ids.len());
+        let mut client = pool.get().await?;
+        let mut tx = client.transaction().await?;
+
+        // Verify the assignee exists
+        if let BulkAction::Assign {
+            user_id: Some(user_id),
+        } = &action
+        {
+            let exists = tx
+                .query_one(
+                    "SELECT EXISTS(SELECT 1 FROM users WHERE uuid = $1)",
+                    &[user_id],
+                )
+                .await?
+                .get::<_, bool>(0);
+            if !exists {
+                return Err(TicketError::Validation(format!(
+                    "User {} does not exist",
+                    user_id
+                )));
+            }
+        }
+
+        let mut results = Vec::with_capacity(ids.len());
+        let mut live_events = Vec::new();
+        for id in &ids {
+            // Apply the action within a savepoint
+            let savepoint = tx.transaction().await?;
+            let outcome = Self::apply_bulk_action(&*savepoint, id, &action, actor).await;
+            let error = match outcome {
+                Ok(event) => {
+                    savepoint.commit().await?;
+                    live_events.extend(event);
+                    None
+                }
+                Err(e) => {
+                    savepoint.rollback().await?;
+                    log::warn!("Bulk operation failed for ticket {}: {}", id, e);
+                    Some(e.to_string())
+                }
+            };
+
+            results.push(BulkTicketResult {
+                ticket_id: *id,
+                success: error.is_none(),
+                error,
+            });
+        }
+
+        // In atomic mode, a single failure cancels the whole operation
+        if atomic && results.iter().any(|r| !r.success) {
+            tx.rollback().await?;
+            for result in results.iter_mut().filter(|r| r.success) {
+                result.success = false;
+                result.error = Some("Rolled back".to_string());
+            }
+            return Ok(results);
+        }
+
+        tx.commit().await?;
+        live_events.into_iter().for_each(events::publish);
+
+        // Synchronize ElasticSearch with the updated tickets
+        let updated: Vec<Uuid> = results
+            .iter()
+            .filter(|r| r.success)
+            .map(|r| r.ticket_id)
+            .collect();
+        match action {
+            BulkAction::Delete => match ESClient::new().await {
+                Ok(es_client) => {
+                    for id in &updated {
+                        if let Err(e) = es_client.delete_document("tickets", &id.to_string()).await
+                        {
+                            log::error!("Failed to remove ticket {} from ElasticSearch: {}", id, e);
+                        }
+                    }
+                }
+                Err(e) => log::error!("Failed to connect to ElasticSearch: {}", e),
+            },
+            _ => {
+                for id in &updated {
+                    if let Some(ticket) = Self::find_by_id(pool, *id).await? {
+                        if let Err(e) = ticket.index_to_es().await {
+                            log::error!("Failed to index ticket to ElasticSearch: {}", e);
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(results)
+    }
+
+    /// Applies a bulk operation to a single ticket.
+    ///
+    /// # Returns
+    /// * `Result<Option<Event>, TicketError>` - Live event to publish once committed, or error
+    async fn apply_bulk_action<C: GenericClient>(
+        client: &C,
+        id: &Uuid,
+        action: &BulkAction,
+        actor: Option<Uuid>,
+    ) -> Result<Option<Event>, TicketError> {
+        let mut event = None;
+        let updated = match action {
+            BulkAction::Status { status } => {
+                let row = client
+                    .query_opt(
+                        "UPDATE tickets t SET status = $1, updated_at = NOW()
+                         FROM tickets old
+                         WHERE t.id = $2 AND old.id = t.id AND t.merged_into IS NULL
+                         RETURNING old.status",
+                        &[&status.to_string(), id],
+                    )
+                    .await?;
+
+                // Notify the watchers if the status changed
+                if let Some(row) = &row {
+                    let old_status: String = row.get("status");
+                    if old_status != status.to_string() {
+                        Notification::notify_watchers(
+                            client,
+                            id,
+                            NotificationKind::StatusChanged,
+                            &format!("Status changed from {} to {}", old_status, status),
+                            actor,
+                        )
+                        .await?;
+                        event = Some(Event::TicketStatusChanged {
+                            ticket_id: *id,
+                            from: Some(TicketStatus::from(old_status)),
+                            to: *status,
+                        });
+                    }
+                }
+                row.map_or(0, |_| 1)
+            }
+            BulkAction::Assign { user_id } => {
+                let row = client
+                    .query_opt(
+                        "UPDATE tickets t SET assigned_to = $1, updated_at = NOW()
+                         FROM tickets old
+                         WHERE t.id = $2 AND old.id = t.id AND t.merged_into IS NULL
+                         RETURNING old.assigned_to",
+                        &[user_id, id],
+                    )
+                    .await?;
+
+                // Assignees follow their tickets, then the watchers are notified
+                if let Some(row) = &row {
+                    let old_assignee: Option<Uuid> = row.get("assigned_to");
+                    if old_assignee != *user_id {
+                        if let Some(user_id) = user_id {
+                            TicketWatcher::watch(client, id, user_id).await?;
+                        }
+                        let message = match user_id {
+                            Some(user_id) => format!("Assigned to {}", user_id),
+                            None => "Unassigned".to_string(),
+                        };
+                        Notification::notify_watchers(
+                            client,
+                            id,
+                            NotificationKind::Assigned,
+                            &message,
+                            actor,
+                        )
+                        .await?;
+                    }
+                }
+                row.map_or(0, |_| 1)
+            }
+            BulkAction::AddTags { tags } => {
+                client
+                    .execute(
+                        "UPDATE tickets SET
+                            tags = ARRAY(
+                                SELECT t FROM unnest(tags || $1::text[]) WITH ORDINALITY AS u(t, n)
+                                GROUP BY t ORDER BY min(n)
+                            ),
+                            updated_at = NOW()
+                         WHERE id = $2 AND merged_into IS NULL",
+                        &[tags, id],
+                    )
+                    .await?
+            }
+            BulkAction::RemoveTags { tags } => {
+                client
+                    .execute(
+                        "UPDATE tickets SET
+                            tags = ARRAY(SELECT t FROM unnest(tags) t WHERE t <> ALL($1)),
+                            updated_at = NOW()
+                         WHERE id = $2 AND merged_into IS NULL",
+                        &[tags, id],
+                    )
+                    .await?
+            }
+            BulkAction::Delete => {
+                client
+                    .execute("DELETE FROM tickets WHERE id = $1", &[id])
+                    .await?
+            }
+        };
+
+        // If no ticket was updated, return an error
+        if updated == 0 {
+            return Err(TicketError::NotFound(format!("Ticket {} not found", id)));
+        }
+
+        Ok(event)
+    }
+
     /// Retrieves associated email IDs.
     ///
     /// # Arguments
@@ -542,6 +1447,7 @@ impl Ticket {
                 "SELECT t.*, COALESCE(array_agg(et.email_id) FILTER (WHERE et.email_id IS NOT NULL), ARRAY[]::uuid[]) as email_ids 
                  FROM tickets t 
                  LEFT JOIN email_tickets et ON t.id = et.ticket_id 
+                 WHERE t.merged_into IS NULL 
                  GROUP BY t.id 
                  ORDER BY t.created_at DESC",
                 &[],
@@ -563,6 +1469,9 @@ impl Ticket {
 
     /// Finds a specific ticket by ID.
     ///
+    /// Lookups of a merged ticket are redirected to the ticket it was
+    /// merged into.
+    ///
     /// # Arguments
     /// * `pool` - Database connection pool
     /// * `id` - Ticket identifier
@@ -573,6 +1482,17 @@ impl Ticket {
         log::info!("Finding ticket {}", id);
         let client = pool.get().await?;
 
+        // Follow the merge redirect to the surviving ticket
+        let id = match Self::resolve_id(&**client, id).await? {
+            Some(resolved) => {
+                if resolved != id {
+                    log::info!("Ticket {} was merged, redirecting to {}", id, resolved);
+                }
+                resolved
+            }
+            None => return Ok(None),
+        };
+
         // Fetch the ticket with associated emails
         let row = client
             .query_opt(
@@ -593,9 +1513,324 @@ impl Ticket {
         }))
     }
 
+    /// Resolves a ticket ID through merge redirects.
+    ///
+    /// # Arguments
+    /// * `client` - Database client for executing queries
+    /// * `id` - Ticket identifier, possibly of a merged ticket
+    ///
+    /// # Returns
+    /// * `Result<Option<Uuid>, TicketError>` - Surviving ticket ID or None if missing
+    pub async fn resolve_id<C: GenericClient>(
+        client: &C,
+        id: Uuid,
+    ) -> Result<Option<Uuid>, TicketError> {
+        let row = client
+            .query_opt(
+                "WITH RECURSIVE chain AS (
+                    SELECT id, merged_into, 0 AS depth FROM tickets WHERE id = $1
+                    UNION ALL
+                    SELECT t.id, t.merged_into, c.depth + 1
+                    FROM tickets t JOIN chain c ON t.id = c.merged_into
+                    WHERE c.depth < 16
+                 )
+                 SELECT id FROM chain WHERE merged_into IS NULL LIMIT 1",
+                &[&id],
+            )
+            .await?;
+
+        Ok(row.map(|r| r.get("id")))
+    }
+
+    /// Merges other tickets into this one.
+    ///
+    /// Moves all email links, comments, watchers, threat indicators and tags of
+    /// the merged tickets into this ticket. Custom field values already set on this ticket win. The merged tickets are closed and redirect to this ticket.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    /// * `ticket_ids` - Tickets to merge into this one
+    ///
+    /// # Returns
+    /// * `Result<Ticket, TicketError>` - Updated surviving ticket or error
+    pub async fn merge(&self, pool: &Pool, ticket_ids: &[Uuid]) -> Result<Ticket, TicketError> {
+        // Deduplicate the merged tickets and drop this ticket if listed
+        let mut source_ids: Vec<Uuid> = ticket_ids
+            .iter()
+            .copied()
+            .filter(|id| *id != self.id)
+            .collect();
+        source_ids.sort();
+        source_ids.dedup();
+
+        // If there is nothing to merge, return an error
+        if source_ids.is_empty() {
+            return Err(TicketError::Validation(
+                "At least one other ticket must be provided".into(),
+            ));
+        }
+
+        log::info!("Merging tickets {:?} into {}", source_ids, self.id);
+        let mut client = pool.get().await?;
+        let tx = client.transaction().await?;
+
+        // Lock every ticket involved in the merge
+        let rows = tx
+            .query(
+                "SELECT id, merged_into FROM tickets WHERE id = $1 OR id = ANY($2) FOR UPDATE",
+                &[&self.id, &source_ids],
+            )
+            .await?;
+
+        // Verify that all tickets exist and none were already merged
+        for id in std::iter::once(&self.id).chain(source_ids.iter()) {
+            match rows.iter().find(|row| row.get::<_, Uuid>("id") == *id) {
+                Some(row) => {
+                    if let Some(target) = row.get::<_, Option<Uuid>>("merged_into") {
+                        return Err(TicketError::Validation(format!(
+                            "Ticket {} was already merged into {}",
+                            id, target
+                        )));
+                    }
+                }
+                None => {
+                    return Err(TicketError::NotFound(format!("Ticket {} not found", id)));
+                }
+            }
+        }
+
+        // Move the email links into this ticket
+        tx.execute(
+            "INSERT INTO email_tickets (email_id, ticket_id, created_at)
+             SELECT email_id, $1, created_at FROM email_tickets WHERE ticket_id = ANY($2)
+             ON CONFLICT DO NOTHING",
+            &[&self.id, &source_ids],
+        )
+        .await?;
+        tx.execute(
+            "DELETE FROM email_tickets WHERE ticket_id = ANY($1)",
+            &[&source_ids],
+        )
+        .await?;
+
+        // Move the comments and watchers into this ticket
+        tx.execute(
+            "UPDATE ticket_comments SET ticket_id = $1 WHERE ticket_id = ANY($2)",
+            &[&self.id, &source_ids],
+        )
+        .await?;
+        tx.execute(
+            "INSERT INTO ticket_watchers (ticket_id, user_id, created_at)
+             SELECT $1, user_id, created_at FROM ticket_watchers WHERE ticket_id = ANY($2)
+             ON CONFLICT DO NOTHING",
+            &[&self.id, &source_ids],
+        )
+        .await?;
+        tx.execute(
+            "DELETE FROM ticket_watchers WHERE ticket_id = ANY($1)",
+            &[&source_ids],
+        )
+        .await?;
+
+        // Combine the threats and indicators of all merged tickets
+        tx.execute(
+            "UPDATE tickets SET
+                identified_threats = ARRAY(
+                    SELECT DISTINCT v FROM tickets s, unnest(s.identified_threats) v
+                    WHERE s.id = $1 OR s.id = ANY($2)
+                ),
+                extracted_indicators = ARRAY(
+                    SELECT DISTINCT v FROM tickets s, unnest(s.extracted_indicators) v
+                    WHERE s.id = $1 OR s.id = ANY($2)
+                ),
+                tags = ARRAY(
+                    SELECT DISTINCT v FROM tickets s, unnest(s.tags) v
+                    WHERE s.id = $1 OR s.id = ANY($2)
+                ),
+                custom_fields = (
+                    SELECT COALESCE(jsonb_object_agg(f.key, f.value), '{}'::jsonb)
+                    FROM tickets s, jsonb_each(s.custom_fields) f
+                    WHERE s.id = ANY($2)
+                ) || custom_fields,
+                customer_link_source = CASE WHEN customer_uuid IS NULL THEN (
+                    SELECT s.customer_link_source FROM tickets s
+                    WHERE s.id = ANY($2) AND s.customer_uuid IS NOT NULL
+                    ORDER BY s.created_at LIMIT 1
+                ) ELSE customer_link_source END,
+                customer_uuid = COALESCE(customer_uuid, (
+                    SELECT s.customer_uuid FROM tickets s
+                    WHERE s.id = ANY($2) AND s.customer_uuid IS NOT NULL
+                    ORDER BY s.created_at LIMIT 1
+                )),
+                updated_at = NOW()
+             WHERE id = $1",
+            &[&self.id, &source_ids],
+        )
+        .await?;
+
+        // Close the merged tickets and redirect them to this ticket
+        tx.execute(
+            "UPDATE tickets SET merged_into = $1, status = $2, updated_at = NOW() WHERE id = ANY($3)",
+            &[&self.id, &TicketStatus::Closed.to_string(), &source_ids],
+        )
+        .await?;
+
+        // Keep redirect chains flat for tickets previously merged into the sources
+        tx.execute(
+            "UPDATE tickets SET merged_into = $1 WHERE merged_into = ANY($2)",
+            &[&self.id, &source_ids],
+        )
+        .await?;
+
+        tx.commit().await?;
+
+        // Remove the merged tickets from ElasticSearch
+        match ESClient::new().await {
+            Ok(es_client) => {
+                for id in &source_ids {
+                    if let Err(e) = es_client.delete_document("tickets", &id.to_string()).await {
+                        log::error!(
+                            "Failed to remove merged ticket {} from ElasticSearch: {}",
+                            id,
+                            e
+                        );
+                    }
+                }
+            }
+            Err(e) => log::error!("Failed to connect to ElasticSearch: {}", e),
+        }
+
+        // Reload and reindex the surviving ticket
+        let merged = Self::find_by_id(pool, self.id)
+            .await?
+            .ok_or_else(|| TicketError::NotFound(format!("Ticket {} not found", self.id)))?;
+        if let Err(e) = merged.index_to_es().await {
+            log::error!("Failed to index ticket to ElasticSearch: {}", e);
+        }
+
+        log::info!("Merged {} tickets into {}", source_ids.len(), self.id);
+        Ok(merged)
+    }
+
+    /// Splits selected emails off into a new ticket.
+    ///
+    /// The new ticket inherits the classification and threat analysis of
+    /// this ticket, and the selected email links are moved to it.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    /// * `email_ids` - Emails to move to the new ticket
+    /// * `subject` - Optional subject for the new ticket
+    ///
+    /// # Returns
+    /// * `Result<Ticket, TicketError>` - Newly created ticket or error
+    pub async fn split(
+        &self,
+        pool: &Pool,
+        email_ids: &[Uuid],
+        subject: Option<String>,
+    ) -> Result<Ticket, TicketError> {
+        // Deduplicate the selected emails
+        let mut email_ids = email_ids.to_vec();
+        email_ids.sort();
+        email_ids.dedup();
+
+        // If no emails were selected, return an error
+        if email_ids.is_empty() {
+            return Err(TicketError::Validation(
+                "At least one email ID must be provided".into(),
+            ));
+        }
+
+        log::info!("Splitting emails {:?} off ticket {}", email_ids, self.id);
+        let mut client = pool.get().await?;
+        let tx = client.transaction().await?;
+
+        // Lock the source ticket
+        let merged_into = tx
+            .query_opt(
+                "SELECT merged_into FROM tickets WHERE id = $1 FOR UPDATE",
+                &[&self.id],
+            )
+            .await?
+            .ok_or_else(|| TicketError::NotFound(format!("Ticket {} not found", self.id)))?
+            .get::<_, Option<Uuid>>("merged_into");
+
+        // If the ticket was merged, return an error
+        if let Some(target) = merged_into {
+            return Err(TicketError::Validation(format!(
+                "Ticket {} was already merged into {}",
+                self.id, target
+            )));
+        }
+
+        // Verify that every selected email is linked to this ticket
+        let linked: Vec<Uuid> = tx
+            .query(
+                "SELECT email_id FROM email_tickets WHERE ticket_id = $1 AND email_id = ANY($2)",
+                &[&self.id, &email_ids],
+            )
+            .await?
+            .iter()
+            .map(|row| row.get("email_id"))
+            .collect();
+        if let Some(missing) = email_ids.iter().find(|id| !linked.contains(id)) {
+            return Err(TicketError::Validation(format!(
+                "Email {} is not linked to ticket {}",
+                missing, self.id
+            )));
+        }
+
+        // Create the new ticket from this ticket's analysis
+        let mut ticket = Ticket::new(
+            self.ticket_type.clone(),
+            subject.unwrap_or_else(|| format!("{} (split)", self.subject)),
+            self.description.clone(),
+            self.ip_address.clone(),
+            self.confidence_score,
+            self.identified_threats.clone(),
+            self.extracted_indicators.clone(),
+            self.analysis_summary.clone(),
+        );
+        ticket.tags = self.tags.clone();
+        ticket.custom_fields = self.custom_fields.clone();
+        ticket.customer_uuid = self.customer_uuid;
+        ticket.customer_link_source = self.customer_link_source;
+        ticket.save_with_client(&tx).await?;
+
+        // Move the selected email links to the new ticket
+        tx.execute(
+            "UPDATE email_tickets SET ticket_id = $1 WHERE ticket_id = $2 AND email_id = ANY($3)",
+            &[&ticket.id, &self.id, &email_ids],
+        )
+        .await?;
+        tx.execute(
+            "UPDATE tickets SET updated_at = NOW() WHERE id = $1",
+            &[&self.id],
+        )
+        .await?;
+
+        tx.commit().await?;
+        ticket.email_ids = email_ids;
+        ticket.publish_created();
+
+        // Index the new ticket and reindex the source ticket
+        if let Err(e) = ticket.index_to_es().await {
+            log::error!("Failed to index ticket to ElasticSearch: {}", e);
+        }
+        if let Some(source) = Self::find_by_id(pool, self.id).await? {
+            if let Err(e) = source.index_to_es().await {
+                log::error!("Failed to index ticket to ElasticSearch: {}", e);
+            }
+        }
+
+        log::info!("Split ticket {} off ticket {}", ticket.id, self.id);
+        Ok(ticket)
+    }
+
     /// Save ticket to database using a specific client (for transactions)
     pub async fn save_with_client(
-        &self,
+        &mut self,
         client: &tokio_postgres::Transaction<'_>,
     ) -> Result<Uuid, TicketError> {
         log::info!("Saving ticket {}", self.id);
@@ -606,9 +1841,9 @@ impl Ticket {
                 "INSERT INTO tickets (
                     id, ticket_type, status, ip_address, subject, description,
                     confidence_score, identified_threats, extracted_indicators, analysis_summary,
-                    created_at, updated_at
-                ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8::text[], $9::text[], $10, $11, $12) 
-                RETURNING id",
+                    created_at, updated_at, tags, custom_fields, customer_uuid, customer_link_source
+                ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8::text[], $9::text[], $10, $11, $12, $13::text[], $14, $15, $16) 
+                RETURNING id, number",
             )
             .await?;
 
@@ -629,10 +1864,15 @@ impl Ticket {
                     &self.analysis_summary,
                     &self.created_at,
                     &self.updated_at,
+                    &self.tags,
+                    &json!(self.custom_fields),
+                    &self.customer_uuid,
+                    &self.customer_link_source.map(|s| s.to_string()),
                 ],
             )
             .await?;
 
+        self.set_number(row.get("number"));
         Ok(row.get("id"))
     }
 
@@ -680,16 +1920,36 @@ impl Ticket {
     pub async fn search(options: SearchOptions) -> Result<SearchResponse, TicketError> {
         let client = ESClient::new().await?;
 
-        // Build the search query
+        // Build the search query, an empty query matches all tickets
+        let text_query = if options.query.trim().is_empty() {
+            json!({ "match_all": {} })
+        } else {
+            json!({
+                "bool": {
+                    "should": [
+                        {
+                            "multi_match": {
+                                "query": options.query,
+                                "fields": ["subject^2", "description", "ip_address"],
+                                "fuzziness": "AUTO"
+                            }
+                        },
+                        {
+                            "term": {
+                                "reference": {
+                                    "value": options.query.trim().to_uppercase(),
+                                    "boost": 10.0
+                                }
+                            }
+                        }
+                    ],
+                    "minimum_should_match": 1
+                }
+            })
+        };
         let mut query = json!({
             "bool": {
-                "must": [{
-                    "multi_match": {
-                        "query": options.query,
-                        "fields": ["subject^2", "description", "ip_address"],
-                        "fuzziness": "AUTO"
-                    }
-                }],
+                "must": [text_query],
                 "filter": []
             }
         });
@@ -711,6 +1971,35 @@ impl Ticket {
                     .push(json!({"term": {"ticket_type.keyword": ticket_type.to_string()}}));
             }
 
+            // Add tag filters if provided
+            for tag in filters.tags.unwrap_or_default() {
+                query["bool"]["filter"]
+                    .as_array_mut()
+                    .unwrap()
+                    .push(json!({"term": {"tags": tag}}));
+            }
+
+            // Add custom field filters if provided
+            for (name, value) in filters.custom_fields.unwrap_or_default() {
+                query["bool"]["filter"]
+                    .as_array_mut()
+                    .unwrap()
+                    .push(json!({"term": {format!("custom_fields.{}", name): value}}));
+            }
+
+            // Add inactivity filter if provided
+            if let Some(stale) = filters.stale {
+                let exists = json!({"exists": {"field": "stale_since"}});
+                query["bool"]["filter"]
+                    .as_array_mut()
+                    .unwrap()
+                    .push(if stale {
+                        exists
+                    } else {
+                        json!({"bool": {"must_not": exists}})
+                    });
+            }
+
             // Add email filter if provided
             if let Some(has_emails) = filters.has_emails {
                 query["bool"]["filter"]
@@ -782,7 +2071,14 @@ impl Ticket {
             "analysis_summary": self.analysis_summary,
             "created_at": self.created_at,
             "updated_at": self.updated_at,
-            "email_ids": self.email_ids
+            "email_ids": self.email_ids,
+            "tags": self.tags,
+            "custom_fields": self.custom_fields,
+            "customer_uuid": self.customer_uuid,
+            "assigned_to": self.assigned_to,
+            "number": self.number,
+            "reference": self.reference,
+            "stale_since": self.stale_since
         });
 
         // Index the document
@@ -0,0 +1,143 @@
+use crate::models::notification::{Notification, NotificationKind};
+use crate::models::ticket::{Ticket, TicketError};
+use chrono::{DateTime, Utc};
+use deadpool_postgres::Pool;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::Row;
+use uuid::Uuid;
+
+/// Maximum comment length in characters.
+const MAX_COMMENT_LENGTH: usize = 10_000;
+
+/// Comment on a ticket.
+///
+/// # Fields
+/// * `id` - Unique identifier
+/// * `ticket_id` - Commented ticket
+/// * `author` - User who wrote the comment
+/// * `body` - Comment text
+/// * `created_at` - Creation timestamp
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TicketComment {
+    pub id: Uuid,
+    pub ticket_id: Uuid,
+    pub author: Option<Uuid>,
+    pub body: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<Row> for TicketComment {
+    fn from(row: Row) -> Self {
+        TicketComment {
+            id: row.get("id"),
+            ticket_id: row.get("ticket_id"),
+            author: row.get("author"),
+            body: row.get("body"),
+            created_at: row.get("created_at"),
+        }
+    }
+}
+
+impl TicketComment {
+    /// Creates a new comment.
+    ///
+    /// # Arguments
+    /// * `ticket_id` - Commented ticket
+    /// * `author` - User who wrote the comment
+    /// * `body` - Comment text
+    pub fn new(ticket_id: Uuid, author: Option<Uuid>, body: String) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            ticket_id,
+            author,
+            body: body.trim().to_string(),
+            created_at: Utc::now(),
+        }
+    }
+
+    /// Stores the comment and notifies the ticket's watchers.
+    ///
+    /// Comments on a merged ticket are stored on the surviving ticket.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    ///
+    /// # Returns
+    /// * `Result<(), TicketError>` - Success or error
+    pub async fn save(&mut self, pool: &Pool) -> Result<(), TicketError> {
+        // Validate the comment
+        if self.body.is_empty() {
+            return Err(TicketError::Validation("Comment cannot be empty".into()));
+        }
+        if self.body.chars().count() > MAX_COMMENT_LENGTH {
+            return Err(TicketError::Validation(format!(
+                "Comment cannot be longer than {} characters",
+                MAX_COMMENT_LENGTH
+            )));
+        }
+
+        let mut client = pool.get().await?;
+        let tx = client.transaction().await?;
+
+        // Follow the merge redirect to the surviving ticket
+        self.ticket_id = Ticket::resolve_id(&*tx, self.ticket_id)
+            .await?
+            .ok_or_else(|| TicketError::NotFound(format!("Ticket {} not found", self.ticket_id)))?;
+
+        // Insert the comment
+        tx.execute(
+            "INSERT INTO ticket_comments (id, ticket_id, author, body, created_at)
+             VALUES ($1, $2, $3, $4, $5)",
+            &[
+                &self.id,
+                &self.ticket_id,
+                &self.author,
+                &self.body,
+                &self.created_at,
+            ],
+        )
+        .await?;
+
+        // Notify the watchers with a short excerpt
+        let excerpt: String = self.body.chars().take(140).collect();
+        Notification::notify_watchers(
+            &*tx,
+            &self.ticket_id,
+            NotificationKind::Comment,
+            &format!("New comment: {}", excerpt),
+            self.author,
+        )
+        .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Lists a ticket's comments, oldest first.
+    ///
+    /// For a merged ticket, lists the comments of the surviving ticket.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    /// * `ticket_id` - Ticket identifier
+    ///
+    /// # Returns
+    /// * `Result<Vec<TicketComment>, TicketError>` - Comments or error
+    pub async fn list_for_ticket(pool: &Pool, ticket_id: Uuid) -> Result<Vec<Self>, TicketError> {
+        let client = pool.get().await?;
+
+        // Follow the merge redirect to the surviving ticket
+        let ticket_id = Ticket::resolve_id(&**client, ticket_id)
+            .await?
+            .unwrap_or(ticket_id);
+
+        let rows = client
+            .query(
+                "SELECT * FROM ticket_comments WHERE ticket_id = $1 ORDER BY created_at, id",
+                &[&ticket_id],
+            )
+            .await?;
+
+        Ok(rows.into_iter().map(TicketComment::from).collect())
+    }
+}
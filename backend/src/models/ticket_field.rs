@@ -0,0 +1,357 @@
+use crate::models::es::ESClient;
+use crate::models::ticket::{Ticket, TicketError};
+use chrono::{DateTime, NaiveDate, Utc};
+use deadpool_postgres::Pool;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio_postgres::{GenericClient, Row};
+use uuid::Uuid;
+
+/// Value types supported by custom ticket fields.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum CustomFieldType {
+    /// Free-form text
+    String,
+    /// Numeric value
+    Number,
+    /// Calendar date or RFC 3339 timestamp
+    Date,
+    /// One of a fixed set of options
+    Enum,
+}
+
+impl std::fmt::Display for CustomFieldType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            CustomFieldType::String => "String",
+            CustomFieldType::Number => "Number",
+            CustomFieldType::Date => "Date",
+            CustomFieldType::Enum => "Enum",
+        })
+    }
+}
+
+impl From<String> for CustomFieldType {
+    fn from(s: String) -> Self {
+        match s.as_str() {
+            "Number" => CustomFieldType::Number,
+            "Date" => CustomFieldType::Date,
+            "Enum" => CustomFieldType::Enum,
+            _ => CustomFieldType::String,
+        }
+    }
+}
+
+impl CustomFieldType {
+    /// Elasticsearch field type used for values of this type.
+    pub fn es_type(&self) -> &'static str {
+        match self {
+            CustomFieldType::String | CustomFieldType::Enum => "keyword",
+            CustomFieldType::Number => "double",
+            CustomFieldType::Date => "date",
+        }
+    }
+}
+
+/// Admin-defined custom ticket field.
+///
+/// # Fields
+/// * `name` - Unique field key used in `Ticket::custom_fields`
+/// * `label` - Display name
+/// * `field_type` - Value type
+/// * `options` - Allowed values for enum fields
+/// * `created_at` - Creation timestamp
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CustomFieldDefinition {
+    pub name: String,
+    pub label: String,
+    pub field_type: CustomFieldType,
+    #[serde(default)]
+    pub options: Vec<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<Row> for CustomFieldDefinition {
+    fn from(row: Row) -> Self {
+        CustomFieldDefinition {
+            name: row.get("name"),
+            label: row.get("label"),
+            field_type: CustomFieldType::from(row.get::<_, String>("field_type")),
+            options: row.get("options"),
+            created_at: row.get("created_at"),
+        }
+    }
+}
+
+impl CustomFieldDefinition {
+    /// Creates a new field definition.
+    ///
+    /// # Arguments
+    /// * `name` - Field key (lowercase letters, digits and underscores)
+    /// * `label` - Display name
+    /// * `field_type` - Value type
+    /// * `options` - Allowed values, required for enum fields
+    pub fn new(
+        name: String,
+        label: String,
+        field_type: CustomFieldType,
+        options: Vec<String>,
+    ) -> Self {
+        Self {
+            name,
+            label,
+            field_type,
+            options,
+            created_at: Utc::now(),
+        }
+    }
+
+    /// Validates the definition before it is stored.
+    pub fn validate(&self) -> Result<(), TicketError> {
+        // Validate the name, it is used as a JSON and Elasticsearch key
+        let valid_name = !self.name.is_empty()
+            && self.name.len() <= 64
+            && self.name.starts_with(|c: char| c.is_ascii_lowercase())
+            && self
+                .name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+        if !valid_name {
+            return Err(TicketError::Validation(
+                "Field name must start with a lowercase letter and contain only lowercase letters, digits and underscores".into(),
+            ));
+        }
+
+        // Validate the label
+        if self.label.trim().is_empty() {
+            return Err(TicketError::Validation("Label cannot be empty".into()));
+        }
+
+        // Validate the options
+        match self.field_type {
+            CustomFieldType::Enum if self.options.is_empty() => Err(TicketError::Validation(
+                "Enum fields require at least one option".into(),
+            )),
+            CustomFieldType::Enum => Ok(()),
+            _ if !self.options.is_empty() => Err(TicketError::Validation(
+                "Only enum fields can have options".into(),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Validates and normalizes a value for this field.
+    ///
+    /// Dates are stored as `YYYY-MM-DD` or RFC 3339 strings, numbers as
+    /// JSON numbers.
+    ///
+    /// # Arguments
+    /// * `value` - Value to validate
+    ///
+    /// # Returns
+    /// * `Result<Value, TicketError>` - Normalized value or validation error
+    pub fn validate_value(&self, value: &Value) -> Result<Value, TicketError> {
+        let invalid = || {
+            TicketError::Validation(format!(
+                "Invalid value {} for {} field {}",
+                value, self.field_type, self.name
+            ))
+        };
+
+        match self.field_type {
+            CustomFieldType::String => value.as_str().map(|s| json!(s)).ok_or_else(invalid),
+            CustomFieldType::Number => value
+                .as_f64()
+                .or_else(|| value.as_str().and_then(|s| s.trim().parse::<f64>().ok()))
+                .filter(|n| n.is_finite())
+                .map(|n| json!(n))
+                .ok_or_else(invalid),
+            CustomFieldType::Date => {
+                let s = value.as_str().ok_or_else(invalid)?.trim();
+                if let Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+                    Ok(json!(date.format("%Y-%m-%d").to_string()))
+                } else {
+                    DateTime::parse_from_rfc3339(s)
+                        .map(|dt| json!(dt.with_timezone(&Utc).to_rfc3339()))
+                        .map_err(|_| invalid())
+                }
+            }
+            CustomFieldType::Enum => value
+                .as_str()
+                .filter(|s| self.options.iter().any(|o| o == s))
+                .map(|s| json!(s))
+                .ok_or_else(invalid),
+        }
+    }
+
+    /// Lists all field definitions.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    ///
+    /// # Returns
+    /// * `Result<Vec<CustomFieldDefinition>, TicketError>` - Definitions or error
+    pub async fn list(pool: &Pool) -> Result<Vec<Self>, TicketError> {
+        let client = pool.get().await?;
+        Self::list_with_client(&**client).await
+    }
+
+    /// Lists all field definitions using a specific client.
+    pub async fn list_with_client<C: GenericClient>(client: &C) -> Result<Vec<Self>, TicketError> {
+        let rows = client
+            .query("SELECT * FROM ticket_field_definitions ORDER BY name", &[])
+            .await?;
+
+        Ok(rows.into_iter().map(CustomFieldDefinition::from).collect())
+    }
+
+    /// Builds the tickets index mapping of the given fields.
+    ///
+    /// # Arguments
+    /// * `definitions` - Fields to map under `custom_fields`
+    ///
+    /// # Returns
+    /// * `Value` - Mapping body for `ESClient::put_mapping`
+    pub fn es_mapping(definitions: &[Self]) -> Value {
+        let properties: serde_json::Map<String, Value> = definitions
+            .iter()
+            .map(|d| (d.name.clone(), json!({ "type": d.field_type.es_type() })))
+            .collect();
+
+        json!({
+            "properties": {
+                "custom_fields": { "properties": properties }
+            }
+        })
+    }
+
+    /// Maps all stored fields in the tickets index.
+    ///
+    /// The index maps `custom_fields` without dynamic fields, so this has
+    /// to run whenever the index is (re)created.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    /// * `es_client` - ElasticSearch client
+    ///
+    /// # Returns
+    /// * `Result<(), TicketError>` - Success or error
+    pub async fn map_all(pool: &Pool, es_client: &ESClient) -> Result<(), TicketError> {
+        let definitions = Self::list(pool).await?;
+        if definitions.is_empty() {
+            return Ok(());
+        }
+
+        log::info!("Mapping {} custom fields", definitions.len());
+        es_client
+            .put_mapping("tickets", Self::es_mapping(&definitions))
+            .await?;
+        Ok(())
+    }
+
+    /// Stores the field definition and maps it in the tickets index.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    ///
+    /// # Returns
+    /// * `Result<(), TicketError>` - Success or error
+    pub async fn save(&self, pool: &Pool) -> Result<(), TicketError> {
+        self.validate()?;
+        log::info!("Saving custom field {}", self.name);
+        let client = pool.get().await?;
+
+        // Insert the definition, names are unique
+        let inserted = client
+            .execute(
+                "INSERT INTO ticket_field_definitions (name, label, field_type, options, created_at)
+                 VALUES ($1, $2, $3, $4, $5) ON CONFLICT (name) DO NOTHING",
+                &[
+                    &self.name,
+                    &self.label,
+                    &self.field_type.to_string(),
+                    &self.options,
+                    &self.created_at,
+                ],
+            )
+            .await?;
+
+        // If the field already exists, return an error
+        if inserted == 0 {
+            return Err(TicketError::Validation(format!(
+                "Custom field {} already exists",
+                self.name
+            )));
+        }
+
+        // Map the field so it can be filtered and aggregated on
+        match ESClient::new().await {
+            Ok(es_client) => {
+                if let Err(e) = es_client
+                    .put_mapping("tickets", Self::es_mapping(std::slice::from_ref(self)))
+                    .await
+                {
+                    log::error!("Failed to map custom field {}: {}", self.name, e);
+                }
+            }
+            Err(e) => log::error!("Failed to connect to ElasticSearch: {}", e),
+        }
+
+        Ok(())
+    }
+
+    /// Deletes a field definition and clears its values from all tickets.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    /// * `name` - Field to delete
+    ///
+    /// # Returns
+    /// * `Result<(), TicketError>` - Success or error
+    pub async fn delete(pool: &Pool, name: &str) -> Result<(), TicketError> {
+        log::info!("Deleting custom field {}", name);
+        let mut client = pool.get().await?;
+        let tx = client.transaction().await?;
+
+        // Delete the definition
+        let deleted = tx
+            .execute(
+                "DELETE FROM ticket_field_definitions WHERE name = $1",
+                &[&name],
+            )
+            .await?;
+
+        // If the field does not exist, return an error
+        if deleted == 0 {
+            return Err(TicketError::NotFound(format!(
+                "Custom field {} not found",
+                name
+            )));
+        }
+
+        // Clear the field's values from the tickets
+        let ticket_ids: Vec<Uuid> = tx
+            .query(
+                "UPDATE tickets SET custom_fields = custom_fields - $1, updated_at = NOW()
+                 WHERE custom_fields ? $1 RETURNING id",
+                &[&name],
+            )
+            .await?
+            .iter()
+            .map(|row| row.get("id"))
+            .collect();
+
+        tx.commit().await?;
+
+        // Reindex the affected tickets
+        for id in ticket_ids {
+            if let Some(ticket) = Ticket::find_by_id(pool, id).await? {
+                if let Err(e) = ticket.index_to_es().await {
+                    log::error!("Failed to index ticket to ElasticSearch: {}", e);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,160 @@
+use crate::models::ticket::{TicketError, TicketStatus};
+use chrono::{DateTime, Utc};
+use deadpool_postgres::Pool;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::{GenericClient, Row};
+use uuid::Uuid;
+
+/// Kinds of ticket lifecycle events.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum TicketEvent {
+    /// Closed by housekeeping after a period of inactivity
+    AutoClosed,
+    /// Flagged by housekeeping as inactive
+    FlaggedStale,
+    /// Reopened because a new email was linked
+    Reopened,
+}
+
+impl std::fmt::Display for TicketEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            TicketEvent::AutoClosed => "AutoClosed",
+            TicketEvent::FlaggedStale => "FlaggedStale",
+            TicketEvent::Reopened => "Reopened",
+        })
+    }
+}
+
+impl From<String> for TicketEvent {
+    fn from(s: String) -> Self {
+        match s.as_str() {
+            "AutoClosed" => TicketEvent::AutoClosed,
+            "FlaggedStale" => TicketEvent::FlaggedStale,
+            _ => TicketEvent::Reopened,
+        }
+    }
+}
+
+/// Ticket history record.
+///
+/// # Fields
+/// * `id` - Unique identifier
+/// * `ticket_id` - Affected ticket
+/// * `event` - Kind of event
+/// * `from_status` - Status before the event
+/// * `to_status` - Status after the event
+/// * `actor` - User who caused the event, None for automatic events
+/// * `details` - Human-readable explanation
+/// * `created_at` - Event timestamp
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TicketHistory {
+    pub id: Uuid,
+    pub ticket_id: Uuid,
+    pub event: TicketEvent,
+    pub from_status: Option<TicketStatus>,
+    pub to_status: Option<TicketStatus>,
+    pub actor: Option<Uuid>,
+    pub details: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<Row> for TicketHistory {
+    fn from(row: Row) -> Self {
+        TicketHistory {
+            id: row.get("id"),
+            ticket_id: row.get("ticket_id"),
+            event: TicketEvent::from(row.get::<_, String>("event")),
+            from_status: row
+                .get::<_, Option<String>>("from_status")
+                .map(TicketStatus::from),
+            to_status: row
+                .get::<_, Option<String>>("to_status")
+                .map(TicketStatus::from),
+            actor: row.get("actor"),
+            details: row.get("details"),
+            created_at: row.get("created_at"),
+        }
+    }
+}
+
+impl TicketHistory {
+    /// Creates a new history record.
+    ///
+    /// # Arguments
+    /// * `ticket_id` - Affected ticket
+    /// * `event` - Kind of event
+    /// * `from_status` - Status before the event
+    /// * `to_status` - Status after the event
+    /// * `details` - Human-readable explanation
+    pub fn new(
+        ticket_id: Uuid,
+        event: TicketEvent,
+        from_status: Option<TicketStatus>,
+        to_status: Option<TicketStatus>,
+        details: Option<String>,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            ticket_id,
+            event,
+            from_status,
+            to_status,
+            actor: None,
+            details,
+            created_at: Utc::now(),
+        }
+    }
+
+    /// Stores the history record.
+    ///
+    /// # Arguments
+    /// * `client` - Database client (usually the transaction making the change)
+    ///
+    /// # Returns
+    /// * `Result<(), TicketError>` - Success or error
+    pub async fn save<C: GenericClient>(&self, client: &C) -> Result<(), TicketError> {
+        client
+            .execute(
+                "INSERT INTO ticket_history (id, ticket_id, event, from_status, to_status, actor, details, created_at)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+                &[
+                    &self.id,
+                    &self.ticket_id,
+                    &self.event.to_string(),
+                    &self.from_status.map(|s| s.to_string()),
+                    &self.to_status.map(|s| s.to_string()),
+                    &self.actor,
+                    &self.details,
+                    &self.created_at,
+                ],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Lists a ticket's history, oldest first.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    /// * `ticket_id` - Ticket identifier
+    ///
+    /// # Returns
+    /// * `Result<Vec<TicketHistory>, TicketError>` - History records or error
+    pub async fn list_for_ticket(
+        pool: &Pool,
+        ticket_id: Uuid,
+    ) -> Result<Vec<TicketHistory>, TicketError> {
+        let client = pool.get().await?;
+
+        let rows = client
+            .query(
+                "SELECT * FROM ticket_history WHERE ticket_id = $1 ORDER BY created_at, id",
+                &[&ticket_id],
+            )
+            .await?;
+
+        Ok(rows.into_iter().map(TicketHistory::from).collect())
+    }
+}
@@ -0,0 +1,150 @@
+//! Token Blacklist
+//!
+//! Access tokens revoked before their expiry, e.g. on logout:
+//!
+//! # Storage
+//! Tokens are identified by their JWT `jti` and stored with their own
+//! expiry, so entries can be purged once the token would be rejected
+//! anyway. Tokens issued without a `jti` are identified by their SHA-256
+//! hash.
+//!
+//! # Cache
+//! Revocations are kept in memory so authentication does not query
+//! Postgres on every request. The cache is loaded on startup and synced
+//! by the cleanup job, revocations made by other instances are picked up
+//! on the next sync.
+
+use crate::auth::hash_token;
+use crate::models::auth::Claims;
+use crate::models::user::UserError;
+use chrono::{DateTime, TimeZone, Utc};
+use deadpool_postgres::Pool;
+use std::collections::HashMap;
+use std::sync::{OnceLock, RwLock};
+
+/// Revoked token ids and their expiry.
+static REVOKED: OnceLock<RwLock<HashMap<String, DateTime<Utc>>>> = OnceLock::new();
+
+fn revoked() -> &'static RwLock<HashMap<String, DateTime<Utc>>> {
+    REVOKED.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+/// Revoked access tokens.
+pub struct TokenBlacklist;
+
+impl TokenBlacklist {
+    /// Identifies a token for the blacklist.
+    ///
+    /// # Arguments
+    /// * `claims` - Verified claims of the token
+    /// * `token` - Encoded token
+    ///
+    /// # Returns
+    /// * `String` - The token's `jti`, or its hash if it has none
+    pub fn token_id(claims: &Claims, token: &str) -> String {
+        claims
+            .jti
+            .map(|jti| jti.to_string())
+            .unwrap_or_else(|| hash_token(token))
+    }
+
+    /// Revokes a token until it expires.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    /// * `claims` - Verified claims of the token
+    /// * `token` - Encoded token
+    ///
+    /// # Returns
+    /// * `Result<(), UserError>` - Success or error
+    pub async fn revoke(pool: &Pool, claims: &Claims, token: &str) -> Result<(), UserError> {
+        let token_id = Self::token_id(claims, token);
+        let expires_at = Utc
+            .timestamp_opt(claims.exp as i64, 0)
+            .single()
+            .unwrap_or_else(Utc::now);
+
+        let client = pool.get().await?;
+        client
+            .execute(
+                "INSERT INTO blacklisted_tokens (token_id, expires_at) VALUES ($1, $2)
+                 ON CONFLICT (token_id) DO NOTHING",
+                &[&token_id, &expires_at],
+            )
+            .await?;
+
+        revoked()
+            .write()
+            .expect("Token blacklist lock poisoned")
+            .insert(token_id, expires_at);
+        Ok(())
+    }
+
+    /// Checks whether a token was revoked, without querying Postgres.
+    ///
+    /// # Arguments
+    /// * `token_id` - Token id, see `token_id`
+    ///
+    /// # Returns
+    /// * `bool` - True if the token is blacklisted
+    pub fn is_revoked(token_id: &str) -> bool {
+        revoked()
+            .read()
+            .expect("Token blacklist lock poisoned")
+            .contains_key(token_id)
+    }
+
+    /// Replaces the cache with the unexpired entries stored in Postgres.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    ///
+    /// # Returns
+    /// * `Result<usize, UserError>` - Number of revoked tokens or error
+    pub async fn sync(pool: &Pool) -> Result<usize, UserError> {
+        let client = pool.get().await?;
+        let rows = client
+            .query(
+                "SELECT token_id, expires_at FROM blacklisted_tokens WHERE expires_at > NOW()",
+                &[],
+            )
+            .await?;
+
+        let mut entries: HashMap<String, DateTime<Utc>> = rows
+            .into_iter()
+            .map(|row| (row.get("token_id"), row.get("expires_at")))
+            .collect();
+        let count = entries.len();
+
+        let mut cache = revoked().write().expect("Token blacklist lock poisoned");
+        // Keep local revocations that raced with the query
+        let now = Utc::now();
+        entries.extend(cache.drain().filter(|(_, expires_at)| *expires_at > now));
+        *cache = entries;
+        Ok(count)
+    }
+
+    /// Deletes entries of tokens that have expired.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    ///
+    /// # Returns
+    /// * `Result<u64, UserError>` - Number of deleted entries or error
+    pub async fn purge_expired(pool: &Pool) -> Result<u64, UserError> {
+        let client = pool.get().await?;
+        let deleted = client
+            .execute(
+                "DELETE FROM blacklisted_tokens WHERE expires_at <= NOW()",
+                &[],
+            )
+            .await?;
+
+        let now = Utc::now();
+        revoked()
+            .write()
+            .expect("Token blacklist lock poisoned")
+            .retain(|_, expires_at| *expires_at > now);
+        Ok(deleted)
+    }
+}
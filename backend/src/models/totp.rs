@@ -0,0 +1,500 @@
+//! Two-Factor Authentication
+//!
+//! TOTP (RFC 6238) second factor for local accounts:
+//!
+//! # Flow
+//! 1. The user starts enrollment and adds the provisioning URI to an
+//!    authenticator app, usually by scanning it as a QR code
+//! 2. The first valid code enables the second factor and returns
+//!    single-use recovery codes
+//! 3. Logins with a password return a login challenge, which is exchanged
+//!    for tokens together with a code or a recovery code
+//!
+//! # Environment Variables
+//! * `TOTP_ISSUER` - Issuer shown in authenticator apps (default "Abuse Helper")
+//! * `LOGIN_CHALLENGE_TTL_MINUTES` - Lifetime of login challenges (default 5)
+
+use crate::auth::{generate_token, hash_token};
+use crate::models::user::{User, UserError};
+use chrono::{DateTime, Duration, Utc};
+use data_encoding::BASE32_NOPAD;
+use deadpool_postgres::Pool;
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use tokio_postgres::GenericClient;
+use uuid::Uuid;
+
+/// Length of a time step in seconds.
+const STEP_SECONDS: i64 = 30;
+
+/// Number of digits in a code.
+const DIGITS: u32 = 6;
+
+/// Accepted clock drift in time steps on either side.
+const DRIFT_STEPS: i64 = 1;
+
+/// Number of recovery codes issued at once.
+const RECOVERY_CODE_COUNT: usize = 10;
+
+/// Wrong codes accepted per login challenge.
+const MAX_CHALLENGE_ATTEMPTS: i32 = 5;
+
+/// Computes the code for a time step (RFC 4226 dynamic truncation).
+fn code_at(secret: &[u8], step: i64) -> u32 {
+    let mut mac = Hmac::<Sha1>::new_from_slice(secret).expect("HMAC accepts any key length");
+    mac.update(&step.to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([
+        digest[offset] & 0x7f,
+        digest[offset + 1],
+        digest[offset + 2],
+        digest[offset + 3],
+    ]);
+    binary % 10u32.pow(DIGITS)
+}
+
+/// Formats the code for a time step, zero padded.
+pub fn code_for(secret: &str, time: DateTime<Utc>) -> Option<String> {
+    let secret = BASE32_NOPAD.decode(secret.as_bytes()).ok()?;
+    Some(format!(
+        "{:0width$}",
+        code_at(&secret, time.timestamp() / STEP_SECONDS),
+        width = DIGITS as usize
+    ))
+}
+
+/// Finds the time step a code is valid for, allowing for clock drift.
+///
+/// # Arguments
+/// * `secret` - Base32 encoded secret
+/// * `code` - Code entered by the user
+/// * `time` - Current time
+///
+/// # Returns
+/// * `Option<i64>` - Matching time step, if the code is valid
+fn matching_step(secret: &str, code: &str, time: DateTime<Utc>) -> Option<i64> {
+    let code: u32 = code.trim().parse().ok()?;
+    let secret = BASE32_NOPAD.decode(secret.as_bytes()).ok()?;
+    let current = time.timestamp() / STEP_SECONDS;
+
+    (current - DRIFT_STEPS..=current + DRIFT_STEPS).find(|step| code_at(&secret, *step) == code)
+}
+
+/// Normalizes a recovery code for hashing, ignoring case and dashes.
+fn normalize_recovery_code(code: &str) -> String {
+    code.trim().replace('-', "").to_lowercase()
+}
+
+/// Pending TOTP enrollment.
+///
+/// # Fields
+/// * `secret` - Base32 encoded secret for manual entry
+/// * `otpauth_uri` - Provisioning URI to render as a QR code
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TotpEnrollment {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+/// Second factor operations on user accounts.
+pub struct TwoFactor;
+
+impl TwoFactor {
+    /// Starts enrollment by generating a new secret.
+    ///
+    /// Restarting an unfinished enrollment replaces its secret.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    /// * `user` - Enrolling user
+    ///
+    /// # Returns
+    /// * `Result<TotpEnrollment, UserError>` - Secret and provisioning URI,
+    ///   or a validation error if the second factor is already enabled
+    pub async fn begin_enrollment(pool: &Pool, user: &User) -> Result<TotpEnrollment, UserError> {
+        if user.totp_enabled_at.is_some() {
+            return Err(UserError::Validation(
+                "Two-factor authentication is already enabled".into(),
+            ));
+        }
+
+        let mut bytes = [0u8; 20];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        let secret = BASE32_NOPAD.encode(&bytes);
+
+        let client = pool.get().await?;
+        client
+            .execute(
+                "UPDATE users SET totp_secret = $2, totp_last_step = NULL, updated_at = NOW()
+                 WHERE uuid = $1 AND totp_enabled_at IS NULL",
+                &[&user.uuid, &secret],
+            )
+            .await?;
+
+        let issuer = std::env::var("TOTP_ISSUER").unwrap_or_else(|_| "Abuse Helper".to_string());
+        let mut uri = url::Url::parse("otpauth://totp/").expect("Valid base URI");
+        uri.set_path(&format!("{}:{}", issuer, user.email));
+        uri.query_pairs_mut()
+            .append_pair("secret", &secret)
+            .append_pair("issuer", &issuer)
+            .append_pair("algorithm", "SHA1")
+            .append_pair("digits", &DIGITS.to_string())
+            .append_pair("period", &STEP_SECONDS.to_string());
+
+        Ok(TotpEnrollment {
+            secret,
+            otpauth_uri: uri.to_string(),
+        })
+    }
+
+    /// Finishes enrollment with the first code from the authenticator app.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    /// * `user_uuid` - Enrolling user
+    /// * `code` - Current code
+    ///
+    /// # Returns
+    /// * `Result<Vec<String>, UserError>` - Recovery codes, shown only once
+    pub async fn confirm_enrollment(
+        pool: &Pool,
+        user_uuid: Uuid,
+        code: &str,
+    ) -> Result<Vec<String>, UserError> {
+        let mut client = pool.get().await?;
+        let tx = client.transaction().await?;
+
+        let row = tx
+            .query_opt(
+                "SELECT totp_secret, totp_enabled_at FROM users WHERE uuid = $1 FOR UPDATE",
+                &[&user_uuid],
+            )
+            .await?
+            .ok_or_else(|| UserError::NotFound(format!("User {} not found", user_uuid)))?;
+        let secret: Option<String> = row.get("totp_secret");
+        let enabled_at: Option<DateTime<Utc>> = row.get("totp_enabled_at");
+
+        let secret = match (secret, enabled_at) {
+            (_, Some(_)) => {
+                return Err(UserError::Validation(
+                    "Two-factor authentication is already enabled".into(),
+                ))
+            }
+            (None, None) => {
+                return Err(UserError::Validation(
+                    "Two-factor enrollment has not been started".into(),
+                ))
+            }
+            (Some(secret), None) => secret,
+        };
+
+        let step = matching_step(&secret, code, Utc::now())
+            .ok_or_else(|| UserError::Validation("Invalid verification code".into()))?;
+
+        tx.execute(
+            "UPDATE users SET totp_enabled_at = NOW(), totp_last_step = $2, updated_at = NOW()
+             WHERE uuid = $1",
+            &[&user_uuid, &step],
+        )
+        .await?;
+        let codes = Self::replace_recovery_codes(&*tx, user_uuid).await?;
+
+        tx.commit().await?;
+        Ok(codes)
+    }
+
+    /// Checks a code or recovery code for a user with the second factor enabled.
+    ///
+    /// Accepted codes cannot be reused: TOTP codes are bound to their time
+    /// step and recovery codes are marked as used.
+    ///
+    /// # Arguments
+    /// * `client` - Database client or transaction
+    /// * `user_uuid` - User identifier
+    /// * `code` - Code or recovery code
+    ///
+    /// # Returns
+    /// * `Result<bool, UserError>` - Whether the code was accepted
+    pub async fn verify<C: GenericClient>(
+        client: &C,
+        user_uuid: Uuid,
+        code: &str,
+    ) -> Result<bool, UserError> {
+        let row = client
+            .query_opt(
+                "SELECT totp_secret, totp_last_step FROM users
+                 WHERE uuid = $1 AND totp_enabled_at IS NOT NULL",
+                &[&user_uuid],
+            )
+            .await?;
+        let Some(row) = row else {
+            return Ok(false);
+        };
+        let secret: String = row.get("totp_secret");
+        let last_step: Option<i64> = row.get("totp_last_step");
+
+        // Time-based code, newer than the last accepted one
+        if let Some(step) = matching_step(&secret, code, Utc::now()) {
+            if last_step.is_some_and(|last| step <= last) {
+                return Ok(false);
+            }
+            let updated = client
+                .execute(
+                    "UPDATE users SET totp_last_step = $2
+                     WHERE uuid = $1 AND (totp_last_step IS NULL OR totp_last_step < $2)",
+                    &[&user_uuid, &step],
+                )
+                .await?;
+            return Ok(updated == 1);
+        }
+
+        // Unused recovery code
+        let used = client
+            .execute(
+                "UPDATE user_recovery_codes SET used_at = NOW()
+                 WHERE user_uuid = $1 AND code_hash = $2 AND used_at IS NULL",
+                &[&user_uuid, &hash_token(&normalize_recovery_code(code))],
+            )
+            .await?;
+        Ok(used == 1)
+    }
+
+    /// Issues a new set of recovery codes, invalidating the previous ones.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    /// * `user_uuid` - User identifier
+    /// * `code` - Current code or an unused recovery code
+    ///
+    /// # Returns
+    /// * `Result<Vec<String>, UserError>` - Recovery codes, shown only once
+    pub async fn regenerate_recovery_codes(
+        pool: &Pool,
+        user_uuid: Uuid,
+        code: &str,
+    ) -> Result<Vec<String>, UserError> {
+        let mut client = pool.get().await?;
+        let tx = client.transaction().await?;
+
+        if !Self::verify(&*tx, user_uuid, code).await? {
+            return Err(UserError::Validation("Invalid verification code".into()));
+        }
+        let codes = Self::replace_recovery_codes(&*tx, user_uuid).await?;
+
+        tx.commit().await?;
+        Ok(codes)
+    }
+
+    /// Turns off the second factor after checking a code.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    /// * `user_uuid` - User identifier
+    /// * `code` - Current code or an unused recovery code
+    ///
+    /// # Returns
+    /// * `Result<(), UserError>` - Success or validation error
+    pub async fn disable(pool: &Pool, user_uuid: Uuid, code: &str) -> Result<(), UserError> {
+        let mut client = pool.get().await?;
+        let tx = client.transaction().await?;
+
+        if !Self::verify(&*tx, user_uuid, code).await? {
+            return Err(UserError::Validation("Invalid verification code".into()));
+        }
+        Self::reset(&*tx, user_uuid).await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Removes a user's second factor without a code, e.g. for lost devices.
+    ///
+    /// # Arguments
+    /// * `client` - Database client or transaction
+    /// * `user_uuid` - User identifier
+    ///
+    /// # Returns
+    /// * `Result<(), UserError>` - Success, or `NotFound` if the user does not exist
+    pub async fn reset<C: GenericClient>(client: &C, user_uuid: Uuid) -> Result<(), UserError> {
+        let updated = client
+            .execute(
+                "UPDATE users
+                 SET totp_secret = NULL, totp_enabled_at = NULL, totp_last_step = NULL,
+                     updated_at = NOW()
+                 WHERE uuid = $1",
+                &[&user_uuid],
+            )
+            .await?;
+        if updated == 0 {
+            return Err(UserError::NotFound(format!("User {} not found", user_uuid)));
+        }
+
+        client
+            .execute(
+                "DELETE FROM user_recovery_codes WHERE user_uuid = $1",
+                &[&user_uuid],
+            )
+            .await?;
+        client
+            .execute(
+                "DELETE FROM login_challenges WHERE user_uuid = $1",
+                &[&user_uuid],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Replaces a user's recovery codes.
+    async fn replace_recovery_codes<C: GenericClient>(
+        client: &C,
+        user_uuid: Uuid,
+    ) -> Result<Vec<String>, UserError> {
+        client
+            .execute(
+                "DELETE FROM user_recovery_codes WHERE user_uuid = $1",
+                &[&user_uuid],
+            )
+            .await?;
+
+        let mut codes = Vec::with_capacity(RECOVERY_CODE_COUNT);
+        for _ in 0..RECOVERY_CODE_COUNT {
+            let raw = generate_token(5);
+            client
+                .execute(
+                    "INSERT INTO user_recovery_codes (user_uuid, code_hash) VALUES ($1, $2)",
+                    &[&user_uuid, &hash_token(&raw)],
+                )
+                .await?;
+            codes.push(format!("{}-{}", &raw[..5], &raw[5..]));
+        }
+
+        Ok(codes)
+    }
+}
+
+/// Pending login waiting for the second factor.
+///
+/// Only the SHA-256 hash of the challenge token is stored.
+///
+/// # Fields
+/// * `mfa_token` - Token to send back with the code
+/// * `expires_at` - Expiry timestamp
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LoginChallenge {
+    pub mfa_token: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Result of a code entered for a login challenge.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ChallengeOutcome {
+    /// Code accepted, the user completed the login
+    Verified(Uuid),
+    /// Wrong code for the user's challenge
+    InvalidCode(Uuid),
+}
+
+impl LoginChallenge {
+    /// Issues a challenge after the password was verified.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    /// * `user_uuid` - User logging in
+    ///
+    /// # Returns
+    /// * `Result<LoginChallenge, UserError>` - Challenge or error
+    pub async fn issue(pool: &Pool, user_uuid: Uuid) -> Result<Self, UserError> {
+        let ttl = std::env::var("LOGIN_CHALLENGE_TTL_MINUTES")
+            .ok()
+            .and_then(|v| v.parse::<i64>().ok())
+            .filter(|m| *m > 0)
+            .unwrap_or(5);
+
+        let mfa_token = generate_token(32);
+        let expires_at = Utc::now() + Duration::minutes(ttl);
+
+        let client = pool.get().await?;
+
+        // Drop expired challenges of the user along the way
+        client
+            .execute(
+                "DELETE FROM login_challenges WHERE user_uuid = $1 AND expires_at <= NOW()",
+                &[&user_uuid],
+            )
+            .await?;
+        client
+            .execute(
+                "INSERT INTO login_challenges (token_hash, user_uuid, expires_at)
+                 VALUES ($1, $2, $3)",
+                &[&hash_token(&mfa_token), &user_uuid, &expires_at],
+            )
+            .await?;
+
+        Ok(Self {
+            mfa_token,
+            expires_at,
+        })
+    }
+
+    /// Completes a challenge with a code or recovery code.
+    ///
+    /// The challenge is consumed on success and after too many wrong codes.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    /// * `mfa_token` - Token from the login response
+    /// * `code` - Code or recovery code
+    ///
+    /// # Returns
+    /// * `Result<ChallengeOutcome, UserError>` - Whether the code was right,
+    ///   or a validation error for unknown or expired challenges
+    pub async fn complete(
+        pool: &Pool,
+        mfa_token: &str,
+        code: &str,
+    ) -> Result<ChallengeOutcome, UserError> {
+        let token_hash = hash_token(mfa_token);
+        let mut client = pool.get().await?;
+        let tx = client.transaction().await?;
+
+        // Count the attempt, locking the challenge
+        let row = tx
+            .query_opt(
+                "UPDATE login_challenges SET attempts = attempts + 1
+                 WHERE token_hash = $1 AND expires_at > NOW()
+                 RETURNING user_uuid, attempts",
+                &[&token_hash],
+            )
+            .await?
+            .ok_or_else(|| UserError::Validation("Invalid or expired login challenge".into()))?;
+        let user_uuid: Uuid = row.get("user_uuid");
+        let attempts: i32 = row.get("attempts");
+
+        if TwoFactor::verify(&*tx, user_uuid, code).await? {
+            tx.execute(
+                "DELETE FROM login_challenges WHERE token_hash = $1",
+                &[&token_hash],
+            )
+            .await?;
+            tx.commit().await?;
+            return Ok(ChallengeOutcome::Verified(user_uuid));
+        }
+
+        // Too many wrong codes, the password has to be entered again
+        if attempts >= MAX_CHALLENGE_ATTEMPTS {
+            tx.execute(
+                "DELETE FROM login_challenges WHERE token_hash = $1",
+                &[&token_hash],
+            )
+            .await?;
+        }
+        tx.commit().await?;
+
+        Ok(ChallengeOutcome::InvalidCode(user_uuid))
+    }
+}
@@ -1,10 +1,56 @@
+use crate::models::auth_cache::AuthCache;
+use crate::models::password::hash_password;
+use crate::models::role::Role;
+use crate::models::session::Session;
 use actix_web::Error;
 use chrono::{DateTime, Utc};
 use deadpool_postgres::Pool;
 use serde::{Deserialize, Serialize};
+use tokio_postgres::error::SqlState;
 use tokio_postgres::types::ToSql;
+use tokio_postgres::{GenericClient, Row};
 use uuid::Uuid;
 
+/// Maximum number of users returned per page.
+pub const USER_PAGE_LIMIT: i64 = 200;
+
+/// Error types for user management operations.
+#[derive(Debug, thiserror::Error)]
+pub enum UserError {
+    #[error("Database error: {0}")]
+    Database(#[from] tokio_postgres::Error),
+
+    #[error("Pool error: {0}")]
+    Pool(String),
+
+    #[error("Validation error: {0}")]
+    Validation(String),
+
+    #[error("Not found: {0}")]
+    NotFound(String),
+
+    #[error("Password hashing error: {0}")]
+    Hash(#[from] bcrypt::BcryptError),
+
+    #[error("Email error: {0}")]
+    Email(String),
+}
+
+impl From<deadpool_postgres::PoolError> for UserError {
+    fn from(error: deadpool_postgres::PoolError) -> Self {
+        UserError::Pool(error.to_string())
+    }
+}
+
+/// Maps unique violations on the email column to a validation error.
+fn map_email_conflict(error: tokio_postgres::Error, email: &str) -> UserError {
+    if error.code() == Some(&SqlState::UNIQUE_VIOLATION) {
+        UserError::Validation(format!("A user with email {} already exists", email))
+    } else {
+        UserError::Database(error)
+    }
+}
+
 /// User account representation.
 ///
 /// Represents a user account in the system with associated metadata
@@ -18,11 +64,14 @@ use uuid::Uuid;
 /// * `role` - User's system role
 /// * `created_at` - Account creation timestamp
 /// * `updated_at` - Last modification timestamp
+/// * `disabled_at` - When the account was disabled, if it is
+/// * `totp_enabled_at` - When two-factor authentication was enabled, if it is
 ///
 /// # Security Notes
 /// - Passwords are never stored in plain text
 /// - Email addresses must be unique
 /// - Roles determine access permissions
+/// - Disabled users can neither log in nor use issued tokens
 #[derive(Debug, Serialize, Deserialize)]
 pub struct User {
     pub uuid: Uuid,
@@ -32,6 +81,24 @@ pub struct User {
     pub role: String,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub disabled_at: Option<DateTime<Utc>>,
+    pub totp_enabled_at: Option<DateTime<Utc>>,
+}
+
+impl From<Row> for User {
+    fn from(row: Row) -> Self {
+        User {
+            uuid: row.get("uuid"),
+            email: row.get("email"),
+            name: row.get("name"),
+            password_hash: row.get("password_hash"),
+            role: row.get("role"),
+            created_at: row.get("created_at"),
+            updated_at: row.get("updated_at"),
+            disabled_at: row.get("disabled_at"),
+            totp_enabled_at: row.get("totp_enabled_at"),
+        }
+    }
 }
 
 impl User {
@@ -57,25 +124,14 @@ impl User {
 
         // Execute the query to find the user by email
         let row = client
-            .query_one(
-                "SELECT uuid, email, name, password_hash, role, created_at, updated_at FROM users WHERE email = $1",
-                &[&email],
-            )
+            .query_one("SELECT * FROM users WHERE email = $1", &[&email])
             .await
             .map_err(|e| {
                 actix_web::error::ErrorInternalServerError(format!("Error finding user: {}", e))
             })?;
 
         // Convert the row to a User object
-        Ok(User {
-            uuid: row.get::<_, Uuid>("uuid"),
-            email: row.get("email"),
-            name: row.get("name"),
-            password_hash: row.get("password_hash"),
-            role: row.get("role"),
-            created_at: row.get("created_at"),
-            updated_at: row.get("updated_at"),
-        })
+        Ok(User::from(row))
     }
 
     /// Creates a new user account.
@@ -116,7 +172,7 @@ impl User {
             .query_one(
                 "INSERT INTO users (uuid, email, name, password_hash, role) 
                  VALUES ($1, $2, $3, $4, $5) 
-                 RETURNING uuid, email, name, password_hash, role, created_at, updated_at",
+                 RETURNING *",
                 &[
                     &uuid as &(dyn ToSql + Sync),
                     &email,
@@ -131,15 +187,7 @@ impl User {
             })?;
 
         // Convert the row to a User object
-        Ok(User {
-            uuid: row.get::<_, Uuid>("uuid"),
-            email: row.get("email"),
-            name: row.get("name"),
-            password_hash: row.get("password_hash"),
-            role: row.get("role"),
-            created_at: row.get("created_at"),
-            updated_at: row.get("updated_at"),
-        })
+        Ok(User::from(row))
     }
 
     /// Finds a user by their UUID.
@@ -169,7 +217,7 @@ impl User {
         // Execute the query to find the user by UUID
         let row = client
             .query_one(
-                "SELECT uuid, email, name, password_hash, role, created_at, updated_at FROM users WHERE uuid = $1",
+                "SELECT * FROM users WHERE uuid = $1",
                 &[&uuid as &(dyn ToSql + Sync)],
             )
             .await
@@ -177,14 +225,262 @@ impl User {
                 actix_web::error::ErrorInternalServerError(format!("Error finding user: {}", e))
             })?;
 
-        Ok(User {
-            uuid: row.get::<_, Uuid>("uuid"),
-            email: row.get("email"),
-            name: row.get("name"),
-            password_hash: row.get("password_hash"),
-            role: row.get("role"),
-            created_at: row.get("created_at"),
-            updated_at: row.get("updated_at"),
-        })
+        Ok(User::from(row))
+    }
+
+    /// Lists users, oldest accounts first.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    /// * `role` - Only return users with this role
+    /// * `disabled` - Only return disabled (`true`) or active (`false`) users
+    /// * `limit` - Page size, capped at `USER_PAGE_LIMIT`
+    /// * `offset` - Pagination offset
+    ///
+    /// # Returns
+    /// * `Result<Vec<User>, UserError>` - Users or error
+    pub async fn list(
+        pool: &Pool,
+        role: Option<&str>,
+        disabled: Option<bool>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<Self>, UserError> {
+        let client = pool.get().await?;
+        let limit = limit.clamp(1, USER_PAGE_LIMIT);
+        let offset = offset.max(0);
+
+        let rows = client
+            .query(
+                "SELECT * FROM users
+                 WHERE ($1::TEXT IS NULL OR role = $1)
+                   AND ($2::BOOLEAN IS NULL OR (disabled_at IS NOT NULL) = $2)
+                 ORDER BY created_at, email
+                 LIMIT $3 OFFSET $4",
+                &[&role, &disabled, &limit, &offset],
+            )
+            .await?;
+
+        Ok(rows.into_iter().map(User::from).collect())
+    }
+
+    /// Loads a user by UUID.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    /// * `uuid` - User identifier
+    ///
+    /// # Returns
+    /// * `Result<User, UserError>` - User, or `NotFound` if it does not exist
+    pub async fn get(pool: &Pool, uuid: Uuid) -> Result<Self, UserError> {
+        let client = pool.get().await?;
+
+        client
+            .query_opt("SELECT * FROM users WHERE uuid = $1", &[&uuid])
+            .await?
+            .map(User::from)
+            .ok_or_else(|| UserError::NotFound(format!("User {} not found", uuid)))
+    }
+
+    /// Updates a user's profile.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    /// * `uuid` - User identifier
+    /// * `name` - New display name, unchanged if `None`
+    /// * `email` - New email address, unchanged if `None`
+    ///
+    /// # Returns
+    /// * `Result<User, UserError>` - Updated user or error
+    pub async fn update(
+        pool: &Pool,
+        uuid: Uuid,
+        name: Option<String>,
+        email: Option<String>,
+    ) -> Result<Self, UserError> {
+        let name = name.map(|n| n.trim().to_string());
+        let email = email.map(|e| e.trim().to_string());
+
+        // Validate the new values
+        if name.as_deref() == Some("") {
+            return Err(UserError::Validation("Name cannot be empty".into()));
+        }
+        if let Some(email) = &email {
+            if !email.contains('@') {
+                return Err(UserError::Validation(format!(
+                    "Invalid email address {}",
+                    email
+                )));
+            }
+        }
+
+        let client = pool.get().await?;
+        let row = client
+            .query_opt(
+                "UPDATE users
+                 SET name = COALESCE($2, name), email = COALESCE($3, email), updated_at = NOW()
+                 WHERE uuid = $1
+                 RETURNING *",
+                &[&uuid, &name, &email],
+            )
+            .await
+            .map_err(|e| map_email_conflict(e, email.as_deref().unwrap_or_default()))?;
+
+        row.map(User::from)
+            .ok_or_else(|| UserError::NotFound(format!("User {} not found", uuid)))
+    }
+
+    /// Assigns a role to a user.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    /// * `uuid` - User identifier
+    /// * `role` - Name of an existing role
+    ///
+    /// # Returns
+    /// * `Result<User, UserError>` - Updated user or error
+    pub async fn set_role(pool: &Pool, uuid: Uuid, role: &str) -> Result<Self, UserError> {
+        Role::validate_assignable(pool, role).await?;
+        let client = pool.get().await?;
+
+        client
+            .query_opt(
+                "UPDATE users SET role = $2, updated_at = NOW() WHERE uuid = $1 RETURNING *",
+                &[&uuid, &role],
+            )
+            .await?
+            .map(User::from)
+            .ok_or_else(|| UserError::NotFound(format!("User {} not found", uuid)))
+            .inspect(|_| AuthCache::invalidate_user(uuid))
+    }
+
+    /// Disables or re-enables a user account.
+    ///
+    /// Disabling an already disabled account keeps the original timestamp.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    /// * `uuid` - User identifier
+    /// * `disabled` - Whether the account should be disabled
+    ///
+    /// # Returns
+    /// * `Result<User, UserError>` - Updated user or error
+    pub async fn set_disabled(pool: &Pool, uuid: Uuid, disabled: bool) -> Result<Self, UserError> {
+        let client = pool.get().await?;
+
+        client
+            .query_opt(
+                "UPDATE users
+                 SET disabled_at = CASE WHEN $2 THEN COALESCE(disabled_at, NOW()) END,
+                     updated_at = NOW()
+                 WHERE uuid = $1
+                 RETURNING *",
+                &[&uuid, &disabled],
+            )
+            .await?
+            .map(User::from)
+            .ok_or_else(|| UserError::NotFound(format!("User {} not found", uuid)))
+            .inspect(|_| AuthCache::invalidate_user(uuid))
+    }
+
+    /// Deletes a user account.
+    ///
+    /// Assignments, comments and history entries of the user are kept
+    /// without an author; watches and notifications are removed.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    /// * `uuid` - User identifier
+    ///
+    /// # Returns
+    /// * `Result<(), UserError>` - Success, or `NotFound` if it does not exist
+    pub async fn delete(pool: &Pool, uuid: Uuid) -> Result<(), UserError> {
+        let client = pool.get().await?;
+
+        let deleted = client
+            .execute("DELETE FROM users WHERE uuid = $1", &[&uuid])
+            .await?;
+        if deleted == 0 {
+            return Err(UserError::NotFound(format!("User {} not found", uuid)));
+        }
+        AuthCache::invalidate_user(uuid);
+
+        Ok(())
+    }
+
+    /// Replaces a user's password hash.
+    ///
+    /// # Arguments
+    /// * `client` - Database client or transaction
+    /// * `uuid` - User identifier
+    /// * `password_hash` - bcrypt hash of the new password
+    ///
+    /// # Returns
+    /// * `Result<(), UserError>` - Success, or `NotFound` if the user does not exist
+    pub async fn set_password_hash<C: GenericClient>(
+        client: &C,
+        uuid: Uuid,
+        password_hash: &str,
+    ) -> Result<(), UserError> {
+        let updated = client
+            .execute(
+                "UPDATE users SET password_hash = $2, updated_at = NOW() WHERE uuid = $1",
+                &[&uuid, &password_hash],
+            )
+            .await?;
+        if updated == 0 {
+            return Err(UserError::NotFound(format!("User {} not found", uuid)));
+        }
+
+        Ok(())
+    }
+
+    /// Changes a user's password after checking the current one.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    /// * `uuid` - User identifier
+    /// * `current_password` - Current plain text password
+    /// * `new_password` - New plain text password, checked against the policy
+    /// * `keep_session` - Session to keep signed in, every other session of
+    ///   the user is revoked
+    ///
+    /// # Returns
+    /// * `Result<(), UserError>` - Success, or a validation error if the
+    ///   current password is wrong or the new one is too weak
+    pub async fn change_password(
+        pool: &Pool,
+        uuid: Uuid,
+        current_password: &str,
+        new_password: &str,
+        keep_session: Option<Uuid>,
+    ) -> Result<(), UserError> {
+        let user = User::get(pool, uuid).await?;
+
+        // Accounts without a local password (Keycloak) have an empty hash
+        if !bcrypt::verify(current_password, &user.password_hash).unwrap_or(false) {
+            return Err(UserError::Validation(
+                "Current password is incorrect".into(),
+            ));
+        }
+        if current_password == new_password {
+            return Err(UserError::Validation(
+                "New password must differ from the current one".into(),
+            ));
+        }
+
+        let password_hash = hash_password(new_password)?;
+        let mut client = pool.get().await?;
+        let tx = client.transaction().await?;
+        User::set_password_hash(&*tx, uuid, &password_hash).await?;
+        Session::revoke_all_with_client(&*tx, uuid, keep_session).await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Whether the account is disabled.
+    pub fn is_disabled(&self) -> bool {
+        self.disabled_at.is_some()
     }
 }
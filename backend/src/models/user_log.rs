@@ -1,71 +1,398 @@
+use crate::models::export::csv_field;
+use crate::models::requests::AuditQuery;
+use crate::models::user::UserError;
 use chrono::{DateTime, Utc};
 use deadpool_postgres::Pool;
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio_postgres::types::ToSql;
+use tokio_postgres::Row;
 use uuid::Uuid;
 
+/// Maximum page size of audit log queries.
+pub const AUDIT_PAGE_LIMIT: i64 = 500;
+
+/// Maximum number of entries in a single audit log export.
+pub const AUDIT_EXPORT_LIMIT: i64 = 10_000;
+
+/// Columns of the CSV export.
+const CSV_HEADER: [&str; 13] = [
+    "uuid",
+    "timestamp",
+    "user_uuid",
+    "method",
+    "route",
+    "route_pattern",
+    "status_code",
+    "action",
+    "entities",
+    "ip_address",
+    "user_agent",
+    "latency_ms",
+    "request_id",
+];
+
+/// Entity named in the path of a logged request.
+///
+/// # Fields
+/// * `entity_type` - Kind of entity, e.g. `ticket`, `email` or `customer`
+/// * `id` - Entity identifier
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct AuditEntity {
+    #[serde(rename = "type")]
+    pub entity_type: String,
+    pub id: Uuid,
+}
+
+impl AuditEntity {
+    /// Extracts the entities named in a matched route.
+    ///
+    /// Parameters named `id` or `uuid` refer to the entity of the path
+    /// segment before them, e.g. `/tickets/{id}` to a ticket, other
+    /// parameters to the entity in their name, e.g. `{email_id}` to an
+    /// email. Parameters that are not UUIDs are skipped.
+    ///
+    /// # Arguments
+    /// * `pattern` - Matched route pattern, e.g. `/tickets/{id}/emails/{email_id}`
+    /// * `params` - Path parameter names and values
+    ///
+    /// # Returns
+    /// * `Vec<AuditEntity>` - Entities in path order
+    pub fn from_path<'a>(
+        pattern: &str,
+        params: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ) -> Vec<Self> {
+        let params: Vec<(&str, &str)> = params.into_iter().collect();
+        let segments: Vec<&str> = pattern.split('/').filter(|s| !s.is_empty()).collect();
+
+        segments
+            .iter()
+            .enumerate()
+            .filter_map(|(i, segment)| {
+                let name = segment.strip_prefix('{')?.strip_suffix('}')?;
+                let (_, value) = params.iter().find(|(param, _)| *param == name)?;
+                let id = Uuid::parse_str(value).ok()?;
+                let entity_type = match name {
+                    "id" | "uuid" => {
+                        let previous = segments[..i].iter().rev().find(|s| !s.starts_with('{'))?;
+                        previous.strip_suffix('s').unwrap_or(previous)
+                    }
+                    _ => name.strip_suffix("_id").unwrap_or(name),
+                };
+                Some(Self {
+                    entity_type: entity_type.to_string(),
+                    id,
+                })
+            })
+            .collect()
+    }
+}
+
 /// User activity log entry.
 ///
 /// Represents a single user action or system interaction event.
 /// Provides comprehensive tracking of user activities for audit
-/// and compliance purposes.
+/// and compliance purposes. Request details are unset for entries
+/// recorded before they were captured.
 ///
 /// # Fields
 /// * `uuid` - Unique identifier for the log entry
-/// * `user_uuid` - Identifier of the user who performed the action
-/// * `action` - Description of the performed action
+/// * `user_uuid` - Identifier of the user who performed the action, unset
+///   once the user is deleted
+/// * `action` - Method and matched route, e.g. `DELETE /email/{id}`
 /// * `route` - API route or system path accessed
-/// * `timestamp` - When the action occurred (automatically set)
+/// * `method` - HTTP method
+/// * `route_pattern` - Matched route pattern, unset for unmatched paths
+/// * `status_code` - Response status code
+/// * `entities` - Entities named in the path
+/// * `ip_address` - Client IP address
+/// * `user_agent` - Client user agent
+/// * `latency_ms` - Time taken to handle the request
+/// * `request_id` - Request ID, also returned in `X-Request-ID`
+/// * `timestamp` - When the request was received
 ///
 /// # Usage
 /// ```rust
 /// // Example log creation (pseudo-code):
-/// UserLog::create(pool, user_id, "login", "/api/auth/login").await?;
+/// UserLog::create(pool, &entry).await?;
 /// ```
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UserLog {
     pub uuid: Uuid,
-    pub user_uuid: Uuid,
+    pub user_uuid: Option<Uuid>,
     pub action: String,
     pub route: String,
+    pub method: Option<String>,
+    pub route_pattern: Option<String>,
+    pub status_code: Option<i16>,
+    pub entities: Vec<AuditEntity>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub latency_ms: Option<i32>,
+    pub request_id: Option<String>,
     pub timestamp: DateTime<Utc>,
 }
 
+impl From<Row> for UserLog {
+    fn from(row: Row) -> Self {
+        let entities: Value = row.get("entities");
+        Self {
+            uuid: row.get("uuid"),
+            user_uuid: row.get("user_uuid"),
+            action: row.get("action"),
+            route: row.get("route"),
+            method: row.get("method"),
+            route_pattern: row.get("route_pattern"),
+            status_code: row.get("status_code"),
+            entities: serde_json::from_value(entities).unwrap_or_default(),
+            ip_address: row.get("ip_address"),
+            user_agent: row.get("user_agent"),
+            latency_ms: row.get("latency_ms"),
+            request_id: row.get("request_id"),
+            timestamp: row.get("timestamp"),
+        }
+    }
+}
+
+/// Escapes `LIKE` wildcards so a value only matches literally.
+fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+/// Parses a status filter into a range of status codes.
+///
+/// # Arguments
+/// * `status` - Status code, e.g. `404`, or class, e.g. `4xx`
+///
+/// # Returns
+/// * `Result<(i16, i16), UserError>` - Lowest and highest matching code
+fn status_range(status: &str) -> Result<(i16, i16), UserError> {
+    let status = status.to_ascii_lowercase();
+    let invalid = || {
+        UserError::Validation(format!(
+            "Invalid status {}, expected a code like 404 or a class like 4xx",
+            status
+        ))
+    };
+
+    let range = match status.strip_suffix("xx") {
+        Some(class) => class.parse::<i16>().map(|c| (c * 100, c * 100 + 99)),
+        None if status.len() == 3 => status.parse::<i16>().map(|c| (c, c)),
+        None => return Err(invalid()),
+    }
+    .map_err(|_| invalid())?;
+
+    if !(100..=599).contains(&range.0) {
+        return Err(invalid());
+    }
+    Ok(range)
+}
+
 impl UserLog {
     /// Creates a new user activity log entry.
     ///
-    /// Records a user action in the database with automatic timestamp
-    /// and UUID generation.
-    ///
     /// # Arguments
     /// * `pool` - Database connection pool
-    /// * `user_uuid` - ID of the user performing the action
-    /// * `action` - Description of the performed action
-    /// * `route` - System route or API endpoint accessed
+    /// * `entry` - Entry to record
     ///
     /// # Returns
     /// * `Result<(), Box<dyn std::error::Error>>` - Success or error
-    ///
-    /// # Database Schema
-    /// Requires table 'user_logs' with columns:
-    /// - uuid (UUID, PRIMARY KEY)
-    /// - user_uuid (UUID, FOREIGN KEY)
-    /// - action (TEXT)
-    /// - route (TEXT)
-    /// - timestamp (TIMESTAMP WITH TIME ZONE, DEFAULT NOW())
-    pub async fn create(
-        pool: &Pool,
-        user_uuid: Uuid,
-        action: &str,
-        route: &str,
-    ) -> Result<(), Box<dyn std::error::Error>> {
+    pub async fn create(pool: &Pool, entry: &UserLog) -> Result<(), Box<dyn std::error::Error>> {
         let client = pool.get().await?;
-        let uuid = Uuid::new_v4();
         client
             .execute(
-                "INSERT INTO user_logs (uuid, user_uuid, action, route) VALUES ($1, $2, $3, $4)",
-                &[&uuid, &user_uuid, &action, &route],
+                "INSERT INTO user_logs
+                     (uuid, user_uuid, action, route, method, route_pattern, status_code,
+                      entities, ip_address, user_agent, latency_ms, request_id, timestamp)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)",
+                &[
+                    &entry.uuid,
+                    &entry.user_uuid,
+                    &entry.action,
+                    &entry.route,
+                    &entry.method,
+                    &entry.route_pattern,
+                    &entry.status_code,
+                    &serde_json::to_value(&entry.entities)?,
+                    &entry.ip_address,
+                    &entry.user_agent,
+                    &entry.latency_ms,
+                    &entry.request_id,
+                    &entry.timestamp,
+                ],
             )
             .await?;
         Ok(())
     }
+
+    /// Filter of audit log queries, shared by searches and exports.
+    ///
+    /// # Returns
+    /// * `Result<(String, Vec<Box<dyn ToSql + Sync + Send>>), UserError>` -
+    ///   `WHERE` clause and its parameters, or a validation error
+    fn filter(
+        query: &AuditQuery,
+    ) -> Result<(String, Vec<Box<dyn ToSql + Sync + Send>>), UserError> {
+        let mut conditions: Vec<String> = Vec::new();
+        let mut params: Vec<Box<dyn ToSql + Sync + Send>> = Vec::new();
+
+        if let Some(user) = query.user {
+            params.push(Box::new(user));
+            conditions.push(format!("user_uuid = ${}", params.len()));
+        }
+        if let Some(route) = query.route.as_deref().filter(|r| !r.is_empty()) {
+            params.push(Box::new(format!("{}%", escape_like(route))));
+            conditions.push(format!("route LIKE ${}", params.len()));
+        }
+        if let Some(status) = query.status.as_deref() {
+            let (lowest, highest) = status_range(status)?;
+            params.push(Box::new(lowest));
+            params.push(Box::new(highest));
+            conditions.push(format!(
+                "status_code BETWEEN ${} AND ${}",
+                params.len() - 1,
+                params.len()
+            ));
+        }
+        if let Some(method) = query.method.as_deref() {
+            params.push(Box::new(method.to_ascii_uppercase()));
+            conditions.push(format!("method = ${}", params.len()));
+        }
+        if let Some(entity) = query.entity {
+            params.push(Box::new(json!([{ "id": entity }])));
+            conditions.push(format!("entities @> ${}", params.len()));
+        }
+        if let Some(request_id) = query.request_id.as_deref() {
+            params.push(Box::new(request_id.to_string()));
+            conditions.push(format!("request_id = ${}", params.len()));
+        }
+        if let Some(from) = query.from {
+            params.push(Box::new(from));
+            conditions.push(format!("timestamp >= ${}", params.len()));
+        }
+        if let Some(to) = query.to {
+            params.push(Box::new(to));
+            conditions.push(format!("timestamp < ${}", params.len()));
+        }
+        if let (Some(from), Some(to)) = (query.from, query.to) {
+            if from > to {
+                return Err(UserError::Validation("from must not be after to".into()));
+            }
+        }
+
+        let clause = if conditions.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", conditions.join(" AND "))
+        };
+        Ok((clause, params))
+    }
+
+    /// Searches the audit log, newest entries first.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    /// * `query` - Filters and pagination
+    ///
+    /// # Returns
+    /// * `Result<(i64, Vec<UserLog>), UserError>` - Number of matching
+    ///   entries and the requested page, or a validation error
+    pub async fn search(pool: &Pool, query: &AuditQuery) -> Result<(i64, Vec<Self>), UserError> {
+        let (clause, mut params) = Self::filter(query)?;
+        let limit = query.limit.unwrap_or(50).clamp(1, AUDIT_PAGE_LIMIT);
+        let offset = query.offset.unwrap_or(0).max(0);
+
+        let client = pool.get().await?;
+        let refs: Vec<&(dyn ToSql + Sync)> = params
+            .iter()
+            .map(|p| p.as_ref() as &(dyn ToSql + Sync))
+            .collect();
+        let total: i64 = client
+            .query_one(&format!("SELECT COUNT(*) FROM user_logs {}", clause), &refs)
+            .await?
+            .get(0);
+
+        params.push(Box::new(limit));
+        params.push(Box::new(offset));
+        let refs: Vec<&(dyn ToSql + Sync)> = params
+            .iter()
+            .map(|p| p.as_ref() as &(dyn ToSql + Sync))
+            .collect();
+        let rows = client
+            .query(
+                &format!(
+                    "SELECT * FROM user_logs {} ORDER BY timestamp DESC, uuid LIMIT ${} OFFSET ${}",
+                    clause,
+                    refs.len() - 1,
+                    refs.len()
+                ),
+                &refs,
+            )
+            .await?;
+
+        Ok((total, rows.into_iter().map(Self::from).collect()))
+    }
+
+    /// Exports the matching audit log entries as CSV, oldest first.
+    ///
+    /// Pagination is ignored, up to `AUDIT_EXPORT_LIMIT` entries are exported.
+    ///
+    /// # Arguments
+    /// * `pool` - Database connection pool
+    /// * `query` - Filters
+    ///
+    /// # Returns
+    /// * `Result<String, UserError>` - CSV file with a header row, or a
+    ///   validation error
+    pub async fn export_csv(pool: &Pool, query: &AuditQuery) -> Result<String, UserError> {
+        let (clause, mut params) = Self::filter(query)?;
+        params.push(Box::new(AUDIT_EXPORT_LIMIT));
+        let refs: Vec<&(dyn ToSql + Sync)> = params
+            .iter()
+            .map(|p| p.as_ref() as &(dyn ToSql + Sync))
+            .collect();
+
+        let client = pool.get().await?;
+        let rows = client
+            .query(
+                &format!(
+                    "SELECT * FROM user_logs {} ORDER BY timestamp, uuid LIMIT ${}",
+                    clause,
+                    refs.len()
+                ),
+                &refs,
+            )
+            .await?;
+
+        let mut csv = format!("{}\r\n", CSV_HEADER.join(","));
+        for entry in rows.into_iter().map(Self::from) {
+            let entities: Vec<String> = entry
+                .entities
+                .iter()
+                .map(|e| format!("{}:{}", e.entity_type, e.id))
+                .collect();
+            let fields = [
+                entry.uuid.to_string(),
+                entry.timestamp.to_rfc3339(),
+                entry.user_uuid.map(|u| u.to_string()).unwrap_or_default(),
+                entry.method.unwrap_or_default(),
+                entry.route,
+                entry.route_pattern.unwrap_or_default(),
+                entry.status_code.map(|c| c.to_string()).unwrap_or_default(),
+                entry.action,
+                entities.join(" "),
+                entry.ip_address.unwrap_or_default(),
+                entry.user_agent.unwrap_or_default(),
+                entry.latency_ms.map(|l| l.to_string()).unwrap_or_default(),
+                entry.request_id.unwrap_or_default(),
+            ];
+            let row: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+            csv.push_str(&row.join(","));
+            csv.push_str("\r\n");
+        }
+        Ok(csv)
+    }
 }
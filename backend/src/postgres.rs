@@ -16,12 +16,36 @@ use tokio_postgres::NoTls;
 /// 9. Create email_tickets junction table
 /// 10. Remove email_id from tickets
 /// 11. Change email_id to UUID type
+/// 12. Add merged_into to tickets
+/// 13. Add correlation reason to email_tickets
+/// 14. Add tags and custom fields to tickets
+/// 15. Add customer link to tickets
+/// 16. Add assignee to tickets
+/// 17. Add sequential numbers to tickets
+/// 18. Create ticket history table and stale flag
+/// 19. Creates ticket watchers, comments and notifications tables
+/// 20. Add disabled flag to users
+/// 21. Create password reset tokens table
+/// 22. Add TOTP two-factor authentication
+/// 23. Create API keys table
+/// 24. Create roles table
+/// 25. Create refresh tokens table
+/// 26. Rework blacklisted tokens table
+/// 27. Create Keycloak role mappings table
+/// 28. Add login lockout and login events
+/// 29. Create sessions table
+/// 30. Add audit log permission and route index
+/// 31. Add request details to user logs
+/// 32. Add audit hash chain and checkpoints
+/// 33. Add unique email ticket links
+/// 34. Add audit manage permission
+/// 35. Create audit signing keys table
 ///
 /// # Migration Safety
 /// - Migrations are executed in order
 /// - Each migration is tracked in the migrations table
 /// - Duplicate migrations are skipped
-const SCRIPTS_UP: [(&str, &str); 11] = [
+const SCRIPTS_UP: [(&str, &str); 35] = [
     (
         "0001_create-customers",
         include_str!("../migrations/0001_create-customers.sql"),
@@ -66,6 +90,102 @@ const SCRIPTS_UP: [(&str, &str); 11] = [
         "0011_change_email_id_to_uuid",
         include_str!("../migrations/0011_change_email_id_to_uuid.sql"),
     ),
+    (
+        "0012_add_ticket_merge",
+        include_str!("../migrations/0012_add_ticket_merge.sql"),
+    ),
+    (
+        "0013_add_email_ticket_correlation",
+        include_str!("../migrations/0013_add_email_ticket_correlation.sql"),
+    ),
+    (
+        "0014_add_ticket_tags_and_custom_fields",
+        include_str!("../migrations/0014_add_ticket_tags_and_custom_fields.sql"),
+    ),
+    (
+        "0015_add_ticket_customer",
+        include_str!("../migrations/0015_add_ticket_customer.sql"),
+    ),
+    (
+        "0016_add_ticket_assignment",
+        include_str!("../migrations/0016_add_ticket_assignment.sql"),
+    ),
+    (
+        "0017_add_ticket_numbers",
+        include_str!("../migrations/0017_add_ticket_numbers.sql"),
+    ),
+    (
+        "0018_create_ticket_history",
+        include_str!("../migrations/0018_create_ticket_history.sql"),
+    ),
+    (
+        "0019_create_ticket_watchers_and_notifications",
+        include_str!("../migrations/0019_create_ticket_watchers_and_notifications.sql"),
+    ),
+    (
+        "0020_add_user_disabled",
+        include_str!("../migrations/0020_add_user_disabled.sql"),
+    ),
+    (
+        "0021_create_password_reset_tokens",
+        include_str!("../migrations/0021_create_password_reset_tokens.sql"),
+    ),
+    (
+        "0022_add_user_totp",
+        include_str!("../migrations/0022_add_user_totp.sql"),
+    ),
+    (
+        "0023_create_api_keys",
+        include_str!("../migrations/0023_create_api_keys.sql"),
+    ),
+    (
+        "0024_create_roles",
+        include_str!("../migrations/0024_create_roles.sql"),
+    ),
+    (
+        "0025_create_refresh_tokens",
+        include_str!("../migrations/0025_create_refresh_tokens.sql"),
+    ),
+    (
+        "0026_rework_blacklisted_tokens",
+        include_str!("../migrations/0026_rework_blacklisted_tokens.sql"),
+    ),
+    (
+        "0027_create_keycloak_role_mappings",
+        include_str!("../migrations/0027_create_keycloak_role_mappings.sql"),
+    ),
+    (
+        "0028_add_login_protection",
+        include_str!("../migrations/0028_add_login_protection.sql"),
+    ),
+    (
+        "0029_create_sessions",
+        include_str!("../migrations/0029_create_sessions.sql"),
+    ),
+    (
+        "0030_add_audit_read_permission",
+        include_str!("../migrations/0030_add_audit_read_permission.sql"),
+    ),
+    (
+        "0031_extend_user_logs",
+        include_str!("../migrations/0031_extend_user_logs.sql"),
+    ),
+    (
+        "0032_add_audit_hash_chain",
+        include_str!("../migrations/0032_add_audit_hash_chain.sql"),
+    ),
+    (
+        "0033_add_email_tickets_unique_link",
+        include_str!("../migrations/0033_add_email_tickets_unique_link.sql"),
+    ),
+    (
+        "0034_add_audit_manage_permission",
+        include_str!("../migrations/0034_add_audit_manage_permission.sql"),
+    ),
+    (
+        "0035_create_audit_signing_keys",
+        include_str!("../migrations/0035_create_audit_signing_keys.sql"),
+    ),
 ];
 
 /// Create a new configuration from environment variables
@@ -117,6 +237,24 @@ pub fn create_pool() -> Pool {
         .expect("couldn't create postgres pool")
 }
 
+/// Opens a dedicated database connection outside the pool
+///
+/// Used for long-lived sessions such as `LISTEN`. The returned connection
+/// must be polled for the client to make progress.
+///
+/// # Returns
+/// Client and connection, or a configuration or connection error
+pub async fn connect() -> Result<
+    (
+        tokio_postgres::Client,
+        tokio_postgres::Connection<tokio_postgres::Socket, tokio_postgres::tls::NoTlsStream>,
+    ),
+    Box<dyn std::error::Error>,
+> {
+    let config = create_config().get_pg_config()?;
+    Ok(config.connect(NoTls).await?)
+}
+
 /// Executes database migrations in order
 ///
 /// # Arguments
@@ -0,0 +1,240 @@
+use crate::models::audit_chain::{AuditChain, AuditTrail};
+use crate::models::requests::{AuditLogResponse, AuditQuery, AuditTrailQuery, CheckpointExport};
+use crate::models::user::UserError;
+use crate::models::user_log::UserLog;
+use actix_web::{get, post, web, HttpResponse};
+use deadpool_postgres::Pool;
+
+/// Query the user activity audit log
+///
+/// # Endpoint
+/// GET /audit
+///
+/// # Query Parameters
+/// - user: Only return entries of this user UUID
+/// - route: Only return entries whose route starts with this prefix
+/// - status: Status code (`404`) or class (`4xx`)
+/// - method: HTTP method
+/// - entity: Only return entries naming this ticket, email, customer or
+///   other entity UUID in their path
+/// - request_id: Only return entries of this `X-Request-ID`
+/// - from: Only return entries at or after this RFC 3339 time
+/// - to: Only return entries before this RFC 3339 time
+/// - limit: Page size (default 50, max 500)
+/// - offset: Pagination offset
+///
+/// # Returns
+/// - 200: Number of matching entries and the requested page, newest first
+/// - 400: Invalid filters
+#[get("")]
+pub async fn list(pool: web::Data<Pool>, query: web::Query<AuditQuery>) -> HttpResponse {
+    match UserLog::search(&pool, &query).await {
+        Ok((total, entries)) => HttpResponse::Ok().json(AuditLogResponse { total, entries }),
+        Err(UserError::Validation(msg)) => {
+            log::warn!("Invalid audit query: {}", msg);
+            HttpResponse::BadRequest().json(msg)
+        }
+        Err(e) => {
+            log::error!("Failed to query audit log: {}", e);
+            HttpResponse::InternalServerError().json(e.to_string())
+        }
+    }
+}
+
+/// Export the user activity audit log
+///
+/// # Endpoint
+/// GET /audit/export
+///
+/// # Query Parameters
+/// Same filters as `GET /audit`, pagination is ignored
+///
+/// # Returns
+/// - 200: CSV file of up to 10,000 matching entries, oldest first
+/// - 400: Invalid filters
+#[get("/export")]
+pub async fn export(pool: web::Data<Pool>, query: web::Query<AuditQuery>) -> HttpResponse {
+    match UserLog::export_csv(&pool, &query).await {
+        Ok(csv) => {
+            let filename = format!("audit-{}.csv", chrono::Utc::now().format("%Y%m%d%H%M%S"));
+            HttpResponse::Ok()
+                .content_type("text/csv; charset=utf-8")
+                .insert_header((
+                    "Content-Disposition",
+                    format!("attachment; filename=\"{}\"", filename),
+                ))
+                .body(csv)
+        }
+        Err(UserError::Validation(msg)) => {
+            log::warn!("Invalid audit export: {}", msg);
+            HttpResponse::BadRequest().json(msg)
+        }
+        Err(e) => {
+            log::error!("Failed to export audit log: {}", e);
+            HttpResponse::InternalServerError().json(e.to_string())
+        }
+    }
+}
+
+/// Trails selected by a query, every trail by default.
+fn trails(query: &AuditTrailQuery) -> Vec<AuditTrail> {
+    match query.trail {
+        Some(trail) => vec![trail],
+        None => AuditTrail::ALL.to_vec(),
+    }
+}
+
+/// Verify the audit hash chains
+///
+/// Seals pending records, then walks each chain from its first record and
+/// checks it against the signed checkpoints.
+///
+/// # Endpoint
+/// GET /audit/verify
+///
+/// # Query Parameters
+/// - trail: `user_logs` or `ticket_history`, every trail if omitted
+///
+/// # Returns
+/// - 200: Verification result per trail, with the first broken link of
+///   chains that do not verify; `valid` is also false if checkpoints were
+///   signed with keys that are no longer loaded
+#[get("/verify")]
+pub async fn verify(pool: web::Data<Pool>, query: web::Query<AuditTrailQuery>) -> HttpResponse {
+    let mut results = Vec::new();
+    for trail in trails(&query) {
+        let result = match AuditChain::seal(&pool, trail).await {
+            Ok(_) => AuditChain::verify(&pool, trail).await,
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(result) => {
+                if let Some(broken) = &result.first_broken {
+                    log::warn!(
+                        "Audit chain {} is broken at {}: {}",
+                        trail,
+                        broken.seq,
+                        broken.reason
+                    );
+                }
+                results.push(result);
+            }
+            Err(e) => {
+                log::error!("Failed to verify audit chain {}: {}", trail, e);
+                return HttpResponse::InternalServerError().json(e.to_string());
+            }
+        }
+    }
+
+    HttpResponse::Ok().json(results)
+}
+
+/// List the signed audit checkpoints
+///
+/// # Endpoint
+/// GET /audit/checkpoints
+///
+/// # Query Parameters
+/// - trail: `user_logs` or `ticket_history`, every trail if omitted
+///
+/// # Returns
+/// - 200: Checkpoints, oldest first
+#[get("/checkpoints")]
+pub async fn list_checkpoints(
+    pool: web::Data<Pool>,
+    query: web::Query<AuditTrailQuery>,
+) -> HttpResponse {
+    match AuditChain::list_checkpoints(&pool, query.trail).await {
+        Ok(checkpoints) => HttpResponse::Ok().json(checkpoints),
+        Err(e) => {
+            log::error!("Failed to list audit checkpoints: {}", e);
+            HttpResponse::InternalServerError().json(e.to_string())
+        }
+    }
+}
+
+/// Create signed audit checkpoints now
+///
+/// # Endpoint
+/// POST /audit/checkpoints
+///
+/// # Query Parameters
+/// - trail: `user_logs` or `ticket_history`, every trail if omitted
+///
+/// # Returns
+/// - 201: Created checkpoints, empty for trails that are empty or whose
+///   head is already checkpointed
+/// - 409: No persistent signing keys are configured
+#[post("/checkpoints")]
+pub async fn create_checkpoints(
+    pool: web::Data<Pool>,
+    query: web::Query<AuditTrailQuery>,
+) -> HttpResponse {
+    let mut checkpoints = Vec::new();
+    for trail in trails(&query) {
+        match AuditChain::checkpoint(&pool, trail).await {
+            Ok(Some(checkpoint)) => checkpoints.push(checkpoint),
+            Ok(None) => {}
+            Err(UserError::Validation(msg)) => {
+                log::warn!("Refused audit checkpoint of {}: {}", trail, msg);
+                return HttpResponse::Conflict().json(msg);
+            }
+            Err(e) => {
+                log::error!("Failed to checkpoint audit chain {}: {}", trail, e);
+                return HttpResponse::InternalServerError().json(e.to_string());
+            }
+        }
+    }
+
+    HttpResponse::Created().json(checkpoints)
+}
+
+/// Export the signed audit checkpoints
+///
+/// The export holds the public keys the checkpoints were signed with, so
+/// it can be verified without access to the service.
+///
+/// # Endpoint
+/// GET /audit/checkpoints/export
+///
+/// # Query Parameters
+/// - trail: `user_logs` or `ticket_history`, every trail if omitted
+///
+/// # Returns
+/// - 200: JSON file of the checkpoints and the JWKS
+#[get("/checkpoints/export")]
+pub async fn export_checkpoints(
+    pool: web::Data<Pool>,
+    query: web::Query<AuditTrailQuery>,
+) -> HttpResponse {
+    let exported = match AuditChain::list_checkpoints(&pool, query.trail).await {
+        Ok(checkpoints) => AuditChain::signing_keys(&pool)
+            .await
+            .map(|jwks| (checkpoints, jwks)),
+        Err(e) => Err(e),
+    };
+
+    match exported {
+        Ok((checkpoints, jwks)) => {
+            let exported_at = chrono::Utc::now();
+            let filename = format!(
+                "audit-checkpoints-{}.json",
+                exported_at.format("%Y%m%d%H%M%S")
+            );
+            HttpResponse::Ok()
+                .insert_header((
+                    "Content-Disposition",
+                    format!("attachment; filename=\"{}\"", filename),
+                ))
+                .json(CheckpointExport {
+                    exported_at,
+                    jwks,
+                    checkpoints,
+                })
+        }
+        Err(e) => {
+            log::error!("Failed to export audit checkpoints: {}", e);
+            HttpResponse::InternalServerError().json(e.to_string())
+        }
+    }
+}
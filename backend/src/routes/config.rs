@@ -1,6 +1,6 @@
 use crate::middleware::Auth;
 use crate::routes;
-use actix_web::{get, web, HttpResponse, Scope};
+use actix_web::{get, guard, web, HttpResponse, Scope};
 
 /// Server health check endpoint
 ///
@@ -30,90 +30,239 @@ async fn api_status() -> HttpResponse {
 ///
 /// ## Public Routes
 /// - `GET /status` - Server health check
+/// - `GET /.well-known/jwks.json` - Public keys verifying access tokens
 /// - `POST /auth/login` - User authentication
+/// - `POST /auth/login/verify` - Second factor of two-step logins
 /// - `POST /auth/refresh` - Token refresh
 /// - `POST /auth/exchange` - Token exchange
+/// - `POST /auth/forgot_password` - Email a password reset token
+/// - `POST /auth/reset_password` - Reset a password with an emailed token
 ///
-/// ## Protected Routes (Requires Authentication)
+/// ## Authenticated Routes (any role)
 /// - `POST /auth/logout` - User logout
+/// - `/auth/sessions/*` - Sessions of the current user and remote sign-out
+/// - `/notifications/*` - Notifications of the current user
+/// - `GET /users/me` - Profile of the current user
+/// - `PUT /users/me/password` - Change the current user's password
+/// - `/users/me/totp/*` - Two-factor authentication enrollment
+/// - `/users/me/api_keys/*` - API keys of the current user
 ///
-/// ## Admin Routes
-/// - `/customer/*` - Customer management
-/// - `/email/*` - Email operations
-///
-/// ## User Routes
-/// - `/nctns/*` - Security notifications
-/// - `/util/*` - Utility functions
-/// - `/tickets/*` - Ticket management
+/// ## Permission Routes
+/// - `/customer/*` - `customers:read`
+/// - `/nctns/*` - `nctns:read`
+/// - `/util/*` - `whois:lookup`
+/// - `/email/*` - `email:read` for reads, `email:delete` for deletions,
+///   `email:send` for sending and `email:write` otherwise
+/// - `/tickets/*` - `tickets:read`, plus `tickets:write` for changes
+/// - `GET /events` - `tickets:read`
+/// - `GET /ticket_fields/list` - `tickets:read`
+/// - `POST /ticket_fields/create`, `DELETE /ticket_fields/{name}` - `ticket_fields:manage`
+/// - `/users/*` - `users:manage`
+/// - `/roles/*` - `roles:manage`, including the Keycloak role mappings
+/// - `/audit/*` - `audit:read`, plus `audit:manage` for `POST /audit/checkpoints`
 ///
 /// # Middleware Configuration
 /// - Authentication required for protected routes
-/// - Role-based access for admin/user routes
+/// - Permission-based access, granted through the user's role
+/// - API keys (`Authorization: ApiKey <key>`) accepted by permission routes
+///   only, if the key's scopes include the permission
 /// - Scoped middleware application
 ///
 /// # Example URL Structure
 /// ```text
 /// /status                     -> Health check
 /// /auth/login                 -> Authentication
-/// /customer/list              -> List customers (admin)
-/// /email/send                 -> Send email (admin)
-/// /tickets/create_ticket      -> Create ticket (user)
-/// /nctns/list                -> List notifications (user)
+/// /customer/list              -> List customers (customers:read)
+/// /email/send                 -> Send email (email:send)
+/// /tickets/create             -> Create ticket (tickets:write)
+/// /nctns/list                 -> List notifications (nctns:read)
 /// ```
 pub fn configure_routes() -> Scope {
     web::scope("")
         .service(api_status)
+        .service(routes::auth::jwks)
         .service(
             web::scope("/auth")
                 .service(routes::auth::login)
+                .service(routes::auth::verify_login)
                 .service(routes::auth::refresh)
-                .service(routes::auth::exchange_token),
+                .service(routes::auth::exchange_token)
+                .service(routes::auth::forgot_password)
+                .service(routes::auth::reset_password),
         )
         .service(
             web::scope("")
-                .wrap(Auth::new())
-                .service(web::scope("/auth").service(routes::auth::logout))
+                .service(
+                    web::scope("/auth")
+                        .wrap(Auth::new())
+                        .service(routes::auth::logout)
+                        .service(routes::auth::list_sessions)
+                        .service(routes::auth::revoke_other_sessions)
+                        .service(routes::auth::revoke_session),
+                )
                 .service(
                     web::scope("/customer")
-                        .wrap(Auth::new().role("admin"))
+                        .wrap(Auth::new().permission("customers:read"))
                         .service(routes::customer::list)
-                        .service(routes::customer::find),
+                        .service(routes::customer::find)
+                        .service(routes::customer::tickets),
                 )
                 .service(
                     web::scope("/nctns")
-                        .wrap(Auth::new().role("user"))
+                        .wrap(Auth::new().permission("nctns:read"))
                         .service(routes::nctns::list),
                 )
                 .service(
                     web::scope("/util")
-                        .wrap(Auth::new().role("user"))
+                        .wrap(Auth::new().permission("whois:lookup"))
                         .service(routes::util::whois),
                 )
                 .service(
                     web::scope("/email")
-                        .wrap(Auth::new().role("admin"))
-                        .service(routes::email::send)
-                        .service(routes::email::list_emails)
-                        .service(routes::email::process_emails)
-                        .service(routes::email::delete_email)
-                        .service(routes::email::mark_analyzed)
-                        .service(routes::email::get_email_tickets)
-                        .service(routes::email::link_to_ticket)
-                        .service(routes::email::unlink_from_ticket)
-                        .service(routes::email::force_delete_email)
-                        .service(routes::email::search_emails),
+                        .service(
+                            web::scope("")
+                                .guard(guard::Get())
+                                .wrap(Auth::new().permission("email:read"))
+                                .service(routes::email::list_emails)
+                                .service(routes::email::get_email_tickets)
+                                .service(routes::email::search_emails),
+                        )
+                        .service(
+                            web::scope("")
+                                .guard(guard::Delete())
+                                .wrap(Auth::new().permission("email:delete"))
+                                .service(routes::email::delete_email)
+                                .service(routes::email::unlink_from_ticket)
+                                .service(routes::email::force_delete_email),
+                        )
+                        .service(
+                            web::scope("")
+                                .guard(guard::fn_guard(|ctx| {
+                                    ctx.head().uri.path().ends_with("/send")
+                                }))
+                                .wrap(Auth::new().permission("email:send"))
+                                .service(routes::email::send),
+                        )
+                        .service(
+                            web::scope("")
+                                .wrap(Auth::new().permission("email:write"))
+                                .service(routes::email::process_emails)
+                                .service(routes::email::mark_analyzed)
+                                .service(routes::email::link_to_ticket),
+                        ),
                 )
                 .service(
                     web::scope("/tickets")
-                        .wrap(Auth::new().role("user"))
-                        .service(routes::ticket::create_ticket)
+                        .wrap(Auth::new().permission("tickets:read"))
                         .service(routes::ticket::list_tickets)
-                        .service(routes::ticket::update_ticket_status)
-                        .service(routes::ticket::add_email_to_ticket)
-                        .service(routes::ticket::remove_email_from_ticket)
                         .service(routes::ticket::get_ticket_emails)
+                        .service(routes::ticket::get_ticket_history)
+                        .service(routes::ticket::get_ticket_report)
+                        .service(routes::ticket::watch_ticket)
+                        .service(routes::ticket::unwatch_ticket)
+                        .service(routes::ticket::get_ticket_watchers)
+                        .service(routes::ticket::get_ticket_comments)
+                        .service(routes::ticket::export_tickets)
+                        .service(routes::ticket::search_tickets)
                         .service(routes::ticket::get_ticket)
-                        .service(routes::ticket::search_tickets),
+                        .service(
+                            web::scope("")
+                                .wrap(Auth::new().permission("tickets:write"))
+                                .service(routes::ticket::create_ticket)
+                                .service(routes::ticket::bulk_update_tickets)
+                                .service(routes::ticket::update_ticket_status)
+                                .service(routes::ticket::add_email_to_ticket)
+                                .service(routes::ticket::remove_email_from_ticket)
+                                .service(routes::ticket::add_ticket_comment)
+                                .service(routes::ticket::merge_tickets)
+                                .service(routes::ticket::split_ticket)
+                                .service(routes::ticket::update_ticket_tags)
+                                .service(routes::ticket::update_ticket_custom_fields)
+                                .service(routes::ticket::set_ticket_customer),
+                        ),
+                )
+                .service(
+                    web::scope("/events")
+                        .wrap(Auth::new().permission("tickets:read"))
+                        .service(routes::events::stream_events),
+                )
+                .service(
+                    web::scope("/notifications")
+                        .wrap(Auth::new())
+                        .service(routes::notification::list)
+                        .service(routes::notification::mark_all_read)
+                        .service(routes::notification::mark_read),
+                )
+                .service(
+                    web::scope("/users")
+                        .wrap(Auth::new())
+                        .service(routes::user::me)
+                        .service(routes::user::change_password)
+                        .service(routes::user::begin_totp_enrollment)
+                        .service(routes::user::confirm_totp_enrollment)
+                        .service(routes::user::regenerate_recovery_codes)
+                        .service(routes::user::disable_totp)
+                        .service(routes::user::create_api_key)
+                        .service(routes::user::list_api_keys)
+                        .service(routes::user::revoke_api_key)
+                        .service(
+                            web::scope("")
+                                .wrap(Auth::new().permission("users:manage"))
+                                .service(routes::auth::create_user)
+                                .service(routes::user::list_users)
+                                .service(routes::user::update_user_role)
+                                .service(routes::user::disable_user)
+                                .service(routes::user::enable_user)
+                                .service(routes::user::unlock_user)
+                                .service(routes::user::list_login_events)
+                                .service(routes::user::list_user_sessions)
+                                .service(routes::user::revoke_user_sessions)
+                                .service(routes::user::revoke_user_session)
+                                .service(routes::user::reset_user_password)
+                                .service(routes::user::reset_user_totp)
+                                .service(routes::user::get_user)
+                                .service(routes::user::update_user)
+                                .service(routes::user::delete_user),
+                        ),
+                )
+                .service(
+                    web::scope("/roles")
+                        .wrap(Auth::new().permission("roles:manage"))
+                        .service(routes::role::list_roles)
+                        .service(routes::role::list_permissions)
+                        .service(routes::role::create_role)
+                        // Registered before `/{name}` so they are not taken for role names
+                        .service(routes::role::list_keycloak_mappings)
+                        .service(routes::role::create_keycloak_mapping)
+                        .service(routes::role::delete_keycloak_mapping)
+                        .service(routes::role::get_role)
+                        .service(routes::role::update_role)
+                        .service(routes::role::delete_role),
+                )
+                .service(
+                    web::scope("/audit")
+                        .wrap(Auth::new().permission("audit:read"))
+                        .service(routes::audit::list)
+                        .service(routes::audit::export)
+                        .service(routes::audit::verify)
+                        .service(routes::audit::list_checkpoints)
+                        .service(routes::audit::export_checkpoints)
+                        .service(
+                            web::scope("")
+                                .wrap(Auth::new().permission("audit:manage"))
+                                .service(routes::audit::create_checkpoints),
+                        ),
+                )
+                .service(
+                    web::scope("/ticket_fields")
+                        .wrap(Auth::new().permission("tickets:read"))
+                        .service(routes::ticket_field::list_fields)
+                        .service(
+                            web::scope("")
+                                .wrap(Auth::new().permission("ticket_fields:manage"))
+                                .service(routes::ticket_field::create_field)
+                                .service(routes::ticket_field::delete_field),
+                        ),
                 ),
         )
 }
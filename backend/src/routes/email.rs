@@ -1,6 +1,7 @@
 use crate::models::email::{Email, EmailError, OutgoingEmail, SearchOptions};
 use actix_web::{delete, get, post, put, web, HttpResponse};
 use deadpool_postgres::Pool;
+use tracing::Instrument;
 use uuid::Uuid;
 
 /// Sends an outgoing email and saves it to the database
@@ -74,7 +75,7 @@ pub async fn list_emails(pool: web::Data<Pool>) -> HttpResponse {
             let unanalyzed_ids: Vec<Uuid> = emails
                 .iter()
                 .filter(|email| !email.analyzed)
-                .map(|email| email.id.clone())
+                .map(|email| email.id)
                 .collect();
 
             // If there are unanalyzed emails, start background processing
@@ -87,8 +88,9 @@ pub async fn list_emails(pool: web::Data<Pool>) -> HttpResponse {
                 // Clone what we need for the background task
                 let pool = pool.clone();
 
-                // Spawn background task
-                actix_web::rt::spawn(async move {
+                // Spawn background task, in the request span so the analysis
+                // logs carry the request ID
+                let task = async move {
                     // Process the batch of unanalyzed emails
                     match Email::process_batch_by_ids(&pool, &unanalyzed_ids).await {
                         Ok(results) => {
@@ -118,7 +120,8 @@ pub async fn list_emails(pool: web::Data<Pool>) -> HttpResponse {
                             log::error!("Failed to process batch: {}", e);
                         }
                     }
-                });
+                };
+                actix_web::rt::spawn(task.in_current_span());
             }
 
             // Return the list of emails
@@ -0,0 +1,129 @@
+use crate::auth::has_permission;
+use crate::events::{self, Event};
+use crate::models::auth::{ApiKeyClaims, Claims};
+use actix_web::web::Bytes;
+use actix_web::{get, web, HttpResponse};
+use deadpool_postgres::Pool;
+use futures::stream;
+use serde::Deserialize;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::broadcast::Receiver;
+
+/// Interval between keep-alive comments on idle streams.
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Query parameters for the event stream.
+///
+/// # Fields
+/// * `types` - Comma-separated event names to receive, all events if omitted
+#[derive(Debug, Deserialize)]
+pub struct EventStreamQuery {
+    pub types: Option<String>,
+}
+
+/// Subscriber state of an open event stream.
+struct EventStream {
+    receiver: Receiver<Event>,
+    keepalive: tokio::time::Interval,
+    types: Option<Vec<String>>,
+    emails: bool,
+}
+
+impl EventStream {
+    /// Waits for the next message to send to the client.
+    ///
+    /// # Returns
+    /// * `Option<String>` - SSE message, or None if the bus was closed
+    async fn next_message(&mut self) -> Option<String> {
+        loop {
+            tokio::select! {
+                _ = self.keepalive.tick() => return Some(": keepalive\n\n".to_string()),
+                received = self.receiver.recv() => match received {
+                    Ok(event) => {
+                        let allowed = self.emails || !matches!(event, Event::EmailIngested { .. });
+                        let wanted = self
+                            .types
+                            .as_ref()
+                            .is_none_or(|types| types.iter().any(|t| t == event.name()));
+                        if allowed && wanted {
+                            return Some(event.to_sse());
+                        }
+                    }
+                    // The client fell behind, tell it to resynchronize
+                    Err(RecvError::Lagged(missed)) => {
+                        return Some(format!("event: lagged\ndata: {}\n\n", missed))
+                    }
+                    Err(RecvError::Closed) => return None,
+                },
+            }
+        }
+    }
+}
+
+/// Stream live email and ticket events
+///
+/// # Endpoint
+/// GET /events
+///
+/// # Query Parameters
+/// - types: Comma-separated event names, e.g. `ticket_created,ticket_status_changed`
+///
+/// # Events
+/// - `email_ingested`: A new email was stored, only sent to callers with
+///   `email:read`
+/// - `ticket_created`: A ticket was created
+/// - `ticket_status_changed`: A ticket's status changed
+/// - `lagged`: The client missed the given number of events and should reload
+///
+/// # Returns
+/// `text/event-stream` response; idle streams receive a keep-alive comment
+/// every 15 seconds
+#[get("")]
+pub async fn stream_events(
+    pool: web::Data<Pool>,
+    claims: web::ReqData<Claims>,
+    key_claims: Option<web::ReqData<ApiKeyClaims>>,
+    query: web::Query<EventStreamQuery>,
+) -> HttpResponse {
+    // Email events carry senders and subjects
+    let emails = match has_permission(&pool, &claims, key_claims.as_deref(), "email:read").await {
+        Ok(allowed) => allowed,
+        Err(e) => {
+            log::error!("Failed to check permissions of {}: {}", claims.sub, e);
+            return HttpResponse::InternalServerError().json(e.to_string());
+        }
+    };
+
+    let types = query.into_inner().types.map(|types| {
+        types
+            .split(',')
+            .map(|t| t.trim().to_string())
+            .filter(|t| !t.is_empty())
+            .collect()
+    });
+
+    // Subscribe before responding so no event is missed
+    let mut keepalive = tokio::time::interval(KEEPALIVE_INTERVAL);
+    keepalive.reset();
+    let state = EventStream {
+        receiver: events::bus().subscribe(),
+        keepalive,
+        types,
+        emails,
+    };
+
+    let retry = stream::once(async { Ok::<_, actix_web::Error>(Bytes::from("retry: 5000\n\n")) });
+    let messages = stream::unfold(state, |mut state| async move {
+        state
+            .next_message()
+            .await
+            .map(|message| (Ok(Bytes::from(message)), state))
+    });
+
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .insert_header(("X-Accel-Buffering", "no"))
+        .streaming(futures::StreamExt::chain(retry, messages))
+}
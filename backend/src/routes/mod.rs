@@ -3,6 +3,12 @@
 //! Centralizes all application routing modules and their functionalities:
 //!
 //! # Modules
+//! - `audit`: User activity audit log
+//!   - Filtered queries
+//!   - CSV export
+//!   - Hash chain verification
+//!   - Signed checkpoints
+//!
 //! - `auth`: Authentication and authorization routes
 //!   - Login/logout
 //!   - Token management
@@ -27,18 +33,40 @@
 //!   - Ticket associations
 //!   - Search functionality
 //!
+//! - `events`: Live updates
+//!   - Server-Sent Events stream
+//!   - Event type filtering
+//!
 //! - `nctns`: Network and Cyber Threat Notification System
 //!   - Threat notifications
 //!   - Security alerts
 //!   - Incident tracking
 //!   - Threat analysis
 //!
+//! - `notification`: In-app notifications
+//!   - Notification listing
+//!   - Read tracking
+//!
+//! - `role`: Role management
+//!   - Role listing and definitions
+//!   - Permission assignment
+//!
 //! - `ticket`: Ticket management system
 //!   - Ticket creation
 //!   - Status updates
 //!   - Email linking
 //!   - Search operations
 //!
+//! - `ticket_field`: Custom ticket field definitions
+//!   - Field listing
+//!   - Field creation and deletion
+//!
+//! - `user`: User management
+//!   - Own profile
+//!   - User listing and updates
+//!   - Role assignment
+//!   - Disabling and deletion
+//!
 //! - `util`: Utility endpoints
 //!   - WHOIS lookups
 //!   - System diagnostics
@@ -47,7 +75,7 @@
 //!
 //! # Security Considerations
 //! - All routes implement appropriate authentication
-//! - Role-based access control
+//! - Permission-based access control
 //! - Input validation
 //! - Rate limiting
 //!
@@ -56,10 +84,16 @@
 //! Routes are configured in the `config` module and registered in the application
 //! startup.
 
+pub mod audit;
 pub mod auth;
 pub mod config;
 pub mod customer;
 pub mod email;
+pub mod events;
 pub mod nctns;
+pub mod notification;
+pub mod role;
 pub mod ticket;
+pub mod ticket_field;
+pub mod user;
 pub mod util;
@@ -45,7 +45,7 @@ pub async fn list(pool: web::Data<Pool>) -> HttpResponse {
         Ok(list) => HttpResponse::Ok().json(list),
         Err(err) => {
             log::info!("unable to fetch NCTNS records: {:?}", err);
-            return HttpResponse::InternalServerError().json("unable to fetch NCTNS records");
+            HttpResponse::InternalServerError().json("unable to fetch NCTNS records")
         }
     }
 }
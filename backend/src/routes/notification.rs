@@ -0,0 +1,109 @@
+use crate::models::auth::Claims;
+use crate::models::notification::Notification;
+use crate::models::requests::{NotificationListResponse, NotificationQuery};
+use crate::models::ticket::TicketError;
+use actix_web::{get, put, web, HttpResponse};
+use deadpool_postgres::Pool;
+use uuid::Uuid;
+
+/// List the current user's notifications
+///
+/// # Endpoint
+/// GET /notifications
+///
+/// # Query Parameters
+/// - unread: Only return unread notifications
+/// - limit: Page size (default 50)
+/// - offset: Pagination offset
+///
+/// # Returns
+/// Unread count and notifications, newest first
+#[get("")]
+pub async fn list(
+    pool: web::Data<Pool>,
+    query: web::Query<NotificationQuery>,
+    claims: web::ReqData<Claims>,
+) -> HttpResponse {
+    let query = query.into_inner();
+
+    let notifications = match Notification::list_for_user(
+        &pool,
+        claims.sub,
+        query.unread,
+        query.limit.unwrap_or(50),
+        query.offset.unwrap_or(0),
+    )
+    .await
+    {
+        Ok(notifications) => notifications,
+        Err(e) => {
+            log::error!("Failed to list notifications for {}: {}", claims.sub, e);
+            return HttpResponse::InternalServerError().json(e.to_string());
+        }
+    };
+
+    match Notification::unread_count(&pool, claims.sub).await {
+        Ok(unread) => HttpResponse::Ok().json(NotificationListResponse {
+            unread,
+            notifications,
+        }),
+        Err(e) => {
+            log::error!("Failed to count notifications for {}: {}", claims.sub, e);
+            HttpResponse::InternalServerError().json(e.to_string())
+        }
+    }
+}
+
+/// Mark one of the current user's notifications as read
+///
+/// # Endpoint
+/// PUT /notifications/{id}/read
+///
+/// # Path Parameters
+/// - id: Notification UUID
+///
+/// # Returns
+/// - 204: Marked as read
+/// - 404: The user has no such notification
+#[put("/{id}/read")]
+pub async fn mark_read(
+    pool: web::Data<Pool>,
+    path: web::Path<Uuid>,
+    claims: web::ReqData<Claims>,
+) -> HttpResponse {
+    let id = path.into_inner();
+
+    match Notification::mark_read(&pool, claims.sub, id).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(TicketError::NotFound(msg)) => {
+            log::warn!("{}", msg);
+            HttpResponse::NotFound().json("Notification not found")
+        }
+        Err(e) => {
+            log::error!("Failed to mark notification {} as read: {}", id, e);
+            HttpResponse::InternalServerError().json(e.to_string())
+        }
+    }
+}
+
+/// Mark all of the current user's notifications as read
+///
+/// # Endpoint
+/// PUT /notifications/read_all
+///
+/// # Returns
+/// Number of notifications marked as read
+#[put("/read_all")]
+pub async fn mark_all_read(pool: web::Data<Pool>, claims: web::ReqData<Claims>) -> HttpResponse {
+    match Notification::mark_all_read(&pool, claims.sub).await {
+        Ok(count) => HttpResponse::Ok().json(count),
+        Err(e) => {
+            log::error!(
+                "Failed to mark notifications as read for {}: {}",
+                claims.sub,
+                e
+            );
+            HttpResponse::InternalServerError().json(e.to_string())
+        }
+    }
+}
@@ -0,0 +1,308 @@
+use crate::models::auth::{
+    Claims, CreateKeycloakMappingRequest, CreateRoleRequest, EditRoleRequest,
+};
+use crate::models::keycloak_mapping::KeycloakMapping;
+use crate::models::role::{Role, PERMISSIONS};
+use crate::models::user::UserError;
+use actix_web::{delete, get, post, put, web, HttpResponse};
+use deadpool_postgres::Pool;
+use uuid::Uuid;
+
+/// List roles
+///
+/// # Endpoint
+/// GET /roles/list
+///
+/// # Returns
+/// Array of roles with their permissions
+#[get("/list")]
+pub async fn list_roles(pool: web::Data<Pool>) -> HttpResponse {
+    match Role::list(&pool).await {
+        Ok(roles) => HttpResponse::Ok().json(roles),
+        Err(e) => {
+            log::error!("Failed to list roles: {}", e);
+            HttpResponse::InternalServerError().json(e.to_string())
+        }
+    }
+}
+
+/// List the permissions that can be granted
+///
+/// # Endpoint
+/// GET /roles/permissions
+///
+/// # Returns
+/// Array of permission names
+#[get("/permissions")]
+pub async fn list_permissions() -> HttpResponse {
+    HttpResponse::Ok().json(PERMISSIONS)
+}
+
+/// Create a role
+///
+/// # Endpoint
+/// POST /roles/create
+///
+/// # Request Body
+/// ```json
+/// {
+///   "name": "auditor",
+///   "description": "Read-only access",
+///   "permissions": ["tickets:read", "email:read", "customers:read"]
+/// }
+/// ```
+///
+/// # Returns
+/// - 201: Role created
+/// - 400: Invalid name, unknown permission or name already in use
+#[post("/create")]
+pub async fn create_role(
+    pool: web::Data<Pool>,
+    role_req: web::Json<CreateRoleRequest>,
+    claims: web::ReqData<Claims>,
+) -> HttpResponse {
+    match Role::create(
+        &pool,
+        &role_req.name,
+        &role_req.description,
+        &role_req.permissions,
+    )
+    .await
+    {
+        Ok(role) => {
+            log::info!("User {} created role {}", claims.sub, role.name);
+            HttpResponse::Created().json(role)
+        }
+        Err(UserError::Validation(msg)) => {
+            log::warn!("Rejected role {}: {}", role_req.name, msg);
+            HttpResponse::BadRequest().json(msg)
+        }
+        Err(e) => {
+            log::error!("Failed to create role {}: {}", role_req.name, e);
+            HttpResponse::InternalServerError().json(e.to_string())
+        }
+    }
+}
+
+/// Get a role
+///
+/// # Endpoint
+/// GET /roles/{name}
+///
+/// # Returns
+/// - 200: Role
+/// - 404: Role not found
+#[get("/{name}")]
+pub async fn get_role(pool: web::Data<Pool>, path: web::Path<String>) -> HttpResponse {
+    let name = path.into_inner();
+
+    match Role::get(&pool, &name).await {
+        Ok(role) => HttpResponse::Ok().json(role),
+        Err(UserError::NotFound(msg)) => {
+            log::warn!("{}", msg);
+            HttpResponse::NotFound().json("Role not found")
+        }
+        Err(e) => {
+            log::error!("Failed to get role {}: {}", name, e);
+            HttpResponse::InternalServerError().json(e.to_string())
+        }
+    }
+}
+
+/// Update a role's description and permissions
+///
+/// # Endpoint
+/// PUT /roles/{name}
+///
+/// # Request Body
+/// ```json
+/// {
+///   "permissions": ["tickets:read", "email:read", "email:send"]
+/// }
+/// ```
+///
+/// # Returns
+/// - 200: Updated role, effective for the next request of its users
+/// - 400: Unknown permission or the admin role
+/// - 404: Role not found
+#[put("/{name}")]
+pub async fn update_role(
+    pool: web::Data<Pool>,
+    path: web::Path<String>,
+    role_req: web::Json<EditRoleRequest>,
+    claims: web::ReqData<Claims>,
+) -> HttpResponse {
+    let name = path.into_inner();
+
+    match Role::update(
+        &pool,
+        &name,
+        role_req.description.as_deref(),
+        role_req.permissions.as_deref(),
+    )
+    .await
+    {
+        Ok(role) => {
+            log::info!(
+                "User {} set permissions of role {} to {}",
+                claims.sub,
+                name,
+                role.permissions.join(", ")
+            );
+            HttpResponse::Ok().json(role)
+        }
+        Err(UserError::Validation(msg)) => {
+            log::warn!("Rejected update of role {}: {}", name, msg);
+            HttpResponse::BadRequest().json(msg)
+        }
+        Err(UserError::NotFound(msg)) => {
+            log::warn!("{}", msg);
+            HttpResponse::NotFound().json("Role not found")
+        }
+        Err(e) => {
+            log::error!("Failed to update role {}: {}", name, e);
+            HttpResponse::InternalServerError().json(e.to_string())
+        }
+    }
+}
+
+/// Delete a role
+///
+/// # Endpoint
+/// DELETE /roles/{name}
+///
+/// # Returns
+/// - 204: Role deleted
+/// - 400: Built-in role or role still assigned to users or Keycloak mappings
+/// - 404: Role not found
+#[delete("/{name}")]
+pub async fn delete_role(
+    pool: web::Data<Pool>,
+    path: web::Path<String>,
+    claims: web::ReqData<Claims>,
+) -> HttpResponse {
+    let name = path.into_inner();
+
+    match Role::delete(&pool, &name).await {
+        Ok(()) => {
+            log::info!("User {} deleted role {}", claims.sub, name);
+            HttpResponse::NoContent().finish()
+        }
+        Err(UserError::Validation(msg)) => {
+            log::warn!("Rejected deletion of role {}: {}", name, msg);
+            HttpResponse::BadRequest().json(msg)
+        }
+        Err(UserError::NotFound(msg)) => {
+            log::warn!("{}", msg);
+            HttpResponse::NotFound().json("Role not found")
+        }
+        Err(e) => {
+            log::error!("Failed to delete role {}: {}", name, e);
+            HttpResponse::InternalServerError().json(e.to_string())
+        }
+    }
+}
+
+/// List the Keycloak role mappings
+///
+/// # Endpoint
+/// GET /roles/keycloak_mappings
+///
+/// # Returns
+/// Array of mappings, highest priority first
+#[get("/keycloak_mappings")]
+pub async fn list_keycloak_mappings(pool: web::Data<Pool>) -> HttpResponse {
+    match KeycloakMapping::list(&pool).await {
+        Ok(mappings) => HttpResponse::Ok().json(mappings),
+        Err(e) => {
+            log::error!("Failed to list Keycloak mappings: {}", e);
+            HttpResponse::InternalServerError().json(e.to_string())
+        }
+    }
+}
+
+/// Map a Keycloak realm role, client role or group to a role
+///
+/// # Endpoint
+/// POST /roles/keycloak_mappings
+///
+/// # Request Body
+/// ```json
+/// {
+///   "claim": "group",
+///   "value": "/soc/analysts",
+///   "role": "auditor",
+///   "priority": 10
+/// }
+/// ```
+///
+/// # Returns
+/// - 201: Mapping created, applied on the next token exchange
+/// - 400: Unknown claim or role, or value already mapped
+#[post("/keycloak_mappings")]
+pub async fn create_keycloak_mapping(
+    pool: web::Data<Pool>,
+    mapping_req: web::Json<CreateKeycloakMappingRequest>,
+    claims: web::ReqData<Claims>,
+) -> HttpResponse {
+    match KeycloakMapping::create(
+        &pool,
+        &mapping_req.claim,
+        &mapping_req.value,
+        &mapping_req.role,
+        mapping_req.priority,
+    )
+    .await
+    {
+        Ok(mapping) => {
+            log::info!(
+                "User {} mapped Keycloak {} {} to role {}",
+                claims.sub,
+                mapping.claim,
+                mapping.value,
+                mapping.role
+            );
+            HttpResponse::Created().json(mapping)
+        }
+        Err(UserError::Validation(msg)) => {
+            log::warn!("Rejected Keycloak mapping: {}", msg);
+            HttpResponse::BadRequest().json(msg)
+        }
+        Err(e) => {
+            log::error!("Failed to create Keycloak mapping: {}", e);
+            HttpResponse::InternalServerError().json(e.to_string())
+        }
+    }
+}
+
+/// Delete a Keycloak role mapping
+///
+/// # Endpoint
+/// DELETE /roles/keycloak_mappings/{id}
+///
+/// # Returns
+/// - 204: Mapping deleted
+/// - 404: Mapping not found
+#[delete("/keycloak_mappings/{id}")]
+pub async fn delete_keycloak_mapping(
+    pool: web::Data<Pool>,
+    path: web::Path<Uuid>,
+    claims: web::ReqData<Claims>,
+) -> HttpResponse {
+    let id = path.into_inner();
+
+    match KeycloakMapping::delete(&pool, id).await {
+        Ok(()) => {
+            log::info!("User {} deleted Keycloak mapping {}", claims.sub, id);
+            HttpResponse::NoContent().finish()
+        }
+        Err(UserError::NotFound(msg)) => {
+            log::warn!("{}", msg);
+            HttpResponse::NotFound().json("Mapping not found")
+        }
+        Err(e) => {
+            log::error!("Failed to delete Keycloak mapping {}: {}", id, e);
+            HttpResponse::InternalServerError().json(e.to_string())
+        }
+    }
+}
@@ -1,5 +1,16 @@
-use crate::models::requests::{AddEmailRequest, CreateTicketRequest, CreateTicketResponse};
-use crate::models::ticket::{SearchOptions, Ticket, TicketError, TicketStatus, TicketType};
+use crate::models::auth::Claims;
+use crate::models::export::{self, TicketReport};
+use crate::models::notification::TicketWatcher;
+use crate::models::requests::{
+    AddEmailRequest, BulkTicketRequest, BulkTicketResponse, CreateCommentRequest,
+    CreateTicketRequest, CreateTicketResponse, ExportTicketsRequest, MergeTicketsRequest,
+    SetCustomerRequest, SplitTicketRequest, UpdateCustomFieldsRequest, UpdateTagsRequest,
+};
+use crate::models::ticket::{
+    SearchOptions, Ticket, TicketError, TicketStatus, TicketType, BULK_LIMIT,
+};
+use crate::models::ticket_comment::TicketComment;
+use crate::models::ticket_history::TicketHistory;
 use actix_web::{delete, get, post, put, web, HttpResponse};
 use deadpool_postgres::Pool;
 use uuid::Uuid;
@@ -43,7 +54,7 @@ pub async fn create_ticket(
     }
 
     // Create the ticket
-    let ticket = Ticket::new(
+    let mut ticket = Ticket::new(
         ticket_data.ticket_type.unwrap_or(TicketType::Other),
         ticket_data.subject,
         ticket_data.description,
@@ -56,6 +67,16 @@ pub async fn create_ticket(
 
     // If no emails to link, use simple save
     if ticket_data.email_ids.is_empty() {
+        // Link the ticket to the responsible customer
+        match pool.get().await {
+            Ok(client) => {
+                if let Err(e) = ticket.resolve_customer(&**client, &[]).await {
+                    log::error!("Failed to resolve customer for ticket {}: {}", ticket.id, e);
+                }
+            }
+            Err(e) => log::error!("Failed to get database connection: {}", e),
+        }
+
         // Save the ticket
         match ticket.save(&pool).await {
             Ok(ticket_id) => {
@@ -91,6 +112,13 @@ pub async fn create_ticket(
         }
     };
 
+    // Link the ticket to the responsible customer
+    if let Err(e) = ticket.resolve_customer(&*tx, &ticket_data.email_ids).await {
+        log::error!("Failed to resolve customer for ticket: {}", e);
+        let _ = tx.rollback().await;
+        return HttpResponse::InternalServerError().json(e.to_string());
+    }
+
     // Save the ticket within the transaction
     let ticket_id = match ticket.save_with_client(&tx).await {
         Ok(id) => id,
@@ -129,6 +157,7 @@ pub async fn create_ticket(
         log::error!("Failed to commit transaction: {}", e);
         return HttpResponse::InternalServerError().json("Failed to commit transaction");
     }
+    ticket.publish_created();
 
     let response = CreateTicketResponse {
         ticket_id,
@@ -284,6 +313,291 @@ pub async fn get_ticket_emails(pool: web::Data<Pool>, path: web::Path<Uuid>) ->
     }
 }
 
+/// Get the lifecycle history of a ticket
+///
+/// # Endpoint
+/// GET /tickets/{id}/history
+///
+/// # Path Parameters
+/// - id: Ticket UUID
+///
+/// # Returns
+/// Array of history records, oldest first
+#[get("/{id}/history")]
+pub async fn get_ticket_history(pool: web::Data<Pool>, path: web::Path<Uuid>) -> HttpResponse {
+    // Extract the ticket ID
+    let ticket_id = path.into_inner();
+
+    // Make sure the ticket exists
+    match Ticket::find_by_id(&pool, ticket_id).await {
+        Ok(Some(_)) => match TicketHistory::list_for_ticket(&pool, ticket_id).await {
+            Ok(history) => HttpResponse::Ok().json(history),
+            Err(e) => {
+                log::error!("Failed to get history for ticket {}: {}", ticket_id, e);
+                HttpResponse::InternalServerError().json(e.to_string())
+            }
+        },
+        Ok(None) => {
+            log::warn!("Ticket {} not found", ticket_id);
+            HttpResponse::NotFound().json("Ticket not found")
+        }
+        Err(e) => {
+            log::error!("Failed to find ticket {}: {}", ticket_id, e);
+            HttpResponse::InternalServerError().json(e.to_string())
+        }
+    }
+}
+
+/// Export tickets matching a search
+///
+/// # Endpoint
+/// POST /tickets/export
+///
+/// # Request Body
+/// ```json
+/// {
+///   "format": "csv",
+///   "query": "phishing",
+///   "filters": { "status": "Open" }
+/// }
+/// ```
+///
+/// # Returns
+/// Streamed CSV or NDJSON file with linked email metadata
+#[post("/export")]
+pub async fn export_tickets(
+    pool: web::Data<Pool>,
+    request: web::Json<ExportTicketsRequest>,
+) -> HttpResponse {
+    let request = request.into_inner();
+    let format = request.format;
+
+    match export::export_tickets(pool.get_ref().clone(), request.search, format).await {
+        Ok(stream) => {
+            let filename = format!(
+                "tickets-{}.{}",
+                chrono::Utc::now().format("%Y%m%d%H%M%S"),
+                format.extension()
+            );
+            HttpResponse::Ok()
+                .content_type(format.content_type())
+                .insert_header((
+                    "Content-Disposition",
+                    format!("attachment; filename=\"{}\"", filename),
+                ))
+                .streaming(stream)
+        }
+        Err(TicketError::Validation(msg)) => {
+            log::warn!("Invalid export request: {}", msg);
+            HttpResponse::BadRequest().json(msg)
+        }
+        Err(e) => {
+            log::error!("Failed to export tickets: {}", e);
+            HttpResponse::InternalServerError().json(e.to_string())
+        }
+    }
+}
+
+/// Get a printable report of a ticket
+///
+/// # Endpoint
+/// GET /tickets/{id}/report
+///
+/// # Path Parameters
+/// - id: Ticket UUID
+///
+/// # Returns
+/// HTML page with the description, analysis summary, indicators and timeline
+#[get("/{id}/report")]
+pub async fn get_ticket_report(pool: web::Data<Pool>, path: web::Path<Uuid>) -> HttpResponse {
+    // Extract the ticket ID
+    let ticket_id = path.into_inner();
+
+    match TicketReport::load(&pool, ticket_id).await {
+        Ok(Some(report)) => HttpResponse::Ok()
+            .content_type("text/html; charset=utf-8")
+            .body(report.render_html()),
+        Ok(None) => {
+            log::warn!("Ticket {} not found", ticket_id);
+            HttpResponse::NotFound().json("Ticket not found")
+        }
+        Err(e) => {
+            log::error!("Failed to build report for ticket {}: {}", ticket_id, e);
+            HttpResponse::InternalServerError().json(e.to_string())
+        }
+    }
+}
+
+/// Start watching a ticket
+///
+/// # Endpoint
+/// POST /tickets/{id}/watch
+///
+/// # Path Parameters
+/// - id: Ticket UUID
+///
+/// # Returns
+/// - 204: The current user now watches the ticket
+/// - 404: Ticket not found
+#[post("/{id}/watch")]
+pub async fn watch_ticket(
+    pool: web::Data<Pool>,
+    path: web::Path<Uuid>,
+    claims: web::ReqData<Claims>,
+) -> HttpResponse {
+    let ticket_id = path.into_inner();
+
+    let client = match pool.get().await {
+        Ok(client) => client,
+        Err(e) => {
+            log::error!("Failed to get database client: {}", e);
+            return HttpResponse::InternalServerError().json(e.to_string());
+        }
+    };
+
+    match TicketWatcher::watch(&**client, &ticket_id, &claims.sub).await {
+        Ok(()) => {
+            log::info!("User {} is watching ticket {}", claims.sub, ticket_id);
+            HttpResponse::NoContent().finish()
+        }
+        Err(TicketError::NotFound(msg)) => {
+            log::warn!("Failed to watch ticket: {}", msg);
+            HttpResponse::NotFound().json("Ticket not found")
+        }
+        Err(e) => {
+            log::error!("Failed to watch ticket {}: {}", ticket_id, e);
+            HttpResponse::InternalServerError().json(e.to_string())
+        }
+    }
+}
+
+/// Stop watching a ticket
+///
+/// # Endpoint
+/// DELETE /tickets/{id}/watch
+///
+/// # Path Parameters
+/// - id: Ticket UUID
+///
+/// # Returns
+/// - 204: The current user no longer watches the ticket
+/// - 404: The current user was not watching the ticket
+#[delete("/{id}/watch")]
+pub async fn unwatch_ticket(
+    pool: web::Data<Pool>,
+    path: web::Path<Uuid>,
+    claims: web::ReqData<Claims>,
+) -> HttpResponse {
+    let ticket_id = path.into_inner();
+
+    match TicketWatcher::unwatch(&pool, ticket_id, claims.sub).await {
+        Ok(true) => {
+            log::info!("User {} stopped watching ticket {}", claims.sub, ticket_id);
+            HttpResponse::NoContent().finish()
+        }
+        Ok(false) => HttpResponse::NotFound().json("Not watching this ticket"),
+        Err(e) => {
+            log::error!("Failed to unwatch ticket {}: {}", ticket_id, e);
+            HttpResponse::InternalServerError().json(e.to_string())
+        }
+    }
+}
+
+/// List the watchers of a ticket
+///
+/// # Endpoint
+/// GET /tickets/{id}/watchers
+///
+/// # Path Parameters
+/// - id: Ticket UUID
+///
+/// # Returns
+/// Array of watchers, earliest first
+#[get("/{id}/watchers")]
+pub async fn get_ticket_watchers(pool: web::Data<Pool>, path: web::Path<Uuid>) -> HttpResponse {
+    let ticket_id = path.into_inner();
+
+    match TicketWatcher::list_for_ticket(&pool, ticket_id).await {
+        Ok(watchers) => HttpResponse::Ok().json(watchers),
+        Err(e) => {
+            log::error!("Failed to get watchers for ticket {}: {}", ticket_id, e);
+            HttpResponse::InternalServerError().json(e.to_string())
+        }
+    }
+}
+
+/// Add a comment to a ticket
+///
+/// # Endpoint
+/// POST /tickets/{id}/comments
+///
+/// # Path Parameters
+/// - id: Ticket UUID
+///
+/// # Request Body
+/// ```json
+/// {
+///   "body": "Contacted the hosting provider"
+/// }
+/// ```
+///
+/// # Returns
+/// - 201: The created comment
+/// - 400: Empty or too long comment
+/// - 404: Ticket not found
+#[post("/{id}/comments")]
+pub async fn add_ticket_comment(
+    pool: web::Data<Pool>,
+    path: web::Path<Uuid>,
+    request: web::Json<CreateCommentRequest>,
+    claims: web::ReqData<Claims>,
+) -> HttpResponse {
+    let ticket_id = path.into_inner();
+    let mut comment = TicketComment::new(ticket_id, Some(claims.sub), request.into_inner().body);
+
+    match comment.save(&pool).await {
+        Ok(()) => {
+            log::info!("User {} commented on ticket {}", claims.sub, ticket_id);
+            HttpResponse::Created().json(comment)
+        }
+        Err(TicketError::Validation(msg)) => {
+            log::warn!("Invalid comment: {}", msg);
+            HttpResponse::BadRequest().json(msg)
+        }
+        Err(TicketError::NotFound(msg)) => {
+            log::warn!("{}", msg);
+            HttpResponse::NotFound().json("Ticket not found")
+        }
+        Err(e) => {
+            log::error!("Failed to comment on ticket {}: {}", ticket_id, e);
+            HttpResponse::InternalServerError().json(e.to_string())
+        }
+    }
+}
+
+/// List the comments of a ticket
+///
+/// # Endpoint
+/// GET /tickets/{id}/comments
+///
+/// # Path Parameters
+/// - id: Ticket UUID
+///
+/// # Returns
+/// Array of comments, oldest first
+#[get("/{id}/comments")]
+pub async fn get_ticket_comments(pool: web::Data<Pool>, path: web::Path<Uuid>) -> HttpResponse {
+    let ticket_id = path.into_inner();
+
+    match TicketComment::list_for_ticket(&pool, ticket_id).await {
+        Ok(comments) => HttpResponse::Ok().json(comments),
+        Err(e) => {
+            log::error!("Failed to get comments for ticket {}: {}", ticket_id, e);
+            HttpResponse::InternalServerError().json(e.to_string())
+        }
+    }
+}
+
 /// List all tickets in the system
 ///
 /// # Endpoint
@@ -321,6 +635,7 @@ pub async fn update_ticket_status(
     pool: web::Data<Pool>,
     path: web::Path<Uuid>,
     status: web::Json<String>,
+    claims: Option<web::ReqData<Claims>>,
 ) -> HttpResponse {
     // Extract the ticket ID and new status
     let id = path.into_inner();
@@ -329,7 +644,10 @@ pub async fn update_ticket_status(
     // Find the ticket
     match Ticket::find_by_id(&pool, id).await {
         // If the ticket exists, update the status
-        Ok(Some(mut ticket)) => match ticket.update_status(&pool, new_status).await {
+        Ok(Some(mut ticket)) => match ticket
+            .update_status(&pool, new_status, claims.map(|c| c.sub))
+            .await
+        {
             Ok(_) => {
                 log::info!("Updated ticket {} status to {:?}", id, new_status);
                 HttpResponse::Ok().finish()
@@ -350,23 +668,28 @@ pub async fn update_ticket_status(
     }
 }
 
-/// Get a single ticket by ID
+/// Get a single ticket by ID or reference
 ///
 /// # Endpoint
 /// GET /tickets/{id}
 ///
 /// # Path Parameters
-/// - id: Ticket UUID
+/// - id: Ticket UUID or reference (e.g. "AH-1234")
 ///
 /// # Returns
 /// Complete ticket object with all fields
 #[get("/{id}")]
-pub async fn get_ticket(pool: web::Data<Pool>, path: web::Path<Uuid>) -> HttpResponse {
+pub async fn get_ticket(pool: web::Data<Pool>, path: web::Path<String>) -> HttpResponse {
     // Extract the ticket ID
     let id = path.into_inner();
 
-    // Find the ticket
-    match Ticket::find_by_id(&pool, id).await {
+    // Find the ticket by UUID, falling back to its reference
+    let result = match Uuid::parse_str(&id) {
+        Ok(uuid) => Ticket::find_by_id(&pool, uuid).await,
+        Err(_) => Ticket::find_by_reference(&pool, &id).await,
+    };
+
+    match result {
         Ok(Some(ticket)) => HttpResponse::Ok().json(ticket),
         Ok(None) => {
             log::warn!("Ticket {} not found", id);
@@ -413,3 +736,407 @@ pub async fn search_tickets(query: web::Query<SearchOptions>) -> HttpResponse {
         }
     }
 }
+
+/// Merge other tickets into a ticket
+///
+/// # Endpoint
+/// POST /tickets/{id}/merge
+///
+/// # Path Parameters
+/// - id: UUID of the surviving ticket
+///
+/// # Request Body
+/// ```json
+/// {
+///   "ticket_ids": ["uuid1", "uuid2"]
+/// }
+/// ```
+///
+/// # Returns
+/// - 200: Merged ticket with all emails and indicators
+/// - 400: Validation error
+/// - 404: Ticket not found
+/// - 500: Database error
+#[post("/{id}/merge")]
+pub async fn merge_tickets(
+    pool: web::Data<Pool>,
+    path: web::Path<Uuid>,
+    merge_req: web::Json<MergeTicketsRequest>,
+) -> HttpResponse {
+    // Extract the ticket ID
+    let id = path.into_inner();
+
+    // Find the surviving ticket
+    match Ticket::find_by_id(&pool, id).await {
+        // If the ticket exists, merge the other tickets into it
+        Ok(Some(ticket)) => match ticket.merge(&pool, &merge_req.ticket_ids).await {
+            Ok(merged) => {
+                log::info!(
+                    "Merged tickets {:?} into {}",
+                    merge_req.ticket_ids,
+                    merged.id
+                );
+                HttpResponse::Ok().json(merged)
+            }
+            Err(TicketError::Validation(msg)) => {
+                log::warn!("Validation error merging tickets: {}", msg);
+                HttpResponse::BadRequest().json(msg)
+            }
+            Err(TicketError::NotFound(msg)) => {
+                log::warn!("Ticket not found while merging: {}", msg);
+                HttpResponse::NotFound().json(msg)
+            }
+            Err(e) => {
+                log::error!("Failed to merge tickets into {}: {}", id, e);
+                HttpResponse::InternalServerError().json(e.to_string())
+            }
+        },
+        Ok(None) => {
+            log::warn!("Ticket {} not found", id);
+            HttpResponse::NotFound().json("Ticket not found")
+        }
+        Err(e) => {
+            log::error!("Failed to find ticket {}: {}", id, e);
+            HttpResponse::InternalServerError().json(e.to_string())
+        }
+    }
+}
+
+/// Split emails off a ticket into a new ticket
+///
+/// # Endpoint
+/// POST /tickets/{id}/split
+///
+/// # Path Parameters
+/// - id: Ticket UUID
+///
+/// # Request Body
+/// ```json
+/// {
+///   "email_ids": ["uuid1"],
+///   "subject": "Optional subject for the new ticket"
+/// }
+/// ```
+///
+/// # Returns
+/// - 201: Newly created ticket
+/// - 400: Validation error
+/// - 404: Ticket not found
+/// - 500: Database error
+#[post("/{id}/split")]
+pub async fn split_ticket(
+    pool: web::Data<Pool>,
+    path: web::Path<Uuid>,
+    split_req: web::Json<SplitTicketRequest>,
+) -> HttpResponse {
+    // Extract the ticket ID and split data
+    let id = path.into_inner();
+    let split_data = split_req.into_inner();
+
+    // Find the ticket
+    match Ticket::find_by_id(&pool, id).await {
+        // If the ticket exists, split the emails off
+        Ok(Some(ticket)) => match ticket
+            .split(&pool, &split_data.email_ids, split_data.subject)
+            .await
+        {
+            Ok(new_ticket) => {
+                log::info!("Split ticket {} off ticket {}", new_ticket.id, id);
+                HttpResponse::Created().json(new_ticket)
+            }
+            Err(TicketError::Validation(msg)) => {
+                log::warn!("Validation error splitting ticket: {}", msg);
+                HttpResponse::BadRequest().json(msg)
+            }
+            Err(TicketError::NotFound(msg)) => {
+                log::warn!("Ticket not found while splitting: {}", msg);
+                HttpResponse::NotFound().json(msg)
+            }
+            Err(e) => {
+                log::error!("Failed to split ticket {}: {}", id, e);
+                HttpResponse::InternalServerError().json(e.to_string())
+            }
+        },
+        Ok(None) => {
+            log::warn!("Ticket {} not found", id);
+            HttpResponse::NotFound().json("Ticket not found")
+        }
+        Err(e) => {
+            log::error!("Failed to find ticket {}: {}", id, e);
+            HttpResponse::InternalServerError().json(e.to_string())
+        }
+    }
+}
+
+/// Replace the tags of a ticket
+///
+/// # Endpoint
+/// PUT /tickets/{id}/tags
+///
+/// # Path Parameters
+/// - id: Ticket UUID
+///
+/// # Request Body
+/// ```json
+/// {
+///   "tags": ["campaign-2024-q3", "enterprise"]
+/// }
+/// ```
+///
+/// # Returns
+/// - 200: Updated ticket
+/// - 400: Validation error
+/// - 404: Ticket not found
+/// - 500: Database error
+#[put("/{id}/tags")]
+pub async fn update_ticket_tags(
+    pool: web::Data<Pool>,
+    path: web::Path<Uuid>,
+    tags_req: web::Json<UpdateTagsRequest>,
+) -> HttpResponse {
+    // Extract the ticket ID
+    let id = path.into_inner();
+
+    // Find the ticket
+    match Ticket::find_by_id(&pool, id).await {
+        // If the ticket exists, update the tags
+        Ok(Some(mut ticket)) => match ticket.set_tags(&pool, tags_req.into_inner().tags).await {
+            Ok(_) => {
+                log::info!("Updated ticket {} tags", id);
+                HttpResponse::Ok().json(ticket)
+            }
+            Err(TicketError::Validation(msg)) => {
+                log::warn!("Validation error updating ticket tags: {}", msg);
+                HttpResponse::BadRequest().json(msg)
+            }
+            Err(e) => {
+                log::error!("Failed to update ticket {} tags: {}", id, e);
+                HttpResponse::InternalServerError().json(e.to_string())
+            }
+        },
+        Ok(None) => {
+            log::warn!("Ticket {} not found", id);
+            HttpResponse::NotFound().json("Ticket not found")
+        }
+        Err(e) => {
+            log::error!("Failed to find ticket {}: {}", id, e);
+            HttpResponse::InternalServerError().json(e.to_string())
+        }
+    }
+}
+
+/// Set custom field values on a ticket
+///
+/// # Endpoint
+/// PUT /tickets/{id}/custom_fields
+///
+/// # Path Parameters
+/// - id: Ticket UUID
+///
+/// # Request Body
+/// ```json
+/// {
+///   "fields": {
+///     "customer_segment": "enterprise",
+///     "internal_reference": null
+///   }
+/// }
+/// ```
+///
+/// # Returns
+/// - 200: Updated ticket
+/// - 400: Unknown field or invalid value
+/// - 404: Ticket not found
+/// - 500: Database error
+#[put("/{id}/custom_fields")]
+pub async fn update_ticket_custom_fields(
+    pool: web::Data<Pool>,
+    path: web::Path<Uuid>,
+    fields_req: web::Json<UpdateCustomFieldsRequest>,
+) -> HttpResponse {
+    // Extract the ticket ID
+    let id = path.into_inner();
+
+    // Find the ticket
+    match Ticket::find_by_id(&pool, id).await {
+        // If the ticket exists, update the custom fields
+        Ok(Some(mut ticket)) => match ticket
+            .update_custom_fields(&pool, fields_req.into_inner().fields)
+            .await
+        {
+            Ok(_) => {
+                log::info!("Updated ticket {} custom fields", id);
+                HttpResponse::Ok().json(ticket)
+            }
+            Err(TicketError::Validation(msg)) => {
+                log::warn!("Validation error updating ticket custom fields: {}", msg);
+                HttpResponse::BadRequest().json(msg)
+            }
+            Err(e) => {
+                log::error!("Failed to update ticket {} custom fields: {}", id, e);
+                HttpResponse::InternalServerError().json(e.to_string())
+            }
+        },
+        Ok(None) => {
+            log::warn!("Ticket {} not found", id);
+            HttpResponse::NotFound().json("Ticket not found")
+        }
+        Err(e) => {
+            log::error!("Failed to find ticket {}: {}", id, e);
+            HttpResponse::InternalServerError().json(e.to_string())
+        }
+    }
+}
+
+/// Link a ticket to a customer
+///
+/// Overrides the automatically resolved customer. A null customer removes
+/// the override and resolves the customer again from the ticket's IP
+/// address and linked emails.
+///
+/// # Endpoint
+/// PUT /tickets/{id}/customer
+///
+/// # Path Parameters
+/// - id: Ticket UUID
+///
+/// # Request Body
+/// ```json
+/// {
+///   "customer_uuid": "123e4567-e89b-12d3-a456-426614175306"
+/// }
+/// ```
+///
+/// # Returns
+/// - 200: Updated ticket
+/// - 404: Ticket or customer not found
+/// - 500: Database error
+#[put("/{id}/customer")]
+pub async fn set_ticket_customer(
+    pool: web::Data<Pool>,
+    path: web::Path<Uuid>,
+    customer_req: web::Json<SetCustomerRequest>,
+) -> HttpResponse {
+    // Extract the ticket ID
+    let id = path.into_inner();
+
+    // Find the ticket
+    match Ticket::find_by_id(&pool, id).await {
+        // If the ticket exists, link the customer
+        Ok(Some(mut ticket)) => {
+            match ticket.set_customer(&pool, customer_req.customer_uuid).await {
+                Ok(_) => {
+                    log::info!("Updated ticket {} customer", id);
+                    HttpResponse::Ok().json(ticket)
+                }
+                Err(TicketError::NotFound(msg)) => {
+                    log::warn!("Customer not found while linking ticket: {}", msg);
+                    HttpResponse::NotFound().json(msg)
+                }
+                Err(e) => {
+                    log::error!("Failed to update ticket {} customer: {}", id, e);
+                    HttpResponse::InternalServerError().json(e.to_string())
+                }
+            }
+        }
+        Ok(None) => {
+            log::warn!("Ticket {} not found", id);
+            HttpResponse::NotFound().json("Ticket not found")
+        }
+        Err(e) => {
+            log::error!("Failed to find ticket {}: {}", id, e);
+            HttpResponse::InternalServerError().json(e.to_string())
+        }
+    }
+}
+
+/// Apply an operation to many tickets at once
+///
+/// Tickets are selected either by ID or by a search query (at most
+/// 1000 tickets). All updates run in one transaction; unless `atomic`
+/// is set, tickets that fail are skipped and reported.
+///
+/// # Endpoint
+/// POST /tickets/bulk
+///
+/// # Request Body
+/// ```json
+/// {
+///   "query": { "query": "spam wave", "filters": { "status": "Open" } },
+///   "action": "status",
+///   "status": "Closed"
+/// }
+/// ```
+///
+/// # Actions
+/// - `status`: `{"status": "Closed"}`
+/// - `assign`: `{"user_id": "uuid"}` or `{"user_id": null}`
+/// - `add_tags` / `remove_tags`: `{"tags": ["spam-wave"]}`
+/// - `delete`
+///
+/// # Returns
+/// - 200: Per-ticket results
+/// - 400: Invalid selection or action
+/// - 500: Database or search error
+#[post("/bulk")]
+pub async fn bulk_update_tickets(
+    pool: web::Data<Pool>,
+    bulk_req: web::Json<BulkTicketRequest>,
+    claims: Option<web::ReqData<Claims>>,
+) -> HttpResponse {
+    // Extract the bulk request
+    let bulk_data = bulk_req.into_inner();
+
+    // Select the tickets by ID or by search query
+    let ticket_ids = match (bulk_data.ticket_ids, bulk_data.query) {
+        (Some(ids), None) => ids,
+        (None, Some(mut options)) => {
+            options.size = Some(options.size.unwrap_or(BULK_LIMIT).min(BULK_LIMIT));
+            match Ticket::search(options).await {
+                Ok(response) => response.hits.into_iter().map(|t| t.id).collect(),
+                Err(e) => {
+                    log::error!("Failed to search tickets for bulk operation: {}", e);
+                    return HttpResponse::InternalServerError().json(e.to_string());
+                }
+            }
+        }
+        _ => {
+            return HttpResponse::BadRequest()
+                .json("Provide either ticket_ids or query, but not both");
+        }
+    };
+
+    // Apply the action
+    let actor = claims.map(|c| c.sub);
+    match Ticket::bulk_update(
+        &pool,
+        &ticket_ids,
+        &bulk_data.action,
+        bulk_data.atomic,
+        actor,
+    )
+    .await
+    {
+        Ok(results) => {
+            let succeeded = results.iter().filter(|r| r.success).count();
+            log::info!(
+                "Bulk operation updated {} of {} tickets",
+                succeeded,
+                results.len()
+            );
+            HttpResponse::Ok().json(BulkTicketResponse {
+                succeeded,
+                failed: results.len() - succeeded,
+                results,
+            })
+        }
+        Err(TicketError::Validation(msg)) => {
+            log::warn!("Validation error in bulk operation: {}", msg);
+            HttpResponse::BadRequest().json(msg)
+        }
+        Err(e) => {
+            log::error!("Failed to apply bulk operation: {}", e);
+            HttpResponse::InternalServerError().json(e.to_string())
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use crate::models::requests::CreateCustomFieldRequest;
+use crate::models::ticket::TicketError;
+use crate::models::ticket_field::CustomFieldDefinition;
+use actix_web::{delete, get, post, web, HttpResponse};
+use deadpool_postgres::Pool;
+
+/// List custom ticket field definitions
+///
+/// # Endpoint
+/// GET /ticket_fields/list
+///
+/// # Returns
+/// Array of field definitions
+#[get("/list")]
+pub async fn list_fields(pool: web::Data<Pool>) -> HttpResponse {
+    // List all field definitions
+    match CustomFieldDefinition::list(&pool).await {
+        Ok(fields) => HttpResponse::Ok().json(fields),
+        Err(e) => {
+            log::error!("Failed to list custom fields: {}", e);
+            HttpResponse::InternalServerError().json(e.to_string())
+        }
+    }
+}
+
+/// Define a new custom ticket field
+///
+/// # Endpoint
+/// POST /ticket_fields/create
+///
+/// # Request Body
+/// ```json
+/// {
+///   "name": "customer_segment",
+///   "label": "Customer segment",
+///   "field_type": "Enum",
+///   "options": ["consumer", "enterprise"]
+/// }
+/// ```
+///
+/// # Returns
+/// - 201: Field created
+/// - 400: Invalid definition or name already in use
+/// - 500: Database error
+#[post("/create")]
+pub async fn create_field(
+    pool: web::Data<Pool>,
+    field_req: web::Json<CreateCustomFieldRequest>,
+) -> HttpResponse {
+    // Extract the field data
+    let field_data = field_req.into_inner();
+    let field = CustomFieldDefinition::new(
+        field_data.name,
+        field_data.label,
+        field_data.field_type,
+        field_data.options,
+    );
+
+    // Save the field definition
+    match field.save(&pool).await {
+        Ok(_) => {
+            log::info!("Created custom field {}", field.name);
+            HttpResponse::Created().json(field)
+        }
+        Err(TicketError::Validation(msg)) => {
+            log::warn!("Validation error creating custom field: {}", msg);
+            HttpResponse::BadRequest().json(msg)
+        }
+        Err(e) => {
+            log::error!("Failed to create custom field: {}", e);
+            HttpResponse::InternalServerError().json(e.to_string())
+        }
+    }
+}
+
+/// Delete a custom ticket field
+///
+/// Clears the field's values from all tickets.
+///
+/// # Endpoint
+/// DELETE /ticket_fields/{name}
+///
+/// # Path Parameters
+/// - name: Field name
+///
+/// # Returns
+/// - 200: Field deleted
+/// - 404: Field not found
+/// - 500: Database error
+#[delete("/{name}")]
+pub async fn delete_field(pool: web::Data<Pool>, path: web::Path<String>) -> HttpResponse {
+    // Extract the field name
+    let name = path.into_inner();
+
+    // Delete the field definition
+    match CustomFieldDefinition::delete(&pool, &name).await {
+        Ok(_) => {
+            log::info!("Deleted custom field {}", name);
+            HttpResponse::Ok().finish()
+        }
+        Err(TicketError::NotFound(msg)) => {
+            log::warn!("Custom field not found: {}", msg);
+            HttpResponse::NotFound().json(msg)
+        }
+        Err(e) => {
+            log::error!("Failed to delete custom field {}: {}", name, e);
+            HttpResponse::InternalServerError().json(e.to_string())
+        }
+    }
+}
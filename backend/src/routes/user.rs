@@ -0,0 +1,867 @@
+use crate::models::api_key::ApiKey;
+use crate::models::auth::{
+    ChangePasswordRequest, Claims, CreateApiKeyRequest, PasswordResetResponse,
+    RecoveryCodesResponse, RevokedSessionsResponse, TotpCodeRequest, UpdateRoleRequest,
+    UpdateUserRequest, UserQuery, UserResponse,
+};
+use crate::models::login_attempt::LoginAttempts;
+use crate::models::password::PasswordResetToken;
+use crate::models::session::Session;
+use crate::models::totp::TwoFactor;
+use crate::models::user::{User, UserError};
+use actix_web::{delete, get, post, put, web, HttpResponse};
+use deadpool_postgres::Pool;
+use uuid::Uuid;
+
+/// Get the current user's profile
+///
+/// # Endpoint
+/// GET /users/me
+///
+/// # Returns
+/// Profile of the authenticated user
+#[get("/me")]
+pub async fn me(pool: web::Data<Pool>, claims: web::ReqData<Claims>) -> HttpResponse {
+    match User::get(&pool, claims.sub).await {
+        Ok(user) => HttpResponse::Ok().json(UserResponse::from(user)),
+        Err(UserError::NotFound(msg)) => {
+            log::warn!("{}", msg);
+            HttpResponse::NotFound().json("User not found")
+        }
+        Err(e) => {
+            log::error!("Failed to get user {}: {}", claims.sub, e);
+            HttpResponse::InternalServerError().json(e.to_string())
+        }
+    }
+}
+
+/// Change the current user's password
+///
+/// Signs out every other session of the user.
+///
+/// # Endpoint
+/// PUT /users/me/password
+///
+/// # Request Body
+/// ```json
+/// {
+///   "current_password": "old_password",
+///   "new_password": "new_secure_password"
+/// }
+/// ```
+///
+/// # Returns
+/// - 204: Password changed
+/// - 400: Wrong current password or new password too weak
+#[put("/me/password")]
+pub async fn change_password(
+    pool: web::Data<Pool>,
+    password_req: web::Json<ChangePasswordRequest>,
+    claims: web::ReqData<Claims>,
+) -> HttpResponse {
+    match User::change_password(
+        &pool,
+        claims.sub,
+        &password_req.current_password,
+        &password_req.new_password,
+        claims.sid,
+    )
+    .await
+    {
+        Ok(()) => {
+            log::info!("User {} changed their password", claims.sub);
+            HttpResponse::NoContent().finish()
+        }
+        Err(UserError::Validation(msg)) => {
+            log::warn!("Rejected password change of {}: {}", claims.sub, msg);
+            HttpResponse::BadRequest().json(msg)
+        }
+        Err(UserError::NotFound(msg)) => {
+            log::warn!("{}", msg);
+            HttpResponse::NotFound().json("User not found")
+        }
+        Err(e) => {
+            log::error!("Failed to change password of {}: {}", claims.sub, e);
+            HttpResponse::InternalServerError().json(e.to_string())
+        }
+    }
+}
+
+/// Start two-factor enrollment
+///
+/// # Endpoint
+/// POST /users/me/totp
+///
+/// # Returns
+/// - 200: Secret and `otpauth://` provisioning URI for authenticator apps
+/// - 400: Two-factor authentication is already enabled
+#[post("/me/totp")]
+pub async fn begin_totp_enrollment(
+    pool: web::Data<Pool>,
+    claims: web::ReqData<Claims>,
+) -> HttpResponse {
+    let user = match User::get(&pool, claims.sub).await {
+        Ok(user) => user,
+        Err(e) => {
+            log::error!("Failed to get user {}: {}", claims.sub, e);
+            return HttpResponse::InternalServerError().json(e.to_string());
+        }
+    };
+
+    match TwoFactor::begin_enrollment(&pool, &user).await {
+        Ok(enrollment) => HttpResponse::Ok().json(enrollment),
+        Err(UserError::Validation(msg)) => {
+            log::warn!("Rejected two-factor enrollment of {}: {}", claims.sub, msg);
+            HttpResponse::BadRequest().json(msg)
+        }
+        Err(e) => {
+            log::error!(
+                "Failed to start two-factor enrollment of {}: {}",
+                claims.sub,
+                e
+            );
+            HttpResponse::InternalServerError().json(e.to_string())
+        }
+    }
+}
+
+/// Confirm two-factor enrollment
+///
+/// # Endpoint
+/// POST /users/me/totp/confirm
+///
+/// # Request Body
+/// ```json
+/// {
+///   "code": "123456"
+/// }
+/// ```
+///
+/// # Returns
+/// - 200: Recovery codes, shown only once
+/// - 400: Invalid code or no pending enrollment
+#[post("/me/totp/confirm")]
+pub async fn confirm_totp_enrollment(
+    pool: web::Data<Pool>,
+    code_req: web::Json<TotpCodeRequest>,
+    claims: web::ReqData<Claims>,
+) -> HttpResponse {
+    match TwoFactor::confirm_enrollment(&pool, claims.sub, &code_req.code).await {
+        Ok(recovery_codes) => {
+            log::info!("User {} enabled two-factor authentication", claims.sub);
+            HttpResponse::Ok().json(RecoveryCodesResponse { recovery_codes })
+        }
+        Err(UserError::Validation(msg)) => {
+            log::warn!(
+                "Rejected two-factor confirmation of {}: {}",
+                claims.sub,
+                msg
+            );
+            HttpResponse::BadRequest().json(msg)
+        }
+        Err(e) => {
+            log::error!(
+                "Failed to confirm two-factor enrollment of {}: {}",
+                claims.sub,
+                e
+            );
+            HttpResponse::InternalServerError().json(e.to_string())
+        }
+    }
+}
+
+/// Replace the current user's recovery codes
+///
+/// # Endpoint
+/// POST /users/me/totp/recovery_codes
+///
+/// # Request Body
+/// Current code or an unused recovery code, see `confirm_totp_enrollment`
+///
+/// # Returns
+/// - 200: New recovery codes, the previous ones stop working
+/// - 400: Invalid code
+#[post("/me/totp/recovery_codes")]
+pub async fn regenerate_recovery_codes(
+    pool: web::Data<Pool>,
+    code_req: web::Json<TotpCodeRequest>,
+    claims: web::ReqData<Claims>,
+) -> HttpResponse {
+    match TwoFactor::regenerate_recovery_codes(&pool, claims.sub, &code_req.code).await {
+        Ok(recovery_codes) => {
+            log::info!("User {} regenerated their recovery codes", claims.sub);
+            HttpResponse::Ok().json(RecoveryCodesResponse { recovery_codes })
+        }
+        Err(UserError::Validation(msg)) => {
+            log::warn!("Rejected recovery code renewal of {}: {}", claims.sub, msg);
+            HttpResponse::BadRequest().json(msg)
+        }
+        Err(e) => {
+            log::error!(
+                "Failed to regenerate recovery codes of {}: {}",
+                claims.sub,
+                e
+            );
+            HttpResponse::InternalServerError().json(e.to_string())
+        }
+    }
+}
+
+/// Turn off two-factor authentication for the current user
+///
+/// # Endpoint
+/// POST /users/me/totp/disable
+///
+/// # Request Body
+/// Current code or an unused recovery code, see `confirm_totp_enrollment`
+///
+/// # Returns
+/// - 204: Two-factor authentication turned off
+/// - 400: Invalid code
+#[post("/me/totp/disable")]
+pub async fn disable_totp(
+    pool: web::Data<Pool>,
+    code_req: web::Json<TotpCodeRequest>,
+    claims: web::ReqData<Claims>,
+) -> HttpResponse {
+    match TwoFactor::disable(&pool, claims.sub, &code_req.code).await {
+        Ok(()) => {
+            log::info!("User {} disabled two-factor authentication", claims.sub);
+            HttpResponse::NoContent().finish()
+        }
+        Err(UserError::Validation(msg)) => {
+            log::warn!("Rejected two-factor removal of {}: {}", claims.sub, msg);
+            HttpResponse::BadRequest().json(msg)
+        }
+        Err(e) => {
+            log::error!(
+                "Failed to disable two-factor authentication of {}: {}",
+                claims.sub,
+                e
+            );
+            HttpResponse::InternalServerError().json(e.to_string())
+        }
+    }
+}
+
+/// Create an API key for the current user
+///
+/// # Endpoint
+/// POST /users/me/api_keys
+///
+/// # Request Body
+/// ```json
+/// {
+///   "name": "SOAR ticket sync",
+///   "scopes": ["tickets:read", "tickets:write"],
+///   "expires_at": "2025-01-01T00:00:00Z"
+/// }
+/// ```
+///
+/// # Returns
+/// - 201: Key metadata and the key itself, shown only once
+/// - 400: Empty name, unknown scopes or expiry in the past
+#[post("/me/api_keys")]
+pub async fn create_api_key(
+    pool: web::Data<Pool>,
+    key_req: web::Json<CreateApiKeyRequest>,
+    claims: web::ReqData<Claims>,
+) -> HttpResponse {
+    match ApiKey::create(
+        &pool,
+        claims.sub,
+        &key_req.name,
+        &key_req.scopes,
+        key_req.expires_at,
+    )
+    .await
+    {
+        Ok(created) => {
+            log::info!(
+                "User {} created API key {}",
+                claims.sub,
+                created.api_key.uuid
+            );
+            HttpResponse::Created().json(created)
+        }
+        Err(UserError::Validation(msg)) => {
+            log::warn!("Rejected API key of {}: {}", claims.sub, msg);
+            HttpResponse::BadRequest().json(msg)
+        }
+        Err(e) => {
+            log::error!("Failed to create API key of {}: {}", claims.sub, e);
+            HttpResponse::InternalServerError().json(e.to_string())
+        }
+    }
+}
+
+/// List the current user's API keys
+///
+/// # Endpoint
+/// GET /users/me/api_keys
+///
+/// # Returns
+/// Key metadata including last use, never the keys themselves
+#[get("/me/api_keys")]
+pub async fn list_api_keys(pool: web::Data<Pool>, claims: web::ReqData<Claims>) -> HttpResponse {
+    match ApiKey::list(&pool, claims.sub).await {
+        Ok(keys) => HttpResponse::Ok().json(keys),
+        Err(e) => {
+            log::error!("Failed to list API keys of {}: {}", claims.sub, e);
+            HttpResponse::InternalServerError().json(e.to_string())
+        }
+    }
+}
+
+/// Revoke one of the current user's API keys
+///
+/// # Endpoint
+/// DELETE /users/me/api_keys/{id}
+///
+/// # Returns
+/// - 204: Key revoked
+/// - 404: Key not found or already revoked
+#[delete("/me/api_keys/{id}")]
+pub async fn revoke_api_key(
+    pool: web::Data<Pool>,
+    path: web::Path<Uuid>,
+    claims: web::ReqData<Claims>,
+) -> HttpResponse {
+    let key_id = path.into_inner();
+
+    match ApiKey::revoke(&pool, claims.sub, key_id).await {
+        Ok(()) => {
+            log::info!("User {} revoked API key {}", claims.sub, key_id);
+            HttpResponse::NoContent().finish()
+        }
+        Err(UserError::NotFound(msg)) => {
+            log::warn!("{}", msg);
+            HttpResponse::NotFound().json("API key not found")
+        }
+        Err(e) => {
+            log::error!("Failed to revoke API key {}: {}", key_id, e);
+            HttpResponse::InternalServerError().json(e.to_string())
+        }
+    }
+}
+
+/// List users
+///
+/// # Endpoint
+/// GET /users
+///
+/// # Query Parameters
+/// - role: Only return users with this role
+/// - disabled: Only return disabled (true) or active (false) users
+/// - limit: Page size (default 50, max 200)
+/// - offset: Pagination offset
+///
+/// # Returns
+/// Array of users, oldest accounts first
+#[get("")]
+pub async fn list_users(pool: web::Data<Pool>, query: web::Query<UserQuery>) -> HttpResponse {
+    let query = query.into_inner();
+
+    match User::list(
+        &pool,
+        query.role.as_deref(),
+        query.disabled,
+        query.limit.unwrap_or(50),
+        query.offset.unwrap_or(0),
+    )
+    .await
+    {
+        Ok(users) => HttpResponse::Ok().json(
+            users
+                .into_iter()
+                .map(UserResponse::from)
+                .collect::<Vec<_>>(),
+        ),
+        Err(e) => {
+            log::error!("Failed to list users: {}", e);
+            HttpResponse::InternalServerError().json(e.to_string())
+        }
+    }
+}
+
+/// Get a user
+///
+/// # Endpoint
+/// GET /users/{id}
+///
+/// # Path Parameters
+/// - id: User UUID
+///
+/// # Returns
+/// - 200: User profile
+/// - 404: User not found
+#[get("/{id}")]
+pub async fn get_user(pool: web::Data<Pool>, path: web::Path<Uuid>) -> HttpResponse {
+    let user_id = path.into_inner();
+
+    match User::get(&pool, user_id).await {
+        Ok(user) => HttpResponse::Ok().json(UserResponse::from(user)),
+        Err(UserError::NotFound(msg)) => {
+            log::warn!("{}", msg);
+            HttpResponse::NotFound().json("User not found")
+        }
+        Err(e) => {
+            log::error!("Failed to get user {}: {}", user_id, e);
+            HttpResponse::InternalServerError().json(e.to_string())
+        }
+    }
+}
+
+/// Update a user's profile
+///
+/// # Endpoint
+/// PUT /users/{id}
+///
+/// # Request Body
+/// ```json
+/// {
+///   "name": "Jane Doe",
+///   "email": "jane@example.com"
+/// }
+/// ```
+///
+/// # Returns
+/// - 200: Updated user
+/// - 400: Invalid values or email already in use
+/// - 404: User not found
+#[put("/{id}")]
+pub async fn update_user(
+    pool: web::Data<Pool>,
+    path: web::Path<Uuid>,
+    update_req: web::Json<UpdateUserRequest>,
+    claims: web::ReqData<Claims>,
+) -> HttpResponse {
+    let user_id = path.into_inner();
+    let update = update_req.into_inner();
+
+    match User::update(&pool, user_id, update.name, update.email).await {
+        Ok(user) => {
+            log::info!("User {} updated user {}", claims.sub, user_id);
+            HttpResponse::Ok().json(UserResponse::from(user))
+        }
+        Err(UserError::Validation(msg)) => {
+            log::warn!("Validation error updating user {}: {}", user_id, msg);
+            HttpResponse::BadRequest().json(msg)
+        }
+        Err(UserError::NotFound(msg)) => {
+            log::warn!("{}", msg);
+            HttpResponse::NotFound().json("User not found")
+        }
+        Err(e) => {
+            log::error!("Failed to update user {}: {}", user_id, e);
+            HttpResponse::InternalServerError().json(e.to_string())
+        }
+    }
+}
+
+/// Assign a role to a user
+///
+/// Admins cannot change their own role, so the last admin cannot lock
+/// everyone out by accident.
+///
+/// # Endpoint
+/// PUT /users/{id}/role
+///
+/// # Request Body
+/// ```json
+/// {
+///   "role": "admin"
+/// }
+/// ```
+///
+/// # Returns
+/// - 200: Updated user
+/// - 400: Unknown role or own account
+/// - 404: User not found
+#[put("/{id}/role")]
+pub async fn update_user_role(
+    pool: web::Data<Pool>,
+    path: web::Path<Uuid>,
+    role_req: web::Json<UpdateRoleRequest>,
+    claims: web::ReqData<Claims>,
+) -> HttpResponse {
+    let user_id = path.into_inner();
+
+    if user_id == claims.sub {
+        return HttpResponse::BadRequest().json("You cannot change your own role");
+    }
+
+    match User::set_role(&pool, user_id, &role_req.role).await {
+        Ok(user) => {
+            log::info!(
+                "User {} assigned role {} to user {}",
+                claims.sub,
+                user.role,
+                user_id
+            );
+            HttpResponse::Ok().json(UserResponse::from(user))
+        }
+        Err(UserError::Validation(msg)) => {
+            log::warn!("Validation error assigning role to {}: {}", user_id, msg);
+            HttpResponse::BadRequest().json(msg)
+        }
+        Err(UserError::NotFound(msg)) => {
+            log::warn!("{}", msg);
+            HttpResponse::NotFound().json("User not found")
+        }
+        Err(e) => {
+            log::error!("Failed to assign role to user {}: {}", user_id, e);
+            HttpResponse::InternalServerError().json(e.to_string())
+        }
+    }
+}
+
+/// Disable a user account
+///
+/// Disabled users cannot log in and their tokens are rejected.
+///
+/// # Endpoint
+/// PUT /users/{id}/disable
+///
+/// # Returns
+/// - 200: Updated user
+/// - 400: Own account
+/// - 404: User not found
+#[put("/{id}/disable")]
+pub async fn disable_user(
+    pool: web::Data<Pool>,
+    path: web::Path<Uuid>,
+    claims: web::ReqData<Claims>,
+) -> HttpResponse {
+    let user_id = path.into_inner();
+
+    if user_id == claims.sub {
+        return HttpResponse::BadRequest().json("You cannot disable your own account");
+    }
+
+    match User::set_disabled(&pool, user_id, true).await {
+        Ok(user) => {
+            log::info!("User {} disabled user {}", claims.sub, user_id);
+            HttpResponse::Ok().json(UserResponse::from(user))
+        }
+        Err(UserError::NotFound(msg)) => {
+            log::warn!("{}", msg);
+            HttpResponse::NotFound().json("User not found")
+        }
+        Err(e) => {
+            log::error!("Failed to disable user {}: {}", user_id, e);
+            HttpResponse::InternalServerError().json(e.to_string())
+        }
+    }
+}
+
+/// Re-enable a disabled user account
+///
+/// # Endpoint
+/// PUT /users/{id}/enable
+///
+/// # Returns
+/// - 200: Updated user
+/// - 404: User not found
+#[put("/{id}/enable")]
+pub async fn enable_user(
+    pool: web::Data<Pool>,
+    path: web::Path<Uuid>,
+    claims: web::ReqData<Claims>,
+) -> HttpResponse {
+    let user_id = path.into_inner();
+
+    match User::set_disabled(&pool, user_id, false).await {
+        Ok(user) => {
+            log::info!("User {} enabled user {}", claims.sub, user_id);
+            HttpResponse::Ok().json(UserResponse::from(user))
+        }
+        Err(UserError::NotFound(msg)) => {
+            log::warn!("{}", msg);
+            HttpResponse::NotFound().json("User not found")
+        }
+        Err(e) => {
+            log::error!("Failed to enable user {}: {}", user_id, e);
+            HttpResponse::InternalServerError().json(e.to_string())
+        }
+    }
+}
+
+/// Unlock a user account locked by failed logins
+///
+/// # Endpoint
+/// PUT /users/{id}/unlock
+///
+/// # Returns
+/// - 204: Failed logins and lockout cleared
+/// - 404: User not found
+#[put("/{id}/unlock")]
+pub async fn unlock_user(
+    pool: web::Data<Pool>,
+    path: web::Path<Uuid>,
+    claims: web::ReqData<Claims>,
+) -> HttpResponse {
+    let user_id = path.into_inner();
+
+    match LoginAttempts::reset(&pool, user_id).await {
+        Ok(()) => {
+            log::info!("User {} unlocked user {}", claims.sub, user_id);
+            HttpResponse::NoContent().finish()
+        }
+        Err(UserError::NotFound(msg)) => {
+            log::warn!("{}", msg);
+            HttpResponse::NotFound().json("User not found")
+        }
+        Err(e) => {
+            log::error!("Failed to unlock user {}: {}", user_id, e);
+            HttpResponse::InternalServerError().json(e.to_string())
+        }
+    }
+}
+
+/// List the recent login attempts of a user
+///
+/// # Endpoint
+/// GET /users/{id}/login_events
+///
+/// # Returns
+/// The 100 most recent login events, newest first, with their outcome,
+/// IP address and user agent
+#[get("/{id}/login_events")]
+pub async fn list_login_events(pool: web::Data<Pool>, path: web::Path<Uuid>) -> HttpResponse {
+    let user_id = path.into_inner();
+
+    match LoginAttempts::list(&pool, user_id, 100).await {
+        Ok(events) => HttpResponse::Ok().json(events),
+        Err(e) => {
+            log::error!("Failed to list login events of user {}: {}", user_id, e);
+            HttpResponse::InternalServerError().json(e.to_string())
+        }
+    }
+}
+
+/// List the active sessions of a user
+///
+/// # Endpoint
+/// GET /users/{id}/sessions
+///
+/// # Returns
+/// Active login sessions, most recently used first, with their login time,
+/// IP address, user agent and last refresh
+#[get("/{id}/sessions")]
+pub async fn list_user_sessions(pool: web::Data<Pool>, path: web::Path<Uuid>) -> HttpResponse {
+    let user_id = path.into_inner();
+
+    match Session::list(&pool, user_id).await {
+        Ok(sessions) => HttpResponse::Ok().json(sessions),
+        Err(e) => {
+            log::error!("Failed to list sessions of user {}: {}", user_id, e);
+            HttpResponse::InternalServerError().json(e.to_string())
+        }
+    }
+}
+
+/// Sign out one session of a user
+///
+/// # Endpoint
+/// DELETE /users/{id}/sessions/{session_id}
+///
+/// # Returns
+/// - 204: Session signed out, its tokens are rejected
+/// - 404: Session not found or not active
+#[delete("/{id}/sessions/{session_id}")]
+pub async fn revoke_user_session(
+    pool: web::Data<Pool>,
+    path: web::Path<(Uuid, Uuid)>,
+    claims: web::ReqData<Claims>,
+) -> HttpResponse {
+    let (user_id, session_id) = path.into_inner();
+
+    match Session::revoke(&pool, user_id, session_id).await {
+        Ok(()) => {
+            log::info!(
+                "User {} signed out session {} of user {}",
+                claims.sub,
+                session_id,
+                user_id
+            );
+            HttpResponse::NoContent().finish()
+        }
+        Err(UserError::NotFound(msg)) => {
+            log::warn!("{}", msg);
+            HttpResponse::NotFound().json("Session not found")
+        }
+        Err(e) => {
+            log::error!("Failed to revoke session {}: {}", session_id, e);
+            HttpResponse::InternalServerError().json(e.to_string())
+        }
+    }
+}
+
+/// Sign out every session of a user
+///
+/// # Endpoint
+/// DELETE /users/{id}/sessions
+///
+/// # Returns
+/// Number of sessions signed out. API keys are not affected
+#[delete("/{id}/sessions")]
+pub async fn revoke_user_sessions(
+    pool: web::Data<Pool>,
+    path: web::Path<Uuid>,
+    claims: web::ReqData<Claims>,
+) -> HttpResponse {
+    let user_id = path.into_inner();
+
+    match Session::revoke_all(&pool, user_id, None).await {
+        Ok(revoked) => {
+            log::info!(
+                "User {} signed out {} sessions of user {}",
+                claims.sub,
+                revoked,
+                user_id
+            );
+            HttpResponse::Ok().json(RevokedSessionsResponse { revoked })
+        }
+        Err(e) => {
+            log::error!("Failed to revoke sessions of user {}: {}", user_id, e);
+            HttpResponse::InternalServerError().json(e.to_string())
+        }
+    }
+}
+
+/// Delete a user account
+///
+/// # Endpoint
+/// DELETE /users/{id}
+///
+/// # Returns
+/// - 204: User deleted
+/// - 400: Own account
+/// - 404: User not found
+#[delete("/{id}")]
+pub async fn delete_user(
+    pool: web::Data<Pool>,
+    path: web::Path<Uuid>,
+    claims: web::ReqData<Claims>,
+) -> HttpResponse {
+    let user_id = path.into_inner();
+
+    if user_id == claims.sub {
+        return HttpResponse::BadRequest().json("You cannot delete your own account");
+    }
+
+    match User::delete(&pool, user_id).await {
+        Ok(()) => {
+            log::info!("User {} deleted user {}", claims.sub, user_id);
+            HttpResponse::NoContent().finish()
+        }
+        Err(UserError::NotFound(msg)) => {
+            log::warn!("{}", msg);
+            HttpResponse::NotFound().json("User not found")
+        }
+        Err(e) => {
+            log::error!("Failed to delete user {}: {}", user_id, e);
+            HttpResponse::InternalServerError().json(e.to_string())
+        }
+    }
+}
+
+/// Email a password reset token to a user
+///
+/// # Endpoint
+/// POST /users/{id}/password_reset
+///
+/// # Returns
+/// - 202: Reset email sent, with the token expiry
+/// - 404: User not found
+/// - 502: The email could not be sent
+#[post("/{id}/password_reset")]
+pub async fn reset_user_password(
+    pool: web::Data<Pool>,
+    path: web::Path<Uuid>,
+    claims: web::ReqData<Claims>,
+) -> HttpResponse {
+    let user_id = path.into_inner();
+
+    let user = match User::get(&pool, user_id).await {
+        Ok(user) => user,
+        Err(UserError::NotFound(msg)) => {
+            log::warn!("{}", msg);
+            return HttpResponse::NotFound().json("User not found");
+        }
+        Err(e) => {
+            log::error!("Failed to get user {}: {}", user_id, e);
+            return HttpResponse::InternalServerError().json(e.to_string());
+        }
+    };
+
+    let token = match PasswordResetToken::issue(&pool, user_id).await {
+        Ok(token) => token,
+        Err(e) => {
+            log::error!("Failed to issue reset token for {}: {}", user_id, e);
+            return HttpResponse::InternalServerError().json(e.to_string());
+        }
+    };
+
+    match token.send(&user).await {
+        Ok(()) => {
+            log::info!("User {} sent a password reset to {}", claims.sub, user_id);
+            HttpResponse::Accepted().json(PasswordResetResponse {
+                expires_at: token.expires_at,
+            })
+        }
+        Err(e) => {
+            log::error!("Failed to send password reset to {}: {}", user_id, e);
+            HttpResponse::BadGateway().json(e.to_string())
+        }
+    }
+}
+
+/// Remove a user's second factor, e.g. after a lost device
+///
+/// # Endpoint
+/// DELETE /users/{id}/totp
+///
+/// # Returns
+/// - 204: Two-factor authentication removed
+/// - 404: User not found
+#[delete("/{id}/totp")]
+pub async fn reset_user_totp(
+    pool: web::Data<Pool>,
+    path: web::Path<Uuid>,
+    claims: web::ReqData<Claims>,
+) -> HttpResponse {
+    let user_id = path.into_inner();
+
+    let client = match pool.get().await {
+        Ok(client) => client,
+        Err(e) => {
+            log::error!("Failed to get db connection: {}", e);
+            return HttpResponse::InternalServerError().json(e.to_string());
+        }
+    };
+
+    match TwoFactor::reset(&**client, user_id).await {
+        Ok(()) => {
+            log::info!(
+                "User {} removed two-factor authentication of {}",
+                claims.sub,
+                user_id
+            );
+            HttpResponse::NoContent().finish()
+        }
+        Err(UserError::NotFound(msg)) => {
+            log::warn!("{}", msg);
+            HttpResponse::NotFound().json("User not found")
+        }
+        Err(e) => {
+            log::error!(
+                "Failed to reset two-factor authentication of {}: {}",
+                user_id,
+                e
+            );
+            HttpResponse::InternalServerError().json(e.to_string())
+        }
+    }
+}
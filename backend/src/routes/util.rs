@@ -67,8 +67,8 @@ pub async fn whois(path: web::Path<String>) -> HttpResponse {
         // If the whois record is not found, return an error
         Err(err) => {
             log::info!("unable to fetch whois: {:?}", err);
-            return HttpResponse::InternalServerError()
-                .json(format!("unable to fetch whois record for {}", &address));
+            HttpResponse::InternalServerError()
+                .json(format!("unable to fetch whois record for {}", &address))
         }
     }
 }
@@ -0,0 +1,388 @@
+//! JWT Signing Keys
+//!
+//! Access tokens are signed with asymmetric keys so other services can
+//! verify them with the public keys published at `/.well-known/jwks.json`.
+//!
+//! # Keys
+//! Keys are PEM encoded private keys, Ed25519 (EdDSA) or RSA (RS256), read
+//! from `JWT_KEYS_DIR`. Each file `<kid>.pem` is a key identified by its
+//! file name. Tokens are signed with the active key and carry its `kid`,
+//! tokens signed with any other loaded key keep verifying.
+//!
+//! # Rotation
+//! 1. Pin `JWT_ACTIVE_KID` to the current key, then add the new key file and
+//!    publish it by restarting. Without the pin the new key becomes active
+//!    right away if its name sorts last
+//! 2. Activate it by setting `JWT_ACTIVE_KID` once other services refreshed
+//!    their JWKS
+//! 3. Remove the old key file once the tokens it signed have expired
+//!
+//! # Environment Variables
+//! * `JWT_KEYS_DIR` - Directory of `<kid>.pem` private keys. Without it an
+//!   ephemeral Ed25519 key is generated on startup, which does not survive
+//!   restarts and is not shared between instances. Audit checkpoints are
+//!   only signed with keys from this directory
+//! * `JWT_ACTIVE_KID` - Key used for signing (default: last file by name)
+
+use crate::models::auth::Claims;
+use data_encoding::BASE64URL_NOPAD;
+use jsonwebtoken::errors::{Error, ErrorKind};
+use jsonwebtoken::jwk::{
+    AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, JwkSet, KeyAlgorithm,
+    OctetKeyPairParameters, OctetKeyPairType, PublicKeyUse, RSAKeyParameters, RSAKeyType,
+};
+use jsonwebtoken::{
+    decode, decode_header, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation,
+};
+use ring::rand::SystemRandom;
+use ring::rsa::PublicKeyComponents;
+use ring::signature::{Ed25519KeyPair, KeyPair, RsaKeyPair};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::sync::OnceLock;
+
+static KEYS: OnceLock<SigningKeys> = OnceLock::new();
+
+/// Key pair used to sign and verify tokens.
+struct SigningKey {
+    kid: String,
+    algorithm: Algorithm,
+    encoding: EncodingKey,
+    decoding: DecodingKey,
+    jwk: Jwk,
+}
+
+impl SigningKey {
+    /// Parses a PEM encoded Ed25519 or RSA private key.
+    fn from_pem(kid: &str, pem: &[u8]) -> Result<Self, String> {
+        let parsed = pem::parse(pem).map_err(|e| format!("Key {}: {}", kid, e))?;
+
+        match parsed.tag() {
+            "PRIVATE KEY" => {
+                if let Ok(key_pair) = Ed25519KeyPair::from_pkcs8_maybe_unchecked(parsed.contents())
+                {
+                    let encoding =
+                        EncodingKey::from_ed_pem(pem).map_err(|e| format!("Key {}: {}", kid, e))?;
+                    return Self::new(kid, encoding, ed25519_parameters(&key_pair));
+                }
+                let key_pair = RsaKeyPair::from_pkcs8(parsed.contents())
+                    .map_err(|e| format!("Key {} is not an Ed25519 or RSA key: {}", kid, e))?;
+                let encoding =
+                    EncodingKey::from_rsa_pem(pem).map_err(|e| format!("Key {}: {}", kid, e))?;
+                Self::new(kid, encoding, rsa_parameters(&key_pair))
+            }
+            "RSA PRIVATE KEY" => {
+                let key_pair = RsaKeyPair::from_der(parsed.contents())
+                    .map_err(|e| format!("Key {} is not a valid RSA key: {}", kid, e))?;
+                let encoding =
+                    EncodingKey::from_rsa_pem(pem).map_err(|e| format!("Key {}: {}", kid, e))?;
+                Self::new(kid, encoding, rsa_parameters(&key_pair))
+            }
+            tag => Err(format!("Key {} has unsupported PEM type {}", kid, tag)),
+        }
+    }
+
+    /// Builds a key from its signing key and public parameters.
+    fn new(
+        kid: &str,
+        encoding: EncodingKey,
+        parameters: (Algorithm, KeyAlgorithm, AlgorithmParameters),
+    ) -> Result<Self, String> {
+        let (algorithm, key_algorithm, parameters) = parameters;
+        let jwk = Jwk {
+            common: CommonParameters {
+                public_key_use: Some(PublicKeyUse::Signature),
+                key_algorithm: Some(key_algorithm),
+                key_id: Some(kid.to_string()),
+                ..Default::default()
+            },
+            algorithm: parameters,
+        };
+        let decoding = DecodingKey::from_jwk(&jwk).map_err(|e| format!("Key {}: {}", kid, e))?;
+
+        Ok(Self {
+            kid: kid.to_string(),
+            algorithm,
+            encoding,
+            decoding,
+            jwk,
+        })
+    }
+}
+
+/// Public parameters of an Ed25519 key.
+fn ed25519_parameters(key_pair: &Ed25519KeyPair) -> (Algorithm, KeyAlgorithm, AlgorithmParameters) {
+    (
+        Algorithm::EdDSA,
+        KeyAlgorithm::EdDSA,
+        AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+            key_type: OctetKeyPairType::OctetKeyPair,
+            curve: EllipticCurve::Ed25519,
+            x: BASE64URL_NOPAD.encode(key_pair.public_key().as_ref()),
+        }),
+    )
+}
+
+/// Public parameters of an RSA key.
+fn rsa_parameters(key_pair: &RsaKeyPair) -> (Algorithm, KeyAlgorithm, AlgorithmParameters) {
+    let components = PublicKeyComponents::<Vec<u8>>::from(key_pair.public());
+    (
+        Algorithm::RS256,
+        KeyAlgorithm::RS256,
+        AlgorithmParameters::RSA(RSAKeyParameters {
+            key_type: RSAKeyType::RSA,
+            n: BASE64URL_NOPAD.encode(&components.n),
+            e: BASE64URL_NOPAD.encode(&components.e),
+        }),
+    )
+}
+
+/// Generates a PEM encoded Ed25519 private key.
+///
+/// # Returns
+/// * `String` - PKCS#8 PEM key, as read from `JWT_KEYS_DIR`
+pub fn generate_ed25519_pem() -> String {
+    let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new())
+        .expect("Failed to generate Ed25519 key");
+    pem::encode(&pem::Pem::new("PRIVATE KEY", pkcs8.as_ref()))
+}
+
+/// Keys used to sign and verify access tokens.
+pub struct SigningKeys {
+    keys: Vec<SigningKey>,
+    active: usize,
+    persistent: bool,
+}
+
+impl SigningKeys {
+    /// Builds a key set from PEM encoded private keys.
+    ///
+    /// # Arguments
+    /// * `keys` - Key ids and PEM encoded keys
+    /// * `active_kid` - Key to sign with
+    ///
+    /// # Returns
+    /// * `Result<SigningKeys, String>` - Key set, or error for invalid keys
+    ///   and unknown active keys
+    pub fn from_pems(keys: &[(String, Vec<u8>)], active_kid: &str) -> Result<Self, String> {
+        let keys = keys
+            .iter()
+            .map(|(kid, pem)| SigningKey::from_pem(kid, pem))
+            .collect::<Result<Vec<_>, _>>()?;
+        let active = keys
+            .iter()
+            .position(|key| key.kid == active_kid)
+            .ok_or_else(|| format!("Active signing key {} is not loaded", active_kid))?;
+
+        Ok(Self {
+            keys,
+            active,
+            persistent: true,
+        })
+    }
+
+    /// Generates a single Ed25519 key for this process.
+    fn ephemeral() -> Self {
+        let kid = format!("ephemeral-{}", crate::auth::generate_token(4));
+        let keys = Self::from_pems(&[(kid.clone(), generate_ed25519_pem().into())], &kid)
+            .expect("Failed to create ephemeral signing key");
+        Self {
+            persistent: false,
+            ..keys
+        }
+    }
+
+    /// Loads the keys configured in the environment.
+    ///
+    /// # Returns
+    /// * `Result<SigningKeys, String>` - Key set or configuration error
+    pub fn from_env() -> Result<Self, String> {
+        let dir = match std::env::var("JWT_KEYS_DIR") {
+            Ok(dir) => dir,
+            Err(_) => {
+                log::warn!("JWT_KEYS_DIR is not set, signing tokens with an ephemeral key");
+                return Ok(Self::ephemeral());
+            }
+        };
+
+        let mut keys = Vec::new();
+        let entries = std::fs::read_dir(&dir).map_err(|e| format!("{}: {}", dir, e))?;
+        for entry in entries {
+            let path = entry.map_err(|e| format!("{}: {}", dir, e))?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("pem") {
+                continue;
+            }
+            let kid = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .ok_or_else(|| format!("Invalid key file name {}", path.display()))?
+                .to_string();
+            let pem = std::fs::read(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+            keys.push((kid, pem));
+        }
+        keys.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let active_kid = match std::env::var("JWT_ACTIVE_KID") {
+            Ok(kid) => kid,
+            Err(_) => {
+                if keys.len() > 1 {
+                    log::warn!(
+                        "JWT_ACTIVE_KID is not set, signing with the last of {} keys",
+                        keys.len()
+                    );
+                }
+                keys.last()
+                    .map(|(kid, _)| kid.clone())
+                    .ok_or_else(|| format!("No .pem keys found in {}", dir))?
+            }
+        };
+
+        let keys = Self::from_pems(&keys, &active_kid)?;
+        log::info!(
+            "Loaded {} JWT signing keys, signing with {}",
+            keys.keys.len(),
+            active_kid
+        );
+        Ok(keys)
+    }
+
+    /// Loads the configured keys for the rest of the process.
+    ///
+    /// # Returns
+    /// * `Result<(), String>` - Success or configuration error
+    pub fn init() -> Result<(), String> {
+        if KEYS.get().is_none() {
+            let _ = KEYS.set(Self::from_env()?);
+        }
+        Ok(())
+    }
+
+    /// Gets the keys of the process, loading them on first use.
+    ///
+    /// # Returns
+    /// * `&SigningKeys` - Loaded keys, an ephemeral key if they are invalid
+    pub fn global() -> &'static Self {
+        KEYS.get_or_init(|| {
+            Self::from_env().unwrap_or_else(|e| {
+                log::error!("Invalid JWT signing keys, using an ephemeral key: {}", e);
+                Self::ephemeral()
+            })
+        })
+    }
+
+    /// Whether the keys outlive the process, false for an ephemeral key.
+    pub fn is_persistent(&self) -> bool {
+        self.persistent
+    }
+
+    /// Id of the key tokens are signed with.
+    pub fn active_kid(&self) -> &str {
+        &self.keys[self.active].kid
+    }
+
+    /// Public key of the key tokens are signed with.
+    pub fn active_jwk(&self) -> &Jwk {
+        &self.keys[self.active].jwk
+    }
+
+    /// Signs claims with the active key.
+    ///
+    /// # Arguments
+    /// * `claims` - Token claims, or any other document to sign
+    ///
+    /// # Returns
+    /// * `Result<String, Error>` - JWT with the key's `kid`, or error
+    pub fn sign<T: Serialize>(&self, claims: &T) -> Result<String, Error> {
+        let key = &self.keys[self.active];
+        let mut header = Header::new(key.algorithm);
+        header.kid = Some(key.kid.clone());
+
+        encode(&header, claims, &key.encoding)
+    }
+
+    /// Verifies a token signed with any loaded key.
+    ///
+    /// # Arguments
+    /// * `token` - JWT string
+    ///
+    /// # Returns
+    /// * `Result<Claims, Error>` - Claims, or error for tokens without a
+    ///   known `kid`, invalid signatures and expired tokens
+    pub fn verify(&self, token: &str) -> Result<Claims, Error> {
+        let key = self.key_of(token)?;
+        Ok(decode::<Claims>(token, &key.decoding, &Validation::new(key.algorithm))?.claims)
+    }
+
+    /// Verifies a signed document that does not expire.
+    ///
+    /// # Arguments
+    /// * `token` - JWS signed with `sign`
+    ///
+    /// # Returns
+    /// * `Result<T, Error>` - Document, or error for tokens without a known
+    ///   `kid` and invalid signatures
+    pub fn verify_document<T: DeserializeOwned>(&self, token: &str) -> Result<T, Error> {
+        let key = self.key_of(token)?;
+        Ok(decode::<T>(token, &key.decoding, &document_validation(key.algorithm))?.claims)
+    }
+
+    /// Whether a key is loaded.
+    pub fn has_key(&self, kid: &str) -> bool {
+        self.keys.iter().any(|key| key.kid == kid)
+    }
+
+    /// Finds the key a token was signed with.
+    fn key_of(&self, token: &str) -> Result<&SigningKey, Error> {
+        let header = decode_header(token)?;
+        let key = header
+            .kid
+            .and_then(|kid| self.keys.iter().find(|key| key.kid == kid))
+            .ok_or_else(|| Error::from(ErrorKind::InvalidToken))?;
+        if header.alg != key.algorithm {
+            return Err(ErrorKind::InvalidAlgorithm.into());
+        }
+        Ok(key)
+    }
+
+    /// Public keys for other services to verify tokens.
+    ///
+    /// # Returns
+    /// * `JwkSet` - Public key of every loaded key
+    pub fn jwks(&self) -> JwkSet {
+        JwkSet {
+            keys: self.keys.iter().map(|key| key.jwk.clone()).collect(),
+        }
+    }
+}
+
+/// Validation of signed documents, which do not expire.
+fn document_validation(algorithm: Algorithm) -> Validation {
+    let mut validation = Validation::new(algorithm);
+    validation.required_spec_claims.clear();
+    validation.validate_exp = false;
+    validation
+}
+
+/// Verifies a signed document with a public key, which need not be loaded.
+///
+/// # Arguments
+/// * `jwk` - Public key of the signing key, as published in the JWKS
+/// * `token` - JWS signed with `SigningKeys::sign`
+///
+/// # Returns
+/// * `Result<T, Error>` - Document, or error for tokens of another key or
+///   algorithm and invalid signatures
+pub fn verify_document_with<T: DeserializeOwned>(jwk: &Jwk, token: &str) -> Result<T, Error> {
+    let algorithm = match jwk.algorithm {
+        AlgorithmParameters::OctetKeyPair(_) => Algorithm::EdDSA,
+        AlgorithmParameters::RSA(_) => Algorithm::RS256,
+        _ => return Err(ErrorKind::InvalidAlgorithm.into()),
+    };
+    let header = decode_header(token)?;
+    if header.kid != jwk.common.key_id {
+        return Err(ErrorKind::InvalidToken.into());
+    }
+
+    let decoding = DecodingKey::from_jwk(jwk)?;
+    Ok(decode::<T>(token, &decoding, &document_validation(algorithm))?.claims)
+}
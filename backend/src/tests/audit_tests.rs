@@ -0,0 +1,490 @@
+use super::common;
+use crate::middleware::{Auth, Logger, RequestId};
+use crate::models::audit_chain::{
+    AuditCheckpoint, AuditTrail, ChainVerification, CheckpointClaims,
+};
+use crate::models::auth::TokenResponse;
+use crate::models::requests::{AuditLogResponse, CheckpointExport};
+use crate::models::role::Role;
+use crate::models::user::User;
+use crate::models::user_log::{AuditEntity, UserLog};
+use crate::routes::{audit, auth, ticket};
+use crate::signing::{generate_ed25519_pem, SigningKeys};
+use actix_web::http::StatusCode;
+use actix_web::{test, web, App};
+use chrono::Utc;
+use uuid::Uuid;
+
+fn log_entry(user_uuid: Uuid, method: &str, route: &str, status_code: i16) -> UserLog {
+    UserLog {
+        uuid: Uuid::new_v4(),
+        user_uuid: Some(user_uuid),
+        action: format!("{} {}", method, route),
+        route: route.to_string(),
+        method: Some(method.to_string()),
+        route_pattern: None,
+        status_code: Some(status_code),
+        entities: Vec::new(),
+        ip_address: None,
+        user_agent: None,
+        latency_ms: Some(1),
+        request_id: None,
+        timestamp: Utc::now(),
+    }
+}
+
+#[actix_rt::test]
+async fn test_audit_log_query() {
+    common::initialize_tests().await;
+    let pool = common::get_db_pool().clone();
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(pool.clone()))
+            .service(web::scope("/auth").service(auth::login))
+            .service(
+                web::scope("/audit")
+                    .wrap(Auth::new().permission("audit:read"))
+                    .service(audit::list)
+                    .service(audit::export),
+            ),
+    )
+    .await;
+    let admin: TokenResponse =
+        test::call_and_read_body_json(&app, common::admin_login_request().to_request()).await;
+    let admin_token = format!("Bearer {}", admin.access_token);
+    let admin_id = admin.user.uuid;
+
+    // Routes under a unique prefix keep other tests' entries out
+    let prefix = format!("/audit_{}", Uuid::new_v4().simple());
+    for (method, route, status_code) in [
+        ("GET", "/tickets/list", 200),
+        ("GET", "/tickets/missing", 404),
+        ("GET", "/users", 403),
+        ("POST", "/email/send", 500),
+    ] {
+        let entry = log_entry(
+            admin_id,
+            method,
+            &format!("{}{}", prefix, route),
+            status_code,
+        );
+        UserLog::create(&pool, &entry)
+            .await
+            .expect("Failed to create log entry");
+    }
+
+    let query = |params: &str| {
+        test::TestRequest::get()
+            .uri(&format!("/audit?{}", params))
+            .insert_header(("Authorization", admin_token.clone()))
+            .to_request()
+    };
+
+    let page: AuditLogResponse =
+        test::call_and_read_body_json(&app, query(&format!("route={}", prefix))).await;
+    assert_eq!(page.total, 4);
+    assert!(page.entries.iter().all(|e| e.user_uuid == Some(admin_id)));
+
+    // Route prefixes, status codes and classes narrow the results
+    let page: AuditLogResponse =
+        test::call_and_read_body_json(&app, query(&format!("route={}/tickets&status=4xx", prefix)))
+            .await;
+    assert_eq!(page.total, 1);
+    assert_eq!(page.entries[0].status_code, Some(404));
+
+    let page: AuditLogResponse = test::call_and_read_body_json(
+        &app,
+        query(&format!(
+            "route={}&status=500&method=post&user={}",
+            prefix, admin_id
+        )),
+    )
+    .await;
+    assert_eq!(page.total, 1);
+    assert!(page.entries[0].route.ends_with("/email/send"));
+
+    // Wildcards in the prefix match literally
+    let page: AuditLogResponse = test::call_and_read_body_json(
+        &app,
+        query(&format!("route={}%25", &prefix[..prefix.len() - 1])),
+    )
+    .await;
+    assert_eq!(page.total, 0);
+
+    // Pagination keeps the total
+    let page: AuditLogResponse =
+        test::call_and_read_body_json(&app, query(&format!("route={}&limit=3&offset=2", prefix)))
+            .await;
+    assert_eq!(page.total, 4);
+    assert_eq!(page.entries.len(), 2);
+
+    // Time ranges exclude entries outside of them
+    let page: AuditLogResponse = test::call_and_read_body_json(
+        &app,
+        query(&format!("route={}&to=2000-01-01T00:00:00Z", prefix)),
+    )
+    .await;
+    assert_eq!(page.total, 0);
+
+    for params in [
+        "status=600",
+        "status=abc",
+        "from=2024-02-01T00:00:00Z&to=2024-01-01T00:00:00Z",
+    ] {
+        assert_eq!(
+            test::call_service(&app, query(params)).await.status(),
+            StatusCode::BAD_REQUEST
+        );
+    }
+
+    // Exports include every matching entry
+    let req = test::TestRequest::get()
+        .uri(&format!("/audit/export?route={}&status=4xx", prefix))
+        .insert_header(("Authorization", admin_token.clone()))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let content_type = resp
+        .headers()
+        .get("Content-Type")
+        .unwrap()
+        .to_str()
+        .unwrap();
+    assert!(content_type.starts_with("text/csv"));
+    let body = test::read_body(resp).await;
+    let csv = std::str::from_utf8(&body).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert!(lines[0].starts_with("uuid,timestamp,user_uuid,method,route,"));
+    assert_eq!(lines.len(), 3);
+    assert!(lines[1].contains(",403,") || lines[2].contains(",403,"));
+}
+
+#[actix_rt::test]
+async fn test_audit_log_records_requests() {
+    common::initialize_tests().await;
+    let pool = common::get_db_pool().clone();
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(pool.clone()))
+            .wrap(Logger::new())
+            .wrap(RequestId::new())
+            .service(web::scope("/auth").service(auth::login))
+            .service(
+                web::scope("/audit")
+                    .wrap(Auth::new().permission("audit:read"))
+                    .service(audit::list),
+            )
+            .service(
+                web::scope("/tickets")
+                    .wrap(Auth::new().permission("tickets:write"))
+                    .service(ticket::remove_email_from_ticket),
+            ),
+    )
+    .await;
+    let admin: TokenResponse =
+        test::call_and_read_body_json(&app, common::admin_login_request().to_request()).await;
+    let admin_token = format!("Bearer {}", admin.access_token);
+
+    // Request IDs from proxies are kept and returned
+    let (ticket_id, email_id) = (Uuid::new_v4(), Uuid::new_v4());
+    let request_id = format!("audit-{}", Uuid::new_v4());
+    let req = test::TestRequest::delete()
+        .uri(&format!("/tickets/{}/emails/{}", ticket_id, email_id))
+        .insert_header(("Authorization", admin_token.clone()))
+        .insert_header(("X-Request-ID", request_id.clone()))
+        .insert_header(("User-Agent", "audit-test"))
+        .peer_addr("192.0.2.44:50000".parse().unwrap())
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    assert_eq!(resp.headers().get("X-Request-ID").unwrap(), &request_id);
+
+    // Entries are written in the background
+    let mut entries = Vec::new();
+    for _ in 0..50 {
+        let req = test::TestRequest::get()
+            .uri(&format!("/audit?entity={}", email_id))
+            .insert_header(("Authorization", admin_token.clone()))
+            .to_request();
+        let page: AuditLogResponse = test::call_and_read_body_json(&app, req).await;
+        if !page.entries.is_empty() {
+            entries = page.entries;
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+    assert_eq!(entries.len(), 1);
+    let entry = &entries[0];
+    assert_eq!(entry.user_uuid, Some(admin.user.uuid));
+    assert_eq!(entry.method.as_deref(), Some("DELETE"));
+    assert_eq!(
+        entry.route_pattern.as_deref(),
+        Some("/tickets/{id}/emails/{email_id}")
+    );
+    assert_eq!(entry.action, "DELETE /tickets/{id}/emails/{email_id}");
+    assert_eq!(entry.status_code, Some(404));
+    assert_eq!(
+        entry.entities,
+        [
+            AuditEntity {
+                entity_type: "ticket".into(),
+                id: ticket_id
+            },
+            AuditEntity {
+                entity_type: "email".into(),
+                id: email_id
+            },
+        ]
+    );
+    assert_eq!(entry.ip_address.as_deref(), Some("192.0.2.44"));
+    assert_eq!(entry.user_agent.as_deref(), Some("audit-test"));
+    assert!(entry.latency_ms.is_some());
+    assert_eq!(entry.request_id.as_deref(), Some(request_id.as_str()));
+
+    // Invalid request IDs are replaced
+    let req = test::TestRequest::delete()
+        .uri(&format!("/tickets/{}/emails/{}", ticket_id, email_id))
+        .insert_header(("Authorization", admin_token.clone()))
+        .insert_header(("X-Request-ID", "bad id"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    let assigned = resp
+        .headers()
+        .get("X-Request-ID")
+        .unwrap()
+        .to_str()
+        .unwrap();
+    assert!(Uuid::parse_str(assigned).is_ok());
+}
+
+#[actix_rt::test]
+async fn test_audit_hash_chain() {
+    common::initialize_tests().await;
+    let pool = common::get_db_pool().clone();
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(pool.clone()))
+            .service(web::scope("/auth").service(auth::login))
+            .service(
+                web::scope("/audit")
+                    .wrap(Auth::new().permission("audit:read"))
+                    .service(audit::verify)
+                    .service(audit::list_checkpoints)
+                    .service(audit::export_checkpoints)
+                    .service(
+                        web::scope("")
+                            .wrap(Auth::new().permission("audit:manage"))
+                            .service(audit::create_checkpoints),
+                    ),
+            ),
+    )
+    .await;
+    let admin: TokenResponse =
+        test::call_and_read_body_json(&app, common::admin_login_request().to_request()).await;
+    let admin_token = format!("Bearer {}", admin.access_token);
+
+    let entry = log_entry(
+        admin.user.uuid,
+        "GET",
+        &format!("/chain_{}", Uuid::new_v4().simple()),
+        200,
+    );
+    UserLog::create(&pool, &entry)
+        .await
+        .expect("Failed to create log entry");
+
+    let verify = |trail: &str| {
+        test::TestRequest::get()
+            .uri(&format!("/audit/verify{}", trail))
+            .insert_header(("Authorization", admin_token.clone()))
+            .to_request()
+    };
+
+    // Reading the audit log does not allow signing checkpoints
+    let role = format!("reader-{}", &Uuid::new_v4().simple().to_string()[..8]);
+    Role::create(&pool, &role, "Audit reader", &["audit:read".to_string()])
+        .await
+        .expect("Failed to create role");
+    let email = format!("reader-{}@example.com", Uuid::new_v4());
+    let hash = bcrypt::hash("reader123", 4).expect("Failed to hash password");
+    User::create(
+        &pool,
+        Uuid::new_v4(),
+        email.clone(),
+        "Reader".to_string(),
+        hash,
+        role,
+    )
+    .await
+    .expect("Failed to create user");
+    let reader: TokenResponse = test::call_and_read_body_json(
+        &app,
+        common::login_request(&email, "reader123").to_request(),
+    )
+    .await;
+    let req = test::TestRequest::post()
+        .uri("/audit/checkpoints")
+        .insert_header(("Authorization", format!("Bearer {}", reader.access_token)))
+        .to_request();
+    let err = test::try_call_service(&app, req)
+        .await
+        .expect_err("Readers must not sign checkpoints");
+    assert_eq!(err.as_response_error().status_code(), StatusCode::FORBIDDEN);
+
+    // Checkpoints seal the chain and sign its head
+    let req = test::TestRequest::post()
+        .uri("/audit/checkpoints?trail=user_logs")
+        .insert_header(("Authorization", admin_token.clone()))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let created: Vec<AuditCheckpoint> = test::read_body_json(resp).await;
+    assert_eq!(created.len(), 1);
+    let checkpoint = &created[0];
+    assert_eq!(checkpoint.trail, AuditTrail::UserLogs);
+    let claims: CheckpointClaims = SigningKeys::global()
+        .verify_document(&checkpoint.signature)
+        .expect("Checkpoint signature should verify");
+    assert_eq!(claims.seq, checkpoint.seq);
+    assert_eq!(claims.hash, checkpoint.hash);
+
+    // Every trail verifies
+    let results: Vec<ChainVerification> = test::call_and_read_body_json(&app, verify("")).await;
+    assert_eq!(results.len(), 2);
+    assert!(results.iter().all(|r| r.valid && r.first_broken.is_none()));
+    let user_logs = results
+        .iter()
+        .find(|r| r.trail == AuditTrail::UserLogs)
+        .unwrap();
+    assert!(user_logs.checked >= checkpoint.seq);
+    assert!(user_logs.checkpoints >= 1);
+
+    // Altering a sealed record breaks the chain at that record
+    let client = pool.get().await.unwrap();
+    client
+        .execute(
+            "UPDATE user_logs SET user_agent = 'tampered' WHERE uuid = $1",
+            &[&entry.uuid],
+        )
+        .await
+        .unwrap();
+    let results: Vec<ChainVerification> =
+        test::call_and_read_body_json(&app, verify("?trail=user_logs")).await;
+    assert_eq!(results.len(), 1);
+    assert!(!results[0].valid);
+    let broken = results[0].first_broken.as_ref().unwrap();
+    assert_eq!(broken.record_id, Some(entry.uuid));
+    assert!(broken.seq <= checkpoint.seq);
+
+    // Restoring it repairs the chain
+    client
+        .execute(
+            "UPDATE user_logs SET user_agent = NULL WHERE uuid = $1",
+            &[&entry.uuid],
+        )
+        .await
+        .unwrap();
+    let results: Vec<ChainVerification> =
+        test::call_and_read_body_json(&app, verify("?trail=user_logs")).await;
+    assert!(results[0].valid);
+
+    // Checkpoints of keys that are no longer loaded cannot be trusted
+    let retired = Uuid::new_v4();
+    client
+        .execute(
+            "INSERT INTO audit_checkpoints (uuid, trail, seq, hash, kid, signature)
+             VALUES ($1, 'user_logs', $2, $3, 'retired-key', 'unverifiable')",
+            &[&retired, &checkpoint.seq, &checkpoint.hash],
+        )
+        .await
+        .unwrap();
+    let results: Vec<ChainVerification> =
+        test::call_and_read_body_json(&app, verify("?trail=user_logs")).await;
+    assert!(!results[0].valid);
+    assert!(results[0].first_broken.is_none());
+    assert_eq!(results[0].unverifiable_checkpoints, 1);
+    client
+        .execute("DELETE FROM audit_checkpoints WHERE uuid = $1", &[&retired])
+        .await
+        .unwrap();
+
+    // Checkpoints of rotated out keys verify with their recorded public key
+    let rotated_kid = format!("rotated-{}", Uuid::new_v4());
+    let rotated = SigningKeys::from_pems(
+        &[(rotated_kid.clone(), generate_ed25519_pem().into())],
+        &rotated_kid,
+    )
+    .unwrap();
+    let rotated_signature = rotated
+        .sign(&CheckpointClaims {
+            trail: AuditTrail::UserLogs,
+            seq: checkpoint.seq,
+            hash: checkpoint.hash.clone(),
+            iat: Utc::now().timestamp(),
+        })
+        .unwrap();
+    client
+        .execute(
+            "INSERT INTO audit_signing_keys (kid, jwk) VALUES ($1, $2)",
+            &[
+                &rotated_kid,
+                &serde_json::to_value(rotated.active_jwk()).unwrap(),
+            ],
+        )
+        .await
+        .unwrap();
+    client
+        .execute(
+            "INSERT INTO audit_checkpoints (uuid, trail, seq, hash, kid, signature)
+             VALUES ($1, 'user_logs', $2, $3, $4, $5)",
+            &[
+                &retired,
+                &checkpoint.seq,
+                &checkpoint.hash,
+                &rotated_kid,
+                &rotated_signature,
+            ],
+        )
+        .await
+        .unwrap();
+    assert!(!SigningKeys::global().has_key(&rotated_kid));
+    let results: Vec<ChainVerification> =
+        test::call_and_read_body_json(&app, verify("?trail=user_logs")).await;
+    assert!(results[0].valid);
+    assert_eq!(results[0].unverifiable_checkpoints, 0);
+    client
+        .execute("DELETE FROM audit_checkpoints WHERE uuid = $1", &[&retired])
+        .await
+        .unwrap();
+
+    // The export carries the checkpoints and the keys to verify them
+    let req = test::TestRequest::get()
+        .uri("/audit/checkpoints/export?trail=user_logs")
+        .insert_header(("Authorization", admin_token.clone()))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert!(resp
+        .headers()
+        .get("Content-Disposition")
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .starts_with("attachment"));
+    let export: CheckpointExport = test::read_body_json(resp).await;
+    assert!(export
+        .checkpoints
+        .iter()
+        .any(|c| c.uuid == checkpoint.uuid && c.signature == checkpoint.signature));
+    assert!(export.jwks.find(&checkpoint.kid).is_some());
+
+    let req = test::TestRequest::get()
+        .uri("/audit/checkpoints?trail=ticket_history")
+        .insert_header(("Authorization", admin_token.clone()))
+        .to_request();
+    let listed: Vec<AuditCheckpoint> = test::call_and_read_body_json(&app, req).await;
+    assert!(listed.iter().all(|c| c.trail == AuditTrail::TicketHistory));
+}
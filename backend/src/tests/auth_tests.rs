@@ -1,7 +1,15 @@
 use super::common;
-use crate::models::auth::{LoginForm, RefreshRequest, TokenResponse};
+use crate::middleware::Auth;
+use crate::models::auth::{Claims, LoginForm, RefreshRequest, TokenResponse, TokenType};
+use crate::models::token_blacklist::TokenBlacklist;
 use crate::routes::auth;
+use crate::signing::{generate_ed25519_pem, SigningKeys};
+use actix_web::http::StatusCode;
 use actix_web::{test, web, App};
+use chrono::{Duration, Utc};
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{decode, decode_header, DecodingKey, Validation};
+use uuid::Uuid;
 
 #[actix_rt::test]
 async fn test_login() {
@@ -98,3 +106,214 @@ async fn test_refresh() {
         ),
     }
 }
+
+#[actix_rt::test]
+async fn test_refresh_rotation() {
+    common::initialize_tests().await;
+    let pool = common::get_db_pool().clone();
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(pool.clone()))
+            .service(
+                web::scope("/auth")
+                    .service(auth::login)
+                    .service(auth::refresh),
+            )
+            .service(
+                web::scope("/session")
+                    .wrap(Auth::new())
+                    .service(auth::logout),
+            ),
+    )
+    .await;
+
+    let login = |email: &str, password: &str| {
+        test::TestRequest::post()
+            .uri("/auth/login")
+            .set_json(&LoginForm {
+                email: email.to_string(),
+                password: password.to_string(),
+            })
+            .to_request()
+    };
+    let refresh = |token: &str| {
+        test::TestRequest::post()
+            .uri("/auth/refresh")
+            .set_json(&RefreshRequest {
+                refresh_token: token.to_string(),
+            })
+            .to_request()
+    };
+
+    // Each refresh returns a new refresh token
+    let login_resp: TokenResponse =
+        test::call_and_read_body_json(&app, login("admin@example.com", "admin123")).await;
+    let first = login_resp.refresh_token.expect("Refresh token is required");
+    let rotated: TokenResponse = test::call_and_read_body_json(&app, refresh(&first)).await;
+    let second = rotated.refresh_token.expect("Refresh token is required");
+    assert_ne!(first, second);
+
+    // Reusing the first token revokes the whole session
+    let resp = test::call_service(&app, refresh(&first)).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    let resp = test::call_service(&app, refresh(&second)).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    let req = test::TestRequest::post()
+        .uri("/session/logout")
+        .insert_header(("Authorization", format!("Bearer {}", rotated.access_token)))
+        .to_request();
+    let err = test::try_call_service(&app, req)
+        .await
+        .expect_err("Access tokens of a revoked session must be rejected");
+    assert_eq!(
+        err.as_response_error().status_code(),
+        StatusCode::UNAUTHORIZED
+    );
+
+    // Logging out revokes the refresh token of the session
+    let login_resp: TokenResponse =
+        test::call_and_read_body_json(&app, login("admin@example.com", "admin123")).await;
+    let req = test::TestRequest::post()
+        .uri("/session/logout")
+        .insert_header((
+            "Authorization",
+            format!("Bearer {}", login_resp.access_token),
+        ))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    let resp = test::call_service(
+        &app,
+        refresh(&login_resp.refresh_token.expect("Refresh token is required")),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[actix_rt::test]
+async fn test_token_blacklist() {
+    common::initialize_tests().await;
+    let pool = common::get_db_pool().clone();
+
+    let claims = |exp: chrono::DateTime<Utc>| Claims {
+        sub: Uuid::new_v4(),
+        role: "user".to_string(),
+        exp: exp.timestamp() as usize,
+        token_type: TokenType::Access,
+        sid: None,
+        jti: Some(Uuid::new_v4()),
+    };
+    let active = claims(Utc::now() + Duration::hours(1));
+    let expired = claims(Utc::now() - Duration::hours(1));
+    let active_id = TokenBlacklist::token_id(&active, "active");
+    let expired_id = TokenBlacklist::token_id(&expired, "expired");
+
+    for claims in [&active, &expired] {
+        TokenBlacklist::revoke(&pool, claims, "token")
+            .await
+            .expect("Failed to revoke token");
+        // Revoking twice is not an error
+        TokenBlacklist::revoke(&pool, claims, "token")
+            .await
+            .expect("Failed to revoke token");
+    }
+    assert!(TokenBlacklist::is_revoked(&active_id));
+    assert!(TokenBlacklist::is_revoked(&expired_id));
+
+    // Entries of expired tokens are purged, the others survive a sync
+    let deleted = TokenBlacklist::purge_expired(&pool)
+        .await
+        .expect("Failed to purge tokens");
+    assert!(deleted >= 1);
+    TokenBlacklist::sync(&pool)
+        .await
+        .expect("Failed to sync tokens");
+    assert!(TokenBlacklist::is_revoked(&active_id));
+    assert!(!TokenBlacklist::is_revoked(&expired_id));
+
+    // Tokens without an id are identified by their hash
+    let legacy = Claims {
+        jti: None,
+        ..active
+    };
+    assert_ne!(
+        TokenBlacklist::token_id(&legacy, "a"),
+        TokenBlacklist::token_id(&legacy, "b")
+    );
+}
+
+#[actix_rt::test]
+async fn test_signing_key_rotation() {
+    let claims = Claims {
+        sub: Uuid::new_v4(),
+        role: "user".to_string(),
+        exp: (Utc::now() + Duration::hours(1)).timestamp() as usize,
+        token_type: TokenType::Access,
+        sid: None,
+        jti: Some(Uuid::new_v4()),
+    };
+    let old_key = ("2026-01".to_string(), generate_ed25519_pem().into_bytes());
+    let new_key = ("2026-02".to_string(), generate_ed25519_pem().into_bytes());
+
+    let before =
+        SigningKeys::from_pems(std::slice::from_ref(&old_key), "2026-01").expect("Invalid keys");
+    let old_token = before.sign(&claims).expect("Failed to sign token");
+
+    // Tokens of the previous key keep verifying after the rotation
+    let during = SigningKeys::from_pems(&[old_key.clone(), new_key.clone()], "2026-02")
+        .expect("Invalid keys");
+    assert_eq!(
+        during.verify(&old_token).expect("Old token rejected").sub,
+        claims.sub
+    );
+    let new_token = during.sign(&claims).expect("Failed to sign token");
+    let header = decode_header(&new_token).expect("Invalid header");
+    assert_eq!(header.kid.as_deref(), Some("2026-02"));
+    assert_eq!(during.jwks().keys.len(), 2);
+
+    // Removing the key rejects its tokens
+    let after = SigningKeys::from_pems(&[new_key], "2026-02").expect("Invalid keys");
+    assert!(after.verify(&old_token).is_err());
+    assert!(after.verify(&new_token).is_ok());
+
+    // Unknown active keys and invalid keys are configuration errors
+    assert!(SigningKeys::from_pems(&[old_key], "2026-03").is_err());
+    assert!(SigningKeys::from_pems(&[("bad".to_string(), b"bad".to_vec())], "bad").is_err());
+}
+
+#[actix_rt::test]
+async fn test_jwks() {
+    common::initialize_tests().await;
+    let pool = common::get_db_pool().clone();
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(pool.clone()))
+            .service(auth::jwks)
+            .service(web::scope("/auth").service(auth::login)),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/auth/login")
+        .set_json(&LoginForm {
+            email: "admin@example.com".to_string(),
+            password: "admin123".to_string(),
+        })
+        .to_request();
+    let login_resp: TokenResponse = test::call_and_read_body_json(&app, req).await;
+
+    // The published key verifies issued tokens
+    let req = test::TestRequest::get()
+        .uri("/.well-known/jwks.json")
+        .to_request();
+    let jwks: JwkSet = test::call_and_read_body_json(&app, req).await;
+    let header = decode_header(&login_resp.access_token).expect("Invalid header");
+    let jwk = jwks
+        .find(&header.kid.expect("Token without kid"))
+        .expect("Signing key not published");
+    let key = DecodingKey::from_jwk(jwk).expect("Invalid JWK");
+    let token = decode::<Claims>(&login_resp.access_token, &key, &Validation::new(header.alg))
+        .expect("Token not verified by the JWKS");
+    assert_eq!(token.claims.sub, login_resp.user.uuid);
+}
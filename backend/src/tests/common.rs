@@ -1,15 +1,21 @@
+use crate::models::auth::LoginForm;
+use crate::models::ticket::{Ticket, TicketType};
+use crate::signing::{generate_ed25519_pem, SigningKeys};
+use actix_web::test;
 use deadpool_postgres::{Config, Pool};
 use once_cell::sync::OnceCell;
-use std::env;
 use tokio_postgres::NoTls;
+use uuid::Uuid;
 
 static DB_POOL: OnceCell<Pool> = OnceCell::new();
+static SIGNING_KEYS: OnceCell<()> = OnceCell::new();
 
 pub fn get_db_pool() -> &'static Pool {
     DB_POOL.get().expect("Database pool not initialized")
 }
 
 pub async fn initialize_tests() {
+    init_signing_keys();
     let pool = setup_test_db().await;
     clear_database(&pool)
         .await
@@ -18,9 +24,18 @@ pub async fn initialize_tests() {
         .await
         .expect("Failed to run migrations");
     let _ = DB_POOL.set(pool);
+}
 
-    // Set JWT_SECRET for tests
-    env::set_var("JWT_SECRET", "dw3QKXwLxzufwTHymvWjfdMfMcDDlckc");
+/// Loads a persistent signing key from a key directory, as in production,
+/// so audit checkpoints can be signed.
+pub fn init_signing_keys() {
+    SIGNING_KEYS.get_or_init(|| {
+        let dir = std::env::temp_dir().join(format!("abuse_helper_keys_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("Failed to create key directory");
+        std::fs::write(dir.join("test.pem"), generate_ed25519_pem()).expect("Failed to write key");
+        std::env::set_var("JWT_KEYS_DIR", &dir);
+        SigningKeys::init().expect("Failed to load signing keys");
+    });
 }
 
 pub async fn setup_test_db() -> Pool {
@@ -115,6 +130,126 @@ pub async fn run_migrations(pool: &Pool) -> Result<(), Box<dyn std::error::Error
             "0005_create_user_logs.sql",
             include_str!("../../migrations/0005_create_user_logs.sql"),
         ),
+        (
+            "0006_create_emails_table.sql",
+            include_str!("../../migrations/0006_create_emails_table.sql"),
+        ),
+        (
+            "0007_add_is_sent_to_emails.sql",
+            include_str!("../../migrations/0007_add_is_sent_to_emails.sql"),
+        ),
+        (
+            "0008_create_tickets_table.sql",
+            include_str!("../../migrations/0008_create_tickets_table.sql"),
+        ),
+        (
+            "0009_create_email_tickets_table.sql",
+            include_str!("../../migrations/0009_create_email_tickets_table.sql"),
+        ),
+        (
+            "0010_remove_email_id_from_tickets.sql",
+            include_str!("../../migrations/0010_remove_email_id_from_tickets.sql"),
+        ),
+        (
+            "0011_change_email_id_to_uuid.sql",
+            include_str!("../../migrations/0011_change_email_id_to_uuid.sql"),
+        ),
+        (
+            "0012_add_ticket_merge.sql",
+            include_str!("../../migrations/0012_add_ticket_merge.sql"),
+        ),
+        (
+            "0013_add_email_ticket_correlation.sql",
+            include_str!("../../migrations/0013_add_email_ticket_correlation.sql"),
+        ),
+        (
+            "0014_add_ticket_tags_and_custom_fields.sql",
+            include_str!("../../migrations/0014_add_ticket_tags_and_custom_fields.sql"),
+        ),
+        (
+            "0015_add_ticket_customer.sql",
+            include_str!("../../migrations/0015_add_ticket_customer.sql"),
+        ),
+        (
+            "0016_add_ticket_assignment.sql",
+            include_str!("../../migrations/0016_add_ticket_assignment.sql"),
+        ),
+        (
+            "0017_add_ticket_numbers.sql",
+            include_str!("../../migrations/0017_add_ticket_numbers.sql"),
+        ),
+        (
+            "0018_create_ticket_history.sql",
+            include_str!("../../migrations/0018_create_ticket_history.sql"),
+        ),
+        (
+            "0019_create_ticket_watchers_and_notifications.sql",
+            include_str!("../../migrations/0019_create_ticket_watchers_and_notifications.sql"),
+        ),
+        (
+            "0020_add_user_disabled.sql",
+            include_str!("../../migrations/0020_add_user_disabled.sql"),
+        ),
+        (
+            "0021_create_password_reset_tokens.sql",
+            include_str!("../../migrations/0021_create_password_reset_tokens.sql"),
+        ),
+        (
+            "0022_add_user_totp.sql",
+            include_str!("../../migrations/0022_add_user_totp.sql"),
+        ),
+        (
+            "0023_create_api_keys.sql",
+            include_str!("../../migrations/0023_create_api_keys.sql"),
+        ),
+        (
+            "0024_create_roles.sql",
+            include_str!("../../migrations/0024_create_roles.sql"),
+        ),
+        (
+            "0025_create_refresh_tokens.sql",
+            include_str!("../../migrations/0025_create_refresh_tokens.sql"),
+        ),
+        (
+            "0026_rework_blacklisted_tokens.sql",
+            include_str!("../../migrations/0026_rework_blacklisted_tokens.sql"),
+        ),
+        (
+            "0027_create_keycloak_role_mappings.sql",
+            include_str!("../../migrations/0027_create_keycloak_role_mappings.sql"),
+        ),
+        (
+            "0028_add_login_protection.sql",
+            include_str!("../../migrations/0028_add_login_protection.sql"),
+        ),
+        (
+            "0029_create_sessions.sql",
+            include_str!("../../migrations/0029_create_sessions.sql"),
+        ),
+        (
+            "0030_add_audit_read_permission.sql",
+            include_str!("../../migrations/0030_add_audit_read_permission.sql"),
+        ),
+        (
+            "0031_extend_user_logs.sql",
+            include_str!("../../migrations/0031_extend_user_logs.sql"),
+        ),
+        (
+            "0032_add_audit_hash_chain.sql",
+            include_str!("../../migrations/0032_add_audit_hash_chain.sql"),
+        ),
+        (
+            "0033_add_email_tickets_unique_link.sql",
+            include_str!("../../migrations/0033_add_email_tickets_unique_link.sql"),
+        ),
+        (
+            "0034_add_audit_manage_permission.sql",
+            include_str!("../../migrations/0034_add_audit_manage_permission.sql"),
+        ),
+        (
+            "0035_create_audit_signing_keys.sql",
+            include_str!("../../migrations/0035_create_audit_signing_keys.sql"),
+        ),
     ];
 
     for (migration_name, migration_sql) in migration_files.iter() {
@@ -148,3 +283,48 @@ pub async fn run_migrations(pool: &Pool) -> Result<(), Box<dyn std::error::Error
 
     Ok(())
 }
+
+pub async fn insert_ticket(
+    pool: &Pool,
+    ticket_type: TicketType,
+    indicators: Vec<String>,
+    email_ids: &[Uuid],
+) -> Uuid {
+    let name = ticket_type.to_string();
+    let mut ticket = Ticket::new(
+        ticket_type,
+        format!("{} report", name),
+        format!("Reported {}", name.to_lowercase()),
+        None,
+        Some(0.9),
+        Some(vec![name.to_lowercase()]),
+        Some(indicators),
+        Some(format!("{} campaign", name)),
+    );
+    let id = ticket.save(pool).await.expect("Failed to save ticket");
+
+    let client = pool.get().await.expect("Failed to get client");
+    for email_id in email_ids {
+        client
+            .execute(
+                "INSERT INTO email_tickets (email_id, ticket_id) VALUES ($1, $2)",
+                &[email_id, &id],
+            )
+            .await
+            .expect("Failed to link email");
+    }
+    id
+}
+
+pub fn login_request(email: &str, password: &str) -> test::TestRequest {
+    test::TestRequest::post()
+        .uri("/auth/login")
+        .set_json(&LoginForm {
+            email: email.to_string(),
+            password: password.to_string(),
+        })
+}
+
+pub fn admin_login_request() -> test::TestRequest {
+    login_request("admin@example.com", "admin123")
+}
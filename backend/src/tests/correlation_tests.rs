@@ -0,0 +1,131 @@
+use super::common;
+use crate as abuse_helper;
+use abuse_helper::models::correlation::{classify_indicator, IndicatorKind};
+use abuse_helper::models::email::Email;
+use abuse_helper::models::ticket::{Ticket, TicketStatus, TicketType};
+
+#[test]
+fn test_classify_indicator() {
+    assert_eq!(classify_indicator("192.0.2.10"), Some(IndicatorKind::Ip));
+    assert_eq!(classify_indicator("2001:db8::1"), Some(IndicatorKind::Ip));
+    assert_eq!(
+        classify_indicator("Evil.Example.com."),
+        Some(IndicatorKind::Domain)
+    );
+    assert_eq!(
+        classify_indicator("http://evil.example.com/login"),
+        Some(IndicatorKind::Url)
+    );
+    assert_eq!(
+        classify_indicator("44d88612fea8a8f36de82e1278abb02f"),
+        Some(IndicatorKind::FileHash)
+    );
+    assert_eq!(classify_indicator("invoice.exe"), None);
+    assert_eq!(classify_indicator("credential harvesting"), None);
+}
+
+#[actix_rt::test]
+async fn test_correlate_email_with_open_ticket() {
+    common::initialize_tests().await;
+    let pool = common::get_db_pool().clone();
+
+    let existing = common::insert_ticket(
+        &pool,
+        TicketType::Malware,
+        vec!["198.51.100.7".to_string(), "payload.exe".to_string()],
+        &[],
+    )
+    .await;
+
+    let email = Email::new(
+        "reporter@example.com".to_string(),
+        vec!["abuse@example.com".to_string()],
+        "Another report".to_string(),
+        "Same host again".to_string(),
+    );
+    email.save(&pool).await.expect("Failed to save email");
+
+    // Shared IP links the email to the existing ticket
+    let indicators = vec!["198.51.100.7".to_string(), "bad.example.net".to_string()];
+    let correlated = email
+        .correlate(&pool, &indicators)
+        .await
+        .expect("Failed to correlate");
+    assert_eq!(correlated, Some(existing));
+
+    let client = pool.get().await.expect("Failed to get client");
+    let row = client
+        .query_one(
+            "SELECT correlation_reason FROM email_tickets WHERE email_id = $1 AND ticket_id = $2",
+            &[&email.id, &existing],
+        )
+        .await
+        .expect("Email was not linked");
+    let reason: Option<String> = row.get("correlation_reason");
+    assert_eq!(
+        reason.as_deref(),
+        Some("Shared indicators: ip 198.51.100.7")
+    );
+
+    let ticket = Ticket::find_by_id(&pool, existing)
+        .await
+        .expect("Failed to load ticket")
+        .expect("Ticket not found");
+    assert!(ticket
+        .extracted_indicators
+        .unwrap_or_default()
+        .contains(&"bad.example.net".to_string()));
+
+    // Correlating an already linked email does not reopen a closed ticket
+    client
+        .execute(
+            "UPDATE tickets SET status = $1 WHERE id = $2",
+            &[&TicketStatus::Closed.to_string(), &existing],
+        )
+        .await
+        .expect("Failed to close ticket");
+    let correlated = email
+        .correlate(&pool, &indicators)
+        .await
+        .expect("Failed to correlate");
+    assert_eq!(correlated, Some(existing));
+    let ticket = Ticket::find_by_id(&pool, existing).await.unwrap().unwrap();
+    assert!(matches!(ticket.status, TicketStatus::Closed));
+
+    // A new email about a recently closed ticket correlates and reopens it
+    let follow_up = Email::new(
+        "reporter@example.com".to_string(),
+        vec!["abuse@example.com".to_string()],
+        "Still happening".to_string(),
+        "Same host once more".to_string(),
+    );
+    follow_up.save(&pool).await.expect("Failed to save email");
+    let correlated = follow_up
+        .correlate(&pool, &indicators)
+        .await
+        .expect("Failed to correlate");
+    assert_eq!(correlated, Some(existing));
+    let ticket = Ticket::find_by_id(&pool, existing).await.unwrap().unwrap();
+    assert!(matches!(ticket.status, TicketStatus::Open));
+
+    // Tickets closed before the correlation window are ignored
+    client
+        .execute(
+            "UPDATE tickets SET status = $1, updated_at = NOW() - INTERVAL '30 days' WHERE id = $2",
+            &[&TicketStatus::Closed.to_string(), &existing],
+        )
+        .await
+        .expect("Failed to close ticket");
+    let late = Email::new(
+        "reporter@example.com".to_string(),
+        vec!["abuse@example.com".to_string()],
+        "Late report".to_string(),
+        "Same host a month later".to_string(),
+    );
+    late.save(&pool).await.expect("Failed to save email");
+    let correlated = late
+        .correlate(&pool, &indicators)
+        .await
+        .expect("Failed to correlate");
+    assert_eq!(correlated, None);
+}
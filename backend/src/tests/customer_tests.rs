@@ -3,9 +3,15 @@ use crate::models::customer::LookUpForm;
 use crate::{self as abuse_helper, middleware::Auth};
 use abuse_helper::models::auth::{LoginForm, TokenResponse};
 use abuse_helper::models::customer::Customer;
-use abuse_helper::routes::{auth, customer};
+use abuse_helper::models::requests::{
+    CreateTicketRequest, CreateTicketResponse, SetCustomerRequest,
+};
+use abuse_helper::models::ticket::{CustomerLinkSource, Ticket, TicketType};
+use abuse_helper::routes::{auth, customer, ticket};
+use actix_web::http::StatusCode;
 use actix_web::{test, web, App};
 use serde_json;
+use uuid::Uuid;
 
 #[actix_rt::test]
 async fn test_list_customers() {
@@ -142,3 +148,125 @@ async fn test_find_customer() {
         ),
     }
 }
+
+#[actix_rt::test]
+async fn test_customer_tickets() {
+    common::initialize_tests().await;
+    let pool = common::get_db_pool().clone();
+    let customer_uuid = Uuid::parse_str("123e4567-e89b-12d3-a456-426614175306").unwrap();
+
+    // Store a report sent by the customer
+    let email_id = Uuid::new_v4();
+    let client = pool.get().await.expect("Failed to get client");
+    client
+        .execute(
+            "INSERT INTO emails (id, sender, recipients, subject, body) VALUES ($1, $2, $3, $4, $5)",
+            &[
+                &email_id,
+                &"John Smith <John.Smith@gmail.com>",
+                &vec!["abuse@example.com".to_string()],
+                &"Compromised host",
+                &"Our server is sending spam",
+            ],
+        )
+        .await
+        .expect("Failed to insert email");
+
+    // Create test app
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(pool.clone()))
+            .service(web::scope("/auth").service(auth::login))
+            .service(
+                web::scope("")
+                    .wrap(Auth::new())
+                    .service(web::scope("/customer").service(customer::tickets))
+                    .service(
+                        web::scope("/tickets")
+                            .service(ticket::create_ticket)
+                            .service(ticket::set_ticket_customer),
+                    ),
+            ),
+    )
+    .await;
+
+    // Login as admin
+    let login_form = LoginForm {
+        email: "admin@example.com".to_string(),
+        password: "admin123".to_string(),
+    };
+
+    let login_req = test::TestRequest::post()
+        .uri("/auth/login")
+        .set_json(&login_form)
+        .to_request();
+
+    let login_resp: TokenResponse = test::call_and_read_body_json(&app, login_req).await;
+    let auth_header = (
+        "Authorization",
+        format!("Bearer {}", login_resp.access_token),
+    );
+
+    // The customer is resolved from the email sender
+    let req = test::TestRequest::post()
+        .uri("/tickets/create")
+        .insert_header(auth_header.clone())
+        .set_json(&CreateTicketRequest {
+            email_ids: vec![email_id],
+            subject: "Compromised host".to_string(),
+            description: "Spam from customer server".to_string(),
+            ticket_type: Some(TicketType::Spam),
+            confidence_score: None,
+            identified_threats: None,
+            extracted_indicators: None,
+            analysis_summary: None,
+        })
+        .to_request();
+    let created: CreateTicketResponse = test::call_and_read_body_json(&app, req).await;
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/customer/{}/tickets", customer_uuid))
+        .insert_header(auth_header.clone())
+        .to_request();
+    let tickets: Vec<Ticket> = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(tickets.len(), 1);
+    assert_eq!(tickets[0].id, created.ticket_id);
+    assert_eq!(
+        tickets[0].customer_link_source,
+        Some(CustomerLinkSource::Sender)
+    );
+
+    // Linking an unknown customer is rejected
+    let req = test::TestRequest::put()
+        .uri(&format!("/tickets/{}/customer", created.ticket_id))
+        .insert_header(auth_header.clone())
+        .set_json(&SetCustomerRequest {
+            customer_uuid: Some(Uuid::new_v4()),
+        })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    // Manual links are kept as overrides
+    let req = test::TestRequest::put()
+        .uri(&format!("/tickets/{}/customer", created.ticket_id))
+        .insert_header(auth_header.clone())
+        .set_json(&SetCustomerRequest {
+            customer_uuid: Some(customer_uuid),
+        })
+        .to_request();
+    let updated: Ticket = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(updated.customer_uuid, Some(customer_uuid));
+    assert_eq!(
+        updated.customer_link_source,
+        Some(CustomerLinkSource::Manual)
+    );
+
+    // Unknown customers have no ticket history
+    let req = test::TestRequest::get()
+        .uri(&format!("/customer/{}/tickets", Uuid::new_v4()))
+        .insert_header(auth_header)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,160 @@
+use super::common;
+use crate::{self as abuse_helper, middleware::Auth};
+use abuse_helper::events::{self as bus, Event};
+use abuse_helper::models::auth::TokenResponse;
+use abuse_helper::models::ticket::{Ticket, TicketType};
+use abuse_helper::models::user::User;
+use abuse_helper::routes::{auth, events};
+use actix_web::body::{BoxBody, MessageBody};
+use actix_web::{test, web, App};
+use chrono::Utc;
+use std::pin::Pin;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Reads an event stream until it contains a text.
+async fn read_until(body: &mut BoxBody, received: &mut String, text: &str) {
+    while !received.contains(text) {
+        let chunk = tokio::time::timeout(
+            Duration::from_secs(5),
+            futures::future::poll_fn(|cx| Pin::new(&mut *body).poll_next(cx)),
+        )
+        .await
+        .expect("No event received")
+        .expect("Stream ended")
+        .expect("Stream failed");
+        received.push_str(&String::from_utf8_lossy(&chunk));
+    }
+}
+
+#[actix_rt::test]
+async fn test_event_stream() {
+    common::initialize_tests().await;
+    let pool = common::get_db_pool().clone();
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(pool.clone()))
+            .service(web::scope("/auth").service(auth::login))
+            .service(
+                web::scope("/events")
+                    .wrap(Auth::new().permission("tickets:read"))
+                    .service(events::stream_events),
+            ),
+    )
+    .await;
+    let admin: TokenResponse =
+        test::call_and_read_body_json(&app, common::admin_login_request().to_request()).await;
+
+    let req = test::TestRequest::get()
+        .uri("/events?types=ticket_created")
+        .insert_header(("Authorization", format!("Bearer {}", admin.access_token)))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(
+        resp.headers().get("content-type").unwrap(),
+        "text/event-stream"
+    );
+
+    // Creating a ticket pushes an event to the open stream
+    let mut ticket = Ticket::new(
+        TicketType::Botnet,
+        "Botnet report".to_string(),
+        "Bot traffic".to_string(),
+        None,
+        None,
+        None,
+        None,
+        None,
+    );
+    let ticket_id = ticket.save(&pool).await.expect("Failed to save ticket");
+
+    let mut body = resp.into_body();
+    let mut received = String::new();
+    read_until(&mut body, &mut received, "event: ticket_created").await;
+
+    assert!(received.starts_with("retry: "));
+    assert!(received.contains(&ticket_id.to_string()));
+    assert!(!received.contains("email_ingested"));
+}
+
+#[actix_rt::test]
+async fn test_event_stream_hides_emails_without_email_read() {
+    common::initialize_tests().await;
+    let pool = common::get_db_pool().clone();
+
+    let email = format!("events-{}@example.com", Uuid::new_v4());
+    let hash = bcrypt::hash("analyst123", 4).expect("Failed to hash password");
+    User::create(
+        &pool,
+        Uuid::new_v4(),
+        email.clone(),
+        "Analyst".to_string(),
+        hash,
+        "user".to_string(),
+    )
+    .await
+    .expect("Failed to create user");
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(pool.clone()))
+            .service(web::scope("/auth").service(auth::login))
+            .service(
+                web::scope("/events")
+                    .wrap(Auth::new().permission("tickets:read"))
+                    .service(events::stream_events),
+            ),
+    )
+    .await;
+    let admin: TokenResponse =
+        test::call_and_read_body_json(&app, common::admin_login_request().to_request()).await;
+    let analyst: TokenResponse = test::call_and_read_body_json(
+        &app,
+        common::login_request(&email, "analyst123").to_request(),
+    )
+    .await;
+
+    let mut streams = Vec::new();
+    for token in [&admin.access_token, &analyst.access_token] {
+        let req = test::TestRequest::get()
+            .uri("/events")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request();
+        streams.push(test::call_service(&app, req).await.into_body());
+    }
+
+    // Only the admin may read emails, the `user` role sees the ticket alone
+    let email_id = Uuid::new_v4();
+    bus::publish(Event::EmailIngested {
+        email_id,
+        sender: "attacker@example.com".to_string(),
+        subject: "Confidential report".to_string(),
+        received_at: Utc::now(),
+    });
+    let mut ticket = Ticket::new(
+        TicketType::Spam,
+        "Spam report".to_string(),
+        "Spam wave".to_string(),
+        None,
+        None,
+        None,
+        None,
+        None,
+    );
+    let ticket_id = ticket.save(&pool).await.expect("Failed to save ticket");
+
+    let mut admin_received = String::new();
+    read_until(&mut streams[0], &mut admin_received, &email_id.to_string()).await;
+    read_until(&mut streams[0], &mut admin_received, &ticket_id.to_string()).await;
+
+    let mut analyst_received = String::new();
+    read_until(
+        &mut streams[1],
+        &mut analyst_received,
+        &ticket_id.to_string(),
+    )
+    .await;
+    assert!(!analyst_received.contains(&email_id.to_string()));
+    assert!(!analyst_received.contains("Confidential report"));
+}
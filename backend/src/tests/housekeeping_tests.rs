@@ -0,0 +1,107 @@
+use super::common;
+use crate as abuse_helper;
+use abuse_helper::jobs::housekeeping::{self, HousekeepingAction, HousekeepingRule};
+use abuse_helper::models::email::Email;
+use abuse_helper::models::ticket::{Ticket, TicketStatus, TicketType};
+use abuse_helper::models::ticket_history::{TicketEvent, TicketHistory};
+use deadpool_postgres::Pool;
+use uuid::Uuid;
+
+async fn insert_inactive_ticket(
+    pool: &Pool,
+    ticket_type: TicketType,
+    status: TicketStatus,
+) -> Uuid {
+    let id = common::insert_ticket(pool, ticket_type, Vec::new(), &[]).await;
+
+    // Backdate the ticket so it looks inactive
+    let client = pool.get().await.expect("Failed to get client");
+    client
+        .execute(
+            "UPDATE tickets SET status = $2, updated_at = NOW() - INTERVAL '10 days' WHERE id = $1",
+            &[&id, &status.to_string()],
+        )
+        .await
+        .expect("Failed to backdate ticket");
+    id
+}
+
+#[actix_rt::test]
+async fn test_housekeeping_rules_and_reopen() {
+    common::initialize_tests().await;
+    let pool = common::get_db_pool().clone();
+
+    let resolved = insert_inactive_ticket(&pool, TicketType::Spam, TicketStatus::Resolved).await;
+    let open = insert_inactive_ticket(&pool, TicketType::Spam, TicketStatus::Open).await;
+    let ddos = insert_inactive_ticket(&pool, TicketType::DDoS, TicketStatus::Open).await;
+
+    // DDoS tickets get a longer grace period than the general rule
+    let mut rules = HousekeepingRule::defaults();
+    rules.push(HousekeepingRule {
+        status: TicketStatus::Open,
+        ticket_type: Some(TicketType::DDoS),
+        inactive_hours: 24 * 30,
+        action: HousekeepingAction::Flag,
+    });
+
+    let report = housekeeping::run(&pool, &rules)
+        .await
+        .expect("Housekeeping failed");
+    assert!(report.closed.contains(&resolved));
+    assert!(report.flagged.contains(&open));
+    assert!(!report.flagged.contains(&ddos));
+
+    let closed = Ticket::find_by_id(&pool, resolved).await.unwrap().unwrap();
+    assert!(matches!(closed.status, TicketStatus::Closed));
+    let flagged = Ticket::find_by_id(&pool, open).await.unwrap().unwrap();
+    assert!(matches!(flagged.status, TicketStatus::Open));
+    assert!(flagged.stale_since.is_some());
+
+    let history = TicketHistory::list_for_ticket(&pool, resolved)
+        .await
+        .unwrap();
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].event, TicketEvent::AutoClosed);
+
+    // Running again does not flag the same ticket twice
+    let report = housekeeping::run(&pool, &rules)
+        .await
+        .expect("Housekeeping failed");
+    assert!(!report.flagged.contains(&open));
+
+    // A new email reopens the closed ticket
+    let email = Email::new(
+        "reporter@example.com".to_string(),
+        vec!["abuse@example.com".to_string()],
+        "Still spamming".to_string(),
+        "The spam continues".to_string(),
+    );
+    email.save(&pool).await.expect("Failed to save email");
+    email
+        .link_ticket(&pool, resolved)
+        .await
+        .expect("Failed to link email");
+
+    let reopened = Ticket::find_by_id(&pool, resolved).await.unwrap().unwrap();
+    assert!(matches!(reopened.status, TicketStatus::Open));
+    let history = TicketHistory::list_for_ticket(&pool, resolved)
+        .await
+        .unwrap();
+    assert_eq!(history.last().unwrap().event, TicketEvent::Reopened);
+
+    // Linking the same email again does not reopen the ticket
+    let client = pool.get().await.unwrap();
+    client
+        .execute(
+            "UPDATE tickets SET status = $1 WHERE id = $2",
+            &[&TicketStatus::Closed.to_string(), &resolved],
+        )
+        .await
+        .unwrap();
+    email
+        .link_ticket(&pool, resolved)
+        .await
+        .expect("Failed to link email");
+    let ticket = Ticket::find_by_id(&pool, resolved).await.unwrap().unwrap();
+    assert!(matches!(ticket.status, TicketStatus::Closed));
+}
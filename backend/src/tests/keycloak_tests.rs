@@ -0,0 +1,259 @@
+use super::common;
+use crate::{self as abuse_helper, middleware::Auth};
+use abuse_helper::keycloak::{role_claims, verify_introspection, verify_with_jwks, KeycloakConfig};
+use abuse_helper::models::auth::{LoginForm, TokenResponse};
+use abuse_helper::models::keycloak_mapping::KeycloakMapping;
+use abuse_helper::models::role::Role;
+use abuse_helper::routes::{auth, role};
+use abuse_helper::signing::{generate_ed25519_pem, SigningKeys};
+use actix_web::http::StatusCode;
+use actix_web::{test, web, App};
+use chrono::{Duration, Utc};
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use serde_json::{json, Value};
+
+fn config() -> KeycloakConfig {
+    KeycloakConfig {
+        url: "http://keycloak:8080".to_string(),
+        realm: "Abuse-Helper".to_string(),
+        client_id: "Abuse-Helper".to_string(),
+        client_secret: None,
+        issuer: "http://localhost:8080/realms/Abuse-Helper".to_string(),
+    }
+}
+
+#[actix_rt::test]
+async fn test_verify_with_jwks() {
+    let config = config();
+    let pem = generate_ed25519_pem();
+    let jwks = SigningKeys::from_pems(
+        &[("realm-key".to_string(), pem.clone().into())],
+        "realm-key",
+    )
+    .expect("Invalid key")
+    .jwks();
+    let key = EncodingKey::from_ed_pem(pem.as_bytes()).expect("Invalid key");
+
+    let sign = |kid: &str, claims: Value| {
+        let mut header = Header::new(Algorithm::EdDSA);
+        header.kid = Some(kid.to_string());
+        encode(&header, &claims, &key).expect("Failed to sign token")
+    };
+    let claims = |iss: &str, azp: &str, exp: i64| {
+        json!({
+            "iss": iss,
+            "aud": "account",
+            "azp": azp,
+            "exp": exp,
+            "email": "analyst@example.com",
+        })
+    };
+    let valid_until = (Utc::now() + Duration::minutes(5)).timestamp();
+
+    let token = sign(
+        "realm-key",
+        claims(&config.issuer, &config.client_id, valid_until),
+    );
+    let verified = verify_with_jwks(&config, &jwks, &token)
+        .expect("Valid token rejected")
+        .expect("Known key not found");
+    assert_eq!(verified["email"], "analyst@example.com");
+
+    // Other issuers, other clients and expired tokens are rejected
+    for claims in [
+        claims(
+            "http://evil/realms/Abuse-Helper",
+            &config.client_id,
+            valid_until,
+        ),
+        claims(&config.issuer, "other-client", valid_until),
+        claims(
+            &config.issuer,
+            &config.client_id,
+            (Utc::now() - Duration::minutes(5)).timestamp(),
+        ),
+    ] {
+        assert!(verify_with_jwks(&config, &jwks, &sign("realm-key", claims)).is_err());
+    }
+
+    // Unknown keys are left to the JWKS refresh and introspection
+    let token = sign(
+        "rotated-key",
+        claims(&config.issuer, &config.client_id, valid_until),
+    );
+    assert!(verify_with_jwks(&config, &jwks, &token)
+        .expect("Unknown key is not an error")
+        .is_none());
+
+    // Symmetric signatures are never accepted
+    let mut header = Header::new(Algorithm::HS256);
+    header.kid = Some("realm-key".to_string());
+    let token = encode(
+        &header,
+        &claims(&config.issuer, &config.client_id, valid_until),
+        &EncodingKey::from_secret(b"secret"),
+    )
+    .expect("Failed to sign token");
+    assert!(verify_with_jwks(&config, &jwks, &token).is_err());
+}
+
+#[actix_rt::test]
+async fn test_verify_introspection() {
+    let config = config();
+    let token_info = |active: bool, iss: &str, aud: Value, azp: &str| {
+        json!({
+            "active": active,
+            "iss": iss,
+            "aud": aud,
+            "azp": azp,
+            "email": "analyst@example.com",
+        })
+    };
+
+    let verified = verify_introspection(
+        &config,
+        token_info(true, &config.issuer, json!("account"), &config.client_id),
+    )
+    .expect("Valid token rejected");
+    assert_eq!(verified["email"], "analyst@example.com");
+    assert!(verify_introspection(
+        &config,
+        token_info(
+            true,
+            &config.issuer,
+            json!(["account", config.client_id]),
+            "other-client"
+        ),
+    )
+    .is_ok());
+
+    // Inactive tokens, other issuers and other clients are rejected
+    for token_info in [
+        token_info(false, &config.issuer, json!("account"), &config.client_id),
+        token_info(
+            true,
+            "http://evil/realms/Abuse-Helper",
+            json!("account"),
+            &config.client_id,
+        ),
+        token_info(true, &config.issuer, json!("account"), "other-client"),
+        json!({ "active": true, "azp": config.client_id }),
+    ] {
+        assert!(verify_introspection(&config, token_info).is_err());
+    }
+}
+
+#[actix_rt::test]
+async fn test_keycloak_role_mappings() {
+    common::initialize_tests().await;
+    let pool = common::get_db_pool().clone();
+    let config = config();
+
+    let token_claims = json!({
+        "realm_access": { "roles": ["offline_access", "user"] },
+        "resource_access": { "Abuse-Helper": { "roles": ["triage"] } },
+        "groups": ["/soc/analysts"],
+    });
+    let claims = role_claims(&config, &token_claims);
+    assert!(claims.contains(&("realm_role", "user".to_string())));
+    assert!(claims.contains(&("client_role", "triage".to_string())));
+    assert!(claims.contains(&("group", "/soc/analysts".to_string())));
+
+    // The seeded mappings keep the previous behaviour
+    assert_eq!(
+        KeycloakMapping::resolve(&pool, &claims)
+            .await
+            .expect("Failed to resolve role"),
+        "user"
+    );
+    assert_eq!(
+        KeycloakMapping::resolve(&pool, &[("realm_role", "admin".to_string())])
+            .await
+            .expect("Failed to resolve role"),
+        "admin"
+    );
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(pool.clone()))
+            .service(web::scope("/auth").service(auth::login))
+            .service(
+                web::scope("/roles")
+                    .wrap(Auth::new().permission("roles:manage"))
+                    .service(role::list_keycloak_mappings)
+                    .service(role::create_keycloak_mapping)
+                    .service(role::delete_keycloak_mapping)
+                    .service(role::get_role),
+            ),
+    )
+    .await;
+    let req = test::TestRequest::post()
+        .uri("/auth/login")
+        .set_json(&LoginForm {
+            email: "admin@example.com".to_string(),
+            password: "admin123".to_string(),
+        })
+        .to_request();
+    let admin: TokenResponse = test::call_and_read_body_json(&app, req).await;
+    let admin_token = format!("Bearer {}", admin.access_token);
+
+    // Unknown claims and roles are rejected
+    for body in [
+        json!({ "claim": "scope", "value": "openid", "role": "user" }),
+        json!({ "claim": "group", "value": "/soc/analysts", "role": "nobody" }),
+    ] {
+        let req = test::TestRequest::post()
+            .uri("/roles/keycloak_mappings")
+            .insert_header(("Authorization", admin_token.clone()))
+            .set_json(body)
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status(),
+            StatusCode::BAD_REQUEST
+        );
+    }
+
+    // A higher priority group mapping wins over the realm role
+    Role::create(&pool, "soc-auditor", "", &["tickets:read".to_string()])
+        .await
+        .expect("Failed to create role");
+    let req = test::TestRequest::post()
+        .uri("/roles/keycloak_mappings")
+        .insert_header(("Authorization", admin_token.clone()))
+        .set_json(json!({
+            "claim": "group",
+            "value": "/soc/analysts",
+            "role": "soc-auditor",
+            "priority": 10
+        }))
+        .to_request();
+    let mapping: KeycloakMapping = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(
+        KeycloakMapping::resolve(&pool, &claims)
+            .await
+            .expect("Failed to resolve role"),
+        "soc-auditor"
+    );
+
+    let req = test::TestRequest::get()
+        .uri("/roles/keycloak_mappings")
+        .insert_header(("Authorization", admin_token.clone()))
+        .to_request();
+    let mappings: Vec<KeycloakMapping> = test::call_and_read_body_json(&app, req).await;
+    assert!(mappings.iter().any(|m| m.uuid == mapping.uuid));
+
+    let req = test::TestRequest::delete()
+        .uri(&format!("/roles/keycloak_mappings/{}", mapping.uuid))
+        .insert_header(("Authorization", admin_token))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::NO_CONTENT
+    );
+    assert_eq!(
+        KeycloakMapping::resolve(&pool, &claims)
+            .await
+            .expect("Failed to resolve role"),
+        "user"
+    );
+}
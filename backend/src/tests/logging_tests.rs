@@ -0,0 +1,160 @@
+use crate::logging::{self, LogFormat};
+use crate::middleware::{AssignedRequestId, RequestId};
+use actix_web::dev::ServiceResponse;
+use actix_web::error::ErrorForbidden;
+use actix_web::http::StatusCode;
+use actix_web::{get, test, web, App, HttpResponse};
+use serde_json::Value;
+use std::future::ready;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use tracing_subscriber::EnvFilter;
+
+/// Log output captured in memory.
+#[derive(Clone, Default)]
+struct Captured(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Captured {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Captured {
+    fn lines(&self) -> Vec<String> {
+        String::from_utf8(self.0.lock().unwrap().clone())
+            .unwrap()
+            .lines()
+            .map(str::to_string)
+            .collect()
+    }
+}
+
+#[get("/work")]
+async fn work(request_id: web::ReqData<AssignedRequestId>) -> HttpResponse {
+    log::info!("handled by log");
+    tracing::info!("handled by tracing");
+    HttpResponse::Ok().body(request_id.into_inner().0)
+}
+
+#[actix_rt::test]
+async fn test_request_id_in_json_logs() {
+    let _ = tracing_log::LogTracer::init();
+    let captured = Captured::default();
+    let writer = captured.clone();
+    let _guard = tracing::subscriber::set_default(logging::subscriber(
+        LogFormat::Json,
+        EnvFilter::new("info"),
+        move || writer.clone(),
+    ));
+
+    let app = test::init_service(App::new().wrap(RequestId::new()).service(work)).await;
+
+    // Request IDs from proxies are kept, returned and logged
+    let req = test::TestRequest::get()
+        .uri("/work")
+        .insert_header(("X-Request-ID", "proxy-id-1"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.headers().get("X-Request-ID").unwrap(), "proxy-id-1");
+    assert_eq!(test::read_body(resp).await, "proxy-id-1");
+
+    let records: Vec<Value> = captured
+        .lines()
+        .iter()
+        .map(|line| serde_json::from_str(line).expect("Log records should be JSON"))
+        .collect();
+    for message in ["handled by log", "handled by tracing"] {
+        let record = records
+            .iter()
+            .find(|r| r["fields"]["message"] == message)
+            .unwrap_or_else(|| panic!("Missing log record {:?}", message));
+        assert_eq!(record["level"], "INFO");
+        assert_eq!(record["span"]["name"], "request");
+        assert_eq!(record["span"]["request_id"], "proxy-id-1");
+        assert_eq!(record["span"]["method"], "GET");
+        assert_eq!(record["span"]["path"], "/work");
+    }
+
+    // Invalid request IDs are replaced by generated ones
+    let req = test::TestRequest::get()
+        .uri("/work")
+        .insert_header(("X-Request-ID", "bad id"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    let assigned = resp
+        .headers()
+        .get("X-Request-ID")
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_string();
+    assert!(uuid::Uuid::parse_str(&assigned).is_ok());
+    assert_eq!(test::read_body(resp).await, assigned.as_str());
+    assert!(captured
+        .lines()
+        .iter()
+        .any(|line| line.contains(&format!("\"request_id\":\"{}\"", assigned))));
+}
+
+#[actix_rt::test]
+async fn test_request_id_in_text_logs() {
+    let _ = tracing_log::LogTracer::init();
+    let captured = Captured::default();
+    let writer = captured.clone();
+    let _guard = tracing::subscriber::set_default(logging::subscriber(
+        LogFormat::Text,
+        EnvFilter::new("info"),
+        move || writer.clone(),
+    ));
+
+    let app = test::init_service(App::new().wrap(RequestId::new()).service(work)).await;
+    let req = test::TestRequest::get()
+        .uri("/work")
+        .insert_header(("X-Request-ID", "text-id-1"))
+        .to_request();
+    test::call_service(&app, req).await;
+
+    // Lines keep the env_logger layout, with the request ID before the message
+    let line = captured
+        .lines()
+        .into_iter()
+        .find(|line| line.contains("handled by log"))
+        .expect("Missing log record");
+    assert!(line.starts_with('['));
+    assert!(line.ends_with(
+        " INFO  abuse_helper::tests::logging_tests] [request_id=text-id-1] handled by log"
+    ));
+}
+
+#[actix_rt::test]
+async fn test_request_id_on_middleware_errors() {
+    let app = test::init_service(
+        App::new().wrap(RequestId::new()).service(
+            web::scope("/denied")
+                .wrap_fn(|_, _| {
+                    ready(Err::<ServiceResponse, _>(ErrorForbidden(
+                        "Insufficient permissions",
+                    )))
+                })
+                .service(work),
+        ),
+    )
+    .await;
+
+    let req = test::TestRequest::get()
+        .uri("/denied/work")
+        .insert_header(("X-Request-ID", "error-id-1"))
+        .to_request();
+    let err = test::try_call_service(&app, req)
+        .await
+        .expect_err("Middleware errors must be passed on");
+    let resp = err.error_response();
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    assert_eq!(resp.headers().get("X-Request-ID").unwrap(), "error-id-1");
+}
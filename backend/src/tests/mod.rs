@@ -1,5 +1,17 @@
+#![allow(clippy::unnecessary_mut_passed)]
+
+mod audit_tests;
 mod auth_tests;
 mod common;
+mod correlation_tests;
 mod customer_tests;
+mod events_tests;
+mod housekeeping_tests;
+mod keycloak_tests;
+mod logging_tests;
 mod nctns_tests;
+mod notification_tests;
+mod role_tests;
+mod ticket_tests;
+mod user_tests;
 mod whois_tests;
@@ -0,0 +1,287 @@
+use super::common;
+use crate::{self as abuse_helper, middleware::Auth};
+use abuse_helper::models::auth::TokenResponse;
+use abuse_helper::models::notification::TicketWatcher;
+use abuse_helper::models::requests::NotificationListResponse;
+use abuse_helper::models::ticket::{Ticket, TicketType};
+use abuse_helper::models::ticket_comment::TicketComment;
+use abuse_helper::routes::{auth, notification, ticket};
+use actix_web::http::StatusCode;
+use actix_web::{test, web, App};
+use deadpool_postgres::Pool;
+use serde_json::json;
+use uuid::Uuid;
+
+async fn insert_user(pool: &Pool, email: &str, password: &str) -> Uuid {
+    let client = pool.get().await.expect("Failed to get client");
+    let id = Uuid::new_v4();
+    let hash = bcrypt::hash(password, 4).expect("Failed to hash password");
+    client
+        .execute(
+            "INSERT INTO users (uuid, email, name, password_hash, role) VALUES ($1, $2, $3, $4, 'user')",
+            &[&id, &email, &"Analyst", &hash],
+        )
+        .await
+        .expect("Failed to insert user");
+    id
+}
+
+#[actix_rt::test]
+async fn test_watchers_and_notifications() {
+    common::initialize_tests().await;
+    let pool = common::get_db_pool().clone();
+
+    let analyst_email = format!("analyst-{}@example.com", Uuid::new_v4());
+    let analyst = insert_user(&pool, &analyst_email, "analyst123").await;
+    let mut new_ticket = Ticket::new(
+        TicketType::Spam,
+        "Spam wave".to_string(),
+        "Spam from a compromised host".to_string(),
+        None,
+        None,
+        None,
+        None,
+        None,
+    );
+    let ticket_id = new_ticket.save(&pool).await.expect("Failed to save ticket");
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(pool.clone()))
+            .service(web::scope("/auth").service(auth::login))
+            .service(
+                web::scope("")
+                    .wrap(Auth::new())
+                    .service(
+                        web::scope("/tickets")
+                            .service(ticket::bulk_update_tickets)
+                            .service(ticket::update_ticket_status)
+                            .service(ticket::watch_ticket)
+                            .service(ticket::add_ticket_comment),
+                    )
+                    .service(
+                        web::scope("/notifications")
+                            .service(notification::list)
+                            .service(notification::mark_all_read)
+                            .service(notification::mark_read),
+                    ),
+            ),
+    )
+    .await;
+    let admin: TokenResponse =
+        test::call_and_read_body_json(&app, common::admin_login_request().to_request()).await;
+    let admin_token = format!("Bearer {}", admin.access_token);
+    let analyst_login: TokenResponse = test::call_and_read_body_json(
+        &app,
+        common::login_request(&analyst_email, "analyst123").to_request(),
+    )
+    .await;
+    let analyst_token = format!("Bearer {}", analyst_login.access_token);
+
+    // The analyst watches the ticket
+    let req = test::TestRequest::post()
+        .uri(&format!("/tickets/{}/watch", ticket_id))
+        .insert_header(("Authorization", analyst_token.clone()))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::NO_CONTENT
+    );
+
+    // The admin comments, closes and reassigns the ticket
+    let req = test::TestRequest::post()
+        .uri(&format!("/tickets/{}/comments", ticket_id))
+        .insert_header(("Authorization", admin_token.clone()))
+        .set_json(json!({ "body": "Host owner contacted" }))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::CREATED
+    );
+
+    let req = test::TestRequest::put()
+        .uri(&format!("/tickets/{}/status", ticket_id))
+        .insert_header(("Authorization", admin_token.clone()))
+        .set_json("Closed")
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+    let req = test::TestRequest::post()
+        .uri("/tickets/bulk")
+        .insert_header(("Authorization", admin_token.clone()))
+        .set_json(json!({
+            "ticket_ids": [ticket_id],
+            "action": "assign",
+            "user_id": analyst
+        }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+    // The analyst was notified about each change, the admin was not
+    let req = test::TestRequest::get()
+        .uri("/notifications")
+        .insert_header(("Authorization", analyst_token.clone()))
+        .to_request();
+    let list: NotificationListResponse = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(list.unread, 3);
+    assert_eq!(list.notifications.len(), 3);
+    assert!(list.notifications.iter().all(|n| n.ticket_id == ticket_id));
+
+    let req = test::TestRequest::get()
+        .uri("/notifications")
+        .insert_header(("Authorization", admin_token.clone()))
+        .to_request();
+    let list_admin: NotificationListResponse = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(list_admin.unread, 0);
+
+    // Users can only mark their own notifications as read
+    let first = list.notifications[0].id;
+    let req = test::TestRequest::put()
+        .uri(&format!("/notifications/{}/read", first))
+        .insert_header(("Authorization", admin_token.clone()))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::NOT_FOUND
+    );
+
+    let req = test::TestRequest::put()
+        .uri(&format!("/notifications/{}/read", first))
+        .insert_header(("Authorization", analyst_token.clone()))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::NO_CONTENT
+    );
+
+    let req = test::TestRequest::put()
+        .uri("/notifications/read_all")
+        .insert_header(("Authorization", analyst_token.clone()))
+        .to_request();
+    let marked: u64 = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(marked, 2);
+
+    let req = test::TestRequest::get()
+        .uri("/notifications?unread=true")
+        .insert_header(("Authorization", analyst_token))
+        .to_request();
+    let list: NotificationListResponse = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(list.unread, 0);
+    assert!(list.notifications.is_empty());
+}
+
+#[actix_rt::test]
+async fn test_merge_moves_comments_and_watchers() {
+    common::initialize_tests().await;
+    let pool = common::get_db_pool().clone();
+
+    let analyst_email = format!("analyst-{}@example.com", Uuid::new_v4());
+    let analyst = insert_user(&pool, &analyst_email, "analyst123").await;
+    let survivor = common::insert_ticket(&pool, TicketType::Spam, Vec::new(), &[]).await;
+    let merged = common::insert_ticket(&pool, TicketType::Spam, Vec::new(), &[]).await;
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(pool.clone()))
+            .service(web::scope("/auth").service(auth::login))
+            .service(
+                web::scope("").wrap(Auth::new()).service(
+                    web::scope("/tickets")
+                        .service(ticket::merge_tickets)
+                        .service(ticket::watch_ticket)
+                        .service(ticket::unwatch_ticket)
+                        .service(ticket::get_ticket_watchers)
+                        .service(ticket::add_ticket_comment)
+                        .service(ticket::get_ticket_comments),
+                ),
+            ),
+    )
+    .await;
+    let admin: TokenResponse =
+        test::call_and_read_body_json(&app, common::admin_login_request().to_request()).await;
+    let admin_token = format!("Bearer {}", admin.access_token);
+    let analyst_login: TokenResponse = test::call_and_read_body_json(
+        &app,
+        common::login_request(&analyst_email, "analyst123").to_request(),
+    )
+    .await;
+    let analyst_token = format!("Bearer {}", analyst_login.access_token);
+
+    // The analyst watches both tickets, the admin only the merged one
+    for (ticket_id, token) in [
+        (survivor, &analyst_token),
+        (merged, &analyst_token),
+        (merged, &admin_token),
+    ] {
+        let req = test::TestRequest::post()
+            .uri(&format!("/tickets/{}/watch", ticket_id))
+            .insert_header(("Authorization", token.clone()))
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status(),
+            StatusCode::NO_CONTENT
+        );
+    }
+    let req = test::TestRequest::post()
+        .uri(&format!("/tickets/{}/comments", merged))
+        .insert_header(("Authorization", admin_token.clone()))
+        .set_json(json!({ "body": "Same campaign as the other ticket" }))
+        .to_request();
+    let comment: TicketComment = test::call_and_read_body_json(&app, req).await;
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/tickets/{}/merge", survivor))
+        .insert_header(("Authorization", admin_token.clone()))
+        .set_json(json!({ "ticket_ids": [merged] }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+    // Comments and watchers moved to the surviving ticket without duplicates
+    for ticket_id in [survivor, merged] {
+        let req = test::TestRequest::get()
+            .uri(&format!("/tickets/{}/comments", ticket_id))
+            .insert_header(("Authorization", admin_token.clone()))
+            .to_request();
+        let comments: Vec<TicketComment> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(comments.len(), 1);
+        assert_eq!(comments[0].id, comment.id);
+        assert_eq!(comments[0].ticket_id, survivor);
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/tickets/{}/watchers", ticket_id))
+            .insert_header(("Authorization", admin_token.clone()))
+            .to_request();
+        let watchers: Vec<TicketWatcher> = test::call_and_read_body_json(&app, req).await;
+        let mut users: Vec<Uuid> = watchers.iter().map(|w| w.user_id).collect();
+        users.sort();
+        let mut expected = vec![analyst, admin.user.uuid];
+        expected.sort();
+        assert_eq!(users, expected);
+        assert!(watchers.iter().all(|w| w.ticket_id == survivor));
+    }
+
+    // Comments and watch changes through the merged ticket reach the survivor
+    let req = test::TestRequest::post()
+        .uri(&format!("/tickets/{}/comments", merged))
+        .insert_header(("Authorization", analyst_token.clone()))
+        .set_json(json!({ "body": "Hosting provider took the page down" }))
+        .to_request();
+    let comment: TicketComment = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(comment.ticket_id, survivor);
+
+    let req = test::TestRequest::delete()
+        .uri(&format!("/tickets/{}/watch", merged))
+        .insert_header(("Authorization", admin_token.clone()))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::NO_CONTENT
+    );
+    let req = test::TestRequest::get()
+        .uri(&format!("/tickets/{}/watchers", survivor))
+        .insert_header(("Authorization", admin_token))
+        .to_request();
+    let watchers: Vec<TicketWatcher> = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(watchers.len(), 1);
+    assert_eq!(watchers[0].user_id, analyst);
+}
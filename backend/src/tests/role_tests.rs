@@ -0,0 +1,176 @@
+use super::common;
+use crate::{self as abuse_helper, middleware::Auth};
+use abuse_helper::models::auth::TokenResponse;
+use abuse_helper::models::role::Role;
+use abuse_helper::models::user::User;
+use abuse_helper::routes::{auth, role, ticket};
+use actix_web::http::StatusCode;
+use actix_web::{test, web, App};
+use serde_json::json;
+use uuid::Uuid;
+
+#[actix_rt::test]
+async fn test_roles_and_permissions() {
+    common::initialize_tests().await;
+    let pool = common::get_db_pool().clone();
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(pool.clone()))
+            .service(web::scope("/auth").service(auth::login))
+            .service(
+                web::scope("/tickets")
+                    .wrap(Auth::new().permission("tickets:read"))
+                    .service(ticket::list_tickets)
+                    .service(
+                        web::scope("")
+                            .wrap(Auth::new().permission("tickets:write"))
+                            .service(ticket::create_ticket),
+                    ),
+            )
+            .service(
+                web::scope("/roles")
+                    .wrap(Auth::new().permission("roles:manage"))
+                    .service(role::list_roles)
+                    .service(role::list_permissions)
+                    .service(role::create_role)
+                    .service(role::get_role)
+                    .service(role::update_role)
+                    .service(role::delete_role),
+            ),
+    )
+    .await;
+    let admin: TokenResponse =
+        test::call_and_read_body_json(&app, common::admin_login_request().to_request()).await;
+    let admin_token = format!("Bearer {}", admin.access_token);
+
+    // Unknown permissions are rejected
+    let req = test::TestRequest::post()
+        .uri("/roles/create")
+        .insert_header(("Authorization", admin_token.clone()))
+        .set_json(json!({ "name": "auditor", "permissions": ["tickets:delete"] }))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::BAD_REQUEST
+    );
+
+    let req = test::TestRequest::post()
+        .uri("/roles/create")
+        .insert_header(("Authorization", admin_token.clone()))
+        .set_json(json!({
+            "name": "auditor",
+            "description": "Read-only access",
+            "permissions": ["tickets:read", "tickets:read"]
+        }))
+        .to_request();
+    let auditor_role: Role = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(auditor_role.permissions, vec!["tickets:read"]);
+    assert!(!auditor_role.built_in);
+
+    // The admin role keeps every permission
+    let req = test::TestRequest::put()
+        .uri("/roles/admin")
+        .insert_header(("Authorization", admin_token.clone()))
+        .set_json(json!({ "permissions": [] }))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::BAD_REQUEST
+    );
+
+    // An auditor can read tickets but not change them or manage roles
+    let email = format!("auditor-{}@example.com", Uuid::new_v4());
+    let hash = bcrypt::hash("auditor123", 4).expect("Failed to hash password");
+    User::create(
+        &pool,
+        Uuid::new_v4(),
+        email.clone(),
+        "Auditor".to_string(),
+        hash,
+        "auditor".to_string(),
+    )
+    .await
+    .expect("Failed to create user");
+    let auditor: TokenResponse = test::call_and_read_body_json(
+        &app,
+        common::login_request(&email, "auditor123").to_request(),
+    )
+    .await;
+    let auditor_token = format!("Bearer {}", auditor.access_token);
+
+    let req = test::TestRequest::get()
+        .uri("/tickets/list")
+        .insert_header(("Authorization", auditor_token.clone()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+    for req in [
+        test::TestRequest::post()
+            .uri("/tickets/create")
+            .insert_header(("Authorization", auditor_token.clone()))
+            .set_json(json!({}))
+            .to_request(),
+        test::TestRequest::get()
+            .uri("/roles/list")
+            .insert_header(("Authorization", auditor_token.clone()))
+            .to_request(),
+    ] {
+        let err = test::try_call_service(&app, req)
+            .await
+            .expect_err("Auditors must not have these permissions");
+        assert_eq!(err.as_response_error().status_code(), StatusCode::FORBIDDEN);
+    }
+
+    // Granting a permission takes effect without a new login
+    let req = test::TestRequest::put()
+        .uri("/roles/auditor")
+        .insert_header(("Authorization", admin_token.clone()))
+        .set_json(json!({ "permissions": ["tickets:read", "tickets:write"] }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+    let req = test::TestRequest::post()
+        .uri("/tickets/create")
+        .insert_header(("Authorization", auditor_token.clone()))
+        .set_json(json!({}))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::BAD_REQUEST
+    );
+
+    // Role changes take effect without a new login, in both directions
+    let roles_request = || {
+        test::TestRequest::get()
+            .uri("/roles/list")
+            .insert_header(("Authorization", auditor_token.clone()))
+            .to_request()
+    };
+    User::set_role(&pool, auditor.user.uuid, "admin")
+        .await
+        .expect("Failed to promote user");
+    assert_eq!(
+        test::call_service(&app, roles_request()).await.status(),
+        StatusCode::OK
+    );
+    User::set_role(&pool, auditor.user.uuid, "auditor")
+        .await
+        .expect("Failed to demote user");
+    let err = test::try_call_service(&app, roles_request())
+        .await
+        .expect_err("Demoted users must lose their permissions");
+    assert_eq!(err.as_response_error().status_code(), StatusCode::FORBIDDEN);
+
+    // Assigned and built-in roles cannot be deleted
+    for name in ["auditor", "user"] {
+        let req = test::TestRequest::delete()
+            .uri(&format!("/roles/{}", name))
+            .insert_header(("Authorization", admin_token.clone()))
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status(),
+            StatusCode::BAD_REQUEST
+        );
+    }
+}
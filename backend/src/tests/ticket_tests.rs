@@ -0,0 +1,524 @@
+use super::common;
+use crate as abuse_helper;
+use abuse_helper::models::export::{csv_field, TicketExport, TicketReport};
+use abuse_helper::models::requests::{
+    BulkTicketRequest, BulkTicketResponse, CreateCustomFieldRequest, MergeTicketsRequest,
+    SplitTicketRequest, UpdateCustomFieldsRequest, UpdateTagsRequest,
+};
+use abuse_helper::models::ticket::{BulkAction, Ticket, TicketStatus, TicketType};
+use abuse_helper::models::ticket_field::{CustomFieldDefinition, CustomFieldType};
+use abuse_helper::routes::{ticket, ticket_field};
+use actix_web::http::StatusCode;
+use actix_web::{test, web, App};
+use deadpool_postgres::Pool;
+use serde_json::json;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+async fn insert_email(pool: &Pool, subject: &str) -> Uuid {
+    let client = pool.get().await.expect("Failed to get client");
+    let id = Uuid::new_v4();
+    client
+        .execute(
+            "INSERT INTO emails (id, sender, recipients, subject, body) VALUES ($1, $2, $3, $4, $5)",
+            &[
+                &id,
+                &"reporter@example.com",
+                &vec!["abuse@example.com".to_string()],
+                &subject,
+                &"Report body",
+            ],
+        )
+        .await
+        .expect("Failed to insert email");
+    id
+}
+
+#[actix_rt::test]
+async fn test_merge_tickets() {
+    common::initialize_tests().await;
+    let pool = common::get_db_pool().clone();
+
+    let first_email = insert_email(&pool, "First report").await;
+    let second_email = insert_email(&pool, "Second report").await;
+    let survivor = common::insert_ticket(
+        &pool,
+        TicketType::Phishing,
+        vec!["1.2.3.4".to_string()],
+        &[first_email],
+    )
+    .await;
+    let merged = common::insert_ticket(
+        &pool,
+        TicketType::Phishing,
+        vec!["evil.example".to_string()],
+        &[second_email],
+    )
+    .await;
+
+    let app = test::init_service(
+        App::new().app_data(web::Data::new(pool.clone())).service(
+            web::scope("/tickets")
+                .service(ticket::merge_tickets)
+                .service(ticket::get_ticket),
+        ),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/tickets/{}/merge", survivor))
+        .set_json(&MergeTicketsRequest {
+            ticket_ids: vec![merged],
+        })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    println!("Response status: {:?}", resp.status());
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let result: Ticket = test::read_body_json(resp).await;
+    assert_eq!(result.id, survivor);
+    assert!(result.email_ids.contains(&first_email));
+    assert!(result.email_ids.contains(&second_email));
+    let indicators = result.extracted_indicators.unwrap_or_default();
+    assert!(indicators.contains(&"1.2.3.4".to_string()));
+    assert!(indicators.contains(&"evil.example".to_string()));
+
+    // Lookups of the merged ticket redirect to the survivor
+    let req = test::TestRequest::get()
+        .uri(&format!("/tickets/{}", merged))
+        .to_request();
+    let redirected: Ticket = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(redirected.id, survivor);
+
+    // Merging an already merged ticket into another ticket is rejected
+    let third = common::insert_ticket(
+        &pool,
+        TicketType::Phishing,
+        vec!["5.6.7.8".to_string()],
+        &[],
+    )
+    .await;
+    let req = test::TestRequest::post()
+        .uri(&format!("/tickets/{}/merge", third))
+        .set_json(&MergeTicketsRequest {
+            ticket_ids: vec![merged],
+        })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let msg: String = test::read_body_json(resp).await;
+    assert!(msg.contains("already merged"));
+
+    // Merging a ticket into itself is rejected
+    let req = test::TestRequest::post()
+        .uri(&format!("/tickets/{}/merge", survivor))
+        .set_json(&MergeTicketsRequest {
+            ticket_ids: vec![survivor],
+        })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_rt::test]
+async fn test_split_ticket() {
+    common::initialize_tests().await;
+    let pool = common::get_db_pool().clone();
+
+    let first_email = insert_email(&pool, "First report").await;
+    let second_email = insert_email(&pool, "Second report").await;
+    let source = common::insert_ticket(
+        &pool,
+        TicketType::Phishing,
+        vec!["1.2.3.4".to_string()],
+        &[first_email, second_email],
+    )
+    .await;
+
+    let app = test::init_service(
+        App::new().app_data(web::Data::new(pool.clone())).service(
+            web::scope("/tickets")
+                .service(ticket::split_ticket)
+                .service(ticket::get_ticket),
+        ),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/tickets/{}/split", source))
+        .set_json(&SplitTicketRequest {
+            email_ids: vec![second_email],
+            subject: Some("Separate incident".to_string()),
+        })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    println!("Response status: {:?}", resp.status());
+    assert_eq!(resp.status(), StatusCode::CREATED);
+
+    let new_ticket: Ticket = test::read_body_json(resp).await;
+    assert_ne!(new_ticket.id, source);
+    assert_eq!(new_ticket.subject, "Separate incident");
+    assert_eq!(new_ticket.email_ids, vec![second_email]);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/tickets/{}", source))
+        .to_request();
+    let remaining: Ticket = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(remaining.email_ids, vec![first_email]);
+
+    // Emails that are not linked to the ticket cannot be split off
+    let req = test::TestRequest::post()
+        .uri(&format!("/tickets/{}/split", source))
+        .set_json(&SplitTicketRequest {
+            email_ids: vec![second_email],
+            subject: None,
+        })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_rt::test]
+async fn test_ticket_tags_and_custom_fields() {
+    common::initialize_tests().await;
+    let pool = common::get_db_pool().clone();
+
+    let ticket_id = common::insert_ticket(
+        &pool,
+        TicketType::Phishing,
+        vec!["1.2.3.4".to_string()],
+        &[],
+    )
+    .await;
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(pool.clone()))
+            .service(
+                web::scope("/tickets")
+                    .service(ticket::update_ticket_tags)
+                    .service(ticket::update_ticket_custom_fields)
+                    .service(ticket::get_ticket),
+            )
+            .service(
+                web::scope("/ticket_fields")
+                    .service(ticket_field::list_fields)
+                    .service(ticket_field::create_field),
+            ),
+    )
+    .await;
+
+    // Define an enum and a date field
+    for field in [
+        CreateCustomFieldRequest {
+            name: "customer_segment".to_string(),
+            label: "Customer segment".to_string(),
+            field_type: CustomFieldType::Enum,
+            options: vec!["consumer".to_string(), "enterprise".to_string()],
+        },
+        CreateCustomFieldRequest {
+            name: "first_seen".to_string(),
+            label: "First seen".to_string(),
+            field_type: CustomFieldType::Date,
+            options: vec![],
+        },
+    ] {
+        let req = test::TestRequest::post()
+            .uri("/ticket_fields/create")
+            .set_json(&field)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+    }
+
+    // Stored fields are mapped again when the tickets index is recreated
+    let definitions = CustomFieldDefinition::list(&pool)
+        .await
+        .expect("Failed to list fields");
+    let mapping = CustomFieldDefinition::es_mapping(&definitions);
+    let mapped = &mapping["properties"]["custom_fields"]["properties"];
+    assert_eq!(mapped["customer_segment"], json!({ "type": "keyword" }));
+    assert_eq!(mapped["first_seen"], json!({ "type": "date" }));
+
+    // Tags are trimmed and deduplicated
+    let req = test::TestRequest::put()
+        .uri(&format!("/tickets/{}/tags", ticket_id))
+        .set_json(&UpdateTagsRequest {
+            tags: vec![
+                "campaign-x".to_string(),
+                " campaign-x ".to_string(),
+                "vip".to_string(),
+            ],
+        })
+        .to_request();
+    let result: Ticket = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(result.tags, vec!["campaign-x", "vip"]);
+
+    // Valid values are stored
+    let req = test::TestRequest::put()
+        .uri(&format!("/tickets/{}/custom_fields", ticket_id))
+        .set_json(&UpdateCustomFieldsRequest {
+            fields: HashMap::from([
+                ("customer_segment".to_string(), json!("enterprise")),
+                ("first_seen".to_string(), json!("2024-05-01")),
+            ]),
+        })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    println!("Response status: {:?}", resp.status());
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/tickets/{}", ticket_id))
+        .to_request();
+    let result: Ticket = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(result.tags, vec!["campaign-x", "vip"]);
+    assert_eq!(
+        result.custom_fields["customer_segment"],
+        json!("enterprise")
+    );
+    assert_eq!(result.custom_fields["first_seen"], json!("2024-05-01"));
+
+    // Values outside the enum options and unknown fields are rejected
+    for (name, value) in [
+        ("customer_segment", json!("government")),
+        ("unknown_field", json!("value")),
+    ] {
+        let req = test::TestRequest::put()
+            .uri(&format!("/tickets/{}/custom_fields", ticket_id))
+            .set_json(&UpdateCustomFieldsRequest {
+                fields: HashMap::from([(name.to_string(), value)]),
+            })
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    // Null clears a field
+    let req = test::TestRequest::put()
+        .uri(&format!("/tickets/{}/custom_fields", ticket_id))
+        .set_json(&UpdateCustomFieldsRequest {
+            fields: HashMap::from([("first_seen".to_string(), json!(null))]),
+        })
+        .to_request();
+    let result: Ticket = test::call_and_read_body_json(&app, req).await;
+    assert!(!result.custom_fields.contains_key("first_seen"));
+    assert_eq!(
+        result.custom_fields["customer_segment"],
+        json!("enterprise")
+    );
+}
+
+#[actix_rt::test]
+async fn test_bulk_update_tickets() {
+    common::initialize_tests().await;
+    let pool = common::get_db_pool().clone();
+
+    let first = common::insert_ticket(
+        &pool,
+        TicketType::Phishing,
+        vec!["1.2.3.4".to_string()],
+        &[],
+    )
+    .await;
+    let second = common::insert_ticket(
+        &pool,
+        TicketType::Phishing,
+        vec!["5.6.7.8".to_string()],
+        &[],
+    )
+    .await;
+    let missing = Uuid::new_v4();
+
+    let app = test::init_service(
+        App::new().app_data(web::Data::new(pool.clone())).service(
+            web::scope("/tickets")
+                .service(ticket::bulk_update_tickets)
+                .service(ticket::get_ticket),
+        ),
+    )
+    .await;
+
+    // Close the tickets, reporting the unknown ticket as failed
+    let req = test::TestRequest::post()
+        .uri("/tickets/bulk")
+        .set_json(&BulkTicketRequest {
+            ticket_ids: Some(vec![first, second, missing]),
+            query: None,
+            action: BulkAction::Status {
+                status: TicketStatus::Closed,
+            },
+            atomic: false,
+        })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    println!("Response status: {:?}", resp.status());
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let result: BulkTicketResponse = test::read_body_json(resp).await;
+    assert_eq!(result.succeeded, 2);
+    assert_eq!(result.failed, 1);
+    assert!(!result.results[2].success);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/tickets/{}", second))
+        .to_request();
+    let closed: Ticket = test::call_and_read_body_json(&app, req).await;
+    assert!(matches!(closed.status, TicketStatus::Closed));
+
+    // Assign the tickets to the admin user
+    let admin = Uuid::nil();
+    let req = test::TestRequest::post()
+        .uri("/tickets/bulk")
+        .set_json(&BulkTicketRequest {
+            ticket_ids: Some(vec![first, second]),
+            query: None,
+            action: BulkAction::Assign {
+                user_id: Some(admin),
+            },
+            atomic: false,
+        })
+        .to_request();
+    let result: BulkTicketResponse = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(result.succeeded, 2);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/tickets/{}", first))
+        .to_request();
+    let assigned: Ticket = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(assigned.assigned_to, Some(admin));
+
+    // Atomic operations roll back every ticket on failure
+    let req = test::TestRequest::post()
+        .uri("/tickets/bulk")
+        .set_json(&BulkTicketRequest {
+            ticket_ids: Some(vec![first, missing]),
+            query: None,
+            action: BulkAction::AddTags {
+                tags: vec!["spam-wave".to_string()],
+            },
+            atomic: true,
+        })
+        .to_request();
+    let result: BulkTicketResponse = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(result.succeeded, 0);
+    assert_eq!(result.failed, 2);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/tickets/{}", first))
+        .to_request();
+    let untouched: Ticket = test::call_and_read_body_json(&app, req).await;
+    assert!(untouched.tags.is_empty());
+
+    // Delete the tickets
+    let req = test::TestRequest::post()
+        .uri("/tickets/bulk")
+        .set_json(&BulkTicketRequest {
+            ticket_ids: Some(vec![first, second]),
+            query: None,
+            action: BulkAction::Delete,
+            atomic: true,
+        })
+        .to_request();
+    let result: BulkTicketResponse = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(result.succeeded, 2);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/tickets/{}", first))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_rt::test]
+async fn test_ticket_numbers() {
+    common::initialize_tests().await;
+    let pool = common::get_db_pool().clone();
+
+    let first = common::insert_ticket(&pool, TicketType::Phishing, vec![], &[]).await;
+    let second = common::insert_ticket(&pool, TicketType::Phishing, vec![], &[]).await;
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(pool.clone()))
+            .service(web::scope("/tickets").service(ticket::get_ticket)),
+    )
+    .await;
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/tickets/{}", first))
+        .to_request();
+    let first: Ticket = test::call_and_read_body_json(&app, req).await;
+    let number = first.number.expect("Ticket has no number");
+    assert_eq!(first.reference, Some(format!("AH-{}", number)));
+
+    // Numbers are sequential and tickets can be looked up by reference
+    for reference in [format!("AH-{}", number + 1), format!("ah-{}", number + 1)] {
+        let req = test::TestRequest::get()
+            .uri(&format!("/tickets/{}", reference))
+            .to_request();
+        let found: Ticket = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(found.id, second);
+    }
+
+    let req = test::TestRequest::get()
+        .uri("/tickets/AH-999999")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    assert_eq!(Ticket::parse_reference("#42"), Some(42));
+    assert_eq!(Ticket::parse_reference("42"), Some(42));
+    assert_eq!(Ticket::parse_reference("XY-42"), None);
+}
+
+#[actix_rt::test]
+async fn test_ticket_report_and_csv_export() {
+    common::initialize_tests().await;
+    let pool = common::get_db_pool().clone();
+
+    let email = insert_email(&pool, "Fake <login> page").await;
+    let id = common::insert_ticket(
+        &pool,
+        TicketType::Phishing,
+        vec!["evil.example".to_string()],
+        &[email],
+    )
+    .await;
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(pool.clone()))
+            .service(web::scope("/tickets").service(ticket::get_ticket_report)),
+    )
+    .await;
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/tickets/{}/report", id))
+        .to_request();
+    let body = test::call_and_read_body(&app, req).await;
+    let html = String::from_utf8(body.to_vec()).expect("Report is not UTF-8");
+    assert!(html.contains("Phishing campaign"));
+    assert!(html.contains("<li>evil.example</li>"));
+    assert!(html.contains("Email received from reporter@example.com: Fake &lt;login&gt; page"));
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/tickets/{}/report", Uuid::new_v4()))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    // CSV rows carry the linked email metadata
+    let report = TicketReport::load(&pool, id).await.unwrap().unwrap();
+    let row = TicketExport {
+        ticket: report.ticket,
+        emails: report.emails,
+    }
+    .to_csv_row();
+    assert!(row.contains(&email.to_string()));
+    assert!(row.contains(",1,"));
+
+    assert_eq!(csv_field("plain"), "plain");
+    assert_eq!(csv_field("a,\"b\""), "\"a,\"\"b\"\"\"");
+    assert_eq!(csv_field("=cmd()"), "'=cmd()");
+}
@@ -0,0 +1,899 @@
+use super::common;
+use crate::{self as abuse_helper, middleware::Auth};
+use abuse_helper::models::api_key::{ApiKey, CreatedApiKey};
+use abuse_helper::models::auth::{
+    MfaChallengeResponse, RecoveryCodesResponse, RefreshRequest, RevokedSessionsResponse,
+    SessionResponse, TokenResponse, UserResponse,
+};
+use abuse_helper::models::login_attempt::{LoginAttempts, LoginEvent};
+use abuse_helper::models::password::PasswordResetToken;
+use abuse_helper::models::session::Session;
+use abuse_helper::models::totp::{self, TotpEnrollment};
+use abuse_helper::models::user::User;
+use abuse_helper::routes::{auth, ticket, user};
+use actix_web::http::StatusCode;
+use actix_web::{test, web, App};
+use chrono::{Duration, Utc};
+use serde_json::json;
+use uuid::Uuid;
+
+#[actix_rt::test]
+async fn test_user_management() {
+    common::initialize_tests().await;
+    let pool = common::get_db_pool().clone();
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(pool.clone()))
+            .service(web::scope("/auth").service(auth::login))
+            .service(
+                web::scope("/users")
+                    .wrap(Auth::new())
+                    .service(user::me)
+                    .service(
+                        web::scope("")
+                            .wrap(Auth::new().permission("users:manage"))
+                            .service(auth::create_user)
+                            .service(user::list_users)
+                            .service(user::update_user_role)
+                            .service(user::disable_user)
+                            .service(user::enable_user)
+                            .service(user::get_user)
+                            .service(user::update_user)
+                            .service(user::delete_user),
+                    ),
+            ),
+    )
+    .await;
+    let admin: TokenResponse =
+        test::call_and_read_body_json(&app, common::admin_login_request().to_request()).await;
+    let admin_token = format!("Bearer {}", admin.access_token);
+
+    // The admin creates an analyst account
+    let analyst_email = format!("analyst-{}@example.com", Uuid::new_v4());
+    let req = test::TestRequest::post()
+        .uri("/users/create_user")
+        .insert_header(("Authorization", admin_token.clone()))
+        .set_json(json!({
+            "email": analyst_email,
+            "name": "Analyst",
+            "password": "analyst123",
+            "role": "user"
+        }))
+        .to_request();
+    let analyst: UserResponse = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(analyst.role, "user");
+    assert!(analyst.disabled_at.is_none());
+
+    // Unknown roles are rejected
+    let req = test::TestRequest::put()
+        .uri(&format!("/users/{}/role", analyst.uuid))
+        .insert_header(("Authorization", admin_token.clone()))
+        .set_json(json!({ "role": "superuser" }))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::BAD_REQUEST
+    );
+
+    // The analyst sees their own profile but cannot manage users
+    let analyst_login: TokenResponse = test::call_and_read_body_json(
+        &app,
+        common::login_request(&analyst_email, "analyst123").to_request(),
+    )
+    .await;
+    let analyst_token = format!("Bearer {}", analyst_login.access_token);
+
+    let req = test::TestRequest::get()
+        .uri("/users/me")
+        .insert_header(("Authorization", analyst_token.clone()))
+        .to_request();
+    let me: UserResponse = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(me.uuid, analyst.uuid);
+
+    let req = test::TestRequest::get()
+        .uri("/users")
+        .insert_header(("Authorization", analyst_token.clone()))
+        .to_request();
+    let err = test::try_call_service(&app, req)
+        .await
+        .expect_err("Non-admins must not list users");
+    assert_eq!(err.as_response_error().status_code(), StatusCode::FORBIDDEN);
+
+    // The admin renames the analyst and lists the users
+    let req = test::TestRequest::put()
+        .uri(&format!("/users/{}", analyst.uuid))
+        .insert_header(("Authorization", admin_token.clone()))
+        .set_json(json!({ "name": "Senior Analyst" }))
+        .to_request();
+    let renamed: UserResponse = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(renamed.name, "Senior Analyst");
+    assert_eq!(renamed.email, analyst_email);
+
+    let req = test::TestRequest::get()
+        .uri("/users?role=user&disabled=false&limit=200")
+        .insert_header(("Authorization", admin_token.clone()))
+        .to_request();
+    let users: Vec<UserResponse> = test::call_and_read_body_json(&app, req).await;
+    assert!(users.iter().any(|u| u.uuid == analyst.uuid));
+    assert!(users.iter().all(|u| u.role == "user"));
+
+    // Admins cannot lock themselves out
+    let req = test::TestRequest::put()
+        .uri(&format!("/users/{}/disable", admin.user.uuid))
+        .insert_header(("Authorization", admin_token.clone()))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::BAD_REQUEST
+    );
+
+    // Disabling the analyst rejects their token and their logins
+    let req = test::TestRequest::put()
+        .uri(&format!("/users/{}/disable", analyst.uuid))
+        .insert_header(("Authorization", admin_token.clone()))
+        .to_request();
+    let disabled: UserResponse = test::call_and_read_body_json(&app, req).await;
+    assert!(disabled.disabled_at.is_some());
+
+    let req = test::TestRequest::get()
+        .uri("/users/me")
+        .insert_header(("Authorization", analyst_token))
+        .to_request();
+    let err = test::try_call_service(&app, req)
+        .await
+        .expect_err("Disabled users must be rejected");
+    assert_eq!(
+        err.as_response_error().status_code(),
+        StatusCode::UNAUTHORIZED
+    );
+
+    let resp = test::call_service(
+        &app,
+        common::login_request(&analyst_email, "analyst123").to_request(),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+    // Deleting the analyst removes the account
+    let req = test::TestRequest::delete()
+        .uri(&format!("/users/{}", analyst.uuid))
+        .insert_header(("Authorization", admin_token.clone()))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::NO_CONTENT
+    );
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/users/{}", analyst.uuid))
+        .insert_header(("Authorization", admin_token))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::NOT_FOUND
+    );
+}
+
+#[actix_rt::test]
+async fn test_password_change_and_reset() {
+    common::initialize_tests().await;
+    let pool = common::get_db_pool().clone();
+
+    let email = format!("analyst-{}@example.com", Uuid::new_v4());
+    let user_id = Uuid::new_v4();
+    let hash = bcrypt::hash("analyst123", 4).expect("Failed to hash password");
+    User::create(
+        &pool,
+        user_id,
+        email.clone(),
+        "Analyst".to_string(),
+        hash,
+        "user".to_string(),
+    )
+    .await
+    .expect("Failed to create user");
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(pool.clone()))
+            .service(
+                web::scope("/auth")
+                    .service(auth::login)
+                    .service(auth::forgot_password)
+                    .service(auth::reset_password),
+            )
+            .service(
+                web::scope("/users")
+                    .wrap(Auth::new())
+                    .service(user::me)
+                    .service(user::change_password),
+            ),
+    )
+    .await;
+    let login: TokenResponse = test::call_and_read_body_json(
+        &app,
+        common::login_request(&email, "analyst123").to_request(),
+    )
+    .await;
+    let token = format!("Bearer {}", login.access_token);
+    let other: TokenResponse = test::call_and_read_body_json(
+        &app,
+        common::login_request(&email, "analyst123").to_request(),
+    )
+    .await;
+    let other_token = format!("Bearer {}", other.access_token);
+
+    // The current password must be known and the new one must follow the policy
+    for (current, new) in [("wrong", "changed-password"), ("analyst123", "short")] {
+        let req = test::TestRequest::put()
+            .uri("/users/me/password")
+            .insert_header(("Authorization", token.clone()))
+            .set_json(json!({ "current_password": current, "new_password": new }))
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status(),
+            StatusCode::BAD_REQUEST
+        );
+    }
+
+    let req = test::TestRequest::put()
+        .uri("/users/me/password")
+        .insert_header(("Authorization", token.clone()))
+        .set_json(json!({
+            "current_password": "analyst123",
+            "new_password": "changed-password"
+        }))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::NO_CONTENT
+    );
+    let resp = test::call_service(
+        &app,
+        common::login_request(&email, "analyst123").to_request(),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+    // Changing the password keeps the caller's session and signs out the others
+    let req = test::TestRequest::get()
+        .uri("/users/me")
+        .insert_header(("Authorization", token.clone()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    let req = test::TestRequest::get()
+        .uri("/users/me")
+        .insert_header(("Authorization", other_token))
+        .to_request();
+    let err = test::try_call_service(&app, req)
+        .await
+        .expect_err("Other sessions must be signed out");
+    assert_eq!(
+        err.as_response_error().status_code(),
+        StatusCode::UNAUTHORIZED
+    );
+
+    // Reset tokens can only be used once
+    let reset = PasswordResetToken::issue(&pool, user_id)
+        .await
+        .expect("Failed to issue reset token");
+    for expected in [StatusCode::NO_CONTENT, StatusCode::BAD_REQUEST] {
+        let req = test::TestRequest::post()
+            .uri("/auth/reset_password")
+            .set_json(json!({ "token": reset.token, "new_password": "reset-password" }))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), expected);
+    }
+
+    let resp = test::call_service(
+        &app,
+        common::login_request(&email, "reset-password").to_request(),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    // Resetting the password signs out every session
+    let req = test::TestRequest::get()
+        .uri("/users/me")
+        .insert_header(("Authorization", token))
+        .to_request();
+    let err = test::try_call_service(&app, req)
+        .await
+        .expect_err("Sessions must be signed out by a reset");
+    assert_eq!(
+        err.as_response_error().status_code(),
+        StatusCode::UNAUTHORIZED
+    );
+
+    // Reset requests never reveal whether the account exists
+    for address in [email.as_str(), "nobody@example.com"] {
+        let req = test::TestRequest::post()
+            .uri("/auth/forgot_password")
+            .set_json(json!({ "email": address }))
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status(),
+            StatusCode::ACCEPTED
+        );
+    }
+}
+
+#[actix_rt::test]
+async fn test_two_factor_login() {
+    common::initialize_tests().await;
+    let pool = common::get_db_pool().clone();
+
+    let email = format!("analyst-{}@example.com", Uuid::new_v4());
+    let hash = bcrypt::hash("analyst123", 4).expect("Failed to hash password");
+    User::create(
+        &pool,
+        Uuid::new_v4(),
+        email.clone(),
+        "Analyst".to_string(),
+        hash,
+        "user".to_string(),
+    )
+    .await
+    .expect("Failed to create user");
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(pool.clone()))
+            .service(
+                web::scope("/auth")
+                    .service(auth::login)
+                    .service(auth::verify_login),
+            )
+            .service(
+                web::scope("/users")
+                    .wrap(Auth::new())
+                    .service(user::begin_totp_enrollment)
+                    .service(user::confirm_totp_enrollment),
+            ),
+    )
+    .await;
+    let login: TokenResponse = test::call_and_read_body_json(
+        &app,
+        common::login_request(&email, "analyst123").to_request(),
+    )
+    .await;
+    let token = format!("Bearer {}", login.access_token);
+
+    // Enroll and confirm with the first code
+    let req = test::TestRequest::post()
+        .uri("/users/me/totp")
+        .insert_header(("Authorization", token.clone()))
+        .to_request();
+    let enrollment: TotpEnrollment = test::call_and_read_body_json(&app, req).await;
+    assert!(enrollment.otpauth_uri.starts_with("otpauth://totp/"));
+    assert!(enrollment.otpauth_uri.contains(&enrollment.secret));
+
+    let first_code = totp::code_for(&enrollment.secret, Utc::now()).expect("Valid secret");
+    let req = test::TestRequest::post()
+        .uri("/users/me/totp/confirm")
+        .insert_header(("Authorization", token))
+        .set_json(json!({ "code": first_code }))
+        .to_request();
+    let codes: RecoveryCodesResponse = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(codes.recovery_codes.len(), 10);
+
+    // The password alone no longer yields tokens
+    let challenge: MfaChallengeResponse = test::call_and_read_body_json(
+        &app,
+        common::login_request(&email, "analyst123").to_request(),
+    )
+    .await;
+    assert!(challenge.mfa_required);
+
+    // Wrong and replayed codes are rejected and count as failed logins
+    for code in ["000000", first_code.as_str(), "999999"] {
+        let req = test::TestRequest::post()
+            .uri("/auth/login/verify")
+            .set_json(json!({ "mfa_token": challenge.mfa_token, "code": code }))
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status(),
+            StatusCode::UNAUTHORIZED
+        );
+    }
+    let resp = test::call_service(
+        &app,
+        common::login_request(&email, "analyst123").to_request(),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    // The next code completes the login and consumes the challenge
+    let next_code = totp::code_for(&enrollment.secret, Utc::now() + Duration::seconds(30))
+        .expect("Valid secret");
+    let req = test::TestRequest::post()
+        .uri("/auth/login/verify")
+        .set_json(json!({ "mfa_token": challenge.mfa_token, "code": next_code }))
+        .to_request();
+    let tokens: TokenResponse = test::call_and_read_body_json(&app, req).await;
+    assert!(tokens.user.totp_enabled);
+
+    // Only the second factor completes the login
+    let events = LoginAttempts::list(&pool, tokens.user.uuid, 6)
+        .await
+        .expect("Failed to list login events");
+    let names: Vec<(&str, bool)> = events
+        .iter()
+        .map(|e| (e.event.as_str(), e.success))
+        .collect();
+    assert_eq!(
+        names,
+        [
+            ("mfa_succeeded", true),
+            ("account_locked", false),
+            ("mfa_failed", false),
+            ("mfa_failed", false),
+            ("mfa_failed", false),
+            ("password_verified", false)
+        ]
+    );
+
+    let req = test::TestRequest::post()
+        .uri("/auth/login/verify")
+        .set_json(json!({ "mfa_token": challenge.mfa_token, "code": next_code }))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::UNAUTHORIZED
+    );
+
+    // Recovery codes work exactly once
+    for expected in [StatusCode::OK, StatusCode::UNAUTHORIZED] {
+        let challenge: MfaChallengeResponse = test::call_and_read_body_json(
+            &app,
+            common::login_request(&email, "analyst123").to_request(),
+        )
+        .await;
+        let req = test::TestRequest::post()
+            .uri("/auth/login/verify")
+            .set_json(json!({
+                "mfa_token": challenge.mfa_token,
+                "code": codes.recovery_codes[0]
+            }))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), expected);
+    }
+}
+
+#[actix_rt::test]
+async fn test_api_keys() {
+    common::initialize_tests().await;
+    let pool = common::get_db_pool().clone();
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(pool.clone()))
+            .service(web::scope("/auth").service(auth::login))
+            .service(
+                web::scope("/tickets")
+                    .wrap(Auth::new().permission("tickets:read"))
+                    .service(ticket::list_tickets)
+                    .service(
+                        web::scope("")
+                            .wrap(Auth::new().permission("tickets:write"))
+                            .service(ticket::create_ticket),
+                    ),
+            )
+            .service(
+                web::scope("/users")
+                    .wrap(Auth::new())
+                    .service(user::me)
+                    .service(user::create_api_key)
+                    .service(user::list_api_keys)
+                    .service(user::revoke_api_key),
+            ),
+    )
+    .await;
+    let admin: TokenResponse =
+        test::call_and_read_body_json(&app, common::admin_login_request().to_request()).await;
+    let admin_token = format!("Bearer {}", admin.access_token);
+
+    // Unknown scopes are rejected
+    let req = test::TestRequest::post()
+        .uri("/users/me/api_keys")
+        .insert_header(("Authorization", admin_token.clone()))
+        .set_json(json!({ "name": "SOAR", "scopes": ["users:write"] }))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::BAD_REQUEST
+    );
+
+    let req = test::TestRequest::post()
+        .uri("/users/me/api_keys")
+        .insert_header(("Authorization", admin_token.clone()))
+        .set_json(json!({ "name": "SOAR", "scopes": ["tickets:read"] }))
+        .to_request();
+    let created: CreatedApiKey = test::call_and_read_body_json(&app, req).await;
+    assert!(created.key.starts_with(&created.api_key.prefix));
+    assert!(created.api_key.last_used_at.is_none());
+    let key_header = format!("ApiKey {}", created.key);
+
+    // Reads are within the scope, writes and other routes are not
+    let req = test::TestRequest::get()
+        .uri("/tickets/list")
+        .insert_header(("Authorization", key_header.clone()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+    let req = test::TestRequest::post()
+        .uri("/tickets/create")
+        .insert_header(("Authorization", key_header.clone()))
+        .set_json(json!({}))
+        .to_request();
+    let err = test::try_call_service(&app, req)
+        .await
+        .expect_err("Writes need the tickets:write scope");
+    assert_eq!(err.as_response_error().status_code(), StatusCode::FORBIDDEN);
+
+    let req = test::TestRequest::get()
+        .uri("/users/me")
+        .insert_header(("Authorization", key_header.clone()))
+        .to_request();
+    let err = test::try_call_service(&app, req)
+        .await
+        .expect_err("Routes without a scope reject API keys");
+    assert_eq!(err.as_response_error().status_code(), StatusCode::FORBIDDEN);
+
+    // Use is recorded
+    let keys = ApiKey::list(&pool, admin.user.uuid)
+        .await
+        .expect("Failed to list keys");
+    let key = keys
+        .iter()
+        .find(|key| key.uuid == created.api_key.uuid)
+        .expect("Key is listed");
+    assert!(key.last_used_at.is_some());
+
+    // Revoked keys stop working
+    let req = test::TestRequest::delete()
+        .uri(&format!("/users/me/api_keys/{}", created.api_key.uuid))
+        .insert_header(("Authorization", admin_token))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::NO_CONTENT
+    );
+
+    let req = test::TestRequest::get()
+        .uri("/tickets/list")
+        .insert_header(("Authorization", key_header))
+        .to_request();
+    let err = test::try_call_service(&app, req)
+        .await
+        .expect_err("Revoked keys must be rejected");
+    assert_eq!(
+        err.as_response_error().status_code(),
+        StatusCode::UNAUTHORIZED
+    );
+}
+
+#[actix_rt::test]
+async fn test_login_lockout() {
+    common::initialize_tests().await;
+    let pool = common::get_db_pool().clone();
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(pool.clone()))
+            .service(web::scope("/auth").service(auth::login))
+            .service(
+                web::scope("/users")
+                    .wrap(Auth::new().permission("users:manage"))
+                    .service(auth::create_user)
+                    .service(user::unlock_user)
+                    .service(user::list_login_events),
+            ),
+    )
+    .await;
+    let admin: TokenResponse =
+        test::call_and_read_body_json(&app, common::admin_login_request().to_request()).await;
+    let admin_token = format!("Bearer {}", admin.access_token);
+
+    let email = format!("lockout-{}@example.com", Uuid::new_v4());
+    let req = test::TestRequest::post()
+        .uri("/users/create_user")
+        .insert_header(("Authorization", admin_token.clone()))
+        .set_json(json!({
+            "email": email,
+            "name": "Lockout",
+            "password": "lockout123",
+            "role": "user"
+        }))
+        .to_request();
+    let created: UserResponse = test::call_and_read_body_json(&app, req).await;
+
+    // Repeated wrong passwords delay further attempts, even correct ones
+    for _ in 0..3 {
+        let resp =
+            test::call_service(&app, common::login_request(&email, "wrong").to_request()).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+    let resp = test::call_service(
+        &app,
+        common::login_request(&email, "lockout123").to_request(),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert!(resp.headers().contains_key("Retry-After"));
+
+    // An admin clears the delay
+    let req = test::TestRequest::put()
+        .uri(&format!("/users/{}/unlock", created.uuid))
+        .insert_header(("Authorization", admin_token.clone()))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::NO_CONTENT
+    );
+    let resp = test::call_service(
+        &app,
+        common::login_request(&email, "lockout123").to_request(),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/users/{}/login_events", created.uuid))
+        .insert_header(("Authorization", admin_token.clone()))
+        .to_request();
+    let events: Vec<LoginEvent> = test::call_and_read_body_json(&app, req).await;
+    let names: Vec<&str> = events.iter().map(|e| e.event.as_str()).collect();
+    assert_eq!(
+        names,
+        [
+            "login_succeeded",
+            "account_locked",
+            "invalid_password",
+            "invalid_password",
+            "invalid_password"
+        ]
+    );
+
+    // Unknown users cannot be unlocked
+    let req = test::TestRequest::put()
+        .uri(&format!("/users/{}/unlock", Uuid::new_v4()))
+        .insert_header(("Authorization", admin_token.clone()))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::NOT_FOUND
+    );
+
+    // Too many failures from one address block it for every account
+    let peer = "198.51.100.7:40000".parse().unwrap();
+    for _ in 0..50 {
+        let req = common::login_request(&format!("nobody-{}@example.com", Uuid::new_v4()), "guess")
+            .peer_addr(peer)
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status(),
+            StatusCode::UNAUTHORIZED
+        );
+    }
+    let req = common::login_request(&email, "lockout123")
+        .peer_addr(peer)
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::TOO_MANY_REQUESTS
+    );
+}
+
+#[actix_rt::test]
+async fn test_sessions() {
+    common::initialize_tests().await;
+    let pool = common::get_db_pool().clone();
+
+    let email = format!("analyst-{}@example.com", Uuid::new_v4());
+    let user_id = Uuid::new_v4();
+    let hash = bcrypt::hash("analyst123", 4).expect("Failed to hash password");
+    User::create(
+        &pool,
+        user_id,
+        email.clone(),
+        "Analyst".to_string(),
+        hash,
+        "user".to_string(),
+    )
+    .await
+    .expect("Failed to create user");
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(pool.clone()))
+            .service(
+                web::scope("/auth")
+                    .service(auth::login)
+                    .service(auth::refresh),
+            )
+            .service(
+                web::scope("/account")
+                    .wrap(Auth::new())
+                    .service(auth::list_sessions)
+                    .service(auth::revoke_other_sessions)
+                    .service(auth::revoke_session),
+            )
+            .service(
+                web::scope("/users")
+                    .wrap(Auth::new())
+                    .service(user::me)
+                    .service(
+                        web::scope("")
+                            .wrap(Auth::new().permission("users:manage"))
+                            .service(user::list_user_sessions)
+                            .service(user::revoke_user_sessions)
+                            .service(user::revoke_user_session),
+                    ),
+            ),
+    )
+    .await;
+    let admin: TokenResponse =
+        test::call_and_read_body_json(&app, common::admin_login_request().to_request()).await;
+    let admin_token = format!("Bearer {}", admin.access_token);
+
+    let laptop: TokenResponse = test::call_and_read_body_json(
+        &app,
+        common::login_request(&email, "analyst123")
+            .insert_header(("User-Agent", "laptop"))
+            .peer_addr("203.0.113.10:50000".parse().unwrap())
+            .to_request(),
+    )
+    .await;
+    let laptop_token = format!("Bearer {}", laptop.access_token);
+    let phone: TokenResponse = test::call_and_read_body_json(
+        &app,
+        common::login_request(&email, "analyst123")
+            .insert_header(("User-Agent", "phone"))
+            .to_request(),
+    )
+    .await;
+    let tablet: TokenResponse = test::call_and_read_body_json(
+        &app,
+        common::login_request(&email, "analyst123")
+            .insert_header(("User-Agent", "tablet"))
+            .to_request(),
+    )
+    .await;
+
+    // Refreshing the phone's tokens is tracked
+    let req = test::TestRequest::post()
+        .uri("/auth/refresh")
+        .set_json(&RefreshRequest {
+            refresh_token: phone.refresh_token.expect("Refresh token is required"),
+        })
+        .to_request();
+    let phone: TokenResponse = test::call_and_read_body_json(&app, req).await;
+    let phone_token = format!("Bearer {}", phone.access_token);
+
+    let req = test::TestRequest::get()
+        .uri("/account/sessions")
+        .insert_header(("Authorization", laptop_token.clone()))
+        .to_request();
+    let sessions: Vec<SessionResponse> = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(sessions.len(), 3);
+    let find = |agent: &str| {
+        sessions
+            .iter()
+            .find(|s| s.session.user_agent.as_deref() == Some(agent))
+            .expect("Session is listed")
+    };
+    let laptop_session = find("laptop");
+    assert!(laptop_session.current);
+    assert_eq!(
+        laptop_session.session.ip_address.as_deref(),
+        Some("203.0.113.10")
+    );
+    assert!(laptop_session.session.last_refreshed_at.is_none());
+    let phone_session = find("phone");
+    assert!(!phone_session.current);
+    assert!(phone_session.session.last_refreshed_at.is_some());
+    let laptop_id = laptop_session.session.uuid;
+    let phone_id = phone_session.session.uuid;
+
+    // Signing out the phone rejects its tokens
+    let req = test::TestRequest::delete()
+        .uri(&format!("/account/sessions/{}", phone_id))
+        .insert_header(("Authorization", laptop_token.clone()))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::NO_CONTENT
+    );
+    let req = test::TestRequest::get()
+        .uri("/users/me")
+        .insert_header(("Authorization", phone_token.clone()))
+        .to_request();
+    let err = test::try_call_service(&app, req)
+        .await
+        .expect_err("Tokens of a signed out session must be rejected");
+    assert_eq!(
+        err.as_response_error().status_code(),
+        StatusCode::UNAUTHORIZED
+    );
+    let req = test::TestRequest::post()
+        .uri("/auth/refresh")
+        .set_json(&RefreshRequest {
+            refresh_token: phone.refresh_token.expect("Refresh token is required"),
+        })
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::UNAUTHORIZED
+    );
+
+    // Signed out and other users' sessions cannot be signed out
+    for session_id in [phone_id, Uuid::new_v4()] {
+        let req = test::TestRequest::delete()
+            .uri(&format!("/account/sessions/{}", session_id))
+            .insert_header(("Authorization", laptop_token.clone()))
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status(),
+            StatusCode::NOT_FOUND
+        );
+    }
+
+    // Signing out everywhere else keeps the current session
+    let req = test::TestRequest::delete()
+        .uri("/account/sessions")
+        .insert_header(("Authorization", laptop_token.clone()))
+        .to_request();
+    let revoked: RevokedSessionsResponse = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(revoked.revoked, 1);
+    let req = test::TestRequest::get()
+        .uri("/users/me")
+        .insert_header(("Authorization", format!("Bearer {}", tablet.access_token)))
+        .to_request();
+    assert!(test::try_call_service(&app, req).await.is_err());
+
+    // Admins see and sign out the remaining session
+    let req = test::TestRequest::get()
+        .uri(&format!("/users/{}/sessions", user_id))
+        .insert_header(("Authorization", admin_token.clone()))
+        .to_request();
+    let sessions: Vec<Session> = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(
+        sessions.iter().map(|s| s.uuid).collect::<Vec<_>>(),
+        [laptop_id]
+    );
+
+    let req = test::TestRequest::delete()
+        .uri(&format!("/users/{}/sessions/{}", user_id, laptop_id))
+        .insert_header(("Authorization", admin_token.clone()))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::NO_CONTENT
+    );
+    let req = test::TestRequest::get()
+        .uri("/users/me")
+        .insert_header(("Authorization", laptop_token.clone()))
+        .to_request();
+    assert!(test::try_call_service(&app, req).await.is_err());
+
+    // Offboarding signs out every session
+    let _: TokenResponse = test::call_and_read_body_json(
+        &app,
+        common::login_request(&email, "analyst123").to_request(),
+    )
+    .await;
+    let req = test::TestRequest::delete()
+        .uri(&format!("/users/{}/sessions", user_id))
+        .insert_header(("Authorization", admin_token.clone()))
+        .to_request();
+    let revoked: RevokedSessionsResponse = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(revoked.revoked, 1);
+    let req = test::TestRequest::get()
+        .uri(&format!("/users/{}/sessions", user_id))
+        .insert_header(("Authorization", admin_token.clone()))
+        .to_request();
+    let sessions: Vec<Session> = test::call_and_read_body_json(&app, req).await;
+    assert!(sessions.is_empty());
+}